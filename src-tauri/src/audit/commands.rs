@@ -0,0 +1,15 @@
+//! Audit Log Tauri Commands
+
+use super::{AuditError, AuditFilter, AuditLogReport};
+
+/// Get audit entries, oldest first, and whether the log is intact
+#[tauri::command]
+pub fn get_audit_log(filter: Option<AuditFilter>) -> Result<AuditLogReport, AuditError> {
+    super::read(&filter.unwrap_or_default())
+}
+
+/// Delete all audit entries
+#[tauri::command]
+pub fn clear_audit_log() -> Result<(), AuditError> {
+    super::clear()
+}
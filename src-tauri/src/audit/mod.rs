@@ -0,0 +1,528 @@
+//! Audit Log
+//!
+//! Append-only record of connects, commands and file operations in
+//! `audit_logs/audit.jsonl`. Entries are chained with an HMAC keyed by
+//! `audit.key` next to the log, so removing or editing a line breaks
+//! verification of everything after it, and the chain can't be rebuilt
+//! without the key. With `Settings::audit_log_encrypted` new lines are also
+//! encrypted with that key; plain and encrypted lines may be mixed.
+//!
+//! Once the log is a month old or large, it is renamed to
+//! `audit-<timestamp>.jsonl` and a new chain starts. Retention prunes those
+//! rotated files; only the current log is read back by `get_audit_log`.
+//!
+//! `log_action` only buffers the entry. The buffer is written when it
+//! fills up, every few seconds, and when the app exits.
+
+pub mod commands;
+
+use crate::datasphere::{self, DataSphereCrypto, EncryptedData};
+use crate::retention::{self, ActiveFileGuard, StorageCategory};
+use crate::utils::{AuditAction, AuditLogEntry};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::AppHandle;
+use thiserror::Error;
+use uuid::Uuid;
+use zeroize::Zeroizing;
+
+/// Hash used as the predecessor of the first entry in a chain
+const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Name of the log file inside the audit log directory
+const LOG_FILE: &str = "audit.jsonl";
+
+/// Name of the key file inside the audit log directory
+const KEY_FILE: &str = "audit.key";
+
+/// Age of the first entry after which the log is rotated
+const ROTATE_AGE: chrono::Duration = chrono::Duration::days(30);
+
+/// Size after which the log is rotated
+const ROTATE_BYTES: u64 = 16 * 1024 * 1024;
+
+/// Buffered entries that trigger a write right away
+const FLUSH_THRESHOLD: usize = 64;
+
+/// How often buffered entries are written
+const FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The audit log; `None` until `init` ran
+static AUDIT_LOG: Lazy<Mutex<Option<AuditLog>>> = Lazy::new(|| Mutex::new(None));
+
+/// Audit log errors
+#[derive(Error, Debug)]
+pub enum AuditError {
+    #[error("Audit log is not initialized")]
+    NotInitialized,
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Encryption error: {0}")]
+    Crypto(String),
+    #[error("Tauri error: {0}")]
+    Tauri(String),
+}
+
+impl Serialize for AuditError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+/// A line of the log file
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum StoredLine {
+    Plain(AuditLogEntry),
+    Encrypted(EncryptedData),
+}
+
+/// Which entries `get_audit_log` returns; unset fields match everything
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AuditFilter {
+    /// Empty matches every action
+    pub actions: Vec<AuditAction>,
+    pub session_id: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    /// Case-insensitive substring of the details
+    pub text: Option<String>,
+    /// Keep only the newest entries
+    pub limit: Option<usize>,
+}
+
+impl AuditFilter {
+    fn matches(&self, entry: &AuditLogEntry) -> bool {
+        (self.actions.is_empty() || self.actions.contains(&entry.action))
+            && self
+                .session_id
+                .as_ref()
+                .is_none_or(|id| entry.session_id.as_ref() == Some(id))
+            && self.since.is_none_or(|since| entry.timestamp >= since)
+            && self.until.is_none_or(|until| entry.timestamp <= until)
+            && self
+                .text
+                .as_ref()
+                .is_none_or(|text| entry.details.to_lowercase().contains(&text.to_lowercase()))
+    }
+}
+
+/// Filtered audit entries with the verification result of the whole log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditLogReport {
+    pub entries: Vec<AuditLogEntry>,
+    pub chain_valid: bool,
+    /// Line of the first entry that failed verification
+    pub broken_at: Option<u64>,
+}
+
+/// Writer for the audit log file
+struct AuditLog {
+    path: PathBuf,
+    /// Keys the hash chain and encrypts lines
+    key: Zeroizing<[u8; 32]>,
+    crypto: DataSphereCrypto,
+    seq: u64,
+    last_hash: String,
+    /// Time of the first entry in the current file
+    started: Option<DateTime<Utc>>,
+    pending: Vec<AuditLogEntry>,
+    _active: ActiveFileGuard,
+}
+
+impl AuditLog {
+    /// Open the log in `dir`, continuing its chain
+    fn open(dir: &Path) -> Result<Self, AuditError> {
+        fs::create_dir_all(dir)?;
+        let key = Zeroizing::new(
+            DataSphereCrypto::load_or_create_key_file(&dir.join(KEY_FILE))
+                .map_err(|e| AuditError::Crypto(e.to_string()))?,
+        );
+        let path = dir.join(LOG_FILE);
+        let mut log = Self {
+            _active: retention::mark_active(&path),
+            path,
+            crypto: DataSphereCrypto::from_key(&key),
+            key,
+            seq: 0,
+            last_hash: GENESIS_HASH.to_string(),
+            started: None,
+            pending: Vec::new(),
+        };
+
+        // A broken line doesn't stop logging; verification reports it
+        let (entries, _) = log.read()?;
+        if let Some(last) = entries.last() {
+            log.seq = last.seq + 1;
+            log.last_hash = last.hash.clone();
+        }
+        log.started = entries.first().map(|first| first.timestamp);
+        Ok(log)
+    }
+
+    fn append(&mut self, action: AuditAction, details: String, session_id: Option<String>) {
+        let timestamp = Utc::now();
+        // Buffered entries belong to the current chain, so rotate only between flushes
+        if self.pending.is_empty() {
+            if let Err(e) = self.rotate_if_due(timestamp) {
+                tracing::warn!("Rotating the audit log failed: {}", e);
+            }
+        }
+        self.started.get_or_insert(timestamp);
+        let hash = compute_hash(
+            self.key.as_slice(),
+            self.seq,
+            &timestamp,
+            action,
+            &details,
+            session_id.as_deref(),
+            &self.last_hash,
+        );
+        self.pending.push(AuditLogEntry {
+            seq: self.seq,
+            timestamp,
+            action,
+            details,
+            session_id,
+            prev_hash: std::mem::replace(&mut self.last_hash, hash.clone()),
+            hash,
+        });
+        self.seq += 1;
+    }
+
+    /// Write buffered entries; they stay buffered if writing fails
+    fn flush(&mut self, encrypt: bool) -> Result<(), AuditError> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+
+        let mut lines = String::new();
+        for entry in &self.pending {
+            let line = if encrypt {
+                let encrypted = self
+                    .crypto
+                    .encrypt_json(entry)
+                    .map_err(|e| AuditError::Crypto(e.to_string()))?;
+                serde_json::to_string(&StoredLine::Encrypted(encrypted))
+            } else {
+                serde_json::to_string(entry)
+            }
+            .map_err(|e| AuditError::Crypto(e.to_string()))?;
+            lines.push_str(&line);
+            lines.push('\n');
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(lines.as_bytes())?;
+        self.pending.clear();
+        Ok(())
+    }
+
+    /// Readable entries, and the line of the first one that couldn't be read
+    fn read(&mut self) -> Result<(Vec<AuditLogEntry>, Option<u64>), AuditError> {
+        if !self.path.exists() {
+            return Ok((Vec::new(), None));
+        }
+        let content = fs::read_to_string(&self.path)?;
+
+        let mut entries = Vec::new();
+        let mut unreadable = None;
+        for (index, line) in content.lines().filter(|l| !l.trim().is_empty()).enumerate() {
+            let entry = match serde_json::from_str(line) {
+                Ok(StoredLine::Plain(entry)) => Some(entry),
+                Ok(StoredLine::Encrypted(encrypted)) => self.crypto.decrypt_json(&encrypted).ok(),
+                Err(_) => None,
+            };
+            match entry {
+                Some(entry) => entries.push(entry),
+                None => {
+                    unreadable.get_or_insert(index as u64);
+                }
+            }
+        }
+        Ok((entries, unreadable))
+    }
+
+    /// Move the log aside for retention and start a new chain, once it is
+    /// old or large enough
+    fn rotate_if_due(&mut self, now: DateTime<Utc>) -> Result<(), AuditError> {
+        let old = self
+            .started
+            .is_some_and(|started| now - started >= ROTATE_AGE);
+        let large = fs::metadata(&self.path).is_ok_and(|m| m.len() >= ROTATE_BYTES);
+        if !old && !large {
+            return Ok(());
+        }
+
+        let rotated = self
+            .path
+            .with_file_name(format!("audit-{}.jsonl", now.format("%Y%m%d-%H%M%S%.3f")));
+        fs::rename(&self.path, &rotated)?;
+        tracing::info!("Rotated the audit log to {}", rotated.display());
+        self.restart();
+        Ok(())
+    }
+
+    /// Remove the log file and start a new chain
+    fn clear(&mut self) -> Result<(), AuditError> {
+        if self.path.exists() {
+            fs::remove_file(&self.path)?;
+        }
+        self.pending.clear();
+        self.restart();
+        Ok(())
+    }
+
+    fn restart(&mut self) {
+        self.seq = 0;
+        self.last_hash = GENESIS_HASH.to_string();
+        self.started = None;
+    }
+}
+
+fn compute_hash(
+    key: &[u8],
+    seq: u64,
+    timestamp: &DateTime<Utc>,
+    action: AuditAction,
+    details: &str,
+    session_id: Option<&str>,
+    prev_hash: &str,
+) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes keys of any length");
+    mac.update(prev_hash.as_bytes());
+    mac.update(&seq.to_be_bytes());
+    mac.update(timestamp.to_rfc3339().as_bytes());
+    mac.update(
+        serde_json::to_string(&action)
+            .unwrap_or_default()
+            .as_bytes(),
+    );
+    mac.update(details.as_bytes());
+    mac.update(session_id.unwrap_or_default().as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Index of the first entry whose chain link doesn't hold
+fn verify_chain(key: &[u8], entries: &[AuditLogEntry]) -> Option<u64> {
+    let mut prev_hash = GENESIS_HASH;
+    for (index, entry) in entries.iter().enumerate() {
+        let expected = compute_hash(
+            key,
+            entry.seq,
+            &entry.timestamp,
+            entry.action,
+            &entry.details,
+            entry.session_id.as_deref(),
+            &entry.prev_hash,
+        );
+        if entry.seq != index as u64 || entry.prev_hash != prev_hash || entry.hash != expected {
+            return Some(index as u64);
+        }
+        prev_hash = &entry.hash;
+    }
+    None
+}
+
+fn encryption_enabled() -> bool {
+    datasphere::storage()
+        .read()
+        .as_ref()
+        .is_some_and(|s| s.get_settings().audit_log_encrypted)
+}
+
+/// Open the audit log below the app data directory
+pub fn init(app: &AppHandle) -> Result<(), AuditError> {
+    let dir = retention::category_dir(app, StorageCategory::AuditLogs)
+        .map_err(|e| AuditError::Tauri(e.to_string()))?;
+    *AUDIT_LOG.lock() = Some(AuditLog::open(&dir)?);
+    Ok(())
+}
+
+/// Record an action; does nothing before `init`
+pub fn log_action(action: AuditAction, details: impl Into<String>, session_id: Option<Uuid>) {
+    let mut log = AUDIT_LOG.lock();
+    let Some(log) = log.as_mut() else {
+        return;
+    };
+    log.append(action, details.into(), session_id.map(|id| id.to_string()));
+    if log.pending.len() >= FLUSH_THRESHOLD {
+        if let Err(e) = flush_log(log) {
+            tracing::warn!("Writing the audit log failed: {}", e);
+        }
+    }
+}
+
+fn flush_log(log: &mut AuditLog) -> Result<(), AuditError> {
+    log.flush(encryption_enabled())
+}
+
+/// Write all buffered entries
+pub fn flush() -> Result<(), AuditError> {
+    match AUDIT_LOG.lock().as_mut() {
+        Some(log) => flush_log(log),
+        None => Ok(()),
+    }
+}
+
+/// Start the task writing buffered entries periodically
+pub fn spawn_flush_task() {
+    tauri::async_runtime::spawn(async {
+        loop {
+            tokio::time::sleep(FLUSH_INTERVAL).await;
+            if let Err(e) = flush() {
+                tracing::warn!("Writing the audit log failed: {}", e);
+            }
+        }
+    });
+}
+
+/// Read the log, verify its chain and apply `filter`
+pub fn read(filter: &AuditFilter) -> Result<AuditLogReport, AuditError> {
+    let mut log = AUDIT_LOG.lock();
+    let log = log.as_mut().ok_or(AuditError::NotInitialized)?;
+    flush_log(log)?;
+
+    let (entries, unreadable) = log.read()?;
+    let broken_at = match (unreadable, verify_chain(log.key.as_slice(), &entries)) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    };
+
+    let mut entries: Vec<_> = entries.into_iter().filter(|e| filter.matches(e)).collect();
+    if let Some(limit) = filter.limit {
+        entries.drain(..entries.len().saturating_sub(limit));
+    }
+    Ok(AuditLogReport {
+        entries,
+        chain_valid: broken_at.is_none(),
+        broken_at,
+    })
+}
+
+/// Delete all entries; the new log starts by recording the clearing
+pub fn clear() -> Result<(), AuditError> {
+    let mut log = AUDIT_LOG.lock();
+    let log = log.as_mut().ok_or(AuditError::NotInitialized)?;
+    log.clear()?;
+    log.append(
+        AuditAction::AuditLogCleared,
+        "Audit log cleared".to_string(),
+        None,
+    );
+    flush_log(log)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chain_and_encryption() {
+        let dir = std::env::temp_dir().join(format!("nexus-audit-{}", Uuid::new_v4()));
+        let session = Some(Uuid::new_v4().to_string());
+
+        let mut log = AuditLog::open(&dir).unwrap();
+        log.append(
+            AuditAction::Connect,
+            "root@example.com:22".into(),
+            session.clone(),
+        );
+        log.flush(false).unwrap();
+        log.append(
+            AuditAction::CommandExecuted,
+            "uptime".into(),
+            session.clone(),
+        );
+        log.append(
+            AuditAction::Disconnect,
+            "root@example.com:22".into(),
+            session,
+        );
+        log.flush(true).unwrap();
+
+        // Reopening continues the chain across plain and encrypted lines
+        let mut log = AuditLog::open(&dir).unwrap();
+        assert_eq!(log.seq, 3);
+        let content = fs::read_to_string(&log.path).unwrap();
+        assert!(!content.contains("uptime"));
+        let (entries, unreadable) = log.read().unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(unreadable, None);
+        assert_eq!(verify_chain(log.key.as_slice(), &entries), None);
+        // Without the key the chain can't be checked, or rebuilt
+        assert_eq!(verify_chain(&[0; 32], &entries), Some(0));
+
+        let filter = AuditFilter {
+            actions: vec![AuditAction::CommandExecuted],
+            ..Default::default()
+        };
+        assert_eq!(entries.iter().filter(|e| filter.matches(e)).count(), 1);
+
+        // Dropping a line breaks the chain there
+        let lines: Vec<&str> = content.lines().collect();
+        fs::write(&log.path, format!("{}\n{}\n", lines[0], lines[2])).unwrap();
+        let (entries, _) = log.read().unwrap();
+        assert_eq!(verify_chain(log.key.as_slice(), &entries), Some(1));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_old_log_is_rotated() {
+        let dir = std::env::temp_dir().join(format!("nexus-audit-{}", Uuid::new_v4()));
+
+        let mut log = AuditLog::open(&dir).unwrap();
+        log.append(AuditAction::Connect, "first".into(), None);
+        log.flush(false).unwrap();
+        log.append(AuditAction::Disconnect, "second".into(), None);
+        log.flush(false).unwrap();
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+
+        // A month later the next entry starts a new file and chain
+        log.started = Some(Utc::now() - ROTATE_AGE);
+        log.append(AuditAction::Connect, "third".into(), None);
+        log.flush(false).unwrap();
+
+        let (entries, _) = log.read().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].seq, 0);
+        assert_eq!(verify_chain(log.key.as_slice(), &entries), None);
+
+        let rotated: Vec<PathBuf> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .filter(|p| {
+                p.file_name()
+                    .unwrap()
+                    .to_string_lossy()
+                    .starts_with("audit-")
+            })
+            .collect();
+        assert_eq!(rotated.len(), 1);
+        let content = fs::read_to_string(&rotated[0]).unwrap();
+        assert!(content.contains("first") && content.contains("second"));
+
+        // Reopened, the new file keeps its own start time
+        let log = AuditLog::open(&dir).unwrap();
+        assert_eq!(log.seq, 1);
+        assert!(log.started.is_some_and(|s| Utc::now() - s < ROTATE_AGE));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
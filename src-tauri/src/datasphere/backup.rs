@@ -0,0 +1,212 @@
+//! Workspace backups
+//!
+//! A `.nexusbackup` file holds hosts, groups, snippets, vault entries,
+//! settings (including the MCP config), user security profiles, trusted
+//! host keys and the list of open sessions,
+//! encrypted as a whole with a key derived from the backup password.
+//! Plugin state is not persisted by the app yet, so it is not part of a
+//! backup.
+
+use super::{
+    DataSphereCrypto, DataSphereError, EncryptedData, Host, HostGroup, Settings, Snippet,
+    VaultEntry,
+};
+use crate::ssh::{KnownHost, SecurityProfile};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::hash::Hash;
+use std::path::Path;
+use uuid::Uuid;
+use zeroize::Zeroizing;
+
+/// Everything a backup restores
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceSnapshot {
+    /// App version that wrote the backup
+    pub app_version: String,
+    pub hosts: Vec<Host>,
+    pub groups: Vec<HostGroup>,
+    pub snippets: Vec<Snippet>,
+    pub vault: Vec<VaultEntry>,
+    pub settings: Settings,
+    /// User-defined security profiles; built-ins ship with the app
+    #[serde(default)]
+    pub profiles: Vec<SecurityProfile>,
+    /// Trusted host keys
+    #[serde(default)]
+    pub known_hosts: Vec<KnownHost>,
+    #[serde(default)]
+    pub sessions: Vec<SessionLayout>,
+}
+
+/// A session that was open when the backup was taken
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionLayout {
+    pub host_id: Uuid,
+    pub name: String,
+}
+
+/// How a backup is combined with the current workspace
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RestoreMode {
+    /// Add items that don't exist yet; keep current items and settings
+    #[default]
+    Merge,
+    /// Discard the current workspace and use the backup
+    Replace,
+}
+
+/// What a restore changed
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RestoreReport {
+    pub hosts_added: usize,
+    pub groups_added: usize,
+    pub snippets_added: usize,
+    pub vault_entries_added: usize,
+    pub profiles_added: usize,
+    pub known_hosts_added: usize,
+    /// Items left alone because an item with the same id already exists
+    pub skipped: usize,
+    pub settings_restored: bool,
+    /// Sessions to reopen
+    pub sessions: Vec<SessionLayout>,
+}
+
+/// On-disk `.nexusbackup` structure
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupFile {
+    pub magic: String,
+    pub version: u8,
+    pub created_at: DateTime<Utc>,
+    /// Salt for key derivation
+    pub salt: String,
+    /// Encrypted `WorkspaceSnapshot`
+    pub data: EncryptedData,
+}
+
+impl BackupFile {
+    pub const MAGIC: &'static str = "NEXUS_BACKUP";
+    pub const VERSION: u8 = 1;
+
+    /// Check that this is a backup this version can read
+    pub fn validate(&self) -> Result<(), DataSphereError> {
+        if self.magic != Self::MAGIC {
+            return Err(DataSphereError::InvalidInput(
+                "Not a Nexus backup file".to_string(),
+            ));
+        }
+        if self.version != Self::VERSION {
+            return Err(DataSphereError::InvalidInput(format!(
+                "Unsupported backup version: {}",
+                self.version
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Encrypt `snapshot` with `password` and write it to `path`
+pub fn write_backup(
+    path: &Path,
+    snapshot: &WorkspaceSnapshot,
+    password: &str,
+) -> Result<(), DataSphereError> {
+    if password.is_empty() {
+        return Err(DataSphereError::InvalidInput(
+            "Backup password must not be empty".to_string(),
+        ));
+    }
+
+    let salt = DataSphereCrypto::generate_salt();
+    let crypto = DataSphereCrypto::from_password(password, &salt)?;
+    let plaintext = Zeroizing::new(serde_json::to_vec(snapshot)?);
+    let file = BackupFile {
+        magic: BackupFile::MAGIC.to_string(),
+        version: BackupFile::VERSION,
+        created_at: Utc::now(),
+        salt: BASE64.encode(salt),
+        data: crypto.encrypt(&plaintext)?,
+    };
+
+    let tmp = path.with_extension("nexusbackup.tmp");
+    fs::write(&tmp, serde_json::to_vec_pretty(&file)?)?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// Read and decrypt the backup at `path`
+pub fn read_backup(path: &Path, password: &str) -> Result<WorkspaceSnapshot, DataSphereError> {
+    let file: BackupFile = serde_json::from_slice(&fs::read(path)?)
+        .map_err(|_| DataSphereError::InvalidInput("Not a Nexus backup file".to_string()))?;
+    file.validate()?;
+
+    let salt = BASE64
+        .decode(&file.salt)
+        .map_err(|e| DataSphereError::Decryption(e.to_string()))?;
+    let crypto = DataSphereCrypto::from_password(password, &salt)?;
+    let plaintext = Zeroizing::new(crypto.decrypt(&file.data).map_err(|_| {
+        DataSphereError::Decryption("Wrong password or corrupted backup".to_string())
+    })?);
+    Ok(serde_json::from_slice(&plaintext)?)
+}
+
+/// Add `incoming` items whose id isn't in `local`, returning (added, skipped)
+pub(crate) fn merge_by_id<K: Eq + Hash, T>(
+    local: &mut HashMap<K, T>,
+    incoming: Vec<T>,
+    id: impl Fn(&T) -> K,
+) -> (usize, usize) {
+    let (mut added, mut skipped) = (0, 0);
+    for item in incoming {
+        match local.entry(id(&item)) {
+            std::collections::hash_map::Entry::Occupied(_) => skipped += 1,
+            std::collections::hash_map::Entry::Vacant(slot) => {
+                slot.insert(item);
+                added += 1;
+            }
+        }
+    }
+    (added, skipped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backup_validate_and_merge() {
+        let mut file = BackupFile {
+            magic: BackupFile::MAGIC.to_string(),
+            version: BackupFile::VERSION,
+            created_at: Utc::now(),
+            salt: String::new(),
+            data: EncryptedData {
+                ciphertext: String::new(),
+                nonce: String::new(),
+                salt: None,
+                version: 1,
+            },
+        };
+        assert!(file.validate().is_ok());
+        file.version = 2;
+        assert!(file.validate().is_err());
+        file.version = BackupFile::VERSION;
+        file.magic = "NEXUS_VAULT".to_string();
+        assert!(file.validate().is_err());
+
+        let kept = Host::new("kept".into(), "a".into(), "root".into());
+        let mut local = HashMap::from([(kept.id, kept.clone())]);
+        let mut replaced = kept.clone();
+        replaced.name = "from backup".into();
+        let new = Host::new("new".into(), "b".into(), "root".into());
+
+        let (added, skipped) = merge_by_id(&mut local, vec![replaced, new.clone()], |h| h.id);
+        assert_eq!((added, skipped), (1, 1));
+        assert_eq!(local[&kept.id].name, "kept");
+        assert!(local.contains_key(&new.id));
+    }
+}
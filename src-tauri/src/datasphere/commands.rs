@@ -1,7 +1,25 @@
 //! DataSphere Tauri Commands
 
-use super::{storage, DataSphereError, Host, HostGroup, NewHost, Settings, Snippet};
+use super::backup::{self, SessionLayout};
+use super::export::{self, ConflictPolicy, ImportReport};
+use super::password::{self, PasswordOptions, StrengthEstimate};
+use super::placeholders::{self, RenderedSnippet};
+use super::{
+    openssh, openssh_import, storage, AppStatus, DataSphereError, GlobalSearchResults, Host,
+    HostGroup, HostPatch, NewHost, NewVaultEntry, RestoreMode, RestoreReport, Settings, Snippet,
+    TrashItem, TrashedItem, VaultAuditOptions, VaultAuditReport, VaultEntry, VaultEntryType,
+};
+use crate::ssh::keygen::{self, GeneratedSshKey, KeyAlgorithm};
+use crate::ssh::reachability::{self, ProbeTarget};
+use crate::ssh::{
+    connection_test, effective, known_hosts, ppk, ConnectionTestResult, HostProbe, KnownHost,
+    ResolvedConfig, SecurityProfile,
+};
+use crate::utils::BulkResult;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use uuid::Uuid;
+use zeroize::Zeroizing;
 
 /// Get all hosts
 #[tauri::command]
@@ -11,6 +29,14 @@ pub fn get_hosts() -> Result<Vec<Host>, DataSphereError> {
     Ok(storage.get_hosts())
 }
 
+/// Search hosts by name, hostname, username, tags or notes
+#[tauri::command]
+pub fn search_hosts(query: String) -> Result<Vec<Host>, DataSphereError> {
+    let storage = storage().read();
+    let storage = storage.as_ref().ok_or(DataSphereError::NotInitialized)?;
+    Ok(storage.search_hosts(&query))
+}
+
 /// Add a new host
 #[tauri::command]
 pub fn add_host(host: NewHost) -> Result<Host, DataSphereError> {
@@ -28,12 +54,159 @@ pub fn update_host(host: Host) -> Result<Host, DataSphereError> {
     storage.update_host(host)
 }
 
-/// Delete a host
+/// Duplicate an existing host under a new name
+#[tauri::command]
+pub fn clone_host(id: Uuid, new_name: String) -> Result<Host, DataSphereError> {
+    let mut storage = storage().write();
+    let storage = storage.as_mut().ok_or(DataSphereError::NotInitialized)?;
+    storage.clone_host(id, new_name)
+}
+
+/// Move a host to the trash, or delete it for good with `permanent`
+#[tauri::command]
+pub fn delete_host(id: Uuid, permanent: Option<bool>) -> Result<(), DataSphereError> {
+    let mut storage = storage().write();
+    let storage = storage.as_mut().ok_or(DataSphereError::NotInitialized)?;
+    storage.delete_host(id, permanent.unwrap_or(false))
+}
+
+/// Apply the same change to many hosts, with the outcome for each host
+#[tauri::command]
+pub fn bulk_update_hosts(
+    ids: Vec<Uuid>,
+    patch: HostPatch,
+) -> Result<BulkResult<Host>, DataSphereError> {
+    let mut storage = storage().write();
+    let storage = storage.as_mut().ok_or(DataSphereError::NotInitialized)?;
+    storage.bulk_update_hosts(&ids, &patch)
+}
+
+/// Move many hosts to the trash, or delete them for good with `permanent`,
+/// with the outcome for each host
+#[tauri::command]
+pub fn bulk_delete_hosts(
+    ids: Vec<Uuid>,
+    permanent: Option<bool>,
+) -> Result<BulkResult<()>, DataSphereError> {
+    let mut storage = storage().write();
+    let storage = storage.as_mut().ok_or(DataSphereError::NotInitialized)?;
+    storage.bulk_delete_hosts(&ids, permanent.unwrap_or(false))
+}
+
+/// Show the effective connection settings of a host and where each came from
+#[tauri::command]
+pub fn resolve_host_config(host_id: Uuid) -> Result<ResolvedConfig, DataSphereError> {
+    let storage = storage().read();
+    let storage = storage.as_ref().ok_or(DataSphereError::NotInitialized)?;
+    let host = storage
+        .get_host(host_id)
+        .ok_or_else(|| DataSphereError::NotFound(format!("Host {}", host_id)))?;
+    let profile = storage.host_security_profile(&host)?;
+    Ok(effective::resolve_host(&host, profile.as_ref()))
+}
+
+/// Check whether a host accepts connections, without logging in
+///
+/// Reads the SSH banner unless `read_banner` is false. Stored hosts are
+/// not changed.
+#[tauri::command]
+pub async fn probe_host(
+    host_id: Uuid,
+    read_banner: Option<bool>,
+) -> Result<HostProbe, DataSphereError> {
+    let target = {
+        let storage = storage().read();
+        let storage = storage.as_ref().ok_or(DataSphereError::NotInitialized)?;
+        let host = storage
+            .get_host(host_id)
+            .ok_or_else(|| DataSphereError::NotFound(format!("Host {}", host_id)))?;
+        probe_target(&host)
+    };
+    Ok(reachability::probe(&target, read_banner.unwrap_or(true)).await)
+}
+
+/// Log in to a host to check its settings, without opening a session
+///
+/// Runs `echo` once logged in unless `run_echo` is false. Nothing is kept
+/// connected afterwards.
 #[tauri::command]
-pub fn delete_host(id: Uuid) -> Result<(), DataSphereError> {
+pub async fn test_host_connection(
+    host_id: Uuid,
+    run_echo: Option<bool>,
+) -> Result<ConnectionTestResult, DataSphereError> {
+    let config = {
+        let storage = storage().read();
+        let storage = storage.as_ref().ok_or(DataSphereError::NotInitialized)?;
+        let host = storage
+            .get_host(host_id)
+            .ok_or_else(|| DataSphereError::NotFound(format!("Host {}", host_id)))?;
+        storage.host_ssh_config(&host)?
+    };
+    Ok(connection_test::test_connection(config, run_echo.unwrap_or(true)).await)
+}
+
+/// Probe every stored host, a bounded number at a time
+#[tauri::command]
+pub async fn probe_all_hosts(read_banner: Option<bool>) -> Result<Vec<HostProbe>, DataSphereError> {
+    let targets = {
+        let storage = storage().read();
+        let storage = storage.as_ref().ok_or(DataSphereError::NotInitialized)?;
+        storage.get_hosts().iter().map(probe_target).collect()
+    };
+    Ok(reachability::probe_many(targets, read_banner.unwrap_or(true)).await)
+}
+
+fn probe_target(host: &Host) -> ProbeTarget {
+    ProbeTarget {
+        host_id: host.id,
+        hostname: host.hostname.clone(),
+        port: host.port,
+        transport: host.transport.clone(),
+    }
+}
+
+/// Get the `ssh_config` block for a single host, without secrets
+#[tauri::command]
+pub fn get_host_ssh_config(host_id: Uuid) -> Result<String, DataSphereError> {
+    let storage = storage().read();
+    let storage = storage.as_ref().ok_or(DataSphereError::NotInitialized)?;
+    let host = storage
+        .get_host(host_id)
+        .ok_or_else(|| DataSphereError::NotFound(format!("Host {}", host_id)))?;
+    let group = host
+        .group_id
+        .and_then(|id| storage.get_groups().into_iter().find(|g| g.id == id));
+    Ok(openssh::host_block(
+        &host,
+        group.as_ref(),
+        &storage.get_hosts(),
+    ))
+}
+
+/// Preview the hosts defined in an OpenSSH config file, e.g. `~/.ssh/config`
+///
+/// Nothing is saved. Names already in use get a ` (2)`-style suffix; pass
+/// the (possibly edited) list to `commit_imported_hosts`.
+#[tauri::command]
+pub fn import_ssh_config(path: String) -> Result<Vec<NewHost>, DataSphereError> {
+    let storage = storage().read();
+    let storage = storage.as_ref().ok_or(DataSphereError::NotInitialized)?;
+    let hosts = openssh_import::parse_file(&openssh_import::expand_home(&path))?;
+    let existing = storage.get_hosts();
+    Ok(openssh_import::dedupe_names(
+        hosts,
+        existing.iter().map(|h| h.name.as_str()),
+    ))
+}
+
+/// Save hosts from `import_ssh_config`, renaming any that clash with existing hosts
+#[tauri::command]
+pub fn commit_imported_hosts(hosts: Vec<NewHost>) -> Result<Vec<Host>, DataSphereError> {
     let mut storage = storage().write();
     let storage = storage.as_mut().ok_or(DataSphereError::NotInitialized)?;
-    storage.delete_host(id)
+    let existing = storage.get_hosts();
+    let hosts = openssh_import::dedupe_names(hosts, existing.iter().map(|h| h.name.as_str()));
+    storage.add_hosts(hosts.into_iter().map(Host::from_new).collect())
 }
 
 /// Get all host groups
@@ -44,6 +217,116 @@ pub fn get_host_groups() -> Result<Vec<HostGroup>, DataSphereError> {
     Ok(storage.get_groups())
 }
 
+/// Create a host group at the end of the list
+#[tauri::command]
+pub fn add_host_group(name: String) -> Result<HostGroup, DataSphereError> {
+    let mut storage = storage().write();
+    let storage = storage.as_mut().ok_or(DataSphereError::NotInitialized)?;
+    storage.add_group(&name)
+}
+
+/// Update a host group's name, icon, color or order
+#[tauri::command]
+pub fn update_host_group(group: HostGroup) -> Result<HostGroup, DataSphereError> {
+    let mut storage = storage().write();
+    let storage = storage.as_mut().ok_or(DataSphereError::NotInitialized)?;
+    storage.update_group(group)
+}
+
+/// Delete a host group
+///
+/// Fails while the group has hosts unless `force` is set, which moves them
+/// out of any group.
+#[tauri::command]
+pub fn delete_host_group(id: Uuid, force: Option<bool>) -> Result<(), DataSphereError> {
+    let mut storage = storage().write();
+    let storage = storage.as_mut().ok_or(DataSphereError::NotInitialized)?;
+    storage.delete_group(id, force.unwrap_or(false))
+}
+
+/// Order host groups as listed; returns the groups in their new order
+#[tauri::command]
+pub fn reorder_host_groups(ids: Vec<Uuid>) -> Result<Vec<HostGroup>, DataSphereError> {
+    let mut storage = storage().write();
+    let storage = storage.as_mut().ok_or(DataSphereError::NotInitialized)?;
+    storage.reorder_groups(&ids)
+}
+
+/// Get built-in and user security profiles
+#[tauri::command]
+pub fn get_security_profiles() -> Result<Vec<SecurityProfile>, DataSphereError> {
+    let storage = storage().read();
+    let storage = storage.as_ref().ok_or(DataSphereError::NotInitialized)?;
+    Ok(storage.get_security_profiles())
+}
+
+/// Add a security profile
+#[tauri::command]
+pub fn add_security_profile(profile: SecurityProfile) -> Result<SecurityProfile, DataSphereError> {
+    let mut storage = storage().write();
+    let storage = storage.as_mut().ok_or(DataSphereError::NotInitialized)?;
+    storage.add_security_profile(profile)
+}
+
+/// Replace a user security profile
+#[tauri::command]
+pub fn update_security_profile(
+    profile: SecurityProfile,
+) -> Result<SecurityProfile, DataSphereError> {
+    let mut storage = storage().write();
+    let storage = storage.as_mut().ok_or(DataSphereError::NotInitialized)?;
+    storage.update_security_profile(profile)
+}
+
+/// Delete a user security profile
+#[tauri::command]
+pub fn delete_security_profile(name: String) -> Result<(), DataSphereError> {
+    let mut storage = storage().write();
+    let storage = storage.as_mut().ok_or(DataSphereError::NotInitialized)?;
+    storage.delete_security_profile(&name)
+}
+
+/// Get trusted host keys; `auto_trusted_only` lists just those trusted on first use
+#[tauri::command]
+pub fn get_known_hosts(auto_trusted_only: Option<bool>) -> Result<Vec<KnownHost>, DataSphereError> {
+    let storage = storage().read();
+    let storage = storage.as_ref().ok_or(DataSphereError::NotInitialized)?;
+    let mut hosts = storage.get_known_hosts();
+    if auto_trusted_only.unwrap_or(false) {
+        hosts.retain(|k| k.auto_trusted);
+    }
+    Ok(hosts)
+}
+
+/// Trust a host key that was refused because the host was unknown
+///
+/// `fingerprint` must match the key the server presented, so the user
+/// accepts exactly the key they were shown.
+#[tauri::command]
+pub fn accept_host_key(
+    host: String,
+    port: u16,
+    fingerprint: String,
+) -> Result<KnownHost, DataSphereError> {
+    let entry = known_hosts::take_pending(&host, port, &fingerprint).ok_or_else(|| {
+        DataSphereError::NotFound(format!(
+            "Pending host key {} for {}:{}",
+            fingerprint, host, port
+        ))
+    })?;
+    let mut storage = storage().write();
+    let storage = storage.as_mut().ok_or(DataSphereError::NotInitialized)?;
+    storage.add_known_host(entry)
+}
+
+/// Forget the trusted keys of a host
+#[tauri::command]
+pub fn remove_known_host(host: String, port: u16) -> Result<usize, DataSphereError> {
+    let mut storage = storage().write();
+    let storage = storage.as_mut().ok_or(DataSphereError::NotInitialized)?;
+    storage.remove_known_host(&host, port)
+}
+
 /// Get all snippets
 #[tauri::command]
 pub fn get_snippets() -> Result<Vec<Snippet>, DataSphereError> {
@@ -52,6 +335,14 @@ pub fn get_snippets() -> Result<Vec<Snippet>, DataSphereError> {
     Ok(storage.get_snippets())
 }
 
+/// Search snippets by name, content, tags, description or language
+#[tauri::command]
+pub fn search_snippets(query: String) -> Result<Vec<Snippet>, DataSphereError> {
+    let storage = storage().read();
+    let storage = storage.as_ref().ok_or(DataSphereError::NotInitialized)?;
+    Ok(storage.search_snippets(&query))
+}
+
 /// Add a new snippet
 #[tauri::command]
 pub fn add_snippet(snippet: Snippet) -> Result<Snippet, DataSphereError> {
@@ -60,6 +351,326 @@ pub fn add_snippet(snippet: Snippet) -> Result<Snippet, DataSphereError> {
     storage.add_snippet(snippet)
 }
 
+/// Fill in a snippet's `{{name}}` placeholders
+///
+/// With a `session_id`, `{{host}}` and `{{user}}` default to the session's
+/// host; `vars` take precedence. Placeholders without a value are kept and
+/// listed in `unfilled`.
+#[tauri::command]
+pub fn render_snippet(
+    snippet_id: Uuid,
+    mut vars: HashMap<String, String>,
+    session_id: Option<Uuid>,
+) -> Result<RenderedSnippet, DataSphereError> {
+    let storage = storage().read();
+    let storage = storage.as_ref().ok_or(DataSphereError::NotInitialized)?;
+    let snippet = storage
+        .get_snippets()
+        .into_iter()
+        .find(|s| s.id == snippet_id)
+        .ok_or_else(|| DataSphereError::NotFound(format!("Snippet {}", snippet_id)))?;
+
+    if let Some(session_id) = session_id {
+        let host_id = crate::session::host_of(session_id)
+            .ok_or_else(|| DataSphereError::NotFound(format!("Session {}", session_id)))?;
+        if let Some(host) = storage.get_host(host_id) {
+            vars.entry(placeholders::HOST.to_string())
+                .or_insert(host.hostname);
+            vars.entry(placeholders::USER.to_string())
+                .or_insert(host.username);
+        }
+    }
+
+    Ok(placeholders::render(&snippet.content, &vars))
+}
+
+/// Get all vault entries, without private keys
+#[tauri::command]
+pub fn get_vault_entries() -> Result<Vec<VaultEntry>, DataSphereError> {
+    let storage = storage().read();
+    let storage = storage.as_ref().ok_or(DataSphereError::NotInitialized)?;
+    Ok(storage
+        .get_vault_entries()
+        .into_iter()
+        .map(VaultEntry::redacted)
+        .collect())
+}
+
+/// Get the secret of a vault entry, including an SSH key's private key
+#[tauri::command]
+pub fn get_vault_secret(id: Uuid) -> Result<String, DataSphereError> {
+    let storage = storage().read();
+    let storage = storage.as_ref().ok_or(DataSphereError::NotInitialized)?;
+    storage
+        .get_vault_entry(id)
+        .map(|entry| entry.secret)
+        .ok_or_else(|| DataSphereError::NotFound(format!("Vault entry {}", id)))
+}
+
+/// Search vault entries by name, username, URL, notes, folder or tags
+///
+/// Private keys are left out, as in `get_vault_entries`.
+#[tauri::command]
+pub fn search_vault(query: String) -> Result<Vec<VaultEntry>, DataSphereError> {
+    let storage = storage().read();
+    let storage = storage.as_ref().ok_or(DataSphereError::NotInitialized)?;
+    Ok(storage
+        .search_vault(&query)
+        .into_iter()
+        .map(VaultEntry::redacted)
+        .collect())
+}
+
+/// Search hosts, snippets and vault entries at once
+///
+/// Private keys are left out, as in `get_vault_entries`.
+#[tauri::command]
+pub fn global_search(query: String) -> Result<GlobalSearchResults, DataSphereError> {
+    let storage = storage().read();
+    let storage = storage.as_ref().ok_or(DataSphereError::NotInitialized)?;
+    let mut results = storage.global_search(&query);
+    results.vault = results
+        .vault
+        .into_iter()
+        .map(VaultEntry::redacted)
+        .collect();
+    Ok(results)
+}
+
+/// Add a new vault entry
+#[tauri::command]
+pub fn add_vault_entry(entry: NewVaultEntry) -> Result<VaultEntry, DataSphereError> {
+    let mut storage = storage().write();
+    let storage = storage.as_mut().ok_or(DataSphereError::NotInitialized)?;
+    storage
+        .add_vault_entry(VaultEntry::from_new(entry))
+        .map(VaultEntry::redacted)
+}
+
+/// Update an existing vault entry
+///
+/// An SSH key entry sent without its private key keeps the stored one.
+#[tauri::command]
+pub fn update_vault_entry(entry: VaultEntry) -> Result<VaultEntry, DataSphereError> {
+    let mut storage = storage().write();
+    let storage = storage.as_mut().ok_or(DataSphereError::NotInitialized)?;
+    storage.update_vault_entry(entry).map(VaultEntry::redacted)
+}
+
+/// Move a vault entry to the trash, or delete it for good with `permanent`
+#[tauri::command]
+pub fn delete_vault_entry(id: Uuid, permanent: Option<bool>) -> Result<(), DataSphereError> {
+    let mut storage = storage().write();
+    let storage = storage.as_mut().ok_or(DataSphereError::NotInitialized)?;
+    storage.delete_vault_entry(id, permanent.unwrap_or(false))
+}
+
+/// Get trashed hosts and vault entries, most recently deleted first
+#[tauri::command]
+pub fn get_trash() -> Result<Vec<TrashedItem>, DataSphereError> {
+    let storage = storage().read();
+    let storage = storage.as_ref().ok_or(DataSphereError::NotInitialized)?;
+    Ok(storage.get_trash())
+}
+
+/// Restore a trashed host or vault entry
+#[tauri::command]
+pub fn restore_item(id: Uuid) -> Result<TrashItem, DataSphereError> {
+    let mut storage = storage().write();
+    let storage = storage.as_mut().ok_or(DataSphereError::NotInitialized)?;
+    storage.restore_item(id)
+}
+
+/// Delete trashed items for good, only those older than `older_than_days`
+/// if given; returns how many were deleted
+#[tauri::command]
+pub fn purge_trash(older_than_days: Option<u32>) -> Result<usize, DataSphereError> {
+    let mut storage = storage().write();
+    let storage = storage.as_mut().ok_or(DataSphereError::NotInitialized)?;
+    storage.purge_trash(older_than_days)
+}
+
+/// Delete a vault entry and overwrite the old vault file on disk
+#[tauri::command]
+pub fn secure_delete_vault_entry(id: Uuid) -> Result<(), DataSphereError> {
+    let mut storage = storage().write();
+    let storage = storage.as_mut().ok_or(DataSphereError::NotInitialized)?;
+    storage.secure_delete_vault_entry(id)
+}
+
+/// Report weak, reused and stale vault secrets by entry id
+///
+/// Entries not updated within `max_age_days` (default 180) count as stale.
+#[tauri::command]
+pub fn audit_vault_security(
+    max_age_days: Option<u32>,
+) -> Result<VaultAuditReport, DataSphereError> {
+    let mut options = VaultAuditOptions::default();
+    if let Some(days) = max_age_days {
+        options.max_age_days = days;
+    }
+
+    let storage = storage().read();
+    let storage = storage.as_ref().ok_or(DataSphereError::NotInitialized)?;
+    Ok(storage.audit_vault(&options))
+}
+
+/// Generate a random password
+#[tauri::command]
+pub fn generate_password(
+    length: usize,
+    opts: Option<PasswordOptions>,
+) -> Result<String, DataSphereError> {
+    Ok(password::generate(length, &opts.unwrap_or_default())?.to_string())
+}
+
+/// Estimate how hard a secret is to guess; nothing is stored
+#[tauri::command]
+pub fn estimate_strength(secret: String) -> StrengthEstimate {
+    let secret = Zeroizing::new(secret);
+    password::estimate_strength(&secret)
+}
+
+/// Get the names of all vault folders
+#[tauri::command]
+pub fn get_vault_folders() -> Result<Vec<String>, DataSphereError> {
+    let storage = storage().read();
+    let storage = storage.as_ref().ok_or(DataSphereError::NotInitialized)?;
+    Ok(storage.get_vault_folders())
+}
+
+/// Rename a vault folder, returning the number of entries moved
+#[tauri::command]
+pub fn rename_vault_folder(old: String, new: String) -> Result<usize, DataSphereError> {
+    let mut storage = storage().write();
+    let storage = storage.as_mut().ok_or(DataSphereError::NotInitialized)?;
+    storage.rename_vault_folder(&old, &new)
+}
+
+/// Move vault entries into a folder; no folder moves them to the top level
+#[tauri::command]
+pub fn move_vault_entries(
+    ids: Vec<Uuid>,
+    target_folder: Option<String>,
+) -> Result<usize, DataSphereError> {
+    let mut storage = storage().write();
+    let storage = storage.as_mut().ok_or(DataSphereError::NotInitialized)?;
+    storage.move_vault_entries(&ids, target_folder.as_deref())
+}
+
+/// Delete a vault folder, moving its entries to `reassign_to`
+#[tauri::command]
+pub fn delete_vault_folder(
+    name: String,
+    reassign_to: Option<String>,
+) -> Result<usize, DataSphereError> {
+    let mut storage = storage().write();
+    let storage = storage.as_mut().ok_or(DataSphereError::NotInitialized)?;
+    storage.delete_vault_folder(&name, reassign_to.as_deref())
+}
+
+/// Import a PuTTY `.ppk` key into the vault as an OpenSSH key
+#[tauri::command]
+pub fn import_ppk_key(
+    path: String,
+    passphrase: Option<String>,
+) -> Result<VaultEntry, DataSphereError> {
+    let content = std::fs::read_to_string(&path)?;
+    let key = ppk::parse_ppk(&content, passphrase.as_deref())
+        .map_err(|e| DataSphereError::Key(e.to_string()))?;
+
+    let name = if key.comment.is_empty() {
+        std::path::Path::new(&path)
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| "Imported key".to_string())
+    } else {
+        key.comment.clone()
+    };
+
+    let entry = VaultEntry::from_new(NewVaultEntry {
+        name,
+        entry_type: VaultEntryType::SshKey,
+        username: None,
+        secret: key.openssh_private.to_string(),
+        public_key: Some(key.public_key),
+        url: None,
+        notes: Some(format!("Imported from {} ({})", path, key.algorithm)),
+        folder: None,
+        tags: vec![],
+    });
+
+    let mut storage = storage().write();
+    let storage = storage.as_mut().ok_or(DataSphereError::NotInitialized)?;
+    storage.add_vault_entry(entry).map(VaultEntry::redacted)
+}
+
+/// Generate an SSH key pair and keep the private key in the vault
+///
+/// Only the public key and fingerprint are returned. With a passphrase
+/// the stored private key is encrypted with it as well.
+#[tauri::command]
+pub async fn generate_ssh_key(
+    algorithm: KeyAlgorithm,
+    comment: Option<String>,
+    passphrase: Option<String>,
+) -> Result<GeneratedSshKey, DataSphereError> {
+    if storage().read().is_none() {
+        return Err(DataSphereError::NotInitialized);
+    }
+    let comment = comment.unwrap_or_default();
+    let passphrase = passphrase.map(Zeroizing::new);
+    let protected = passphrase.as_ref().is_some_and(|p| !p.is_empty());
+
+    // RSA generation can take seconds
+    let generate_algorithm = algorithm.clone();
+    let generate_comment = comment.clone();
+    let key = tokio::task::spawn_blocking(move || {
+        keygen::generate(
+            &generate_algorithm,
+            &generate_comment,
+            passphrase.as_deref().map(|p| p.as_str()),
+        )
+    })
+    .await
+    .map_err(|e| DataSphereError::Key(e.to_string()))?
+    .map_err(|e| DataSphereError::Key(e.to_string()))?;
+
+    let name = if comment.trim().is_empty() {
+        format!("{} key", algorithm.ssh_name())
+    } else {
+        comment.trim().to_string()
+    };
+    let entry = VaultEntry::from_new(NewVaultEntry {
+        name,
+        entry_type: VaultEntryType::SshKey,
+        username: None,
+        secret: key.private_pem.to_string(),
+        public_key: Some(key.public_key.clone()),
+        url: None,
+        notes: Some(format!(
+            "Generated {} key{}",
+            algorithm.ssh_name(),
+            if protected {
+                ", passphrase protected"
+            } else {
+                ""
+            }
+        )),
+        folder: None,
+        tags: vec![],
+    });
+
+    let mut storage = storage().write();
+    let storage = storage.as_mut().ok_or(DataSphereError::NotInitialized)?;
+    let entry = storage.add_vault_entry(entry)?;
+    Ok(GeneratedSshKey {
+        entry_id: entry.id,
+        public_key: key.public_key,
+        fingerprint: key.fingerprint,
+    })
+}
+
 /// Get application settings
 #[tauri::command]
 pub fn get_settings() -> Result<Settings, DataSphereError> {
@@ -75,3 +686,164 @@ pub fn update_settings(settings: Settings) -> Result<Settings, DataSphereError>
     let storage = storage.as_mut().ok_or(DataSphereError::NotInitialized)?;
     storage.update_settings(settings)
 }
+
+/// Write an encrypted backup of the whole workspace to `path`
+#[tauri::command]
+pub async fn backup_workspace(path: String, password: String) -> Result<(), DataSphereError> {
+    let password = Zeroizing::new(password);
+    let mut snapshot = {
+        let storage = storage().read();
+        let storage = storage.as_ref().ok_or(DataSphereError::NotInitialized)?;
+        storage.snapshot()
+    };
+
+    let mut sessions = crate::session::manager().read().get_sessions();
+    sessions.sort_by_key(|s| s.created_at);
+    snapshot.sessions = sessions
+        .into_iter()
+        .map(|s| SessionLayout {
+            host_id: s.host_id,
+            name: s.name,
+        })
+        .collect();
+
+    // Key derivation takes a while; keep it off the async workers
+    tokio::task::spawn_blocking(move || {
+        backup::write_backup(Path::new(&path), &snapshot, &password)
+    })
+    .await
+    .map_err(|e| DataSphereError::Encryption(e.to_string()))??;
+
+    tracing::info!("Workspace backup written");
+    Ok(())
+}
+
+/// Restore a workspace backup, merging with or replacing the current data
+#[tauri::command]
+pub async fn restore_workspace(
+    path: String,
+    password: String,
+    mode: Option<RestoreMode>,
+) -> Result<RestoreReport, DataSphereError> {
+    let password = Zeroizing::new(password);
+    let snapshot =
+        tokio::task::spawn_blocking(move || backup::read_backup(Path::new(&path), &password))
+            .await
+            .map_err(|e| DataSphereError::Decryption(e.to_string()))??;
+
+    let mode = mode.unwrap_or_default();
+    tracing::info!(
+        "Restoring workspace backup from Nexus {} ({:?})",
+        snapshot.app_version,
+        mode
+    );
+
+    let mut storage = storage().write();
+    let storage = storage.as_mut().ok_or(DataSphereError::NotInitialized)?;
+    storage.restore_snapshot(snapshot, mode)
+}
+
+/// Export hosts, groups, snippets, vault entries and profiles, secrets included
+///
+/// The file is encrypted with `password`, not the master password, and
+/// written to the downloads folder (or the data directory without one).
+/// Returns its path.
+#[tauri::command]
+pub async fn export_vault(password: String) -> Result<PathBuf, DataSphereError> {
+    let password = Zeroizing::new(password);
+    let (data, data_dir) = {
+        let storage = storage().read();
+        let storage = storage.as_ref().ok_or(DataSphereError::NotInitialized)?;
+        (storage.export_data(), storage.data_dir().to_path_buf())
+    };
+
+    let dir = directories::UserDirs::new()
+        .and_then(|dirs| dirs.download_dir().map(Path::to_path_buf))
+        .unwrap_or_else(|| data_dir.join("exports"));
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join(format!(
+        "nexus-export-{}.{}",
+        chrono::Local::now().format("%Y%m%d-%H%M%S"),
+        export::EXTENSION
+    ));
+
+    // Key derivation takes a while; keep it off the async workers
+    let target = path.clone();
+    tokio::task::spawn_blocking(move || export::write_export(&target, &data, &password))
+        .await
+        .map_err(|e| DataSphereError::Encryption(e.to_string()))??;
+
+    tracing::info!("Vault exported to {}", path.display());
+    Ok(path)
+}
+
+/// Import an export from `export_vault`, merging it into the current data
+///
+/// `policy` decides what happens to items that already exist; by default
+/// they are skipped.
+#[tauri::command]
+pub async fn import_vault(
+    path: String,
+    password: String,
+    policy: Option<ConflictPolicy>,
+) -> Result<ImportReport, DataSphereError> {
+    let password = Zeroizing::new(password);
+    let data =
+        tokio::task::spawn_blocking(move || export::read_export(Path::new(&path), &password))
+            .await
+            .map_err(|e| DataSphereError::Decryption(e.to_string()))??;
+
+    let policy = policy.unwrap_or_default();
+    tracing::info!(
+        "Importing vault export from Nexus {} ({:?})",
+        data.app_version,
+        policy
+    );
+
+    let mut storage = storage().write();
+    let storage = storage.as_mut().ok_or(DataSphereError::NotInitialized)?;
+    storage.import_data(data, policy)
+}
+
+/// Unlock the storage with the master password, setting it on first use
+#[tauri::command]
+pub async fn unlock_datasphere(password: String) -> Result<AppStatus, DataSphereError> {
+    let password = Zeroizing::new(password);
+    // Key derivation takes a while; keep it off the async workers
+    tokio::task::spawn_blocking(move || super::unlock(&password))
+        .await
+        .map_err(|e| DataSphereError::Decryption(e.to_string()))??;
+    crate::plugins::apply_saved_state();
+    Ok(super::app_status())
+}
+
+/// Re-encrypt all data under a new master password
+#[tauri::command]
+pub async fn change_master_password(old: String, new: String) -> Result<(), DataSphereError> {
+    let (old, new) = (Zeroizing::new(old), Zeroizing::new(new));
+    tokio::task::spawn_blocking(move || {
+        let mut storage = storage().write();
+        let storage = storage.as_mut().ok_or(DataSphereError::NotInitialized)?;
+        storage.change_master_password(&old, &new)
+    })
+    .await
+    .map_err(|e| DataSphereError::Encryption(e.to_string()))?
+}
+
+/// Lock the storage, dropping decrypted data from memory
+#[tauri::command]
+pub fn lock_datasphere() {
+    super::lock();
+}
+
+/// Whether the storage is waiting for the master password
+#[tauri::command]
+pub fn is_datasphere_locked() -> bool {
+    super::is_locked()
+}
+
+/// Report application health, including data files that failed to load
+#[tauri::command]
+pub fn get_app_status() -> AppStatus {
+    super::app_status()
+}
@@ -14,6 +14,8 @@ use argon2::{
 };
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
 use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
 use zeroize::Zeroizing;
 
 use super::DataSphereError;
@@ -36,6 +38,12 @@ pub struct DataSphereCrypto {
     cipher: ChaCha20Poly1305,
 }
 
+impl std::fmt::Debug for DataSphereCrypto {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DataSphereCrypto").finish_non_exhaustive()
+    }
+}
+
 impl DataSphereCrypto {
     /// Create a new crypto instance with a derived key from password
     pub fn from_password(password: &str, salt: &[u8]) -> Result<Self, DataSphereError> {
@@ -58,6 +66,30 @@ impl DataSphereCrypto {
         result
     }
 
+    /// Load a base64 key file, generating it (mode 600) on first use
+    pub fn load_or_create_key_file(path: &Path) -> Result<[u8; 32], DataSphereError> {
+        if path.exists() {
+            let encoded = fs::read_to_string(path)?;
+            let bytes = BASE64
+                .decode(encoded.trim())
+                .map_err(|e| DataSphereError::Key(e.to_string()))?;
+            return bytes
+                .try_into()
+                .map_err(|_| DataSphereError::Key(format!("Invalid key in {}", path.display())));
+        }
+
+        let key = Self::generate_key();
+        fs::write(path, BASE64.encode(key))?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let _ = fs::set_permissions(path, fs::Permissions::from_mode(0o600));
+        }
+
+        Ok(key)
+    }
+
     /// Generate a new random salt (16 bytes)
     pub fn generate_salt() -> [u8; 16] {
         let mut salt = [0u8; 16];
@@ -164,6 +196,8 @@ pub struct VaultFile {
 
 impl VaultFile {
     pub const MAGIC: &'static str = "NEXUS_VAULT";
+    /// Marks a portable export rather than a local data file
+    pub const EXPORT_MAGIC: &'static str = "NEXUS_EXPORT";
     pub const VERSION: u8 = 1;
 
     /// Create a new vault file
@@ -176,6 +210,30 @@ impl VaultFile {
         }
     }
 
+    /// Create a portable export file
+    pub fn export(salt: &[u8], data: EncryptedData) -> Self {
+        Self {
+            magic: Self::EXPORT_MAGIC.to_string(),
+            ..Self::new(salt, data)
+        }
+    }
+
+    /// Validate an export file
+    pub fn validate_export(&self) -> Result<(), DataSphereError> {
+        if self.magic != Self::EXPORT_MAGIC {
+            return Err(DataSphereError::InvalidInput(
+                "Not a Nexus export file".to_string(),
+            ));
+        }
+        if self.version != Self::VERSION {
+            return Err(DataSphereError::InvalidInput(format!(
+                "Unsupported export version: {}",
+                self.version
+            )));
+        }
+        Ok(())
+    }
+
     /// Validate vault file
     pub fn validate(&self) -> Result<(), DataSphereError> {
         if self.magic != Self::MAGIC {
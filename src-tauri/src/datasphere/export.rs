@@ -0,0 +1,166 @@
+//! Portable vault exports
+//!
+//! An export bundles hosts (passwords and key paths included), groups,
+//! snippets, vault entries and user security profiles into one `VaultFile`
+//! marked `NEXUS_EXPORT`. It is encrypted with its own password rather than
+//! the master key, so it can be imported on another machine. Unlike a
+//! workspace backup it carries no settings or open sessions.
+
+use super::{DataSphereCrypto, DataSphereError, Host, HostGroup, Snippet, VaultEntry, VaultFile};
+use crate::ssh::SecurityProfile;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use uuid::Uuid;
+use zeroize::Zeroizing;
+
+/// File extension of exports
+pub const EXTENSION: &str = "nexusexport";
+
+/// The collections in an export
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VaultExport {
+    /// App version that wrote the export
+    pub app_version: String,
+    pub hosts: Vec<Host>,
+    pub groups: Vec<HostGroup>,
+    pub snippets: Vec<Snippet>,
+    pub vault: Vec<VaultEntry>,
+    #[serde(default)]
+    pub profiles: Vec<SecurityProfile>,
+}
+
+/// What to do with an imported item whose id already exists
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictPolicy {
+    /// Keep the local item
+    #[default]
+    Skip,
+    /// Replace the local item
+    Overwrite,
+    /// Keep both, adding the imported one under a new id and name
+    Rename,
+}
+
+/// What an import changed
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImportReport {
+    pub added: usize,
+    pub overwritten: usize,
+    pub renamed: usize,
+    pub skipped: usize,
+}
+
+/// Encrypt `export` with `password` and write it to `path`
+pub fn write_export(
+    path: &Path,
+    export: &VaultExport,
+    password: &str,
+) -> Result<(), DataSphereError> {
+    if password.is_empty() {
+        return Err(DataSphereError::InvalidInput(
+            "Export password must not be empty".to_string(),
+        ));
+    }
+
+    let salt = DataSphereCrypto::generate_salt();
+    let crypto = DataSphereCrypto::from_password(password, &salt)?;
+    let plaintext = Zeroizing::new(serde_json::to_vec(export)?);
+    let file = VaultFile::export(&salt, crypto.encrypt(&plaintext)?);
+
+    let tmp = path.with_extension(format!("{}.tmp", EXTENSION));
+    fs::write(&tmp, serde_json::to_vec_pretty(&file)?)?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// Read and decrypt the export at `path`
+pub fn read_export(path: &Path, password: &str) -> Result<VaultExport, DataSphereError> {
+    let file: VaultFile = serde_json::from_slice(&fs::read(path)?)
+        .map_err(|_| DataSphereError::InvalidInput("Not a Nexus export file".to_string()))?;
+    file.validate_export()?;
+
+    let crypto = DataSphereCrypto::from_password(password, &file.get_salt()?)?;
+    let plaintext = Zeroizing::new(crypto.decrypt(&file.data).map_err(|_| {
+        DataSphereError::Decryption("Wrong password or corrupted export".to_string())
+    })?);
+    Ok(serde_json::from_slice(&plaintext)?)
+}
+
+/// Merge `incoming` into `local` by id according to `policy`
+///
+/// `key` gives access to an item's id and name, which `Rename` changes.
+pub(crate) fn merge<T>(
+    local: &mut HashMap<Uuid, T>,
+    incoming: Vec<T>,
+    policy: ConflictPolicy,
+    key: impl Fn(&mut T) -> (&mut Uuid, &mut String),
+    report: &mut ImportReport,
+) {
+    for mut item in incoming {
+        let (id, name) = key(&mut item);
+        if !local.contains_key(id) {
+            local.insert(*id, item);
+            report.added += 1;
+            continue;
+        }
+        match policy {
+            ConflictPolicy::Skip => report.skipped += 1,
+            ConflictPolicy::Overwrite => {
+                local.insert(*id, item);
+                report.overwritten += 1;
+            }
+            ConflictPolicy::Rename => {
+                *id = Uuid::new_v4();
+                *name = format!("{} (imported)", name);
+                local.insert(*id, item);
+                report.renamed += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn merged(policy: ConflictPolicy) -> (HashMap<Uuid, Host>, ImportReport, Uuid) {
+        let local_host = Host::new("web".into(), "a".into(), "root".into());
+        let id = local_host.id;
+        let mut local = HashMap::from([(id, local_host.clone())]);
+        let mut incoming = local_host;
+        incoming.hostname = "b".into();
+        let new = Host::new("db".into(), "c".into(), "root".into());
+
+        let mut report = ImportReport::default();
+        merge(
+            &mut local,
+            vec![incoming, new],
+            policy,
+            |h| (&mut h.id, &mut h.name),
+            &mut report,
+        );
+        (local, report, id)
+    }
+
+    #[test]
+    fn test_merge_policies() {
+        let (local, report, id) = merged(ConflictPolicy::Skip);
+        assert_eq!((report.added, report.skipped), (1, 1));
+        assert_eq!(local[&id].hostname, "a");
+
+        let (local, report, id) = merged(ConflictPolicy::Overwrite);
+        assert_eq!((report.added, report.overwritten), (1, 1));
+        assert_eq!(local[&id].hostname, "b");
+
+        let (local, report, id) = merged(ConflictPolicy::Rename);
+        assert_eq!((report.added, report.renamed), (1, 1));
+        assert_eq!(local.len(), 3);
+        assert_eq!(local[&id].hostname, "a");
+        assert!(local
+            .values()
+            .any(|h| h.name == "web (imported)" && h.hostname == "b"));
+    }
+}
@@ -6,41 +6,129 @@
 //! - Snippets
 //! - Settings
 //!
-//! Uses ChaCha20-Poly1305 for encryption and Argon2id for key derivation
+//! Uses ChaCha20-Poly1305 for encryption and Argon2id for key derivation.
+//! The storage stays locked, and `storage()` empty, until `unlock` is
+//! called with the master password.
 
+pub mod backup;
 pub mod commands;
 pub mod crypto;
+pub mod export;
 mod models;
+pub mod openssh;
+pub mod openssh_import;
+pub mod password;
+pub mod placeholders;
+pub mod search;
 mod storage;
+pub mod validation;
+pub mod vault_audit;
 
+pub use backup::{RestoreMode, RestoreReport};
 pub use crypto::{DataSphereCrypto, EncryptedData, VaultFile};
+pub use export::{ConflictPolicy, ImportReport};
 pub use models::*;
-pub use storage::DataSphereStorage;
+pub use placeholders::RenderedSnippet;
+pub use search::GlobalSearchResults;
+pub use storage::{DataSet, DataSphereStorage};
+pub use validation::FieldError;
+pub use vault_audit::{VaultAuditOptions, VaultAuditReport};
 
 use parking_lot::RwLock;
-use once_cell::sync::Lazy;
-use tauri::AppHandle;
+use once_cell::sync::{Lazy, OnceCell};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
 
-/// Global DataSphere storage instance
+/// Global DataSphere storage instance; `None` while locked
 static DATASPHERE: Lazy<RwLock<Option<DataSphereStorage>>> = Lazy::new(|| RwLock::new(None));
 
-/// Initialize DataSphere
+/// Directory the data files live in
+static DATA_DIR: OnceCell<PathBuf> = OnceCell::new();
+
+/// Initialize DataSphere; the storage stays locked until `unlock`
 pub fn init(app: &AppHandle) -> Result<(), DataSphereError> {
-    let storage = DataSphereStorage::new(app)?;
-    *DATASPHERE.write() = Some(storage);
-    tracing::info!("DataSphere initialized");
+    let dir = app.path().app_data_dir()?;
+    std::fs::create_dir_all(&dir)?;
+    let _ = DATA_DIR.set(dir);
+    tracing::info!("DataSphere initialized (locked)");
+    Ok(())
+}
+
+/// Decrypt the storage with the master password
+///
+/// Slow by design (Argon2id); call it off the async workers. Unlocking an
+/// unlocked storage does nothing.
+pub fn unlock(password: &str) -> Result<(), DataSphereError> {
+    if !is_locked() {
+        return Ok(());
+    }
+    let dir = DATA_DIR.get().ok_or(DataSphereError::NotInitialized)?;
+    let storage = DataSphereStorage::open(dir.clone(), password)?;
+    let mut slot = DATASPHERE.write();
+    if slot.is_none() {
+        *slot = Some(storage);
+        tracing::info!("DataSphere unlocked");
+    }
     Ok(())
 }
 
+/// Drop the decrypted data and key from memory
+pub fn lock() {
+    if let Some(mut storage) = DATASPHERE.write().take() {
+        storage.wipe_secrets();
+        tracing::info!("DataSphere locked");
+    }
+}
+
+pub fn is_locked() -> bool {
+    DATASPHERE.read().is_none()
+}
+
 /// Get the DataSphere storage instance
 pub fn storage() -> &'static RwLock<Option<DataSphereStorage>> {
     &DATASPHERE
 }
 
+/// A data file that couldn't be parsed at startup
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoadFailure {
+    pub file: String,
+    pub error: String,
+    /// Name the unreadable file was moved to, next to the original
+    pub preserved_as: String,
+}
+
+/// Overall application health, for the frontend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppStatus {
+    pub version: String,
+    pub datasphere_initialized: bool,
+    /// Storage is waiting for the master password
+    pub locked: bool,
+    /// Data files that were set aside; their categories started empty
+    pub load_failures: Vec<LoadFailure>,
+}
+
+/// Current application status
+pub fn app_status() -> AppStatus {
+    let storage = DATASPHERE.read();
+    AppStatus {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        datasphere_initialized: DATA_DIR.get().is_some(),
+        locked: storage.is_none(),
+        load_failures: storage
+            .as_ref()
+            .map(|s| s.load_failures().to_vec())
+            .unwrap_or_default(),
+    }
+}
+
 /// DataSphere Error types
 #[derive(Debug, thiserror::Error)]
 pub enum DataSphereError {
-    #[error("Storage not initialized")]
+    /// The storage hasn't been unlocked, or couldn't be set up
+    #[error("Storage is locked")]
     NotInitialized,
     #[error("Encryption error: {0}")]
     Encryption(String),
@@ -52,8 +140,15 @@ pub enum DataSphereError {
     Serialization(#[from] serde_json::Error),
     #[error("Item not found: {0}")]
     NotFound(String),
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
     #[error("Tauri error: {0}")]
     Tauri(String),
+    #[error("Key error: {0}")]
+    Key(String),
+    /// Host input failed validation; serialized with the field errors
+    #[error("Invalid host: {}", validation::summarize(.0))]
+    Validation(Vec<FieldError>),
 }
 
 impl From<tauri::Error> for DataSphereError {
@@ -62,11 +157,34 @@ impl From<tauri::Error> for DataSphereError {
     }
 }
 
+impl crate::utils::ErrorCode for DataSphereError {
+    fn code(&self) -> &'static str {
+        match self {
+            DataSphereError::NotInitialized => "locked",
+            DataSphereError::NotFound(_) => "not_found",
+            DataSphereError::InvalidInput(_) => "invalid_input",
+            DataSphereError::Validation(_) => "validation",
+            _ => "error",
+        }
+    }
+}
+
 impl serde::Serialize for DataSphereError {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
     {
-        serializer.serialize_str(&self.to_string())
+        use serde::ser::SerializeStruct;
+
+        match self {
+            DataSphereError::Validation(errors) => {
+                let mut error = serializer.serialize_struct("DataSphereError", 3)?;
+                error.serialize_field("code", "validation")?;
+                error.serialize_field("message", &self.to_string())?;
+                error.serialize_field("fields", errors)?;
+                error.end()
+            }
+            _ => serializer.serialize_str(&self.to_string()),
+        }
     }
 }
@@ -1,7 +1,13 @@
 //! DataSphere Data Models
 
+use crate::mcp::McpConfig;
+use crate::ssh::{
+    AddressFamily, AuthMethod, SshConfig, Transport, TrustPolicy, DEFAULT_KEEPALIVE_MAX,
+};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use uuid::Uuid;
+use zeroize::Zeroize;
 use chrono::{DateTime, Utc};
 
 /// SSH Host configuration
@@ -21,6 +27,40 @@ pub struct Host {
     pub icon: Option<String>,
     pub color: Option<String>,
     pub notes: Option<String>,
+    /// Directory the file browser opens in
+    #[serde(default)]
+    pub initial_path: Option<String>,
+    /// Strip the login banner/MOTD from terminal output
+    #[serde(default)]
+    pub suppress_motd: bool,
+    /// Run before helper commands, for servers whose exec `PATH` lacks tools
+    #[serde(default)]
+    pub exec_profile: Option<String>,
+    /// Reach the server through a local socket or pipe instead of TCP
+    #[serde(default)]
+    pub transport: Transport,
+    /// Terminal output encoding for legacy hosts; UTF-8 when unset
+    #[serde(default)]
+    pub encoding: Option<String>,
+    /// Terminal input encoding; defaults to `encoding`
+    #[serde(default)]
+    pub send_encoding: Option<String>,
+    /// Turn bare CR or LF line endings into CRLF
+    #[serde(default)]
+    pub normalize_newlines: bool,
+    /// Name of the security profile to connect with
+    #[serde(default)]
+    pub security_profile: Option<String>,
+    /// Jump hosts as in OpenSSH `ProxyJump`: comma-separated stored host
+    /// names or `[user@]host[:port]`
+    #[serde(default)]
+    pub proxy_jump: Option<String>,
+    /// Environment variables for terminal shells
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    /// Commands typed into each terminal shell once it shows a prompt
+    #[serde(default)]
+    pub init_commands: Vec<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub last_connected: Option<DateTime<Utc>>,
@@ -44,6 +84,17 @@ impl Host {
             icon: None,
             color: None,
             notes: None,
+            initial_path: None,
+            suppress_motd: false,
+            exec_profile: None,
+            transport: Transport::Tcp,
+            encoding: None,
+            send_encoding: None,
+            normalize_newlines: false,
+            security_profile: None,
+            proxy_jump: None,
+            env: HashMap::new(),
+            init_commands: vec![],
             created_at: now,
             updated_at: now,
             last_connected: None,
@@ -67,11 +118,58 @@ impl Host {
             icon: new.icon,
             color: new.color,
             notes: new.notes,
+            initial_path: new.initial_path,
+            suppress_motd: new.suppress_motd,
+            exec_profile: new.exec_profile,
+            transport: new.transport,
+            encoding: new.encoding,
+            send_encoding: new.send_encoding,
+            normalize_newlines: new.normalize_newlines,
+            security_profile: new.security_profile,
+            proxy_jump: new.proxy_jump,
+            env: new.env,
+            init_commands: new.init_commands,
             created_at: now,
             updated_at: now,
             last_connected: None,
         }
     }
+
+    /// Connection parameters stored for this host
+    ///
+    /// The security profile is looked up by name in storage, so it is
+    /// applied by `DataSphereStorage::host_ssh_config` rather than here.
+    pub fn ssh_config(&self) -> SshConfig {
+        let auth_method = match self.auth_type {
+            AuthType::Password => AuthMethod::Password(self.password.clone().unwrap_or_default()),
+            AuthType::PrivateKey => AuthMethod::PrivateKey {
+                key_path: self.private_key.clone().unwrap_or_default(),
+                passphrase: self.passphrase.clone(),
+            },
+            AuthType::Agent => AuthMethod::Agent,
+        };
+
+        SshConfig {
+            host: self.hostname.clone(),
+            port: self.port,
+            username: self.username.clone(),
+            auth_method,
+            timeout_seconds: 30,
+            address_family: AddressFamily::default(),
+            suppress_motd: self.suppress_motd,
+            exec_profile: self.exec_profile.clone(),
+            transport: self.transport.clone(),
+            encoding: self.encoding.clone(),
+            send_encoding: self.send_encoding.clone(),
+            normalize_newlines: self.normalize_newlines,
+            security: None,
+            keepalive_seconds: None,
+            keepalive_max: DEFAULT_KEEPALIVE_MAX,
+            jump_hosts: Vec::new(),
+            env: self.env.clone(),
+            init_commands: self.init_commands.clone(),
+        }
+    }
 }
 
 /// New host data (for creating hosts without id/timestamps)
@@ -90,6 +188,82 @@ pub struct NewHost {
     pub icon: Option<String>,
     pub color: Option<String>,
     pub notes: Option<String>,
+    #[serde(default)]
+    pub initial_path: Option<String>,
+    #[serde(default)]
+    pub suppress_motd: bool,
+    #[serde(default)]
+    pub exec_profile: Option<String>,
+    #[serde(default)]
+    pub transport: Transport,
+    #[serde(default)]
+    pub encoding: Option<String>,
+    #[serde(default)]
+    pub send_encoding: Option<String>,
+    #[serde(default)]
+    pub normalize_newlines: bool,
+    #[serde(default)]
+    pub security_profile: Option<String>,
+    #[serde(default)]
+    pub proxy_jump: Option<String>,
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    #[serde(default)]
+    pub init_commands: Vec<String>,
+}
+
+/// Changes applied to many hosts at once
+///
+/// Unset fields are left alone. For `group_id`, `color` and `icon` an
+/// explicit `null` clears the value.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HostPatch {
+    /// Replace all tags
+    #[serde(default)]
+    pub set_tags: Option<Vec<String>>,
+    #[serde(default)]
+    pub add_tags: Vec<String>,
+    #[serde(default)]
+    pub remove_tags: Vec<String>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub group_id: Option<Option<Uuid>>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub color: Option<Option<String>>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub icon: Option<Option<String>>,
+}
+
+impl HostPatch {
+    pub fn apply(&self, host: &mut Host) {
+        if let Some(tags) = &self.set_tags {
+            host.tags = tags.clone();
+        }
+        for tag in &self.add_tags {
+            if !host.tags.contains(tag) {
+                host.tags.push(tag.clone());
+            }
+        }
+        host.tags.retain(|t| !self.remove_tags.contains(t));
+
+        if let Some(group_id) = self.group_id {
+            host.group_id = group_id;
+        }
+        if let Some(color) = &self.color {
+            host.color = color.clone();
+        }
+        if let Some(icon) = &self.icon {
+            host.icon = icon.clone();
+        }
+    }
+}
+
+/// Tell a missing field (`None`) apart from an explicit `null` (`Some(None)`)
+fn double_option<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
 }
 
 /// Authentication type for hosts
@@ -154,6 +328,106 @@ impl Snippet {
     }
 }
 
+/// Secret stored in the vault
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VaultEntry {
+    pub id: Uuid,
+    pub name: String,
+    pub entry_type: VaultEntryType,
+    pub username: Option<String>,
+    /// Password, token or private key (OpenSSH or PKCS#8 PEM for SSH keys)
+    pub secret: String,
+    /// OpenSSH `authorized_keys` line for SSH keys
+    pub public_key: Option<String>,
+    pub url: Option<String>,
+    pub notes: Option<String>,
+    pub folder: Option<String>,
+    pub tags: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl VaultEntry {
+    pub fn from_new(new: NewVaultEntry) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            name: new.name,
+            entry_type: new.entry_type,
+            username: new.username,
+            secret: new.secret,
+            public_key: new.public_key,
+            url: new.url,
+            notes: new.notes,
+            folder: new.folder,
+            tags: new.tags,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    /// The entry as returned by listings and searches
+    ///
+    /// The private key of an SSH key entry is left out, as it may be
+    /// unencrypted; `get_vault_secret` returns it.
+    pub fn redacted(mut self) -> Self {
+        if self.entry_type == VaultEntryType::SshKey {
+            self.secret.zeroize();
+        }
+        self
+    }
+}
+
+/// New vault entry data (for creating entries without id/timestamps)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewVaultEntry {
+    pub name: String,
+    pub entry_type: VaultEntryType,
+    pub username: Option<String>,
+    pub secret: String,
+    pub public_key: Option<String>,
+    pub url: Option<String>,
+    pub notes: Option<String>,
+    pub folder: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// A deleted host or vault entry waiting in the trash
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashedItem {
+    pub deleted_at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub item: TrashItem,
+}
+
+/// What a trashed item was before it was deleted
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "item", rename_all = "snake_case")]
+pub enum TrashItem {
+    Host(Box<Host>),
+    VaultEntry(Box<VaultEntry>),
+}
+
+impl TrashItem {
+    pub fn id(&self) -> Uuid {
+        match self {
+            TrashItem::Host(host) => host.id,
+            TrashItem::VaultEntry(entry) => entry.id,
+        }
+    }
+}
+
+/// Kind of secret held by a vault entry
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum VaultEntryType {
+    Password,
+    SshKey,
+    ApiKey,
+    Note,
+}
+
 /// Application settings
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Settings {
@@ -166,6 +440,47 @@ pub struct Settings {
     pub show_latency: bool,
     pub sync_enabled: bool,
     pub sync_provider: Option<SyncProvider>,
+    #[serde(default)]
+    pub retention: RetentionSettings,
+    #[serde(default)]
+    pub mcp: McpConfig,
+    /// How host keys of servers not seen before are handled
+    #[serde(default)]
+    pub host_key_policy: TrustPolicy,
+    /// Delete the partial file when a transfer is cancelled
+    #[serde(default = "default_remove_partial_transfers")]
+    pub remove_partial_transfers: bool,
+    /// Terminal output kept per session for repainting and export
+    #[serde(default = "default_scrollback_bytes")]
+    pub scrollback_bytes: usize,
+    /// Manifest ids of plugins the user turned off
+    #[serde(default)]
+    pub disabled_plugins: Vec<String>,
+    /// Largest remote file the editor opens
+    #[serde(default = "default_editor_max_file_bytes")]
+    pub editor_max_file_bytes: u64,
+    /// Encrypt new audit log entries
+    #[serde(default)]
+    pub audit_log_encrypted: bool,
+    /// Days deleted hosts and vault entries stay in the trash; forever when unset
+    #[serde(default = "default_trash_retention_days")]
+    pub trash_retention_days: Option<u32>,
+}
+
+fn default_remove_partial_transfers() -> bool {
+    true
+}
+
+fn default_editor_max_file_bytes() -> u64 {
+    crate::sftp::editor::EDITOR_MAX_BYTES
+}
+
+fn default_trash_retention_days() -> Option<u32> {
+    Some(30)
+}
+
+fn default_scrollback_bytes() -> usize {
+    crate::terminal::scrollback::SCROLLBACK_LIMIT
 }
 
 impl Default for Settings {
@@ -180,6 +495,51 @@ impl Default for Settings {
             show_latency: true,
             sync_enabled: false,
             sync_provider: None,
+            retention: RetentionSettings::default(),
+            mcp: McpConfig::default(),
+            host_key_policy: TrustPolicy::default(),
+            remove_partial_transfers: true,
+            scrollback_bytes: default_scrollback_bytes(),
+            disabled_plugins: Vec::new(),
+            editor_max_file_bytes: default_editor_max_file_bytes(),
+            audit_log_encrypted: false,
+            trash_retention_days: default_trash_retention_days(),
+        }
+    }
+}
+
+/// Limits for one category of files written by the app
+///
+/// `None` means no limit on that axis.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    pub max_age_days: Option<u32>,
+    pub max_total_mb: Option<u64>,
+}
+
+/// Retention limits per storage category
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetentionSettings {
+    pub recordings: RetentionPolicy,
+    pub audit_logs: RetentionPolicy,
+    pub input_logs: RetentionPolicy,
+}
+
+impl Default for RetentionSettings {
+    fn default() -> Self {
+        Self {
+            recordings: RetentionPolicy {
+                max_age_days: Some(90),
+                max_total_mb: Some(2048),
+            },
+            audit_logs: RetentionPolicy {
+                max_age_days: Some(365),
+                max_total_mb: None,
+            },
+            input_logs: RetentionPolicy {
+                max_age_days: Some(365),
+                max_total_mb: Some(1024),
+            },
         }
     }
 }
@@ -206,7 +566,60 @@ pub enum CursorStyle {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum SyncProvider {
-    WebDAV { url: String, username: String },
-    S3 { bucket: String, region: String },
-    Nextcloud { url: String, username: String },
+    /// `url` is the collection the vault is stored in
+    WebDAV {
+        url: String,
+        username: String,
+        /// Vault entry holding the password
+        #[serde(default)]
+        password_entry: Option<Uuid>,
+    },
+    S3 {
+        bucket: String,
+        region: String,
+        /// Folder the vault object is stored under
+        #[serde(default)]
+        prefix: String,
+        /// Base URL of an S3-compatible server such as MinIO; AWS if unset
+        #[serde(default)]
+        endpoint: Option<String>,
+        /// Vault entry with the access key id as username and the secret key
+        #[serde(default)]
+        credentials_entry: Option<Uuid>,
+    },
+    /// `url` is the server's base URL
+    Nextcloud {
+        url: String,
+        username: String,
+        /// Vault entry holding the (app) password
+        #[serde(default)]
+        password_entry: Option<Uuid>,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_host_patch() {
+        let mut host = Host::new(
+            "web".to_string(),
+            "web.local".to_string(),
+            "root".to_string(),
+        );
+        host.tags = vec!["prod".to_string(), "old".to_string()];
+        host.color = Some("red".to_string());
+        host.icon = Some("server".to_string());
+
+        let patch: HostPatch = serde_json::from_str(
+            r#"{"add_tags": ["web", "prod"], "remove_tags": ["old"], "color": null}"#,
+        )
+        .unwrap();
+        patch.apply(&mut host);
+
+        assert_eq!(host.tags, vec!["prod".to_string(), "web".to_string()]);
+        assert_eq!(host.color, None);
+        assert_eq!(host.icon, Some("server".to_string()));
+    }
 }
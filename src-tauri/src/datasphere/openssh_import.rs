@@ -0,0 +1,358 @@
+//! Importing hosts from an OpenSSH `ssh_config` file
+//!
+//! Every wildcard-free alias on a `Host` line becomes a host. Settings are
+//! collected the way `ssh` does: blocks are read in order and the first
+//! value of a directive from any matching block wins, so `Host *` defaults
+//! further down still apply. `Match` blocks are skipped. `Include` is
+//! followed, with `*` and `?` globs and relative paths under `~/.ssh`.
+
+use super::{AuthType, DataSphereError, NewHost};
+use crate::utils::wildcard_match;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Deepest `Include` nesting followed, as in OpenSSH
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+/// A `Host` block, or the lines before the first one
+struct Block {
+    patterns: Vec<String>,
+    /// Lower-cased keyword and its arguments, in file order
+    directives: Vec<(String, Vec<String>)>,
+}
+
+/// Read the hosts defined in a config file
+pub fn parse_file(path: &Path) -> Result<Vec<NewHost>, DataSphereError> {
+    let mut blocks = vec![top_level()];
+    read_into(path, &mut blocks, 0)?;
+    Ok(hosts_from_blocks(&blocks))
+}
+
+fn top_level() -> Block {
+    Block {
+        patterns: vec!["*".to_string()],
+        directives: Vec::new(),
+    }
+}
+
+fn read_into(path: &Path, blocks: &mut Vec<Block>, depth: usize) -> Result<(), DataSphereError> {
+    if depth > MAX_INCLUDE_DEPTH {
+        return Err(DataSphereError::InvalidInput(format!(
+            "Include nested too deeply at {}",
+            path.display()
+        )));
+    }
+    let text = fs::read_to_string(path)?;
+    parse_into(&text, blocks, depth)
+}
+
+fn parse_into(text: &str, blocks: &mut Vec<Block>, depth: usize) -> Result<(), DataSphereError> {
+    for line in text.lines() {
+        let Some((keyword, args)) = split_line(line) else {
+            continue;
+        };
+        match keyword.as_str() {
+            "host" => blocks.push(Block {
+                patterns: args,
+                directives: Vec::new(),
+            }),
+            // Conditions can't be evaluated ahead of time; a block without
+            // patterns never matches
+            "match" => blocks.push(Block {
+                patterns: Vec::new(),
+                directives: Vec::new(),
+            }),
+            "include" => {
+                let patterns = blocks
+                    .last()
+                    .map(|b| b.patterns.clone())
+                    .unwrap_or_default();
+                let before = blocks.len();
+                for arg in &args {
+                    for file in include_paths(arg) {
+                        read_into(&file, blocks, depth + 1)?;
+                    }
+                }
+                // Blocks opened by the included file end with it
+                if blocks.len() != before {
+                    blocks.push(Block {
+                        patterns,
+                        directives: Vec::new(),
+                    });
+                }
+            }
+            _ => {
+                if let Some(block) = blocks.last_mut() {
+                    block.directives.push((keyword, args));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Split a line into its lower-cased keyword and arguments
+///
+/// Keyword and arguments may be separated by whitespace or `=`; arguments
+/// may be double-quoted.
+fn split_line(line: &str) -> Option<(String, Vec<String>)> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    let end = line
+        .find(|c: char| c.is_whitespace() || c == '=')
+        .unwrap_or(line.len());
+    let (keyword, rest) = line.split_at(end);
+    let rest = rest.trim_start();
+    let rest = rest.strip_prefix('=').unwrap_or(rest);
+
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut in_arg = false;
+    for c in rest.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                in_arg = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if in_arg {
+                    args.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            }
+            c => {
+                current.push(c);
+                in_arg = true;
+            }
+        }
+    }
+    if in_arg {
+        args.push(current);
+    }
+    Some((keyword.to_ascii_lowercase(), args))
+}
+
+/// Files named by an `Include` argument, in name order
+fn include_paths(arg: &str) -> Vec<PathBuf> {
+    let path = expand_home(arg);
+    let path = if path.is_relative() {
+        home_dir().join(".ssh").join(path)
+    } else {
+        path
+    };
+
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    if !name.contains(['*', '?']) {
+        return if path.is_file() {
+            vec![path]
+        } else {
+            Vec::new()
+        };
+    }
+
+    let Some(dir) = path.parent() else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| wildcard_match(&name, &entry.file_name().to_string_lossy()))
+        .map(|entry| entry.path())
+        .filter(|p| p.is_file())
+        .collect();
+    files.sort();
+    files
+}
+
+fn hosts_from_blocks(blocks: &[Block]) -> Vec<NewHost> {
+    let mut seen = HashSet::new();
+    let aliases: Vec<&str> = blocks
+        .iter()
+        .skip(1)
+        .flat_map(|b| b.patterns.iter())
+        .filter(|p| !p.contains(['*', '?', '!']))
+        .filter(|p| seen.insert(p.to_ascii_lowercase()))
+        .map(String::as_str)
+        .collect();
+
+    aliases
+        .into_iter()
+        .map(|alias| {
+            let mut values: Vec<(&str, &[String])> = Vec::new();
+            for block in blocks.iter().filter(|b| host_matches(&b.patterns, alias)) {
+                for (keyword, args) in &block.directives {
+                    if !values.iter().any(|(k, _)| k == keyword) {
+                        values.push((keyword, args));
+                    }
+                }
+            }
+            let get = |keyword: &str| {
+                values
+                    .iter()
+                    .find(|(k, _)| *k == keyword)
+                    .and_then(|(_, args)| args.first())
+                    .map(String::as_str)
+            };
+            new_host(alias, get)
+        })
+        .collect()
+}
+
+fn new_host<'a>(alias: &str, get: impl Fn(&str) -> Option<&'a str>) -> NewHost {
+    let identity = get("identityfile")
+        .filter(|f| !f.eq_ignore_ascii_case("none"))
+        .map(|f| {
+            expand_home(&f.replace("%h", alias))
+                .to_string_lossy()
+                .to_string()
+        });
+    let proxy_jump = get("proxyjump")
+        .filter(|j| !j.eq_ignore_ascii_case("none"))
+        .map(str::to_string);
+
+    NewHost {
+        name: alias.to_string(),
+        hostname: get("hostname")
+            .map(|h| h.replace("%h", alias))
+            .unwrap_or_else(|| alias.to_string()),
+        port: get("port").and_then(|p| p.parse().ok()).unwrap_or(22),
+        username: get("user").unwrap_or_default().to_string(),
+        // Without a key file, `ssh` would fall back to the agent
+        auth_type: if identity.is_some() {
+            AuthType::PrivateKey
+        } else {
+            AuthType::Agent
+        },
+        password: None,
+        private_key: identity,
+        passphrase: None,
+        group_id: None,
+        tags: vec![],
+        icon: None,
+        color: None,
+        notes: None,
+        initial_path: None,
+        suppress_motd: false,
+        exec_profile: None,
+        transport: Default::default(),
+        encoding: None,
+        send_encoding: None,
+        normalize_newlines: false,
+        security_profile: None,
+        proxy_jump,
+        env: Default::default(),
+        init_commands: vec![],
+    }
+}
+
+/// Whether a `Host` pattern list matches `host`; any negated match excludes it
+fn host_matches(patterns: &[String], host: &str) -> bool {
+    let host = host.to_ascii_lowercase();
+    let mut matched = false;
+    for pattern in patterns {
+        let pattern = pattern.to_ascii_lowercase();
+        match pattern.strip_prefix('!') {
+            Some(negated) if wildcard_match(negated, &host) => return false,
+            Some(_) => {}
+            None => matched |= wildcard_match(&pattern, &host),
+        }
+    }
+    matched
+}
+
+/// Give hosts names not used by `existing` or each other, suffixing ` (2)` etc.
+pub fn dedupe_names<'a>(
+    hosts: Vec<NewHost>,
+    existing: impl IntoIterator<Item = &'a str>,
+) -> Vec<NewHost> {
+    let mut taken: HashSet<String> = existing.into_iter().map(str::to_lowercase).collect();
+    hosts
+        .into_iter()
+        .map(|mut host| {
+            let base = host.name.clone();
+            let mut n = 2;
+            while !taken.insert(host.name.to_lowercase()) {
+                host.name = format!("{} ({})", base, n);
+                n += 1;
+            }
+            host
+        })
+        .collect()
+}
+
+/// Expand a leading `~` to the home directory
+pub fn expand_home(path: &str) -> PathBuf {
+    match path.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with(['/', '\\']) => {
+            home_dir().join(rest.trim_start_matches(['/', '\\']))
+        }
+        _ => PathBuf::from(path),
+    }
+}
+
+fn home_dir() -> PathBuf {
+    directories::BaseDirs::new()
+        .map(|dirs| dirs.home_dir().to_path_buf())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        let config = "\
+User root
+
+Host web web-alias
+    HostName %h.example.com
+    Port=2222
+    IdentityFile \"/keys/my key\"
+
+Host db-*
+    User dbadmin
+
+Match host web
+    User ignored
+
+Host db-1 !db-2
+    ProxyJump bastion
+
+Host *
+    User fallback
+    ProxyJump none
+";
+        let mut blocks = vec![top_level()];
+        parse_into(config, &mut blocks, 0).unwrap();
+        let hosts = hosts_from_blocks(&blocks);
+        let names: Vec<_> = hosts.iter().map(|h| h.name.as_str()).collect();
+        assert_eq!(names, ["web", "web-alias", "db-1"]);
+
+        let web = &hosts[0];
+        assert_eq!(web.hostname, "web.example.com");
+        assert_eq!(web.port, 2222);
+        assert_eq!(web.username, "root");
+        assert!(matches!(web.auth_type, AuthType::PrivateKey));
+        assert_eq!(web.private_key.as_deref(), Some("/keys/my key"));
+        assert_eq!(web.proxy_jump, None);
+
+        let db = &hosts[2];
+        assert_eq!(db.hostname, "db-1");
+        assert_eq!(db.username, "root");
+        assert!(matches!(db.auth_type, AuthType::Agent));
+        assert_eq!(db.proxy_jump.as_deref(), Some("bastion"));
+
+        let renamed = dedupe_names(hosts, ["WEB"]);
+        assert_eq!(renamed[0].name, "web (2)");
+        assert_eq!(renamed[1].name, "web-alias");
+    }
+}
@@ -0,0 +1,483 @@
+//! Password generation and strength estimation
+//!
+//! Strength follows zxcvbn's idea: split the password into the patterns an
+//! attacker tries first (common passwords, keyboard rows, repeats,
+//! sequences, years) and count guesses for those, brute force for the rest.
+//! It is a heuristic, not a port; scores use zxcvbn's guess thresholds.
+
+use super::DataSphereError;
+use rand::rngs::OsRng;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+const LOWERCASE: &str = "abcdefghijklmnopqrstuvwxyz";
+const UPPERCASE: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const DIGITS: &str = "0123456789";
+const SYMBOLS: &str = "!@#$%^&*()-_=+[]{}:;,.?/~";
+/// Characters easily mistaken for one another
+const AMBIGUOUS: &str = "Il1O0o";
+const CONSONANTS: &str = "bcdfghjklmnprstvwz";
+const VOWELS: &str = "aeiou";
+
+const MIN_LENGTH: usize = 4;
+const MAX_LENGTH: usize = 256;
+
+/// Passwords and words attackers try first
+const COMMON_WORDS: &[&str] = &[
+    "password",
+    "passwort",
+    "letmein",
+    "welcome",
+    "admin",
+    "administrator",
+    "root",
+    "login",
+    "master",
+    "secret",
+    "dragon",
+    "monkey",
+    "shadow",
+    "sunshine",
+    "princess",
+    "football",
+    "baseball",
+    "iloveyou",
+    "trustno1",
+    "superman",
+    "batman",
+    "starwars",
+    "hello",
+    "freedom",
+    "whatever",
+    "charlie",
+    "michael",
+    "jordan",
+    "summer",
+    "winter",
+    "spring",
+    "autumn",
+    "love",
+    "test",
+    "guest",
+    "default",
+    "changeme",
+    "server",
+    "nexus",
+];
+
+/// Keyboard rows and digit runs, matched forwards and backwards
+const KEYBOARD_ROWS: &[&str] = &[
+    "qwertyuiop",
+    "asdfghjkl",
+    "zxcvbnm",
+    "qwertzuiop",
+    "yxcvbnm",
+    "1234567890",
+];
+
+/// Offline attack against a slow password hash
+const GUESSES_PER_SECOND: f64 = 1e4;
+
+/// Character classes and style of a generated password
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PasswordOptions {
+    pub lowercase: bool,
+    pub uppercase: bool,
+    pub digits: bool,
+    pub symbols: bool,
+    /// Leave out characters such as `l`, `1` and `O` that are easy to misread
+    pub exclude_ambiguous: bool,
+    /// Alternate consonants and vowels, then add digits and a symbol
+    pub pronounceable: bool,
+}
+
+impl Default for PasswordOptions {
+    fn default() -> Self {
+        Self {
+            lowercase: true,
+            uppercase: true,
+            digits: true,
+            symbols: true,
+            exclude_ambiguous: false,
+            pronounceable: false,
+        }
+    }
+}
+
+/// How hard a secret is to guess
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StrengthEstimate {
+    /// 0 (guessed at once) to 4 (very strong)
+    pub score: u8,
+    /// Time to guess it offline, e.g. "3 hours" or "centuries"
+    pub crack_time_estimate: String,
+    pub warnings: Vec<String>,
+}
+
+/// Generate a random password of `length` characters
+pub fn generate(
+    length: usize,
+    options: &PasswordOptions,
+) -> Result<Zeroizing<String>, DataSphereError> {
+    if !(MIN_LENGTH..=MAX_LENGTH).contains(&length) {
+        return Err(DataSphereError::InvalidInput(format!(
+            "Password length must be between {} and {}",
+            MIN_LENGTH, MAX_LENGTH
+        )));
+    }
+    let pick = |set: &str| -> Vec<char> {
+        set.chars()
+            .filter(|c| !options.exclude_ambiguous || !AMBIGUOUS.contains(*c))
+            .collect()
+    };
+
+    if options.pronounceable {
+        return pronounceable(length, options, &pick);
+    }
+
+    let classes: Vec<Vec<char>> = [
+        (options.lowercase, LOWERCASE),
+        (options.uppercase, UPPERCASE),
+        (options.digits, DIGITS),
+        (options.symbols, SYMBOLS),
+    ]
+    .into_iter()
+    .filter(|(enabled, _)| *enabled)
+    .map(|(_, set)| pick(set))
+    .collect();
+    if classes.is_empty() {
+        return Err(DataSphereError::InvalidInput(
+            "Select at least one character class".to_string(),
+        ));
+    }
+
+    // One character of every class, the rest from all of them
+    let pool: Vec<char> = classes.concat();
+    let mut chars = Zeroizing::new(Vec::with_capacity(length));
+    for class in &classes {
+        chars.push(class[OsRng.gen_range(0..class.len())]);
+    }
+    while chars.len() < length {
+        chars.push(pool[OsRng.gen_range(0..pool.len())]);
+    }
+    chars.shuffle(&mut OsRng);
+    Ok(Zeroizing::new(chars.iter().collect()))
+}
+
+/// Syllables like `tavo`, then a symbol and digits if enabled
+fn pronounceable(
+    length: usize,
+    options: &PasswordOptions,
+    pick: &dyn Fn(&str) -> Vec<char>,
+) -> Result<Zeroizing<String>, DataSphereError> {
+    if !options.lowercase && !options.uppercase {
+        return Err(DataSphereError::InvalidInput(
+            "Pronounceable passwords need letters".to_string(),
+        ));
+    }
+    let (consonants, vowels) = (pick(CONSONANTS), pick(VOWELS));
+    let digits = if options.digits {
+        pick(DIGITS)
+    } else {
+        Vec::new()
+    };
+    let symbols = if options.symbols {
+        pick(SYMBOLS)
+    } else {
+        Vec::new()
+    };
+    let digit_count = match digits.is_empty() {
+        true => 0,
+        false if length >= 8 => 2,
+        false => 1,
+    };
+    let symbol_count = usize::from(!symbols.is_empty());
+    let letters = length - digit_count - symbol_count;
+
+    let mut password = Zeroizing::new(String::with_capacity(length));
+    for i in 0..letters {
+        let set = if i % 2 == 0 { &consonants } else { &vowels };
+        let c = set[OsRng.gen_range(0..set.len())];
+        // Capitalise the start of every other syllable
+        let upper = !options.lowercase || (options.uppercase && i % 4 == 0);
+        password.push(if upper { c.to_ascii_uppercase() } else { c });
+    }
+    for _ in 0..symbol_count {
+        password.push(symbols[OsRng.gen_range(0..symbols.len())]);
+    }
+    for _ in 0..digit_count {
+        password.push(digits[OsRng.gen_range(0..digits.len())]);
+    }
+    Ok(password)
+}
+
+/// Estimate how hard `secret` is to guess
+pub fn estimate_strength(secret: &str) -> StrengthEstimate {
+    let chars = Zeroizing::new(secret.chars().collect::<Vec<char>>());
+    let lower = Zeroizing::new(
+        chars
+            .iter()
+            .map(char::to_ascii_lowercase)
+            .collect::<Vec<char>>(),
+    );
+    // With common substitutions undone, for finding words
+    let plain = Zeroizing::new(chars.iter().map(|c| unleet(*c)).collect::<Vec<char>>());
+    let char_bits = f64::from(pool_size(&chars).max(1)).log2();
+
+    let mut warnings = Vec::new();
+    let mut warn = |message: &str| {
+        if !warnings.iter().any(|w| w == message) {
+            warnings.push(message.to_string());
+        }
+    };
+
+    let mut bits = 0.0;
+    let mut i = 0;
+    while i < chars.len() {
+        if let Some(len) = match_list(&plain[i..], COMMON_WORDS) {
+            warn("Contains a common password or word");
+            bits += (COMMON_WORDS.len() as f64).log2() + variations(&chars[i..i + len]);
+            i += len;
+        } else if let Some(len) = match_keyboard(&lower[i..]) {
+            warn("Keyboard patterns are easy to guess");
+            bits += (len as f64 * KEYBOARD_ROWS.len() as f64 * 2.0).log2();
+            i += len;
+        } else if let Some(len) = match_year(&chars[i..]) {
+            warn("Dates and years are easy to guess");
+            bits += 200f64.log2();
+            i += len;
+        } else if i > 0 && lower[i] == lower[i - 1] {
+            warn("Repeated characters are easy to guess");
+            bits += 1.0;
+            i += 1;
+        } else if i > 0 && is_sequence(lower[i - 1], lower[i]) {
+            warn("Sequences like abc or 123 are easy to guess");
+            bits += 1.5;
+            i += 1;
+        } else {
+            bits += char_bits;
+            i += 1;
+        }
+    }
+
+    let mut score = match bits {
+        b if b < 10.0 => 0,
+        b if b < 20.0 => 1,
+        b if b < 27.0 => 2,
+        b if b < 33.0 => 3,
+        _ => 4,
+    };
+    if chars.len() < 8 {
+        warn("Short passwords are easy to guess");
+        score = score.min(1);
+    }
+    if !chars.is_empty() && pool_size(&chars) <= 26 {
+        warn("Mix upper and lower case, digits and symbols");
+    }
+
+    StrengthEstimate {
+        score,
+        crack_time_estimate: crack_time(2f64.powf(bits) / 2.0 / GUESSES_PER_SECOND),
+        warnings,
+    }
+}
+
+/// Size of the character pool the secret's character classes span
+fn pool_size(chars: &[char]) -> u32 {
+    let has = |f: fn(&char) -> bool| chars.iter().any(f);
+    [
+        (has(char::is_ascii_lowercase), 26),
+        (has(char::is_ascii_uppercase), 26),
+        (has(char::is_ascii_digit), 10),
+        (has(|c| c.is_ascii() && !c.is_ascii_alphanumeric()), 33),
+        (has(|c| !c.is_ascii()), 100),
+    ]
+    .iter()
+    .filter(|(present, _)| *present)
+    .map(|(_, size)| size)
+    .sum()
+}
+
+fn unleet(c: char) -> char {
+    match c {
+        '@' | '4' => 'a',
+        '3' => 'e',
+        '1' | '!' => 'i',
+        '0' => 'o',
+        '$' | '5' => 's',
+        '7' => 't',
+        c => c.to_ascii_lowercase(),
+    }
+}
+
+/// Extra guesses for capitalisation and substitutions within a word
+fn variations(chars: &[char]) -> f64 {
+    let changed = chars
+        .iter()
+        .filter(|c| c.is_ascii_uppercase() || !c.is_ascii_alphabetic())
+        .count();
+    changed as f64
+}
+
+/// Length of the longest word in `list` that `chars` starts with
+fn match_list(chars: &[char], list: &[&str]) -> Option<usize> {
+    list.iter()
+        .filter(|word| word.len() >= 4 && starts_with(chars, word))
+        .map(|word| word.len())
+        .max()
+}
+
+/// Length of a run of four or more neighbouring keys
+fn match_keyboard(chars: &[char]) -> Option<usize> {
+    let mut longest = None;
+    for row in KEYBOARD_ROWS {
+        let reversed: String = row.chars().rev().collect();
+        for row in [*row, reversed.as_str()] {
+            for start in 0..row.len() {
+                let len = row[start..]
+                    .chars()
+                    .zip(chars)
+                    .take_while(|(a, b)| a == *b)
+                    .count();
+                if len >= 4 && longest.is_none_or(|l| len > l) {
+                    longest = Some(len);
+                }
+            }
+        }
+    }
+    longest
+}
+
+/// Four digits from 1900 to 2099
+fn match_year(chars: &[char]) -> Option<usize> {
+    let year: String = chars.iter().take(4).collect();
+    let digits = year.len() == 4 && year.chars().all(|c| c.is_ascii_digit());
+    (digits && (year.starts_with("19") || year.starts_with("20"))).then_some(4)
+}
+
+fn is_sequence(prev: char, c: char) -> bool {
+    prev.is_ascii_alphanumeric()
+        && c.is_ascii_alphanumeric()
+        && (c as u32).abs_diff(prev as u32) == 1
+}
+
+fn starts_with(chars: &[char], word: &str) -> bool {
+    chars.len() >= word.len() && word.chars().map(unleet).zip(chars).all(|(a, b)| a == *b)
+}
+
+/// Human-readable duration, as zxcvbn displays it
+fn crack_time(seconds: f64) -> String {
+    const UNITS: [(f64, &str); 6] = [
+        (60.0, "second"),
+        (60.0, "minute"),
+        (24.0, "hour"),
+        (30.0, "day"),
+        (12.0, "month"),
+        (100.0, "year"),
+    ];
+    if seconds < 1.0 {
+        return "less than a second".to_string();
+    }
+    let mut value = seconds;
+    for (size, unit) in UNITS {
+        if value < size {
+            let n = value.round() as u64;
+            return format!("{} {}{}", n, unit, if n == 1 { "" } else { "s" });
+        }
+        value /= size;
+    }
+    "centuries".to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate() {
+        let options = PasswordOptions {
+            exclude_ambiguous: true,
+            ..PasswordOptions::default()
+        };
+        for _ in 0..20 {
+            let password = generate(24, &options).unwrap();
+            assert_eq!(password.chars().count(), 24);
+            assert!(password.chars().any(|c| c.is_ascii_lowercase()));
+            assert!(password.chars().any(|c| c.is_ascii_uppercase()));
+            assert!(password.chars().any(|c| c.is_ascii_digit()));
+            assert!(password.chars().any(|c| SYMBOLS.contains(c)));
+            assert!(!password.chars().any(|c| AMBIGUOUS.contains(c)));
+            assert_eq!(estimate_strength(&password).score, 4);
+        }
+
+        let digits_only = PasswordOptions {
+            lowercase: false,
+            uppercase: false,
+            symbols: false,
+            ..PasswordOptions::default()
+        };
+        assert!(generate(6, &digits_only)
+            .unwrap()
+            .chars()
+            .all(|c| c.is_ascii_digit()));
+
+        let pronounceable = PasswordOptions {
+            pronounceable: true,
+            uppercase: false,
+            ..PasswordOptions::default()
+        };
+        let password = generate(12, &pronounceable).unwrap();
+        let letters: Vec<char> = password.chars().take(9).collect();
+        assert!(letters.iter().step_by(2).all(|c| CONSONANTS.contains(*c)));
+        assert!(letters
+            .iter()
+            .skip(1)
+            .step_by(2)
+            .all(|c| VOWELS.contains(*c)));
+        assert!(password[10..].chars().all(|c| c.is_ascii_digit()));
+
+        assert!(generate(3, &options).is_err());
+        let none = PasswordOptions {
+            lowercase: false,
+            uppercase: false,
+            digits: false,
+            symbols: false,
+            ..PasswordOptions::default()
+        };
+        assert!(generate(16, &none).is_err());
+    }
+
+    #[test]
+    fn test_estimate_strength() {
+        for weak in [
+            "password",
+            "P@ssw0rd1",
+            "qwerty123",
+            "aaaaaaaaaaaa",
+            "abcdefgh",
+            "letmein1990",
+        ] {
+            let estimate = estimate_strength(weak);
+            assert!(estimate.score <= 1, "{} scored {}", weak, estimate.score);
+            assert!(!estimate.warnings.is_empty(), "{} has no warnings", weak);
+        }
+        let common = estimate_strength("password");
+        assert_eq!(common.score, 0);
+        assert_eq!(common.crack_time_estimate, "less than a second");
+
+        for strong in [
+            "k3#Vq9!zLm@2xWp7",
+            "Zr8$wQ2!nB5^tY7&",
+            "tavoriku-Mesano-93!",
+        ] {
+            assert_eq!(estimate_strength(strong).score, 4, "{}", strong);
+        }
+        assert_eq!(
+            estimate_strength("k3#Vq9!zLm@2xWp7").crack_time_estimate,
+            "centuries"
+        );
+    }
+}
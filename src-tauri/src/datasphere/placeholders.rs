@@ -0,0 +1,129 @@
+//! Snippet placeholders
+//!
+//! A placeholder is `{{name}}`, where the name is made of letters, digits,
+//! `_`, `-` and `.`, with optional spaces inside the braces. `\{{` stands
+//! for a literal `{{`. Placeholders without a value are left as they are.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Placeholder filled from the active session's host name
+pub const HOST: &str = "host";
+
+/// Placeholder filled from the active session's user name
+pub const USER: &str = "user";
+
+/// A snippet with its placeholders filled in
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RenderedSnippet {
+    pub content: String,
+    /// Placeholders left in `content` for lack of a value, in order
+    pub unfilled: Vec<String>,
+}
+
+enum Token<'a> {
+    Text(&'a str),
+    Placeholder { name: &'a str, raw: &'a str },
+}
+
+/// Split `content` into text and placeholders
+fn tokens(content: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut text_start = 0;
+    let mut i = 0;
+    while i < content.len() {
+        let rest = &content[i..];
+        if rest.starts_with("\\{{") {
+            tokens.push(Token::Text(&content[text_start..i]));
+            tokens.push(Token::Text("{{"));
+            i += 3;
+            text_start = i;
+        } else if let Some((name, len)) =
+            rest.starts_with("{{").then(|| placeholder(rest)).flatten()
+        {
+            tokens.push(Token::Text(&content[text_start..i]));
+            tokens.push(Token::Placeholder {
+                name,
+                raw: &rest[..len],
+            });
+            i += len;
+            text_start = i;
+        } else {
+            i += rest.chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    tokens.push(Token::Text(&content[text_start..]));
+    tokens
+}
+
+/// The name and length of the placeholder `text` starts with
+fn placeholder(text: &str) -> Option<(&str, usize)> {
+    let end = text.find("}}")?;
+    let name = text[2..end].trim();
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'));
+    valid.then_some((name, end + 2))
+}
+
+/// Names of the placeholders in `content`, each once, in order
+pub fn parse_placeholders(content: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for token in tokens(content) {
+        if let Token::Placeholder { name, .. } = token {
+            if !names.iter().any(|n| n == name) {
+                names.push(name.to_string());
+            }
+        }
+    }
+    names
+}
+
+/// Substitute `vars` into `content`
+pub fn render(content: &str, vars: &HashMap<String, String>) -> RenderedSnippet {
+    let mut rendered = String::with_capacity(content.len());
+    let mut unfilled: Vec<String> = Vec::new();
+    for token in tokens(content) {
+        match token {
+            Token::Text(text) => rendered.push_str(text),
+            Token::Placeholder { name, raw } => match vars.get(name) {
+                Some(value) => rendered.push_str(value),
+                None => {
+                    rendered.push_str(raw);
+                    if !unfilled.iter().any(|n| n == name) {
+                        unfilled.push(name.to_string());
+                    }
+                }
+            },
+        }
+    }
+    RenderedSnippet {
+        content: rendered,
+        unfilled,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_placeholders() {
+        let content = "ssh {{user}}@{{ host }} -p {{port}} # \\{{user}} {{not valid}} {{host}}";
+        assert_eq!(parse_placeholders(content), ["user", "host", "port"]);
+
+        let vars = HashMap::from([
+            ("user".to_string(), "root".to_string()),
+            ("host".to_string(), "web-1".to_string()),
+        ]);
+        let rendered = render(content, &vars);
+        assert_eq!(
+            rendered.content,
+            "ssh root@web-1 -p {{port}} # {{user}} {{not valid}} web-1"
+        );
+        assert_eq!(rendered.unfilled, ["port"]);
+
+        assert_eq!(render("ünï {{x}}", &HashMap::new()).content, "ünï {{x}}");
+    }
+}
@@ -0,0 +1,98 @@
+//! Case-insensitive substring search over hosts, snippets and the vault
+//!
+//! Vault entries are matched on their descriptive fields only, never on
+//! the secret. A blank query matches nothing.
+
+use super::{Host, Snippet, VaultEntry};
+use serde::{Deserialize, Serialize};
+
+/// Matches in every category, each sorted by name
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GlobalSearchResults {
+    pub hosts: Vec<Host>,
+    pub snippets: Vec<Snippet>,
+    pub vault: Vec<VaultEntry>,
+}
+
+/// A lower-cased search query
+pub(crate) struct Query(String);
+
+impl Query {
+    /// `None` for a blank query
+    pub fn new(query: &str) -> Option<Self> {
+        let query = query.trim();
+        (!query.is_empty()).then(|| Self(query.to_lowercase()))
+    }
+
+    fn any<'a>(&self, fields: impl IntoIterator<Item = &'a str>) -> bool {
+        fields
+            .into_iter()
+            .any(|field| field.to_lowercase().contains(&self.0))
+    }
+
+    pub fn host(&self, host: &Host) -> bool {
+        self.any(
+            [
+                host.name.as_str(),
+                host.hostname.as_str(),
+                host.username.as_str(),
+            ]
+            .into_iter()
+            .chain(host.notes.as_deref())
+            .chain(host.tags.iter().map(String::as_str)),
+        )
+    }
+
+    pub fn snippet(&self, snippet: &Snippet) -> bool {
+        self.any(
+            [snippet.name.as_str(), snippet.content.as_str()]
+                .into_iter()
+                .chain(snippet.description.as_deref())
+                .chain(snippet.language.as_deref())
+                .chain(snippet.tags.iter().map(String::as_str)),
+        )
+    }
+
+    pub fn vault_entry(&self, entry: &VaultEntry) -> bool {
+        self.any(
+            [entry.name.as_str()]
+                .into_iter()
+                .chain(entry.username.as_deref())
+                .chain(entry.url.as_deref())
+                .chain(entry.notes.as_deref())
+                .chain(entry.folder.as_deref())
+                .chain(entry.tags.iter().map(String::as_str)),
+        )
+    }
+}
+
+/// Sort search results by name, ignoring case
+pub(crate) fn sort_by_name<T>(items: &mut [T], name: impl Fn(&T) -> &str) {
+    items.sort_by_cached_key(|item| name(item).to_lowercase());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_fields() {
+        let mut host = Host::new("Web".into(), "10.0.0.5".into(), "deploy".into());
+        host.tags = vec!["Production".into()];
+        host.notes = Some("behind the LB".into());
+
+        let matches = |q: &str| Query::new(q).is_some_and(|q| q.host(&host));
+        assert!(matches("web"));
+        assert!(matches("PRODUCTION"));
+        assert!(matches("lb"));
+        assert!(matches("10.0"));
+        assert!(!matches("staging"));
+        assert!(!matches("   "));
+
+        let mut snippet = Snippet::new("Restart".into(), "systemctl restart nginx".into());
+        snippet.language = Some("bash".into());
+        let query = Query::new("NGINX").unwrap();
+        assert!(query.snippet(&snippet));
+        assert!(Query::new("bash").unwrap().snippet(&snippet));
+    }
+}
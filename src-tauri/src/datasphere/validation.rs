@@ -0,0 +1,156 @@
+//! Host input validation
+//!
+//! Errors name the offending field as it is spelled in `NewHost`, so the
+//! host form can show each message next to its input.
+
+use super::{AuthType, Host, NewHost};
+use crate::ssh::{shell, Transport};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// A problem with one input field
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    fn new(field: &str, message: &str) -> Self {
+        Self {
+            field: field.to_string(),
+            message: message.to_string(),
+        }
+    }
+}
+
+/// Host fields that are checked
+struct HostFields<'a> {
+    name: &'a str,
+    hostname: &'a str,
+    port: u16,
+    auth_type: &'a AuthType,
+    private_key: Option<&'a str>,
+    transport: &'a Transport,
+    env: &'a HashMap<String, String>,
+}
+
+impl HostFields<'_> {
+    fn errors(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        if self.name.trim().is_empty() {
+            errors.push(FieldError::new("name", "Name is required"));
+        }
+        // Socket and pipe transports only use the hostname for display
+        if *self.transport == Transport::Tcp && self.hostname.trim().is_empty() {
+            errors.push(FieldError::new("hostname", "Hostname is required"));
+        }
+        if self.port == 0 {
+            errors.push(FieldError::new("port", "Port must be between 1 and 65535"));
+        }
+        // Password hosts may leave the password out; it is asked for on connect
+        let missing_key = self.private_key.map(str::trim).is_none_or(str::is_empty);
+        if matches!(self.auth_type, AuthType::PrivateKey) && missing_key {
+            errors.push(FieldError::new(
+                "private_key",
+                "Private key is required for key authentication",
+            ));
+        }
+        let mut invalid_names: Vec<&str> = self
+            .env
+            .keys()
+            .filter(|name| !shell::is_env_name(name))
+            .map(String::as_str)
+            .collect();
+        if !invalid_names.is_empty() {
+            invalid_names.sort();
+            errors.push(FieldError::new(
+                "env",
+                &format!(
+                    "Invalid environment variable name: {}",
+                    invalid_names.join(", ")
+                ),
+            ));
+        }
+        errors
+    }
+}
+
+impl NewHost {
+    /// Problems that keep this host from being saved; empty if valid
+    pub fn validate(&self) -> Vec<FieldError> {
+        HostFields {
+            name: &self.name,
+            hostname: &self.hostname,
+            port: self.port,
+            auth_type: &self.auth_type,
+            private_key: self.private_key.as_deref(),
+            transport: &self.transport,
+            env: &self.env,
+        }
+        .errors()
+    }
+}
+
+impl Host {
+    /// Problems that keep this host from being saved; empty if valid
+    pub fn validate(&self) -> Vec<FieldError> {
+        HostFields {
+            name: &self.name,
+            hostname: &self.hostname,
+            port: self.port,
+            auth_type: &self.auth_type,
+            private_key: self.private_key.as_deref(),
+            transport: &self.transport,
+            env: &self.env,
+        }
+        .errors()
+    }
+}
+
+/// `field: message` pairs joined for the error's display text
+pub(crate) fn summarize(errors: &[FieldError]) -> String {
+    errors
+        .iter()
+        .map(|e| format!("{}: {}", e.field, e.message))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(errors: &[FieldError]) -> Vec<&str> {
+        errors.iter().map(|e| e.field.as_str()).collect()
+    }
+
+    #[test]
+    fn test_host_validation() {
+        let mut host = Host::new(" ".into(), "".into(), "root".into());
+        host.port = 0;
+        assert_eq!(fields(&host.validate()), ["name", "hostname", "port"]);
+
+        host.name = "web".into();
+        host.hostname = "10.0.0.1".into();
+        host.port = 22;
+        assert!(host.password.is_none() && host.validate().is_empty());
+
+        host.auth_type = AuthType::PrivateKey;
+        host.private_key = Some("  ".into());
+        assert_eq!(fields(&host.validate()), ["private_key"]);
+
+        host.auth_type = AuthType::Agent;
+        assert!(host.validate().is_empty());
+
+        host.hostname.clear();
+        host.transport = Transport::UnixSocket {
+            path: "/run/ssh.sock".into(),
+        };
+        assert!(host.validate().is_empty());
+
+        host.env.insert("APP_ENV".into(), "prod".into());
+        host.env.insert("bad-name".into(), "x".into());
+        assert_eq!(fields(&host.validate()), ["env"]);
+    }
+}
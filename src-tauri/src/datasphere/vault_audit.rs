@@ -0,0 +1,200 @@
+//! Vault security audit
+//!
+//! Finds weak, reused and stale secrets. Secrets are only read in place:
+//! reuse is detected by comparing HMAC digests under a key that lives for
+//! a single scan, so no secret is copied or compared directly, and any
+//! derived plaintext is wiped on drop.
+
+use super::{DataSphereCrypto, VaultEntry, VaultEntryType};
+use chrono::{Duration, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashMap;
+use uuid::Uuid;
+use zeroize::Zeroizing;
+
+/// Substrings that make a password weak regardless of length
+const COMMON_PATTERNS: &[&str] = &[
+    "password", "passwort", "qwerty", "123456", "letmein", "admin", "welcome",
+];
+
+/// Thresholds for the audit
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VaultAuditOptions {
+    pub min_length: usize,
+    pub min_entropy_bits: f64,
+    /// Entries not updated for this long are stale
+    pub max_age_days: u32,
+}
+
+impl Default for VaultAuditOptions {
+    fn default() -> Self {
+        Self {
+            min_length: 12,
+            min_entropy_bits: 60.0,
+            max_age_days: 180,
+        }
+    }
+}
+
+/// Findings of a vault audit; lists entry ids, never secrets
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VaultAuditReport {
+    /// Entries that were checked
+    pub scanned: usize,
+    /// Passwords that are too short, too simple or too common
+    pub weak: Vec<Uuid>,
+    /// Groups of entries sharing the same secret
+    pub reused: Vec<Vec<Uuid>>,
+    /// Entries not updated within `max_age_days`
+    ///
+    /// Any edit counts as an update, since the vault keeps no rotation date.
+    pub stale: Vec<Uuid>,
+    pub weak_count: usize,
+    /// Number of entries in `reused`
+    pub reused_count: usize,
+    pub stale_count: usize,
+}
+
+/// Audit vault entries; notes are skipped
+pub fn audit<'a>(
+    entries: impl IntoIterator<Item = &'a VaultEntry>,
+    options: &VaultAuditOptions,
+) -> VaultAuditReport {
+    let mut report = VaultAuditReport::default();
+    let stale_before = Utc::now() - Duration::days(i64::from(options.max_age_days));
+
+    let key = Zeroizing::new(DataSphereCrypto::generate_key());
+    let mut digests: HashMap<[u8; 32], Vec<Uuid>> = HashMap::new();
+
+    for entry in entries {
+        if entry.entry_type == VaultEntryType::Note || entry.secret.is_empty() {
+            continue;
+        }
+        report.scanned += 1;
+
+        if entry.entry_type == VaultEntryType::Password && is_weak(&entry.secret, options) {
+            report.weak.push(entry.id);
+        }
+        if entry.updated_at < stale_before {
+            report.stale.push(entry.id);
+        }
+
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key.as_slice())
+            .expect("HMAC accepts any key length");
+        mac.update(entry.secret.as_bytes());
+        digests
+            .entry(mac.finalize().into_bytes().into())
+            .or_default()
+            .push(entry.id);
+    }
+
+    report.reused = digests.into_values().filter(|ids| ids.len() > 1).collect();
+    for ids in &mut report.reused {
+        ids.sort();
+    }
+    report.reused.sort();
+
+    report.weak_count = report.weak.len();
+    report.reused_count = report.reused.iter().map(Vec::len).sum();
+    report.stale_count = report.stale.len();
+    report
+}
+
+fn is_weak(secret: &str, options: &VaultAuditOptions) -> bool {
+    let length = secret.chars().count();
+    if length < options.min_length {
+        return true;
+    }
+
+    let lower = Zeroizing::new(secret.to_lowercase());
+    if COMMON_PATTERNS.iter().any(|p| lower.contains(p)) {
+        return true;
+    }
+
+    entropy_bits(secret) < options.min_entropy_bits
+}
+
+/// Rough entropy: character pool size times length, with repeats discounted
+fn entropy_bits(secret: &str) -> f64 {
+    let (mut lower, mut upper, mut digit, mut symbol, mut other) =
+        (false, false, false, false, false);
+    // The distinct characters may hold pieces of the secret
+    let mut distinct = Zeroizing::new(Vec::new());
+    for c in secret.chars() {
+        match c {
+            'a'..='z' => lower = true,
+            'A'..='Z' => upper = true,
+            '0'..='9' => digit = true,
+            c if c.is_ascii() => symbol = true,
+            _ => other = true,
+        }
+        if !distinct.contains(&c) {
+            distinct.push(c);
+        }
+    }
+
+    let pool = [
+        (lower, 26),
+        (upper, 26),
+        (digit, 10),
+        (symbol, 33),
+        (other, 100),
+    ]
+    .iter()
+    .filter(|(present, _)| *present)
+    .map(|(_, size)| size)
+    .sum::<u32>();
+    // A secret made of few distinct characters is only as strong as those
+    let effective_len = secret.chars().count().min(distinct.len() * 2);
+    effective_len as f64 * f64::from(pool.max(1)).log2()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datasphere::NewVaultEntry;
+
+    fn entry(secret: &str, entry_type: VaultEntryType) -> VaultEntry {
+        VaultEntry::from_new(NewVaultEntry {
+            name: "test".to_string(),
+            entry_type,
+            username: None,
+            secret: secret.to_string(),
+            public_key: None,
+            url: None,
+            notes: None,
+            folder: None,
+            tags: vec![],
+        })
+    }
+
+    #[test]
+    fn test_vault_audit() {
+        let strong = entry("k3#Vq9!zLm@2xWp7", VaultEntryType::Password);
+        let short = entry("hunter2", VaultEntryType::Password);
+        let common = entry("MyPassword2024!!", VaultEntryType::Password);
+        let repeated = entry("aaaaaaaaaaaaaaaaaaaa", VaultEntryType::Password);
+        let reused = entry("k3#Vq9!zLm@2xWp7", VaultEntryType::ApiKey);
+        let note = entry("hunter2", VaultEntryType::Note);
+        let mut old = entry("Zr8$wQ2!nB5^tY7&", VaultEntryType::Password);
+        old.updated_at = Utc::now() - Duration::days(365);
+
+        let entries = [&strong, &short, &common, &repeated, &reused, &note, &old];
+        let report = audit(entries, &VaultAuditOptions::default());
+
+        assert_eq!(report.scanned, 6);
+        let mut weak = vec![short.id, common.id, repeated.id];
+        weak.sort();
+        let mut found = report.weak.clone();
+        found.sort();
+        assert_eq!(found, weak);
+
+        let mut pair = vec![strong.id, reused.id];
+        pair.sort();
+        assert_eq!(report.reused, vec![pair]);
+        assert_eq!(report.reused_count, 2);
+        assert_eq!(report.stale, vec![old.id]);
+    }
+}
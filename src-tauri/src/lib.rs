@@ -8,12 +8,16 @@
 //! - Plugin system
 //! - MCP Server for AI integrations
 
+pub mod audit;
 pub mod datasphere;
 pub mod mcp;
 pub mod plugins;
+pub mod retention;
+pub mod services;
 pub mod session;
 pub mod sftp;
 pub mod ssh;
+pub mod sync;
 pub mod terminal;
 pub mod utils;
 
@@ -47,45 +51,205 @@ pub fn run() {
             // Initialize Session Manager
             session::init(&app_handle)?;
 
+            // Load plugin manifests
+            plugins::init(&app_handle)?;
+
+            // Drop sessions whose connection died
+            session::spawn_reaper_task(&app_handle);
+
+            // Track connection quality
+            session::spawn_latency_monitor(&app_handle);
+
+            // Prune old recordings and logs
+            retention::spawn_cleanup_task(&app_handle);
+
+            // Open the audit log and write its buffer periodically; the app
+            // still starts without one
+            match audit::init(&app_handle) {
+                Ok(()) => audit::spawn_flush_task(),
+                Err(e) => tracing::warn!("Audit log unavailable: {}", e),
+            }
+
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             // DataSphere commands
+            datasphere::commands::get_app_status,
+            datasphere::commands::unlock_datasphere,
+            datasphere::commands::lock_datasphere,
+            datasphere::commands::change_master_password,
+            datasphere::commands::is_datasphere_locked,
             datasphere::commands::get_hosts,
+            datasphere::commands::search_hosts,
             datasphere::commands::add_host,
             datasphere::commands::update_host,
+            datasphere::commands::clone_host,
             datasphere::commands::delete_host,
+            datasphere::commands::bulk_update_hosts,
+            datasphere::commands::bulk_delete_hosts,
+            datasphere::commands::resolve_host_config,
+            datasphere::commands::get_host_ssh_config,
+            datasphere::commands::probe_host,
+            datasphere::commands::test_host_connection,
+            datasphere::commands::probe_all_hosts,
+            datasphere::commands::import_ssh_config,
+            datasphere::commands::commit_imported_hosts,
             datasphere::commands::get_host_groups,
+            datasphere::commands::add_host_group,
+            datasphere::commands::update_host_group,
+            datasphere::commands::delete_host_group,
+            datasphere::commands::reorder_host_groups,
+            datasphere::commands::get_security_profiles,
+            datasphere::commands::add_security_profile,
+            datasphere::commands::update_security_profile,
+            datasphere::commands::delete_security_profile,
+            datasphere::commands::get_known_hosts,
+            datasphere::commands::accept_host_key,
+            datasphere::commands::remove_known_host,
             datasphere::commands::get_snippets,
+            datasphere::commands::search_snippets,
             datasphere::commands::add_snippet,
+            datasphere::commands::render_snippet,
+            datasphere::commands::get_vault_entries,
+            datasphere::commands::get_vault_secret,
+            datasphere::commands::search_vault,
+            datasphere::commands::global_search,
+            datasphere::commands::add_vault_entry,
+            datasphere::commands::update_vault_entry,
+            datasphere::commands::delete_vault_entry,
+            datasphere::commands::secure_delete_vault_entry,
+            datasphere::commands::get_trash,
+            datasphere::commands::restore_item,
+            datasphere::commands::purge_trash,
+            datasphere::commands::audit_vault_security,
+            datasphere::commands::generate_password,
+            datasphere::commands::estimate_strength,
+            datasphere::commands::get_vault_folders,
+            datasphere::commands::rename_vault_folder,
+            datasphere::commands::move_vault_entries,
+            datasphere::commands::delete_vault_folder,
+            datasphere::commands::import_ppk_key,
+            datasphere::commands::generate_ssh_key,
             datasphere::commands::get_settings,
             datasphere::commands::update_settings,
+            datasphere::commands::backup_workspace,
+            datasphere::commands::restore_workspace,
+            datasphere::commands::export_vault,
+            datasphere::commands::import_vault,
+            // MCP commands
+            mcp::commands::test_mcp,
+            mcp::commands::set_provider_permission,
+            mcp::commands::get_provider_permissions,
+            mcp::commands::issue_provider_token,
+            mcp::commands::revoke_provider_token,
+            mcp::commands::stream_command_to_mcp,
+            mcp::commands::set_mcp_sftp_jail,
+            mcp::commands::create_mcp_grant,
+            mcp::commands::list_grants,
+            mcp::commands::revoke_grant,
+            mcp::commands::list_mcp_approvals,
+            mcp::commands::resolve_mcp_approval,
+            // Plugin commands
+            plugins::commands::list_plugins,
+            plugins::commands::enable_plugin,
+            plugins::commands::disable_plugin,
+            plugins::commands::run_plugin_command,
+            // Retention commands
+            retention::commands::get_storage_usage,
+            retention::commands::cleanup_now,
+            // Audit log commands
+            audit::commands::get_audit_log,
+            audit::commands::clear_audit_log,
+            sync::commands::get_sync_status,
+            sync::commands::sync_now,
             // SSH commands
             ssh::commands::connect,
             ssh::commands::disconnect,
+            ssh::commands::probe_auth_methods,
+            ssh::commands::install_public_key,
+            ssh::commands::get_host_fingerprint,
             ssh::commands::send_command,
+            ssh::commands::send_command_streaming,
+            ssh::commands::cancel_command,
+            ssh::commands::send_command_interactive,
+            ssh::commands::run_local_script,
+            ssh::commands::detect_remote_shell,
+            ssh::commands::detect_remote_info,
+            ssh::commands::create_tunnel,
+            ssh::commands::close_tunnel,
+            ssh::commands::list_tunnels,
             // SFTP commands
+            sftp::commands::open_sftp_session,
+            sftp::commands::open_sftp_sessions,
+            sftp::commands::get_sftp_session,
             sftp::commands::list_directory,
+            sftp::commands::list_directory_stream,
+            sftp::commands::sftp_find,
             sftp::commands::upload_file,
             sftp::commands::download_file,
+            sftp::commands::transfer_remote_to_remote,
+            sftp::commands::upload_directory,
+            sftp::commands::download_directory,
+            sftp::commands::cancel_transfer,
             sftp::commands::delete_path,
             sftp::commands::create_directory,
+            sftp::commands::chmod_path,
+            sftp::commands::set_permissions,
+            sftp::commands::set_owner,
+            sftp::commands::create_symlink,
+            sftp::commands::read_symlink,
+            sftp::commands::resolve_remote_path,
+            sftp::commands::compare_directories,
+            sftp::commands::get_remote_fs_stats,
+            sftp::commands::get_extended_attributes,
+            sftp::commands::set_extended_attributes,
+            sftp::commands::create_remote_archive,
+            sftp::commands::extract_remote_archive,
+            sftp::commands::read_remote_file,
+            sftp::commands::write_remote_file,
+            sftp::commands::save_remote_edit_sudo,
             sftp::commands::list_local_directory,
             sftp::commands::create_local_directory,
             sftp::commands::delete_local_path,
+            // Service commands
+            services::commands::manage_service,
             // Session commands
             session::commands::get_sessions,
             session::commands::create_session,
             session::commands::close_session,
+            session::commands::get_session_stats,
+            session::commands::reconnect_session,
+            session::commands::reconnect_all,
+            session::commands::get_latency_history,
             // Terminal commands
             terminal::commands::create_terminal,
             terminal::commands::get_terminal,
             terminal::commands::get_terminals,
             terminal::commands::connect_terminal,
             terminal::commands::write_terminal,
+            terminal::commands::create_terminal_group,
+            terminal::commands::add_to_group,
+            terminal::commands::remove_from_group,
+            terminal::commands::delete_terminal_group,
+            terminal::commands::write_terminal_broadcast,
             terminal::commands::resize_terminal,
             terminal::commands::close_terminal,
+            terminal::commands::get_login_banner,
+            terminal::commands::get_terminal_scrollback,
+            terminal::commands::export_terminal_output,
+            terminal::commands::export_recording,
+            terminal::commands::start_recording,
+            terminal::commands::stop_recording,
+            terminal::commands::set_input_logging,
+            terminal::commands::get_input_log,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|_app, event| {
+            if let tauri::RunEvent::Exit = event {
+                if let Err(e) = audit::flush() {
+                    tracing::warn!("Writing the audit log on exit failed: {}", e);
+                }
+            }
+        });
 }
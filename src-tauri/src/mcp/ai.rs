@@ -0,0 +1,321 @@
+//! AI backends for `nexus.ai.invoke`
+//!
+//! Params: `prompt`, and optionally `provider` and `model`. Prompts go to
+//! a local Ollama by default. `{"custom": name}` selects an
+//! OpenAI-compatible endpoint configured under that name, whose API key is
+//! read from a vault entry so it never has to be stored in the settings.
+
+use super::{vault, AiProvider, McpError};
+use crate::datasphere::DataSphereStorage;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// How long connecting to a backend may take
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Longest a single generation may run
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// AI backend settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AiConfig {
+    #[serde(default)]
+    pub ollama: OllamaConfig,
+    /// OpenAI-compatible endpoints for `AiProvider::Custom`, by name
+    #[serde(default)]
+    pub custom: HashMap<String, OpenAiEndpoint>,
+}
+
+/// Where Ollama runs and which model it uses
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OllamaConfig {
+    pub base_url: String,
+    pub model: String,
+}
+
+impl Default for OllamaConfig {
+    fn default() -> Self {
+        Self {
+            base_url: "http://localhost:11434".to_string(),
+            model: "llama3.2".to_string(),
+        }
+    }
+}
+
+/// An OpenAI-compatible chat completions endpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenAiEndpoint {
+    /// Base URL up to `/v1`, e.g. `https://api.example.com/v1`
+    pub base_url: String,
+    pub model: String,
+    /// Id or name of the vault entry holding the API key
+    #[serde(default)]
+    pub api_key_entry: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct InvokeParams {
+    prompt: String,
+    #[serde(default = "default_provider")]
+    provider: AiProvider,
+    model: Option<String>,
+}
+
+fn default_provider() -> AiProvider {
+    AiProvider::Ollama
+}
+
+/// Handle `nexus.ai.invoke`
+pub async fn invoke(
+    config: &AiConfig,
+    storage: &RwLock<Option<DataSphereStorage>>,
+    params: &Value,
+) -> Result<Value, McpError> {
+    let params: InvokeParams = serde_json::from_value(params.clone())
+        .map_err(|e| McpError::InvalidRequest(e.to_string()))?;
+    if params.prompt.trim().is_empty() {
+        return Err(McpError::InvalidRequest("Missing prompt".to_string()));
+    }
+    tracing::info!(
+        "MCP: AI invoke via {} with prompt length {}",
+        params.provider.key(),
+        params.prompt.len()
+    );
+
+    let (model, response) = match &params.provider {
+        AiProvider::Ollama => {
+            let client = OllamaClient::new(&config.ollama)?;
+            let model = params.model.unwrap_or_else(|| config.ollama.model.clone());
+            let response = client.generate(&model, &params.prompt).await?;
+            (model, response)
+        }
+        AiProvider::Custom(name) => {
+            let endpoint = config.custom.get(name).ok_or_else(|| {
+                McpError::InvalidRequest(format!("No AI endpoint configured for {}", name))
+            })?;
+            let api_key = endpoint
+                .api_key_entry
+                .as_deref()
+                .map(|key| api_key(storage, key))
+                .transpose()?;
+            let client = OpenAiClient::new(endpoint, api_key)?;
+            let model = params.model.unwrap_or_else(|| endpoint.model.clone());
+            let response = client.complete(&model, &params.prompt).await?;
+            (model, response)
+        }
+        other => {
+            return Err(McpError::InvalidRequest(format!(
+                "No AI backend for provider {}",
+                other.key()
+            )))
+        }
+    };
+
+    Ok(json!({
+        "provider": params.provider,
+        "model": model,
+        "response": response,
+    }))
+}
+
+/// Secret of the vault entry `key`
+fn api_key(storage: &RwLock<Option<DataSphereStorage>>, key: &str) -> Result<String, McpError> {
+    let storage = storage.read();
+    let storage = storage
+        .as_ref()
+        .ok_or_else(|| McpError::ExecutionError("Storage not initialized".to_string()))?;
+    vault::find_entry(&storage.get_vault_entries(), key)?
+        .map(|entry| entry.secret)
+        .ok_or_else(|| McpError::InvalidRequest(format!("No vault entry {}", key)))
+}
+
+fn http_client() -> Result<reqwest::Client, McpError> {
+    reqwest::Client::builder()
+        .connect_timeout(CONNECT_TIMEOUT)
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .map_err(|e| McpError::ExecutionError(e.to_string()))
+}
+
+/// Map a request failure, telling an unreachable backend apart
+fn request_error(base_url: &str, error: reqwest::Error) -> McpError {
+    if error.is_connect() {
+        McpError::AiBackendUnavailable(format!("{}: {}", base_url, error))
+    } else {
+        McpError::ExecutionError(error.to_string())
+    }
+}
+
+/// Client for Ollama's `/api/generate`
+pub struct OllamaClient {
+    http: reqwest::Client,
+    base_url: String,
+}
+
+impl OllamaClient {
+    pub fn new(config: &OllamaConfig) -> Result<Self, McpError> {
+        Ok(Self {
+            http: http_client()?,
+            base_url: config.base_url.trim_end_matches('/').to_string(),
+        })
+    }
+
+    /// Generate a reply to `prompt`, reading the streamed response to the end
+    pub async fn generate(&self, model: &str, prompt: &str) -> Result<String, McpError> {
+        let mut response = self
+            .http
+            .post(format!("{}/api/generate", self.base_url))
+            .json(&json!({
+                "model": model,
+                "prompt": prompt,
+                "stream": true,
+            }))
+            .send()
+            .await
+            .map_err(|e| request_error(&self.base_url, e))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(McpError::ExecutionError(format!(
+                "Ollama returned HTTP {}: {}",
+                status,
+                body.trim()
+            )));
+        }
+
+        let mut reply = GenerateReply::default();
+        let mut pending = Vec::new();
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .map_err(|e| request_error(&self.base_url, e))?
+        {
+            pending.extend_from_slice(&chunk);
+            while let Some(end) = pending.iter().position(|&b| b == b'\n') {
+                let line: Vec<u8> = pending.drain(..=end).collect();
+                reply.feed(&line)?;
+            }
+        }
+        reply.feed(&pending)?;
+
+        if !reply.done {
+            return Err(McpError::ExecutionError(
+                "Ollama ended the response early".to_string(),
+            ));
+        }
+        Ok(reply.text)
+    }
+}
+
+/// Text collected from Ollama's newline-delimited JSON stream
+#[derive(Default)]
+struct GenerateReply {
+    text: String,
+    done: bool,
+}
+
+#[derive(Deserialize)]
+struct GenerateChunk {
+    #[serde(default)]
+    response: String,
+    #[serde(default)]
+    done: bool,
+    error: Option<String>,
+}
+
+impl GenerateReply {
+    fn feed(&mut self, line: &[u8]) -> Result<(), McpError> {
+        if line.iter().all(u8::is_ascii_whitespace) {
+            return Ok(());
+        }
+        let chunk: GenerateChunk = serde_json::from_slice(line)
+            .map_err(|e| McpError::ExecutionError(format!("Bad Ollama response: {}", e)))?;
+        if let Some(error) = chunk.error {
+            return Err(McpError::ExecutionError(format!("Ollama: {}", error)));
+        }
+        self.text.push_str(&chunk.response);
+        self.done |= chunk.done;
+        Ok(())
+    }
+}
+
+/// Client for an OpenAI-compatible `/chat/completions`
+pub struct OpenAiClient {
+    http: reqwest::Client,
+    base_url: String,
+    api_key: Option<String>,
+}
+
+impl OpenAiClient {
+    pub fn new(endpoint: &OpenAiEndpoint, api_key: Option<String>) -> Result<Self, McpError> {
+        Ok(Self {
+            http: http_client()?,
+            base_url: endpoint.base_url.trim_end_matches('/').to_string(),
+            api_key,
+        })
+    }
+
+    /// Send `prompt` as a single user message and return the reply
+    pub async fn complete(&self, model: &str, prompt: &str) -> Result<String, McpError> {
+        let mut request = self
+            .http
+            .post(format!("{}/chat/completions", self.base_url))
+            .json(&json!({
+                "model": model,
+                "messages": [{"role": "user", "content": prompt}],
+            }));
+        if let Some(api_key) = &self.api_key {
+            request = request.bearer_auth(api_key);
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| request_error(&self.base_url, e))?;
+        let status = response.status();
+        let body: Value = response
+            .json()
+            .await
+            .map_err(|e| McpError::ExecutionError(e.to_string()))?;
+        if !status.is_success() {
+            let message = body["error"]["message"].as_str().unwrap_or_default();
+            return Err(McpError::ExecutionError(format!(
+                "AI endpoint returned HTTP {}: {}",
+                status, message
+            )));
+        }
+
+        body["choices"][0]["message"]["content"]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| McpError::ExecutionError("AI endpoint sent no reply".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_reply_stream() {
+        let mut reply = GenerateReply::default();
+        for line in [
+            r#"{"model":"llama3.2","response":"Hel","done":false}"#,
+            "",
+            r#"{"model":"llama3.2","response":"lo","done":false}"#,
+            r#"{"model":"llama3.2","response":"","done":true,"eval_count":2}"#,
+        ] {
+            reply.feed(line.as_bytes()).unwrap();
+        }
+        assert_eq!(reply.text, "Hello");
+        assert!(reply.done);
+
+        let mut failed = GenerateReply::default();
+        assert!(failed.feed(br#"{"error":"model 'x' not found"}"#).is_err());
+    }
+}
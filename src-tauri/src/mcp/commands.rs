@@ -0,0 +1,325 @@
+//! MCP Tauri Commands
+
+use super::{
+    exec, grants, handlers::JsonRpcResponse, http::http_provider, http_server, jail,
+    permissions::ApprovalRequest, AiProvider, McpAbility, McpConfig, McpError, McpHttpServer,
+    PermissionRule, ProviderPermission,
+};
+use crate::datasphere;
+use crate::sftp;
+use crate::ssh::client_for_session;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// Timeout for the loopback self-test request
+const TEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Step of the MCP self-test that failed
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum McpTestStage {
+    /// MCP is disabled in settings
+    Config,
+    /// The server could not listen on its port
+    Bind,
+    /// The HTTP request did not reach the server
+    Connect,
+    /// The server rejected the bearer token
+    Auth,
+    /// The server works, but `allowed_providers` leaves out the `http`
+    /// provider that requests with the main token count as
+    Provider,
+    /// The request arrived but the RPC call failed
+    Dispatch,
+}
+
+/// Result of an MCP self-test
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpTestResult {
+    pub success: bool,
+    pub failed_stage: Option<McpTestStage>,
+    pub error: Option<String>,
+    pub latency_ms: Option<u64>,
+    pub response: Option<serde_json::Value>,
+}
+
+impl McpTestResult {
+    fn failed(stage: McpTestStage, error: impl ToString) -> Self {
+        Self {
+            success: false,
+            failed_stage: Some(stage),
+            error: Some(error.to_string()),
+            latency_ms: None,
+            response: None,
+        }
+    }
+}
+
+/// Check the MCP server end-to-end
+///
+/// Starts the server if needed, then sends `nexus.server.list` over the
+/// real HTTP path with the bearer token. If `allowed_providers` leaves out
+/// the `http` provider that request counts as, it fails at the `provider`
+/// stage.
+#[tauri::command]
+pub async fn test_mcp() -> Result<McpTestResult, McpError> {
+    let config = match ensure_started().await {
+        Ok(config) => config,
+        Err(McpError::NotEnabled) => {
+            return Ok(McpTestResult::failed(
+                McpTestStage::Config,
+                McpError::NotEnabled,
+            ))
+        }
+        Err(e) => return Ok(McpTestResult::failed(McpTestStage::Bind, e)),
+    };
+
+    let client = reqwest::Client::builder()
+        .timeout(TEST_TIMEOUT)
+        .build()
+        .map_err(|e| McpError::ExecutionError(e.to_string()))?;
+
+    let start = Instant::now();
+    let response = match client
+        .post(format!("http://127.0.0.1:{}/rpc", config.port))
+        .bearer_auth(&config.auth_token)
+        .json(&serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "nexus.server.list",
+        }))
+        .send()
+        .await
+    {
+        Ok(response) => response,
+        Err(e) => return Ok(McpTestResult::failed(McpTestStage::Connect, e)),
+    };
+    let latency_ms = start.elapsed().as_millis() as u64;
+
+    if response.status() == reqwest::StatusCode::UNAUTHORIZED {
+        return Ok(McpTestResult::failed(
+            McpTestStage::Auth,
+            "Server rejected the bearer token",
+        ));
+    }
+    if !response.status().is_success() {
+        return Ok(McpTestResult::failed(
+            McpTestStage::Dispatch,
+            format!("HTTP {}", response.status()),
+        ));
+    }
+
+    let body: JsonRpcResponse = match response.json().await {
+        Ok(body) => body,
+        Err(e) => return Ok(McpTestResult::failed(McpTestStage::Dispatch, e)),
+    };
+    if let Some(error) = body.error {
+        // The server checks the allow-list as saved, which may be newer than `config`
+        let allowed = datasphere::storage()
+            .read()
+            .as_ref()
+            .map_or(config.allowed_providers, |s| {
+                s.get_settings().mcp.allowed_providers
+            });
+        let stage = if allowed.is_empty() || allowed.contains(&http_provider()) {
+            McpTestStage::Dispatch
+        } else {
+            McpTestStage::Provider
+        };
+        return Ok(McpTestResult::failed(
+            stage,
+            format!("{} ({})", error.message, error.code),
+        ));
+    }
+
+    Ok(McpTestResult {
+        success: true,
+        failed_stage: None,
+        error: None,
+        latency_ms: Some(latency_ms),
+        response: body.result,
+    })
+}
+
+/// Set whether `provider` may use `ability`, overriding the global rules
+///
+/// Saved with the MCP settings; returns all rules for the provider.
+#[tauri::command]
+pub fn set_provider_permission(
+    provider: AiProvider,
+    ability: McpAbility,
+    permission: PermissionRule,
+) -> Result<ProviderPermission, McpError> {
+    let mut storage = datasphere::storage().write();
+    let storage = storage.as_mut().ok_or_else(storage_unavailable)?;
+
+    let mut settings = storage.get_settings();
+    settings
+        .mcp
+        .permissions
+        .set_provider_rule(&provider, &ability, permission);
+    let rules = settings.mcp.permissions.provider_rules(&provider);
+    storage
+        .update_settings(settings)
+        .map_err(|e| McpError::ExecutionError(e.to_string()))?;
+
+    tracing::info!(
+        "MCP: {} set to {:?} for {}",
+        ability.as_str(),
+        permission,
+        provider.key()
+    );
+    Ok(rules)
+}
+
+/// Issue `provider` its own bearer token, replacing any it had
+///
+/// Requests made with the token are attributed to the provider, so its
+/// permission rules and rate limits apply to them.
+#[tauri::command]
+pub fn issue_provider_token(provider: AiProvider) -> Result<String, McpError> {
+    let mut storage = datasphere::storage().write();
+    let storage = storage.as_mut().ok_or_else(storage_unavailable)?;
+
+    let mut settings = storage.get_settings();
+    let token = settings.mcp.issue_provider_token(provider.clone());
+    storage
+        .update_settings(settings)
+        .map_err(|e| McpError::ExecutionError(e.to_string()))?;
+    tracing::info!("MCP: issued a token for {}", provider.key());
+    Ok(token)
+}
+
+/// Revoke the token issued to `provider`; returns whether it had one
+#[tauri::command]
+pub fn revoke_provider_token(provider: AiProvider) -> Result<bool, McpError> {
+    let mut storage = datasphere::storage().write();
+    let storage = storage.as_mut().ok_or_else(storage_unavailable)?;
+
+    let mut settings = storage.get_settings();
+    let before = settings.mcp.provider_tokens.len();
+    settings
+        .mcp
+        .provider_tokens
+        .retain(|t| t.provider != provider);
+    let revoked = settings.mcp.provider_tokens.len() < before;
+    storage
+        .update_settings(settings)
+        .map_err(|e| McpError::ExecutionError(e.to_string()))?;
+    Ok(revoked)
+}
+
+/// Get the per-ability rules set for `provider`
+#[tauri::command]
+pub fn get_provider_permissions(provider: AiProvider) -> Result<ProviderPermission, McpError> {
+    let storage = datasphere::storage().read();
+    let storage = storage.as_ref().ok_or_else(storage_unavailable)?;
+    Ok(storage
+        .get_settings()
+        .mcp
+        .permissions
+        .provider_rules(&provider))
+}
+
+/// Run a command and stream its output to MCP WebSocket clients
+///
+/// Returns the `exec_id` carried by the command's events; the command
+/// keeps running in the background.
+#[tauri::command]
+pub async fn stream_command_to_mcp(session_id: Uuid, command: String) -> Result<Uuid, McpError> {
+    let client =
+        client_for_session(session_id).map_err(|e| McpError::ExecutionError(e.to_string()))?;
+    exec::execute_published(client, session_id, command).await
+}
+
+/// Confine MCP file transfers on an SFTP session to `root`
+///
+/// `None` removes the jail. Returns the resolved root.
+#[tauri::command]
+pub async fn set_mcp_sftp_jail(
+    session_id: Uuid,
+    root: Option<String>,
+) -> Result<Option<String>, McpError> {
+    let client = sftp::manager()
+        .read()
+        .get_client(&session_id)
+        .ok_or_else(|| McpError::ExecutionError(format!("No SFTP session {}", session_id)))?;
+    jail::set_jail(&client, session_id, root.as_deref()).await
+}
+
+/// Create a scoped, time-limited MCP token
+///
+/// The token only allows `abilities`, on `host_ids` for abilities that act
+/// on a host, for `expires_in` seconds and at most `max_uses` requests.
+/// Permission rules and approvals still apply on top.
+#[tauri::command]
+pub fn create_mcp_grant(
+    abilities: Vec<McpAbility>,
+    host_ids: Vec<Uuid>,
+    expires_in: u64,
+    max_uses: Option<u32>,
+) -> Result<grants::GrantToken, McpError> {
+    grants::create(abilities, host_ids, expires_in, max_uses)
+}
+
+/// List live MCP grants, without their tokens
+#[tauri::command]
+pub fn list_grants() -> Vec<grants::McpGrant> {
+    grants::list()
+}
+
+/// Revoke an MCP grant; returns whether it existed
+#[tauri::command]
+pub fn revoke_grant(id: Uuid) -> bool {
+    grants::revoke(id)
+}
+
+/// List MCP requests waiting for the user's approval
+#[tauri::command]
+pub async fn list_mcp_approvals() -> Vec<ApprovalRequest> {
+    let server = http_server().lock().await;
+    match server.as_ref().and_then(|s| s.state()) {
+        Some(state) => state.pending_approvals.read().await.clone(),
+        None => Vec::new(),
+    }
+}
+
+/// Approve or deny a waiting MCP request
+///
+/// Returns whether the request was still waiting.
+#[tauri::command]
+pub async fn resolve_mcp_approval(id: Uuid, approved: bool) -> bool {
+    let server = http_server().lock().await;
+    match server.as_ref().and_then(|s| s.state()) {
+        Some(state) => state.resolve_approval(id, approved).await,
+        None => false,
+    }
+}
+
+fn storage_unavailable() -> McpError {
+    McpError::ExecutionError("Storage not initialized".to_string())
+}
+
+/// Start the MCP server from settings unless it is already running
+///
+/// Returns the configuration of the running server.
+async fn ensure_started() -> Result<McpConfig, McpError> {
+    let mut server = http_server().lock().await;
+
+    if let Some(running) = server.as_ref().filter(|s| s.is_running()) {
+        return Ok(running.config().clone());
+    }
+
+    let config = datasphere::storage()
+        .read()
+        .as_ref()
+        .map(|s| s.get_settings().mcp)
+        .unwrap_or_default();
+
+    let mut instance = McpHttpServer::new(config.clone());
+    instance.start().await?;
+    *server = Some(instance);
+
+    Ok(config)
+}
@@ -0,0 +1,142 @@
+//! Publishing remote command output to MCP clients
+//!
+//! Output goes out on the MCP event channel as `CommandStarted`,
+//! `CommandOutput` and `CommandExited` events sharing one `exec_id`.
+//! Publishing needs `nexus.logs.stream` to be allowed for WebSocket
+//! clients; output can't wait for an approval, so "ask" counts as denied.
+//! A client that falls behind misses chunks, which shows as a gap in `seq`.
+
+use super::http::websocket_provider;
+use super::permissions::{PermissionManager, PermissionResult};
+use super::{events, http_server, McpAbility, McpError, McpEvent};
+use crate::datasphere;
+use crate::ssh::{ChunkDecoder, CommandOutput, OutputChunk, SshClient, SshError};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Sends the events for one command
+pub struct ExecPublisher {
+    exec_id: Uuid,
+}
+
+impl ExecPublisher {
+    /// Announce a command, if its output may be published
+    pub async fn start(session_id: Uuid, command: &str) -> Result<Self, McpError> {
+        let running = http_server()
+            .lock()
+            .await
+            .as_ref()
+            .is_some_and(|s| s.is_running());
+        if !running {
+            return Err(McpError::ExecutionError(
+                "MCP server is not running".to_string(),
+            ));
+        }
+
+        let permissions = datasphere::storage()
+            .read()
+            .as_ref()
+            .map(|s| s.get_settings().mcp.permissions)
+            .unwrap_or_default();
+        if !output_permitted(&permissions) {
+            return Err(McpError::PermissionDenied(format!(
+                "{} is not allowed for WebSocket clients",
+                McpAbility::LogsStream.as_str()
+            )));
+        }
+
+        let publisher = Self {
+            exec_id: Uuid::new_v4(),
+        };
+        publisher.send(McpEvent::CommandStarted {
+            exec_id: publisher.exec_id,
+            session_id,
+            command: command.to_string(),
+        });
+        Ok(publisher)
+    }
+
+    pub fn exec_id(&self) -> Uuid {
+        self.exec_id
+    }
+
+    pub fn output(&self, chunk: OutputChunk) {
+        self.send(McpEvent::CommandOutput {
+            exec_id: self.exec_id,
+            chunk,
+        });
+    }
+
+    /// Announce how the command ended
+    pub fn finish(self, result: &Result<CommandOutput, SshError>) {
+        let (exit_code, error) = match result {
+            Ok(output) => (Some(output.exit_code), None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+        self.send(McpEvent::CommandExited {
+            exec_id: self.exec_id,
+            exit_code,
+            error,
+        });
+    }
+
+    fn send(&self, event: McpEvent) {
+        // No subscribers just means nobody is listening right now
+        let _ = events().send(event);
+    }
+}
+
+/// Run `command` in the background, publishing its output
+///
+/// Returns the `exec_id` once the start event has been sent.
+pub async fn execute_published(
+    client: Arc<SshClient>,
+    session_id: Uuid,
+    command: String,
+) -> Result<Uuid, McpError> {
+    let publisher = ExecPublisher::start(session_id, &command).await?;
+    let exec_id = publisher.exec_id();
+
+    tokio::spawn(async move {
+        let mut decoder = ChunkDecoder::new(|chunk| publisher.output(chunk));
+        let result = client
+            .execute_with_callback(&command, &[], |stream, data| decoder.push(stream, data))
+            .await;
+        decoder.finish();
+        drop(decoder);
+
+        tracing::info!("MCP: published command {} finished", exec_id);
+        publisher.finish(&result);
+    });
+
+    Ok(exec_id)
+}
+
+/// Whether WebSocket clients may receive command output
+fn output_permitted(permissions: &PermissionManager) -> bool {
+    permissions.check_permission(&websocket_provider(), &McpAbility::LogsStream)
+        == PermissionResult::Allowed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::PermissionRule;
+
+    #[test]
+    fn test_output_permitted() {
+        let mut permissions = PermissionManager::default();
+        assert!(!output_permitted(&permissions));
+
+        permissions.grant(&McpAbility::LogsStream);
+        assert!(output_permitted(&permissions));
+
+        // A provider rule wins over the global grant
+        permissions.set_provider_rule(
+            &websocket_provider(),
+            &McpAbility::LogsStream,
+            PermissionRule::Ask,
+        );
+        assert!(!output_permitted(&permissions));
+    }
+}
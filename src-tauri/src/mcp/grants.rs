@@ -0,0 +1,264 @@
+//! Time-limited, scoped MCP access grants
+//!
+//! A grant is a separate bearer token limited to some abilities, some
+//! hosts, a lifetime and optionally a number of requests. It is checked
+//! on every request before the usual permission and approval rules, which
+//! still apply. Grants are kept in memory only, so a restart revokes them.
+
+use super::{McpAbility, McpError};
+use chrono::{DateTime, Duration, Utc};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Active grants by id
+static GRANTS: Lazy<RwLock<HashMap<Uuid, McpGrant>>> = Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// A scoped grant; the token itself is only returned once, at creation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpGrant {
+    pub id: Uuid,
+    #[serde(skip_serializing)]
+    token: String,
+    pub abilities: Vec<McpAbility>,
+    /// Hosts the grant may touch; empty means no host at all
+    pub host_ids: Vec<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub max_uses: Option<u32>,
+    pub uses: u32,
+}
+
+impl McpGrant {
+    fn is_live(&self, now: DateTime<Utc>) -> bool {
+        now < self.expires_at && self.max_uses.is_none_or(|max| self.uses < max)
+    }
+
+    /// Check one request against the grant's scope
+    fn allows(&self, ability: &McpAbility, host_id: Option<Uuid>) -> Result<(), McpError> {
+        if !self.abilities.contains(ability) {
+            return Err(McpError::PermissionDenied(format!(
+                "{} is outside the grant",
+                ability.as_str()
+            )));
+        }
+        if targets_host(ability) {
+            match host_id {
+                Some(host) if self.host_ids.contains(&host) => {}
+                Some(host) => {
+                    return Err(McpError::PermissionDenied(format!(
+                        "host {} is outside the grant",
+                        host
+                    )))
+                }
+                None => {
+                    return Err(McpError::PermissionDenied(
+                        "the grant only covers saved hosts".to_string(),
+                    ))
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Token handed to the client
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GrantToken {
+    pub id: Uuid,
+    pub token: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Create a grant valid for `expires_in` seconds
+pub fn create(
+    abilities: Vec<McpAbility>,
+    host_ids: Vec<Uuid>,
+    expires_in: u64,
+    max_uses: Option<u32>,
+) -> Result<GrantToken, McpError> {
+    if abilities.is_empty() {
+        return Err(McpError::InvalidRequest(
+            "A grant needs at least one ability".to_string(),
+        ));
+    }
+    if expires_in == 0 || max_uses == Some(0) {
+        return Err(McpError::InvalidRequest(
+            "A grant must allow at least one request".to_string(),
+        ));
+    }
+
+    let now = Utc::now();
+    let grant = McpGrant {
+        id: Uuid::new_v4(),
+        token: format!("nxg_{}", super::generate_token()),
+        abilities,
+        host_ids,
+        created_at: now,
+        expires_at: now + Duration::seconds(expires_in.min(i64::MAX as u64) as i64),
+        max_uses,
+        uses: 0,
+    };
+    let token = GrantToken {
+        id: grant.id,
+        token: grant.token.clone(),
+        expires_at: grant.expires_at,
+    };
+    tracing::info!(
+        "MCP: created grant {} for {:?} until {}",
+        grant.id,
+        grant.abilities,
+        grant.expires_at
+    );
+    GRANTS.write().insert(grant.id, grant);
+    Ok(token)
+}
+
+/// Live grants; expired and used-up ones are dropped first
+pub fn list() -> Vec<McpGrant> {
+    let mut grants = GRANTS.write();
+    prune(&mut grants);
+    let mut list: Vec<_> = grants.values().cloned().collect();
+    list.sort_by_key(|g| g.created_at);
+    list
+}
+
+/// Revoke a grant; returns whether it existed
+pub fn revoke(id: Uuid) -> bool {
+    let removed = GRANTS.write().remove(&id).is_some();
+    if removed {
+        tracing::info!("MCP: revoked grant {}", id);
+    }
+    removed
+}
+
+/// The live grant whose token is `token`, if any
+pub fn find(token: &str) -> Option<Uuid> {
+    let now = Utc::now();
+    GRANTS
+        .read()
+        .values()
+        .find(|g| super::token_matches(token, &g.token) && g.is_live(now))
+        .map(|g| g.id)
+}
+
+/// Whether a live grant includes `ability`, without using it up
+pub fn covers(id: Uuid, ability: &McpAbility) -> bool {
+    let now = Utc::now();
+    GRANTS
+        .read()
+        .get(&id)
+        .is_some_and(|g| g.is_live(now) && g.abilities.contains(ability))
+}
+
+/// Check a request against a grant and count it as one use
+pub fn consume(id: Uuid, ability: &McpAbility, params: &serde_json::Value) -> Result<(), McpError> {
+    let host_id = request_host(params)?;
+    let mut grants = GRANTS.write();
+    prune(&mut grants);
+    let grant = grants
+        .get_mut(&id)
+        .ok_or_else(|| McpError::PermissionDenied("grant expired or revoked".to_string()))?;
+    grant.allows(ability, host_id)?;
+    grant.uses += 1;
+    Ok(())
+}
+
+/// Drop grants that can no longer be used
+fn prune(grants: &mut HashMap<Uuid, McpGrant>) {
+    let now = Utc::now();
+    grants.retain(|id, grant| {
+        let live = grant.is_live(now);
+        if !live {
+            tracing::info!("MCP: grant {} expired", id);
+        }
+        live
+    });
+}
+
+/// Abilities that act on a particular host
+fn targets_host(ability: &McpAbility) -> bool {
+    matches!(
+        ability,
+        McpAbility::SshConnect
+            | McpAbility::SshExecute
+            | McpAbility::SshUpload
+            | McpAbility::SshDownload
+            | McpAbility::SftpList
+            | McpAbility::LogsStream
+    )
+}
+
+/// Host a request refers to, by `host_id` or through its `session_id`
+///
+/// A session's host always wins; a `host_id` sent alongside it must agree.
+fn request_host(params: &serde_json::Value) -> Result<Option<Uuid>, McpError> {
+    let id = |key: &str| {
+        params
+            .get(key)
+            .and_then(|v| v.as_str())
+            .and_then(|s| Uuid::parse_str(s).ok())
+    };
+    match id("session_id") {
+        Some(session) => session_host(id("host_id"), crate::session::host_of(session)),
+        None => Ok(id("host_id")),
+    }
+}
+
+/// Check a claimed host against the one the session is actually on
+fn session_host(claimed: Option<Uuid>, actual: Option<Uuid>) -> Result<Option<Uuid>, McpError> {
+    match claimed {
+        Some(claimed) if Some(claimed) != actual => Err(McpError::PermissionDenied(format!(
+            "host {} is not the session's host",
+            claimed
+        ))),
+        _ => Ok(actual),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grant_scope() {
+        let host = Uuid::new_v4();
+        let now = Utc::now();
+        let mut grant = McpGrant {
+            id: Uuid::new_v4(),
+            token: "t".to_string(),
+            abilities: vec![McpAbility::SshExecute, McpAbility::ServerList],
+            host_ids: vec![host],
+            created_at: now,
+            expires_at: now + Duration::hours(1),
+            max_uses: Some(2),
+            uses: 0,
+        };
+
+        assert!(grant.allows(&McpAbility::SshExecute, Some(host)).is_ok());
+        assert!(grant
+            .allows(&McpAbility::SshExecute, Some(Uuid::new_v4()))
+            .is_err());
+        assert!(grant.allows(&McpAbility::SshExecute, None).is_err());
+        assert!(grant.allows(&McpAbility::ServerList, None).is_ok());
+        assert!(grant.allows(&McpAbility::SshUpload, Some(host)).is_err());
+
+        assert!(grant.is_live(now));
+        grant.uses = 2;
+        assert!(!grant.is_live(now));
+        grant.uses = 0;
+        assert!(!grant.is_live(now + Duration::hours(2)));
+    }
+
+    #[test]
+    fn test_session_host() {
+        let host = Uuid::new_v4();
+        assert_eq!(session_host(None, Some(host)).unwrap(), Some(host));
+        assert_eq!(session_host(Some(host), Some(host)).unwrap(), Some(host));
+        assert_eq!(session_host(None, None).unwrap(), None);
+        assert!(session_host(Some(host), Some(Uuid::new_v4())).is_err());
+        assert!(session_host(Some(host), None).is_err());
+    }
+}
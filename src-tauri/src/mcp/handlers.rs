@@ -66,6 +66,9 @@ impl From<McpError> for ErrorMessage {
                 McpError::InvalidRequest(_) => "INVALID_REQUEST",
                 McpError::ProviderNotAllowed(_) => "PROVIDER_NOT_ALLOWED",
                 McpError::ExecutionError(_) => "EXECUTION_ERROR",
+                McpError::BindFailed(_) => "BIND_FAILED",
+                McpError::RateLimited(_) => "RATE_LIMITED",
+                McpError::AiBackendUnavailable(_) => "AI_BACKEND_UNAVAILABLE",
             }
             .to_string(),
             message: err.to_string(),
@@ -124,4 +127,24 @@ impl JsonRpcResponse {
             }),
         }
     }
+
+    /// Error response for a failed ability
+    ///
+    /// Rate limiting gets its own code, with `retry_after` in seconds as data.
+    pub fn from_mcp_error(id: Option<serde_json::Value>, error: &McpError) -> Self {
+        let mut response = Self::error(id, -32000, error.to_string());
+        if let (McpError::RateLimited(retry_after), Some(rpc_error)) =
+            (error, response.error.as_mut())
+        {
+            rpc_error.code = RATE_LIMITED;
+            rpc_error.message = "rate limited".to_string();
+            rpc_error.data = Some(serde_json::json!({
+                "retry_after": retry_after.as_secs_f64().ceil() as u64,
+            }));
+        }
+        response
+    }
 }
+
+/// JSON-RPC error code for requests refused by the rate limiter
+pub const RATE_LIMITED: i32 = -32029;
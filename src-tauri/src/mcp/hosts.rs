@@ -0,0 +1,206 @@
+//! Host abilities (`nexus.server.list` / `nexus.server.add`)
+//!
+//! The list carries connection details but no passwords, passphrases or
+//! key paths.
+//!
+//! Add params mirror `NewHost`: `name`, `hostname` and `username` are
+//! required; `port` defaults to 22 and `auth_type` to `agent`. The MCP
+//! server speaks plain HTTP, so `password` and `passphrase` are refused;
+//! secrets have to be added in the app. `private_key` is a key file path,
+//! not key material.
+
+use super::McpError;
+use crate::datasphere::{AuthType, DataSphereStorage, Host, NewHost};
+use parking_lot::RwLock;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ServerAddParams {
+    name: String,
+    hostname: String,
+    username: String,
+    #[serde(default = "default_port")]
+    port: u16,
+    #[serde(default = "default_auth_type")]
+    auth_type: AuthType,
+    private_key: Option<String>,
+    password: Option<String>,
+    passphrase: Option<String>,
+    group_id: Option<Uuid>,
+    #[serde(default)]
+    tags: Vec<String>,
+    notes: Option<String>,
+    initial_path: Option<String>,
+}
+
+fn default_port() -> u16 {
+    22
+}
+
+fn default_auth_type() -> AuthType {
+    AuthType::Agent
+}
+
+/// Handle `nexus.server.list`
+pub fn list(storage: &RwLock<Option<DataSphereStorage>>) -> Result<Value, McpError> {
+    let storage = storage.read();
+    let storage = storage
+        .as_ref()
+        .ok_or_else(|| McpError::ExecutionError("Storage not initialized".to_string()))?;
+
+    let mut hosts = storage.get_hosts();
+    hosts.sort_by_key(|h| h.name.to_lowercase());
+    let servers: Vec<Value> = hosts
+        .iter()
+        .map(|host| {
+            json!({
+                "host_id": host.id,
+                "name": host.name,
+                "hostname": host.hostname,
+                "port": host.port,
+                "username": host.username,
+                "auth_type": host.auth_type,
+                "group_id": host.group_id,
+                "tags": host.tags,
+                "notes": host.notes,
+                "last_connected": host.last_connected,
+            })
+        })
+        .collect();
+
+    Ok(json!({ "servers": servers }))
+}
+
+/// Handle `nexus.server.add`
+pub fn add(storage: &RwLock<Option<DataSphereStorage>>, params: &Value) -> Result<Value, McpError> {
+    let new = validate(params)?;
+
+    let mut storage = storage.write();
+    let storage = storage
+        .as_mut()
+        .ok_or_else(|| McpError::ExecutionError("Storage not initialized".to_string()))?;
+    if let Some(group_id) = new.group_id {
+        if !storage.get_groups().iter().any(|g| g.id == group_id) {
+            return Err(McpError::InvalidRequest(format!(
+                "Unknown group {}",
+                group_id
+            )));
+        }
+    }
+
+    let host = storage
+        .add_host(Host::from_new(new))
+        .map_err(|e| McpError::ExecutionError(e.to_string()))?;
+    tracing::info!("MCP: added host {} ({})", host.name, host.hostname);
+
+    Ok(json!({
+        "host_id": host.id,
+        "name": host.name,
+    }))
+}
+
+fn validate(params: &Value) -> Result<NewHost, McpError> {
+    let params: ServerAddParams = serde_json::from_value(params.clone())
+        .map_err(|e| McpError::InvalidRequest(e.to_string()))?;
+
+    if params.password.is_some() || params.passphrase.is_some() {
+        return Err(McpError::InvalidRequest(
+            "Secrets can't be sent over the unencrypted MCP connection; add them in Nexus"
+                .to_string(),
+        ));
+    }
+
+    for (field, value) in [
+        ("name", &params.name),
+        ("hostname", &params.hostname),
+        ("username", &params.username),
+    ] {
+        if value.trim().is_empty() {
+            return Err(McpError::InvalidRequest(format!("Missing {}", field)));
+        }
+    }
+    if params.hostname.chars().any(char::is_whitespace) {
+        return Err(McpError::InvalidRequest(format!(
+            "Invalid hostname: {}",
+            params.hostname
+        )));
+    }
+    if params.port == 0 {
+        return Err(McpError::InvalidRequest("Port must not be 0".to_string()));
+    }
+    if matches!(params.auth_type, AuthType::PrivateKey)
+        && params
+            .private_key
+            .as_deref()
+            .is_none_or(|p| p.trim().is_empty())
+    {
+        return Err(McpError::InvalidRequest(
+            "private_key path is required for key authentication".to_string(),
+        ));
+    }
+
+    Ok(NewHost {
+        name: params.name.trim().to_string(),
+        hostname: params.hostname,
+        port: params.port,
+        username: params.username.trim().to_string(),
+        auth_type: params.auth_type,
+        password: None,
+        private_key: params.private_key,
+        passphrase: None,
+        group_id: params.group_id,
+        tags: params.tags,
+        icon: None,
+        color: None,
+        notes: params.notes,
+        initial_path: params.initial_path,
+        suppress_motd: false,
+        exec_profile: None,
+        transport: Default::default(),
+        encoding: None,
+        send_encoding: None,
+        normalize_newlines: false,
+        security_profile: None,
+        proxy_jump: None,
+        env: Default::default(),
+        init_commands: vec![],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_server_add_validation() {
+        let new = validate(&json!({
+            "name": "web-1",
+            "hostname": "10.0.0.5",
+            "username": "deploy",
+        }))
+        .unwrap();
+        assert_eq!(new.port, 22);
+        assert!(matches!(new.auth_type, AuthType::Agent));
+
+        let with_secret = json!({
+            "name": "web-1",
+            "hostname": "10.0.0.5",
+            "username": "deploy",
+            "auth_type": "password",
+            "password": "hunter2",
+        });
+        assert!(validate(&with_secret).is_err());
+        assert!(validate(&json!({"name": "web-1", "hostname": "", "username": "x"})).is_err());
+        assert!(validate(
+            &json!({"name": "a", "hostname": "b", "username": "c", "auth_type": "private_key"})
+        )
+        .is_err());
+        assert!(
+            validate(&json!({"name": "a", "hostname": "b", "username": "c", "shell": "sh"}))
+                .is_err()
+        );
+    }
+}
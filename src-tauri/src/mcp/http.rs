@@ -5,31 +5,75 @@
 use axum::{
     extract::{
         ws::{Message, WebSocket, WebSocketUpgrade},
-        State,
+        Extension, Request, State,
     },
-    http::StatusCode,
-    response::IntoResponse,
+    http::{header::AUTHORIZATION, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
     routing::{get, post},
     Json, Router,
 };
 use futures::{sink::SinkExt, stream::StreamExt};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
-use tokio::sync::{broadcast, RwLock};
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc, RwLock};
 use tower_http::cors::{Any, CorsLayer};
 use uuid::Uuid;
 
+use crate::datasphere::{self, DataSphereStorage};
+use crate::ssh::{self, OutputChunk, SshClient};
+use std::collections::HashMap;
+
 use super::{
+    ai, grants,
     handlers::{CapabilitiesMessage, JsonRpcRequest, JsonRpcResponse},
-    permissions::ApprovalRequest,
-    AiProvider, McpAbility, McpConfig, McpError, McpRequest,
+    hosts,
+    logs::{self, LogSubscriptions},
+    permissions::{ApprovalRequest, PermissionResult},
+    ratelimit::RateLimiter,
+    sessions, transfer, vault, AiProvider, McpAbility, McpConfig, McpError, McpRequest,
 };
 
+/// How long a write ability waits for the user's approval
+const APPROVAL_TIMEOUT: Duration = Duration::from_secs(120);
+
 /// Shared application state
 pub struct AppState {
     pub config: McpConfig,
     pub pending_approvals: RwLock<Vec<ApprovalRequest>>,
     pub event_tx: broadcast::Sender<McpEvent>,
+    /// Hosts and vault, see `datasphere::storage`
+    pub storage: &'static parking_lot::RwLock<Option<DataSphereStorage>>,
+    /// Standalone SSH sessions, see `ssh::clients`
+    pub ssh_clients: &'static parking_lot::RwLock<HashMap<Uuid, Arc<SshClient>>>,
+    pub rate_limiter: RateLimiter,
+}
+
+impl AppState {
+    /// MCP settings as currently saved, so rule and limit changes apply
+    /// without a restart; the configuration the server started with while
+    /// storage is locked
+    fn current_config(&self) -> McpConfig {
+        self.storage
+            .read()
+            .as_ref()
+            .map(|s| s.get_settings().mcp)
+            .unwrap_or_else(|| self.config.clone())
+    }
+
+    /// Approve or deny a waiting request; returns whether it was still waiting
+    pub async fn resolve_approval(&self, id: Uuid, approved: bool) -> bool {
+        let mut approvals = self.pending_approvals.write().await;
+        let Some(pos) = approvals.iter().position(|a| a.id == id) else {
+            return false;
+        };
+        approvals.remove(pos);
+        let _ = self
+            .event_tx
+            .send(McpEvent::ApprovalResolved { id, approved });
+        true
+    }
 }
 
 /// Events that can be broadcast to WebSocket clients
@@ -37,15 +81,60 @@ pub struct AppState {
 #[serde(tag = "type", content = "data")]
 pub enum McpEvent {
     ApprovalRequired(ApprovalRequest),
-    ApprovalResolved { id: Uuid, approved: bool },
-    SessionOutput { session_id: Uuid, data: String },
-    Error { message: String },
+    ApprovalResolved {
+        id: Uuid,
+        approved: bool,
+    },
+    /// Terminal output, sent to connections streaming the session (see `logs`)
+    SessionOutput {
+        session_id: Uuid,
+        data: String,
+    },
+    /// A command published with `exec::execute_published` started
+    CommandStarted {
+        exec_id: Uuid,
+        session_id: Uuid,
+        command: String,
+    },
+    CommandOutput {
+        exec_id: Uuid,
+        chunk: OutputChunk,
+    },
+    /// The command finished; `error` is set if it couldn't be run
+    CommandExited {
+        exec_id: Uuid,
+        exit_code: Option<i32>,
+        error: Option<String>,
+    },
+    Error {
+        message: String,
+    },
+}
+
+/// Set on requests authenticated with a grant token instead of the main one
+#[derive(Debug, Clone, Copy)]
+struct GrantId(Uuid);
+
+/// Set on requests authenticated with a provider's own token
+#[derive(Debug, Clone)]
+struct Client(AiProvider);
+
+/// Provider that requests arriving over `/ws` with the main token are
+/// attributed to
+pub(crate) fn websocket_provider() -> AiProvider {
+    AiProvider::Custom("websocket".to_string())
+}
+
+/// Provider that requests to `/rpc` with the main token are attributed to
+pub(crate) fn http_provider() -> AiProvider {
+    AiProvider::Custom("http".to_string())
 }
 
 /// HTTP server for MCP
 pub struct McpHttpServer {
     config: McpConfig,
     shutdown_tx: Option<tokio::sync::oneshot::Sender<()>>,
+    state: Option<Arc<AppState>>,
 }
 
 impl McpHttpServer {
@@ -53,6 +142,7 @@ impl McpHttpServer {
         Self {
             config,
             shutdown_tx: None,
+            state: None,
         }
     }
 
@@ -62,13 +152,16 @@ impl McpHttpServer {
             return Err(McpError::NotEnabled);
         }
 
-        let (event_tx, _) = broadcast::channel::<McpEvent>(100);
+        let event_tx = super::events().clone();
         let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
 
         let state = Arc::new(AppState {
             config: self.config.clone(),
             pending_approvals: RwLock::new(Vec::new()),
             event_tx,
+            storage: datasphere::storage(),
+            ssh_clients: ssh::clients(),
+            rate_limiter: RateLimiter::default(),
         });
 
         let cors = CorsLayer::new()
@@ -76,9 +169,8 @@ impl McpHttpServer {
             .allow_methods(Any)
             .allow_headers(Any);
 
-        let app = Router::new()
-            // REST endpoints
-            .route("/health", get(health_check))
+        // Everything except the health check requires the bearer token
+        let protected = Router::new()
             .route("/capabilities", get(get_capabilities))
             .route("/rpc", post(handle_rpc))
             .route("/approvals", get(get_approvals))
@@ -86,17 +178,24 @@ impl McpHttpServer {
             .route("/approvals/:id/deny", post(deny_request))
             // WebSocket endpoint
             .route("/ws", get(websocket_handler))
+            .route_layer(middleware::from_fn_with_state(state.clone(), require_token));
+
+        let app = Router::new()
+            .route("/health", get(health_check))
+            .merge(protected)
             .layer(cors)
-            .with_state(state);
+            .with_state(state.clone());
 
+        // Loopback only: responses, vault secrets included, aren't encrypted
         let addr = format!("127.0.0.1:{}", self.config.port);
         let listener = tokio::net::TcpListener::bind(&addr)
             .await
-            .map_err(|e| McpError::ExecutionError(e.to_string()))?;
+            .map_err(|e| McpError::BindFailed(format!("{}: {}", addr, e)))?;
 
         tracing::info!("MCP HTTP server listening on {}", addr);
 
         self.shutdown_tx = Some(shutdown_tx);
+        self.state = Some(state.clone());
 
         tokio::spawn(async move {
             axum::serve(listener, app)
@@ -116,6 +215,74 @@ impl McpHttpServer {
             let _ = tx.send(());
             tracing::info!("MCP HTTP server stopped");
         }
+        self.state = None;
+    }
+
+    /// Whether the server is currently listening
+    pub fn is_running(&self) -> bool {
+        self.shutdown_tx.is_some()
+    }
+
+    /// Configuration the server was created with
+    pub fn config(&self) -> &McpConfig {
+        &self.config
+    }
+
+    /// State shared with the request handlers while running
+    pub fn state(&self) -> Option<&Arc<AppState>> {
+        self.state.as_ref()
+    }
+}
+
+/// Reject requests without the configured bearer token, a provider's token
+/// or a live grant
+///
+/// WebSocket clients that can't set headers may pass `?token=` instead.
+/// Provider tokens are read from the saved settings, so newly issued ones
+/// work right away.
+async fn require_token(
+    State(state): State<Arc<AppState>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let expected = state.config.auth_token.as_str();
+    let config = state.current_config();
+
+    let from_header = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::to_string);
+    let from_query = request.uri().query().and_then(|q| {
+        q.split('&')
+            .find_map(|pair| pair.strip_prefix("token="))
+            .map(str::to_string)
+    });
+    let presented: Vec<String> = from_header.into_iter().chain(from_query).collect();
+
+    if presented
+        .iter()
+        .any(|token| super::token_matches(token, expected))
+    {
+        return next.run(request).await;
+    }
+    if let Some(provider) = presented
+        .iter()
+        .find_map(|token| config.token_provider(token))
+    {
+        request.extensions_mut().insert(Client(provider.clone()));
+        return next.run(request).await;
+    }
+    if let Some(grant) = presented.iter().find_map(|token| grants::find(token)) {
+        request.extensions_mut().insert(GrantId(grant));
+        next.run(request).await
+    } else {
+        (
+            StatusCode::UNAUTHORIZED,
+            Json(serde_json::json!({"error": "Missing or invalid bearer token"})),
+        )
+            .into_response()
     }
 }
 
@@ -143,6 +310,8 @@ struct RpcRequest {
 
 async fn handle_rpc(
     State(state): State<Arc<AppState>>,
+    grant: Option<Extension<GrantId>>,
+    client: Option<Extension<Client>>,
     Json(request): Json<RpcRequest>,
 ) -> impl IntoResponse {
     // Parse the method to an ability
@@ -160,16 +329,15 @@ async fn handle_rpc(
     // Create MCP request
     let mcp_request = McpRequest {
         id: Uuid::new_v4(),
-        provider: AiProvider::Custom("http".to_string()),
+        provider: client.map_or_else(http_provider, |Extension(Client(provider))| provider),
         ability,
         params: request.params.unwrap_or(serde_json::json!({})),
         timestamp: chrono::Utc::now(),
     };
 
-    // Check if provider is allowed
-    if !state.config.allowed_providers.is_empty()
-        && !state.config.allowed_providers.contains(&mcp_request.provider)
-    {
+    // Check if provider is allowed, by the allow-list as currently saved
+    let allowed_providers = state.current_config().allowed_providers;
+    if !allowed_providers.is_empty() && !allowed_providers.contains(&mcp_request.provider) {
         return Json(JsonRpcResponse::error(
             request.id,
             -32600,
@@ -178,69 +346,97 @@ async fn handle_rpc(
     }
 
     // Execute the ability
-    let result = execute_ability(&state, &mcp_request).await;
+    let grant = grant.map(|Extension(GrantId(id))| id);
+    let result = run_request(&state, &mcp_request, grant, None).await;
 
     match result {
         Ok(data) => Json(JsonRpcResponse::success(request.id, data)),
-        Err(e) => Json(JsonRpcResponse::error(request.id, -32000, e.to_string())),
+        Err(e) => Json(JsonRpcResponse::from_mcp_error(request.id, &e)),
     }
 }
 
-async fn get_approvals(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+async fn get_approvals(
+    State(state): State<Arc<AppState>>,
+    grant: Option<Extension<GrantId>>,
+) -> Response {
+    if grant.is_some() {
+        return grant_forbidden();
+    }
     let approvals = state.pending_approvals.read().await;
     Json(serde_json::json!({
         "approvals": *approvals
     }))
+    .into_response()
 }
 
 async fn approve_request(
     State(state): State<Arc<AppState>>,
+    grant: Option<Extension<GrantId>>,
     axum::extract::Path(id): axum::extract::Path<Uuid>,
-) -> impl IntoResponse {
-    let mut approvals = state.pending_approvals.write().await;
-    if let Some(pos) = approvals.iter().position(|a| a.id == id) {
-        approvals.remove(pos);
-        let _ = state
-            .event_tx
-            .send(McpEvent::ApprovalResolved { id, approved: true });
-        (StatusCode::OK, Json(serde_json::json!({"status": "approved"})))
-    } else {
-        (
-            StatusCode::NOT_FOUND,
-            Json(serde_json::json!({"error": "Approval not found"})),
-        )
-    }
+) -> Response {
+    resolve_request(&state, grant, id, true).await
 }
 
 async fn deny_request(
     State(state): State<Arc<AppState>>,
+    grant: Option<Extension<GrantId>>,
     axum::extract::Path(id): axum::extract::Path<Uuid>,
-) -> impl IntoResponse {
-    let mut approvals = state.pending_approvals.write().await;
-    if let Some(pos) = approvals.iter().position(|a| a.id == id) {
-        approvals.remove(pos);
-        let _ = state
-            .event_tx
-            .send(McpEvent::ApprovalResolved { id, approved: false });
-        (StatusCode::OK, Json(serde_json::json!({"status": "denied"})))
+) -> Response {
+    resolve_request(&state, grant, id, false).await
+}
+
+/// Answer an approval; grant holders may not answer their own requests
+async fn resolve_request(
+    state: &AppState,
+    grant: Option<Extension<GrantId>>,
+    id: Uuid,
+    approved: bool,
+) -> Response {
+    if grant.is_some() {
+        return grant_forbidden();
+    }
+    if state.resolve_approval(id, approved).await {
+        let status = if approved { "approved" } else { "denied" };
+        (StatusCode::OK, Json(serde_json::json!({"status": status}))).into_response()
     } else {
         (
             StatusCode::NOT_FOUND,
             Json(serde_json::json!({"error": "Approval not found"})),
         )
+            .into_response()
     }
 }
 
+fn grant_forbidden() -> Response {
+    (
+        StatusCode::FORBIDDEN,
+        Json(serde_json::json!({"error": "Grant tokens can't manage approvals"})),
+    )
+        .into_response()
+}
+
 async fn websocket_handler(
     ws: WebSocketUpgrade,
     State(state): State<Arc<AppState>>,
+    grant: Option<Extension<GrantId>>,
+    client: Option<Extension<Client>>,
 ) -> impl IntoResponse {
-    ws.on_upgrade(|socket| handle_websocket(socket, state))
+    let grant = grant.map(|Extension(GrantId(id))| id);
+    let provider = client.map_or_else(websocket_provider, |Extension(Client(provider))| provider);
+    ws.on_upgrade(move |socket| handle_websocket(socket, state, grant, provider))
 }
 
-async fn handle_websocket(socket: WebSocket, state: Arc<AppState>) {
+async fn handle_websocket(
+    socket: WebSocket,
+    state: Arc<AppState>,
+    grant: Option<Uuid>,
+    provider: AiProvider,
+) {
     let (mut sender, mut receiver) = socket.split();
     let mut event_rx = state.event_tx.subscribe();
+    let (response_tx, mut response_rx) = mpsc::unbounded_channel::<JsonRpcResponse>();
+    // Dropped with the last pending request once the socket closes
+    let subscriptions = Arc::new(LogSubscriptions::default());
 
     // Send capabilities on connect
     let caps = CapabilitiesMessage::new();
@@ -255,20 +451,48 @@ async fn handle_websocket(socket: WebSocket, state: Arc<AppState>) {
                 match msg {
                     Some(Ok(Message::Text(text))) => {
                         if let Ok(request) = serde_json::from_str::<JsonRpcRequest>(&text) {
-                            let response = process_ws_request(&state, request).await;
-                            let response_text = serde_json::to_string(&response).unwrap();
-                            if sender.send(Message::Text(response_text)).await.is_err() {
-                                break;
-                            }
+                            // A request waiting for approval mustn't hold up the socket
+                            let state = state.clone();
+                            let response_tx = response_tx.clone();
+                            let subscriptions = subscriptions.clone();
+                            let provider = provider.clone();
+                            tokio::spawn(async move {
+                                let response = process_ws_request(
+                                    &state,
+                                    request,
+                                    provider,
+                                    grant,
+                                    &subscriptions,
+                                )
+                                .await;
+                                let _ = response_tx.send(response);
+                            });
                         }
                     }
                     Some(Ok(Message::Close(_))) | None => break,
                     _ => {}
                 }
             }
+            // Send responses as their requests finish
+            Some(response) = response_rx.recv() => {
+                let response_text = serde_json::to_string(&response).unwrap();
+                if sender.send(Message::Text(response_text)).await.is_err() {
+                    break;
+                }
+            }
             // Broadcast events to client
             event = event_rx.recv() => {
-                if let Ok(event) = event {
+                // Grant holders only see events if the grant covers log streaming
+                let visible = grant.is_none_or(|id| grants::covers(id, &McpAbility::LogsStream));
+                // Session output only goes to connections streaming that session
+                let wanted = |event: &McpEvent| match event {
+                    McpEvent::SessionOutput { session_id, .. } => subscriptions.contains(*session_id),
+                    _ => true,
+                };
+                if let (Ok(event), true) = (event, visible) {
+                    if !wanted(&event) {
+                        continue;
+                    }
                     let event_json = serde_json::to_string(&event).unwrap();
                     if sender.send(Message::Text(event_json)).await.is_err() {
                         break;
@@ -282,6 +506,9 @@ async fn handle_websocket(socket: WebSocket, state: Arc<AppState>) {
 async fn process_ws_request(
     state: &Arc<AppState>,
     request: JsonRpcRequest,
+    provider: AiProvider,
+    grant: Option<Uuid>,
+    subscriptions: &LogSubscriptions,
 ) -> JsonRpcResponse {
     let ability = match parse_ability(&request.method) {
         Some(a) => a,
@@ -296,25 +523,27 @@ async fn process_ws_request(
 
     let mcp_request = McpRequest {
         id: Uuid::new_v4(),
-        provider: AiProvider::Custom("websocket".to_string()),
+        provider,
         ability,
         params: request.params.unwrap_or(serde_json::json!({})),
         timestamp: chrono::Utc::now(),
     };
 
-    match execute_ability(state, &mcp_request).await {
+    match run_request(state, &mcp_request, grant, Some(subscriptions)).await {
         Ok(data) => JsonRpcResponse::success(request.id, data),
-        Err(e) => JsonRpcResponse::error(request.id, -32000, e.to_string()),
+        Err(e) => JsonRpcResponse::from_mcp_error(request.id, &e),
     }
 }
 
 fn parse_ability(method: &str) -> Option<McpAbility> {
     match method {
         "nexus.server.list" => Some(McpAbility::ServerList),
+        "nexus.server.add" => Some(McpAbility::ServerAdd),
         "nexus.ssh.connect" => Some(McpAbility::SshConnect),
         "nexus.ssh.execute" => Some(McpAbility::SshExecute),
         "nexus.ssh.upload" => Some(McpAbility::SshUpload),
         "nexus.ssh.download" => Some(McpAbility::SshDownload),
+        "nexus.sftp.list" => Some(McpAbility::SftpList),
         "nexus.datasphere.get" => Some(McpAbility::DatasphereGet),
         "nexus.datasphere.set" => Some(McpAbility::DatasphereSet),
         "nexus.logs.stream" => Some(McpAbility::LogsStream),
@@ -323,81 +552,136 @@ fn parse_ability(method: &str) -> Option<McpAbility> {
     }
 }
 
+/// Check a request against the permission rules
+///
+/// Rules are read from the saved settings on every request, so changes
+/// apply to a running server. Blocked abilities are refused even if they
+/// only read. Without a standing rule, abilities that `needs_approval`
+/// wait up to `APPROVAL_TIMEOUT` for `/approvals/:id/approve` or `/deny`,
+/// or the matching app command. Abilities that `reveals_secrets` always
+/// wait, so a secret only leaves through the loopback listener once the
+/// user said so.
+async fn authorize(state: &Arc<AppState>, request: &McpRequest) -> Result<(), McpError> {
+    let config = state.current_config();
+    let always_ask = request.ability.reveals_secrets();
+    match config
+        .permissions
+        .check_permission(&request.provider, &request.ability)
+    {
+        PermissionResult::Denied => {
+            return Err(McpError::PermissionDenied(
+                request.ability.as_str().to_string(),
+            ))
+        }
+        _ if always_ask => {}
+        PermissionResult::Allowed => return Ok(()),
+        // Only abilities with effects wait for an answer
+        PermissionResult::RequiresApproval
+            if !config.require_approval || !request.ability.needs_approval() =>
+        {
+            return Ok(())
+        }
+        PermissionResult::RequiresApproval => {}
+    }
+
+    let approval = ApprovalRequest::new(
+        request.provider.clone(),
+        request.ability.clone(),
+        &request.params,
+    );
+    let id = approval.id;
+    // Subscribe first so a quick answer isn't missed
+    let mut events = state.event_tx.subscribe();
+    state.pending_approvals.write().await.push(approval.clone());
+    let _ = state.event_tx.send(McpEvent::ApprovalRequired(approval));
+
+    let answer = tokio::time::timeout(APPROVAL_TIMEOUT, async {
+        loop {
+            match events.recv().await {
+                Ok(McpEvent::ApprovalResolved {
+                    id: resolved,
+                    approved,
+                }) if resolved == id => return approved,
+                Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return false,
+            }
+        }
+    })
+    .await;
+
+    match answer {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(McpError::PermissionDenied(format!(
+            "{} was denied",
+            request.ability.as_str()
+        ))),
+        Err(_) => {
+            state.pending_approvals.write().await.retain(|a| a.id != id);
+            Err(McpError::PermissionDenied(format!(
+                "{} was not approved in time",
+                request.ability.as_str()
+            )))
+        }
+    }
+}
+
+/// Run a request within its provider's rate limits
+///
+/// Limits are tracked per client: each grant on its own, otherwise the
+/// provider the token belongs to. An execution slot is only taken once the
+/// request is approved, so waiting for the user doesn't hold one. WebSocket
+/// requests pass their connection's log subscriptions.
+async fn run_request(
+    state: &Arc<AppState>,
+    request: &McpRequest,
+    grant: Option<Uuid>,
+    subscriptions: Option<&LogSubscriptions>,
+) -> Result<serde_json::Value, McpError> {
+    let limit = state
+        .current_config()
+        .rate_limits
+        .for_provider(&request.provider);
+    let client = grant.map_or_else(|| request.provider.key(), |id| format!("grant:{}", id));
+    state.rate_limiter.admit(&client, limit)?;
+
+    if let Some(grant) = grant {
+        grants::consume(grant, &request.ability, &request.params)?;
+    }
+    authorize(state, request).await?;
+
+    // Held until the ability finishes
+    let _slot = state.rate_limiter.start(&client, &request.ability, limit)?;
+    execute_ability(state, request, subscriptions).await
+}
+
+/// Run an ability that was allowed
 async fn execute_ability(
-    _state: &Arc<AppState>,
+    state: &Arc<AppState>,
     request: &McpRequest,
+    subscriptions: Option<&LogSubscriptions>,
 ) -> Result<serde_json::Value, McpError> {
-    // TODO: Connect to actual DataSphere and SSH modules
+
     match request.ability {
-        McpAbility::ServerList => {
-            // Get hosts from DataSphere
-            Ok(serde_json::json!({
-                "servers": []
-            }))
-        }
+        McpAbility::ServerList => hosts::list(state.storage),
+        McpAbility::ServerAdd => hosts::add(state.storage, &request.params),
         McpAbility::SshConnect => {
-            let host_id = request.params.get("host_id")
-                .and_then(|v| v.as_str())
-                .ok_or_else(|| McpError::InvalidRequest("Missing host_id".to_string()))?;
-
-            Ok(serde_json::json!({
-                "status": "connected",
-                "session_id": Uuid::new_v4(),
-                "host_id": host_id
-            }))
-        }
-        McpAbility::SshExecute => {
-            let command = request.params.get("command")
-                .and_then(|v| v.as_str())
-                .ok_or_else(|| McpError::InvalidRequest("Missing command".to_string()))?;
-
-            tracing::info!("MCP executing command: {}", command);
-
-            Ok(serde_json::json!({
-                "stdout": format!("Output of: {}", command),
-                "stderr": "",
-                "exit_code": 0
-            }))
+            sessions::connect(state.storage, state.ssh_clients, &request.params).await
         }
+        McpAbility::SshExecute => sessions::execute(state.ssh_clients, &request.params).await,
         McpAbility::SshUpload => {
-            Ok(serde_json::json!({
-                "status": "uploaded"
-            }))
+            let transfer_dir = state.current_config().transfer_dir;
+            transfer::upload(&request.params, transfer_dir.as_deref()).await
         }
         McpAbility::SshDownload => {
-            Ok(serde_json::json!({
-                "status": "downloaded"
-            }))
-        }
-        McpAbility::DatasphereGet => {
-            let key = request.params.get("key")
-                .and_then(|v| v.as_str())
-                .ok_or_else(|| McpError::InvalidRequest("Missing key".to_string()))?;
-
-            Ok(serde_json::json!({
-                "key": key,
-                "value": null
-            }))
-        }
-        McpAbility::DatasphereSet => {
-            Ok(serde_json::json!({
-                "status": "set"
-            }))
-        }
-        McpAbility::LogsStream => {
-            Ok(serde_json::json!({
-                "status": "streaming"
-            }))
+            let transfer_dir = state.current_config().transfer_dir;
+            transfer::download(&request.params, transfer_dir.as_deref()).await
         }
+        McpAbility::SftpList => transfer::list(&request.params).await,
+        McpAbility::DatasphereGet => vault::get(state.storage, &request.params),
+        McpAbility::DatasphereSet => vault::set(state.storage, &request.params),
+        McpAbility::LogsStream => logs::stream(subscriptions, &request.params),
         McpAbility::AiInvoke => {
-            let prompt = request.params.get("prompt")
-                .and_then(|v| v.as_str())
-                .ok_or_else(|| McpError::InvalidRequest("Missing prompt".to_string()))?;
-
-            // TODO: Connect to Ollama or other local AI
-            Ok(serde_json::json!({
-                "response": format!("AI response to: {}", &prompt[..prompt.len().min(50)])
-            }))
+            ai::invoke(&state.current_config().ai, state.storage, &request.params).await
         }
     }
 }
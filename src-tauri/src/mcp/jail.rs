@@ -0,0 +1,158 @@
+//! Confining MCP file operations to a directory
+//!
+//! An SFTP session can be given a jail root. Remote paths passed to
+//! `nexus.ssh.upload`, `nexus.ssh.download` and `nexus.sftp.list` are resolved against it and
+//! refused with `PermissionDenied` if they end up outside, whether through
+//! `..`, an absolute path or a symlink. Jails are off unless set; with
+//! `McpConfig::require_sftp_jail` sessions without one are refused.
+
+use super::McpError;
+use crate::datasphere;
+use crate::sftp::SftpClient;
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Jail roots by session id
+static JAILS: Lazy<RwLock<HashMap<Uuid, String>>> = Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// Set or clear the jail root of a session
+pub async fn set_jail(
+    client: &SftpClient,
+    session_id: Uuid,
+    root: Option<&str>,
+) -> Result<Option<String>, McpError> {
+    let Some(root) = root.map(str::trim).filter(|r| !r.is_empty()) else {
+        JAILS.write().remove(&session_id);
+        tracing::info!("MCP: cleared SFTP jail of session {}", session_id);
+        return Ok(None);
+    };
+
+    // Store the real location so symlinked roots compare correctly later
+    let root = client
+        .canonicalize(root)
+        .await
+        .map_err(|e| McpError::InvalidRequest(e.to_string()))?;
+    if !client
+        .stat(&root)
+        .await
+        .map_err(|e| McpError::InvalidRequest(e.to_string()))?
+        .is_dir
+    {
+        return Err(McpError::InvalidRequest(format!(
+            "{} is not a directory",
+            root
+        )));
+    }
+
+    JAILS.write().insert(session_id, root.clone());
+    tracing::info!("MCP: SFTP session {} jailed to {}", session_id, root);
+    Ok(Some(root))
+}
+
+/// Jail root of a session, if any
+pub fn jail(session_id: Uuid) -> Option<String> {
+    JAILS.read().get(&session_id).cloned()
+}
+
+/// Check `requested` against the session's jail and return the path to use
+pub async fn check(
+    client: &SftpClient,
+    session_id: Uuid,
+    requested: &str,
+) -> Result<String, McpError> {
+    let Some(root) = jail(session_id) else {
+        let required = datasphere::storage()
+            .read()
+            .as_ref()
+            .is_some_and(|s| s.get_settings().mcp.require_sftp_jail);
+        if required {
+            return Err(McpError::PermissionDenied(format!(
+                "SFTP session {} has no jail root",
+                session_id
+            )));
+        }
+        return Ok(requested.to_string());
+    };
+
+    let path = confine(&root, requested)?;
+
+    // A symlink inside the jail may still point outside it. New files
+    // don't exist yet, so their parent directory is resolved instead.
+    let resolved = match client.canonicalize(&path).await {
+        Ok(real) => Some(real),
+        Err(_) => match path.rsplit_once('/') {
+            Some((parent, name)) => client
+                .canonicalize(if parent.is_empty() { "/" } else { parent })
+                .await
+                .ok()
+                .map(|real| format!("{}/{}", real.trim_end_matches('/'), name)),
+            None => None,
+        },
+    };
+    if let Some(real) = resolved {
+        confine(&root, &real)?;
+    }
+
+    Ok(path)
+}
+
+/// Normalize `requested` against `root` without touching the server
+///
+/// Relative paths are taken from `root`. Fails if the result is outside it.
+fn confine(root: &str, requested: &str) -> Result<String, McpError> {
+    let joined = if requested.starts_with('/') {
+        requested.to_string()
+    } else {
+        format!("{}/{}", root, requested)
+    };
+
+    let mut parts: Vec<&str> = Vec::new();
+    for part in joined.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            name => parts.push(name),
+        }
+    }
+    let path = format!("/{}", parts.join("/"));
+
+    let root = root.trim_end_matches('/');
+    let inside = root.is_empty()
+        || path == root
+        || path
+            .strip_prefix(root)
+            .is_some_and(|rest| rest.starts_with('/'));
+    if inside {
+        Ok(path)
+    } else {
+        Err(McpError::PermissionDenied(format!(
+            "{} is outside the SFTP jail {}",
+            requested,
+            if root.is_empty() { "/" } else { root }
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_confine() {
+        let root = "/srv/app";
+        assert_eq!(confine(root, "logs/a.txt").unwrap(), "/srv/app/logs/a.txt");
+        assert_eq!(confine(root, "/srv/app/./x/../y").unwrap(), "/srv/app/y");
+        assert_eq!(confine(root, "/srv/app").unwrap(), "/srv/app");
+
+        assert!(confine(root, "../../etc/shadow").is_err());
+        assert!(confine(root, "/etc/shadow").is_err());
+        assert!(confine(root, "/srv/app/../app2/secret").is_err());
+        assert!(confine(root, "/srv/application").is_err());
+
+        assert_eq!(confine("/", "../etc/hosts").unwrap(), "/etc/hosts");
+    }
+}
@@ -0,0 +1,152 @@
+//! Live terminal output for MCP clients (`nexus.logs.stream`)
+//!
+//! A WebSocket connection calls `nexus.logs.stream` with a terminal
+//! `session_id` to receive that session's output as `SessionOutput`
+//! events; `unsubscribe: true` stops it again. Subscriptions belong to the
+//! connection and end when it closes. Terminals only publish output while
+//! at least one connection is subscribed to them.
+
+use super::{events, McpError, McpEvent};
+use crate::terminal;
+use once_cell::sync::Lazy;
+use parking_lot::{Mutex, RwLock};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+/// Subscribed connections per session
+static SUBSCRIBERS: Lazy<RwLock<HashMap<Uuid, usize>>> = Lazy::new(|| RwLock::new(HashMap::new()));
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StreamParams {
+    session_id: Uuid,
+    #[serde(default)]
+    unsubscribe: bool,
+}
+
+/// Sessions one WebSocket connection streams; dropping it unsubscribes all
+#[derive(Default)]
+pub struct LogSubscriptions {
+    sessions: Mutex<HashSet<Uuid>>,
+}
+
+impl LogSubscriptions {
+    pub fn contains(&self, session_id: Uuid) -> bool {
+        self.sessions.lock().contains(&session_id)
+    }
+
+    fn subscribe(&self, session_id: Uuid) {
+        if self.sessions.lock().insert(session_id) {
+            *SUBSCRIBERS.write().entry(session_id).or_insert(0) += 1;
+        }
+    }
+
+    fn unsubscribe(&self, session_id: Uuid) -> bool {
+        let removed = self.sessions.lock().remove(&session_id);
+        if removed {
+            release(session_id);
+        }
+        removed
+    }
+}
+
+impl Drop for LogSubscriptions {
+    fn drop(&mut self) {
+        for session_id in self.sessions.get_mut().drain() {
+            release(session_id);
+        }
+    }
+}
+
+fn release(session_id: Uuid) {
+    let mut subscribers = SUBSCRIBERS.write();
+    if let Some(count) = subscribers.get_mut(&session_id) {
+        *count -= 1;
+        if *count == 0 {
+            subscribers.remove(&session_id);
+        }
+    }
+}
+
+/// Whether any connection streams the session
+pub fn is_streamed(session_id: Uuid) -> bool {
+    SUBSCRIBERS.read().contains_key(&session_id)
+}
+
+/// Publish terminal output if a connection streams the session
+pub fn publish(session_id: Uuid, data: &str) {
+    if is_streamed(session_id) {
+        // No receivers just means the last connection is closing
+        let _ = events().send(McpEvent::SessionOutput {
+            session_id,
+            data: data.to_string(),
+        });
+    }
+}
+
+/// Handle `nexus.logs.stream` for a WebSocket connection
+///
+/// `subscriptions` is `None` for plain HTTP requests, which can't stream.
+pub fn stream(subscriptions: Option<&LogSubscriptions>, params: &Value) -> Result<Value, McpError> {
+    let Some(subscriptions) = subscriptions else {
+        return Err(McpError::InvalidRequest(
+            "nexus.logs.stream needs a WebSocket connection to /ws".to_string(),
+        ));
+    };
+    let params: StreamParams = serde_json::from_value(params.clone())
+        .map_err(|e| McpError::InvalidRequest(e.to_string()))?;
+
+    if params.unsubscribe {
+        let was_subscribed = subscriptions.unsubscribe(params.session_id);
+        return Ok(json!({
+            "status": "unsubscribed",
+            "session_id": params.session_id,
+            "was_subscribed": was_subscribed,
+        }));
+    }
+
+    if terminal::manager()
+        .read()
+        .get_session(params.session_id)
+        .is_none()
+    {
+        return Err(McpError::InvalidRequest(format!(
+            "No terminal session {}",
+            params.session_id
+        )));
+    }
+    subscriptions.subscribe(params.session_id);
+    tracing::info!("MCP: streaming output of session {}", params.session_id);
+
+    Ok(json!({
+        "status": "streaming",
+        "session_id": params.session_id,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subscriptions_end_with_connection() {
+        let session_id = Uuid::new_v4();
+        let first = LogSubscriptions::default();
+        let second = LogSubscriptions::default();
+
+        first.subscribe(session_id);
+        first.subscribe(session_id);
+        second.subscribe(session_id);
+        assert!(is_streamed(session_id));
+        assert!(first.contains(session_id));
+
+        assert!(first.unsubscribe(session_id));
+        assert!(!first.unsubscribe(session_id));
+        assert!(is_streamed(session_id));
+
+        drop(second);
+        assert!(!is_streamed(session_id));
+    }
+}
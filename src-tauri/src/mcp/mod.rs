@@ -5,25 +5,68 @@
 //!
 //! Capabilities:
 //! - nexus.server.list
+//! - nexus.server.add
 //! - nexus.ssh.connect
 //! - nexus.ssh.execute
 //! - nexus.ssh.upload
 //! - nexus.ssh.download
+//! - nexus.sftp.list
 //! - nexus.datasphere.set
 //! - nexus.datasphere.get
 //! - nexus.logs.stream
 //! - nexus.ai.invoke
 
+pub mod ai;
+pub mod commands;
+pub mod exec;
+pub mod grants;
 pub mod handlers;
+pub mod hosts;
 pub mod http;
+pub mod jail;
+pub mod logs;
 pub mod permissions;
+pub mod ratelimit;
 pub mod server;
+pub mod sessions;
+pub mod transfer;
+pub mod vault;
 
-pub use http::McpHttpServer;
+pub use ai::AiConfig;
+pub use http::{McpEvent, McpHttpServer};
+pub use permissions::{PermissionManager, PermissionRule, ProviderPermission};
+pub use ratelimit::{RateLimit, RateLimits};
 
+use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use subtle::ConstantTimeEq;
+use tokio::sync::{broadcast, Mutex};
 use uuid::Uuid;
 
+/// Global MCP HTTP server instance
+static MCP_HTTP_SERVER: Lazy<Mutex<Option<McpHttpServer>>> = Lazy::new(|| Mutex::new(None));
+
+/// Get the MCP HTTP server slot
+pub fn http_server() -> &'static Mutex<Option<McpHttpServer>> {
+    &MCP_HTTP_SERVER
+}
+
+/// Events queued per WebSocket client before it starts missing some
+const EVENT_CAPACITY: usize = 256;
+
+/// Channel for events sent to WebSocket clients
+///
+/// Shared across server restarts so other modules can publish without
+/// holding the server lock.
+static MCP_EVENTS: Lazy<broadcast::Sender<McpEvent>> =
+    Lazy::new(|| broadcast::channel(EVENT_CAPACITY).0);
+
+/// Get the MCP event channel
+pub fn events() -> &'static broadcast::Sender<McpEvent> {
+    &MCP_EVENTS
+}
+
 /// MCP Server configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct McpConfig {
@@ -31,6 +74,33 @@ pub struct McpConfig {
     pub port: u16,
     pub require_approval: bool,
     pub allowed_providers: Vec<AiProvider>,
+    /// Bearer token clients must send on every request except `/health`
+    ///
+    /// Generated and saved the first time the DataSphere is unlocked
+    /// without one, so it stays the same across restarts.
+    #[serde(default)]
+    pub auth_token: String,
+    /// Tokens issued to single providers; requests made with one are
+    /// attributed to its provider, so that provider's rules and limits apply
+    #[serde(default)]
+    pub provider_tokens: Vec<ProviderToken>,
+    #[serde(default)]
+    pub permissions: PermissionManager,
+    /// Refuse MCP file transfers on SFTP sessions without a jail root
+    ///
+    /// Off by default so existing setups keep working; recommended on.
+    #[serde(default)]
+    pub require_sftp_jail: bool,
+    /// Local directory that `local_path` transfers are confined to; without
+    /// one they are refused
+    #[serde(default)]
+    pub transfer_dir: Option<PathBuf>,
+    /// Request rate and concurrency limits per provider
+    #[serde(default)]
+    pub rate_limits: RateLimits,
+    /// Backends for `nexus.ai.invoke`
+    #[serde(default)]
+    pub ai: AiConfig,
 }
 
 impl Default for McpConfig {
@@ -40,10 +110,61 @@ impl Default for McpConfig {
             port: 9742,
             require_approval: true,
             allowed_providers: vec![],
+            auth_token: String::new(),
+            provider_tokens: vec![],
+            permissions: PermissionManager::default(),
+            require_sftp_jail: false,
+            transfer_dir: None,
+            rate_limits: RateLimits::default(),
+            ai: AiConfig::default(),
         }
     }
 }
 
+/// Parse an ability's params, refusing malformed ones as invalid requests
+fn parse_params<T: for<'de> Deserialize<'de>>(params: &serde_json::Value) -> Result<T, McpError> {
+    serde_json::from_value(params.clone()).map_err(|e| McpError::InvalidRequest(e.to_string()))
+}
+
+/// Generate a random bearer token
+pub fn generate_token() -> String {
+    format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+/// Compare a presented token with a known one in constant time, so the
+/// response time doesn't reveal how much of it was right
+pub(crate) fn token_matches(presented: &str, expected: &str) -> bool {
+    !expected.is_empty() && bool::from(presented.as_bytes().ct_eq(expected.as_bytes()))
+}
+
+/// Bearer token that identifies a provider
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ProviderToken {
+    pub provider: AiProvider,
+    pub token: String,
+}
+
+impl McpConfig {
+    /// Provider a token was issued to
+    pub fn token_provider(&self, token: &str) -> Option<&AiProvider> {
+        self.provider_tokens
+            .iter()
+            .find(|t| token_matches(token, &t.token))
+            .map(|t| &t.provider)
+    }
+
+    /// Give `provider` a new token, replacing the one it had
+    pub fn issue_provider_token(&mut self, provider: AiProvider) -> String {
+        self.provider_tokens.retain(|t| t.provider != provider);
+        let token = generate_token();
+        self.provider_tokens.push(ProviderToken {
+            provider,
+            token: token.clone(),
+        });
+        token
+    }
+}
+
 /// Supported AI providers
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "lowercase")]
@@ -54,6 +175,18 @@ pub enum AiProvider {
     Custom(String),
 }
 
+impl AiProvider {
+    /// Stable key for per-provider settings
+    pub fn key(&self) -> String {
+        match self {
+            Self::Claude => "claude".to_string(),
+            Self::ChatGPT => "chatgpt".to_string(),
+            Self::Ollama => "ollama".to_string(),
+            Self::Custom(name) => format!("custom:{}", name),
+        }
+    }
+}
+
 /// MCP Request from AI
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct McpRequest {
@@ -80,6 +213,7 @@ pub struct McpResponse {
 pub enum McpAbility {
     // Server abilities
     ServerList,
+    ServerAdd,
 
     // SSH abilities
     SshConnect,
@@ -87,6 +221,9 @@ pub enum McpAbility {
     SshUpload,
     SshDownload,
 
+    // SFTP abilities
+    SftpList,
+
     // DataSphere abilities
     DatasphereGet,
     DatasphereSet,
@@ -103,10 +240,12 @@ impl McpAbility {
     pub fn as_str(&self) -> &'static str {
         match self {
             Self::ServerList => "nexus.server.list",
+            Self::ServerAdd => "nexus.server.add",
             Self::SshConnect => "nexus.ssh.connect",
             Self::SshExecute => "nexus.ssh.execute",
             Self::SshUpload => "nexus.ssh.upload",
             Self::SshDownload => "nexus.ssh.download",
+            Self::SftpList => "nexus.sftp.list",
             Self::DatasphereGet => "nexus.datasphere.get",
             Self::DatasphereSet => "nexus.datasphere.set",
             Self::LogsStream => "nexus.logs.stream",
@@ -114,14 +253,43 @@ impl McpAbility {
         }
     }
 
+    /// Whether the ability changes stored data and needs approval
+    pub fn is_write(&self) -> bool {
+        matches!(self, Self::ServerAdd | Self::DatasphereSet)
+    }
+
+    /// Whether the ability hands out secrets, so every use is approved by
+    /// the user whatever the permission rules say
+    pub fn reveals_secrets(&self) -> bool {
+        matches!(self, Self::DatasphereGet)
+    }
+
+    /// Whether the ability acts on servers or exposes secrets and so has
+    /// to pass the permission check before it runs
+    pub fn needs_approval(&self) -> bool {
+        self.is_write()
+            || matches!(
+                self,
+                Self::SshConnect
+                    | Self::SshExecute
+                    | Self::SshUpload
+                    | Self::SshDownload
+                    | Self::SftpList
+                    | Self::LogsStream
+                    | Self::DatasphereGet
+            )
+    }
+
     /// Get all available abilities
     pub fn all() -> Vec<Self> {
         vec![
             Self::ServerList,
+            Self::ServerAdd,
             Self::SshConnect,
             Self::SshExecute,
             Self::SshUpload,
             Self::SshDownload,
+            Self::SftpList,
             Self::DatasphereGet,
             Self::DatasphereSet,
             Self::LogsStream,
@@ -143,6 +311,12 @@ pub enum McpError {
     ProviderNotAllowed(AiProvider),
     #[error("Execution error: {0}")]
     ExecutionError(String),
+    #[error("Could not bind MCP server: {0}")]
+    BindFailed(String),
+    #[error("Rate limited, retry after {}s", .0.as_secs_f64().ceil())]
+    RateLimited(std::time::Duration),
+    #[error("AI backend unavailable: {0}")]
+    AiBackendUnavailable(String),
 }
 
 impl Serialize for McpError {
@@ -13,7 +13,10 @@ pub struct PermissionManager {
     pub auto_approve: HashSet<String>,
     /// Abilities that are always denied
     pub blocked: HashSet<String>,
-    /// Per-provider permissions
+    /// Per-provider permissions, keyed by `AiProvider::key`
+    ///
+    /// A provider's rule for an ability takes precedence over the global sets.
+    #[serde(default)]
     pub provider_permissions: std::collections::HashMap<String, ProviderPermission>,
 }
 
@@ -35,7 +38,19 @@ impl PermissionManager {
         ability: &McpAbility,
     ) -> PermissionResult {
         let ability_str = ability.as_str().to_string();
-        let provider_str = format!("{:?}", provider).to_lowercase();
+
+        // Provider-specific rules are authoritative
+        if let Some(rule) = self
+            .provider_permissions
+            .get(&provider.key())
+            .and_then(|perms| perms.rules.get(&ability_str))
+        {
+            return match rule {
+                PermissionRule::Allow => PermissionResult::Allowed,
+                PermissionRule::Block => PermissionResult::Denied,
+                PermissionRule::Ask => PermissionResult::RequiresApproval,
+            };
+        }
 
         // Check if blocked
         if self.blocked.contains(&ability_str) {
@@ -47,16 +62,6 @@ impl PermissionManager {
             return PermissionResult::Allowed;
         }
 
-        // Check provider-specific permissions
-        if let Some(perms) = self.provider_permissions.get(&provider_str) {
-            if perms.allowed_abilities.contains(&ability_str) {
-                return PermissionResult::Allowed;
-            }
-            if perms.blocked_abilities.contains(&ability_str) {
-                return PermissionResult::Denied;
-            }
-        }
-
         // Default: require approval
         PermissionResult::RequiresApproval
     }
@@ -72,13 +77,46 @@ impl PermissionManager {
         self.blocked.insert(ability.as_str().to_string());
         self.auto_approve.remove(ability.as_str());
     }
+
+    /// Set how `provider` is treated for `ability`
+    pub fn set_provider_rule(
+        &mut self,
+        provider: &AiProvider,
+        ability: &McpAbility,
+        rule: PermissionRule,
+    ) {
+        self.provider_permissions
+            .entry(provider.key())
+            .or_default()
+            .rules
+            .insert(ability.as_str().to_string(), rule);
+    }
+
+    /// Rules set for `provider`
+    pub fn provider_rules(&self, provider: &AiProvider) -> ProviderPermission {
+        self.provider_permissions
+            .get(&provider.key())
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+/// How a provider is treated for one ability
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum PermissionRule {
+    Allow,
+    Block,
+    /// Always ask, even if the ability is auto-approved globally
+    Ask,
 }
 
 /// Provider-specific permissions
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct ProviderPermission {
-    pub allowed_abilities: HashSet<String>,
-    pub blocked_abilities: HashSet<String>,
+    /// Rule per ability, keyed by `McpAbility::as_str`
+    #[serde(default)]
+    pub rules: std::collections::HashMap<String, PermissionRule>,
 }
 
 /// Result of a permission check
@@ -119,10 +157,12 @@ impl ApprovalRequest {
     fn describe_ability(ability: &McpAbility) -> String {
         match ability {
             McpAbility::ServerList => "List available servers".to_string(),
+            McpAbility::ServerAdd => "Add a server to the host list".to_string(),
             McpAbility::SshConnect => "Connect to an SSH server".to_string(),
             McpAbility::SshExecute => "Execute a command on a server".to_string(),
             McpAbility::SshUpload => "Upload a file to a server".to_string(),
             McpAbility::SshDownload => "Download a file from a server".to_string(),
+            McpAbility::SftpList => "List a directory on a server".to_string(),
             McpAbility::DatasphereGet => "Read data from DataSphere".to_string(),
             McpAbility::DatasphereSet => "Write data to DataSphere".to_string(),
             McpAbility::LogsStream => "Stream logs from a session".to_string(),
@@ -130,3 +170,44 @@ impl ApprovalRequest {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_provider_rules_take_precedence() {
+        let mut perms = PermissionManager::default();
+        perms.grant(&McpAbility::SshExecute);
+        perms.block(&McpAbility::DatasphereSet);
+
+        perms.set_provider_rule(
+            &AiProvider::Claude,
+            &McpAbility::SshExecute,
+            PermissionRule::Ask,
+        );
+        perms.set_provider_rule(
+            &AiProvider::Ollama,
+            &McpAbility::DatasphereSet,
+            PermissionRule::Allow,
+        );
+
+        assert_eq!(
+            perms.check_permission(&AiProvider::Claude, &McpAbility::SshExecute),
+            PermissionResult::RequiresApproval
+        );
+        assert_eq!(
+            perms.check_permission(&AiProvider::ChatGPT, &McpAbility::SshExecute),
+            PermissionResult::Allowed
+        );
+        assert_eq!(
+            perms.check_permission(&AiProvider::Ollama, &McpAbility::DatasphereSet),
+            PermissionResult::Allowed
+        );
+        assert_eq!(
+            perms.check_permission(&AiProvider::Claude, &McpAbility::DatasphereSet),
+            PermissionResult::Denied
+        );
+        assert_eq!(perms.provider_rules(&AiProvider::Ollama).rules.len(), 1);
+    }
+}
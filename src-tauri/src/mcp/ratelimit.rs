@@ -0,0 +1,204 @@
+//! Per-provider request limits for the MCP server
+//!
+//! Limits are configured per provider and tracked per client: each provider
+//! token and each grant gets a token bucket holding up to one minute's
+//! worth of requests, refilled continuously at `requests_per_minute`.
+//! Separately, at most `max_concurrent_executions` `nexus.ssh.execute`
+//! requests per client may run at once. Requests over either limit are
+//! refused with a retry delay rather than queued.
+
+use super::{AiProvider, McpAbility, McpError};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Limits applied to one provider
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct RateLimit {
+    /// Requests per minute; 0 means unlimited
+    pub requests_per_minute: u32,
+    /// `nexus.ssh.execute` requests running at once; 0 means unlimited
+    pub max_concurrent_executions: u32,
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        Self {
+            requests_per_minute: 60,
+            max_concurrent_executions: 4,
+        }
+    }
+}
+
+/// Default limits and per-provider overrides
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RateLimits {
+    #[serde(default)]
+    pub default: RateLimit,
+    /// Overrides keyed by `AiProvider::key`
+    #[serde(default)]
+    pub providers: HashMap<String, RateLimit>,
+}
+
+impl RateLimits {
+    /// Limits that apply to `provider`
+    pub fn for_provider(&self, provider: &AiProvider) -> RateLimit {
+        self.providers
+            .get(&provider.key())
+            .copied()
+            .unwrap_or(self.default)
+    }
+}
+
+/// Token bucket refilled at a steady rate
+struct TokenBucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn new(capacity: f64, now: Instant) -> Self {
+        Self {
+            tokens: capacity,
+            updated: now,
+        }
+    }
+
+    /// Take a token, or return how long until one is available
+    fn take(&mut self, per_minute: u32, now: Instant) -> Result<(), Duration> {
+        let capacity = per_minute as f64;
+        let per_second = capacity / 60.0;
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * per_second).min(capacity);
+        self.updated = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - self.tokens) / per_second))
+        }
+    }
+}
+
+/// Tracks request rates and running executions per client
+#[derive(Default)]
+pub struct RateLimiter {
+    buckets: Mutex<HashMap<String, TokenBucket>>,
+    executions: Arc<Mutex<HashMap<String, u32>>>,
+}
+
+/// Held while an execution runs; frees its slot when dropped
+pub struct ExecutionSlot {
+    client: String,
+    executions: Arc<Mutex<HashMap<String, u32>>>,
+}
+
+impl Drop for ExecutionSlot {
+    fn drop(&mut self) {
+        if let Some(running) = self.executions.lock().get_mut(&self.client) {
+            *running = running.saturating_sub(1);
+        }
+    }
+}
+
+impl RateLimiter {
+    /// Count a request from `client` against its rate, or refuse it with
+    /// `RateLimited`
+    pub fn admit(&self, client: &str, limit: RateLimit) -> Result<(), McpError> {
+        self.admit_at(client, limit, Instant::now())
+    }
+
+    fn admit_at(&self, client: &str, limit: RateLimit, now: Instant) -> Result<(), McpError> {
+        if limit.requests_per_minute == 0 {
+            return Ok(());
+        }
+        self.buckets
+            .lock()
+            .entry(client.to_string())
+            .or_insert_with(|| TokenBucket::new(limit.requests_per_minute as f64, now))
+            .take(limit.requests_per_minute, now)
+            .map_err(McpError::RateLimited)
+    }
+
+    /// Claim an execution slot for `client`, or refuse with `RateLimited`
+    ///
+    /// Only `nexus.ssh.execute` takes a slot; it must be kept until the
+    /// execution finishes.
+    pub fn start(
+        &self,
+        client: &str,
+        ability: &McpAbility,
+        limit: RateLimit,
+    ) -> Result<Option<ExecutionSlot>, McpError> {
+        if *ability != McpAbility::SshExecute || limit.max_concurrent_executions == 0 {
+            return Ok(None);
+        }
+        let mut executions = self.executions.lock();
+        let running = executions.entry(client.to_string()).or_insert(0);
+        if *running >= limit.max_concurrent_executions {
+            return Err(McpError::RateLimited(Duration::from_secs(1)));
+        }
+        *running += 1;
+        Ok(Some(ExecutionSlot {
+            client: client.to_string(),
+            executions: self.executions.clone(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_burst_is_throttled_to_rate() {
+        let limiter = RateLimiter::default();
+        let limit = RateLimit {
+            requests_per_minute: 30,
+            max_concurrent_executions: 2,
+        };
+        let start = Instant::now();
+        let admit = |now: Instant| limiter.admit_at("claude", limit, now);
+
+        let admitted = (0..100).filter(|_| admit(start).is_ok()).count();
+        assert_eq!(admitted, 30);
+        match admit(start) {
+            Err(McpError::RateLimited(retry_after)) => {
+                assert_eq!(retry_after, Duration::from_secs(2))
+            }
+            other => panic!("expected rate limit, got {:?}", other.map(|_| ())),
+        }
+
+        // Another client has its own bucket
+        assert!(limiter.admit_at("ollama", limit, start).is_ok());
+
+        // Half a minute later, half a minute's worth is available again
+        let later = start + Duration::from_secs(30);
+        let admitted = (0..100).filter(|_| admit(later).is_ok()).count();
+        assert_eq!(admitted, 15);
+    }
+
+    #[test]
+    fn test_concurrent_executions() {
+        let limiter = RateLimiter::default();
+        let limit = RateLimit {
+            requests_per_minute: 30,
+            max_concurrent_executions: 2,
+        };
+        let start = |ability: &McpAbility| limiter.start("claude", ability, limit);
+
+        // Capped until a slot is released
+        let first = start(&McpAbility::SshExecute).unwrap();
+        let _second = start(&McpAbility::SshExecute).unwrap();
+        assert!(start(&McpAbility::SshExecute).is_err());
+        assert!(start(&McpAbility::ServerList).unwrap().is_none());
+        assert!(limiter
+            .start("ollama", &McpAbility::SshExecute, limit)
+            .is_ok());
+        drop(first);
+        assert!(start(&McpAbility::SshExecute).is_ok());
+    }
+}
@@ -1,6 +1,7 @@
 //! MCP Server Implementation
 
 use super::{
+    hosts,
     permissions::{PermissionManager, PermissionResult, ApprovalRequest},
     McpAbility, McpConfig, McpError, McpRequest, McpResponse,
 };
@@ -19,8 +20,8 @@ impl McpServer {
     /// Create a new MCP server
     pub fn new(config: McpConfig) -> Self {
         Self {
+            permissions: config.permissions.clone(),
             config,
-            permissions: PermissionManager::default(),
             pending_approvals: VecDeque::new(),
             running: false,
         }
@@ -90,6 +91,7 @@ impl McpServer {
             McpAbility::ServerList => {
                 self.handle_server_list().await
             }
+            McpAbility::ServerAdd => hosts::add(crate::datasphere::storage(), &request.params),
             McpAbility::SshConnect => {
                 self.handle_ssh_connect(&request.params).await
             }
@@ -102,6 +104,7 @@ impl McpServer {
             McpAbility::SshDownload => {
                 self.handle_ssh_download(&request.params).await
             }
+            McpAbility::SftpList => super::transfer::list(&request.params).await,
             McpAbility::DatasphereGet => {
                 self.handle_datasphere_get(&request.params).await
             }
@@ -215,16 +218,7 @@ impl McpServer {
     }
 
     async fn handle_ai_invoke(&self, params: &serde_json::Value) -> Result<serde_json::Value, McpError> {
-        let prompt = params.get("prompt")
-            .and_then(|v| v.as_str())
-            .ok_or_else(|| McpError::InvalidRequest("Missing prompt".to_string()))?;
-
-        // TODO: Invoke local AI (Ollama) or pass through
-        tracing::info!("MCP: AI invoke with prompt length: {}", prompt.len());
-
-        Ok(serde_json::json!({
-            "response": "AI response placeholder"
-        }))
+        super::ai::invoke(&self.config.ai, crate::datasphere::storage(), params).await
     }
 
     /// Get pending approval requests
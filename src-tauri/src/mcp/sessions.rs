@@ -0,0 +1,120 @@
+//! SSH session abilities (`nexus.ssh.connect` / `nexus.ssh.execute`)
+//!
+//! `nexus.ssh.connect` takes a stored `host_id` and opens a standalone
+//! session with the host's saved credentials, security profile and jump
+//! hosts, so no secrets cross the MCP connection. The session joins
+//! `ssh::clients()` like one opened from the app.
+//!
+//! `nexus.ssh.execute` takes a `session_id` and `command` and returns
+//! `stdout`, `stderr` and `exit_code` once the command exits. Only
+//! standalone sessions can be used; terminal tabs are left alone.
+
+use super::{parse_params, McpError};
+use crate::datasphere::DataSphereStorage;
+use crate::plugins::{self, CommandContext, HookEvent, SessionContext};
+use crate::ssh::SshClient;
+use parking_lot::RwLock;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConnectParams {
+    host_id: Uuid,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ExecuteParams {
+    session_id: Uuid,
+    command: String,
+}
+
+/// Handle `nexus.ssh.connect`
+pub async fn connect(
+    storage: &RwLock<Option<DataSphereStorage>>,
+    clients: &RwLock<HashMap<Uuid, Arc<SshClient>>>,
+    params: &Value,
+) -> Result<Value, McpError> {
+    let params: ConnectParams = parse_params(params)?;
+
+    // The guard must be gone before connecting
+    let (host, config) = {
+        let storage = storage.read();
+        let storage = storage
+            .as_ref()
+            .ok_or_else(|| McpError::ExecutionError("Storage not initialized".to_string()))?;
+        let host = storage
+            .get_host(params.host_id)
+            .ok_or_else(|| McpError::InvalidRequest(format!("Unknown host {}", params.host_id)))?;
+        let config = storage
+            .host_ssh_config(&host)
+            .map_err(|e| McpError::ExecutionError(e.to_string()))?;
+        (host, config)
+    };
+
+    let mut client = SshClient::new(config);
+    client.host_id = Some(host.id);
+    let session_id = client.id;
+    client
+        .connect()
+        .await
+        .map_err(|e| McpError::ExecutionError(e.to_string()))?;
+
+    let context = SessionContext {
+        session_id,
+        host: client.config.host.clone(),
+        port: client.config.port,
+        username: client.config.username.clone(),
+    };
+    clients.write().insert(session_id, Arc::new(client));
+    plugins::dispatch(HookEvent::SessionConnect(context));
+    tracing::info!("MCP: connected session {} to {}", session_id, host.name);
+
+    Ok(json!({
+        "status": "connected",
+        "session_id": session_id,
+        "host_id": host.id,
+    }))
+}
+
+/// Handle `nexus.ssh.execute`
+pub async fn execute(
+    clients: &RwLock<HashMap<Uuid, Arc<SshClient>>>,
+    params: &Value,
+) -> Result<Value, McpError> {
+    let params: ExecuteParams = parse_params(params)?;
+    if params.command.trim().is_empty() {
+        return Err(McpError::InvalidRequest("Missing command".to_string()));
+    }
+
+    // Clone the handle out so the lock isn't held across await
+    let client = clients
+        .read()
+        .get(&params.session_id)
+        .cloned()
+        .ok_or_else(|| McpError::ExecutionError(format!("No SSH session {}", params.session_id)))?;
+
+    tracing::info!(
+        "MCP executing command on {}: {}",
+        params.session_id,
+        params.command
+    );
+    plugins::dispatch(HookEvent::Command(CommandContext::new(
+        params.session_id,
+        &params.command,
+    )));
+    let output = client
+        .execute(&params.command)
+        .await
+        .map_err(|e| McpError::ExecutionError(e.to_string()))?;
+
+    Ok(json!({
+        "stdout": output.stdout,
+        "stderr": output.stderr,
+        "exit_code": output.exit_code,
+    }))
+}
@@ -0,0 +1,322 @@
+//! File abilities (`nexus.ssh.upload` / `nexus.ssh.download` / `nexus.sftp.list`)
+//!
+//! File bytes travel as base64 in the JSON-RPC params and results. Small
+//! files fit in one message; anything larger must be moved in
+//! `offset`/`chunk` pieces, typically over the WebSocket so one connection
+//! carries the whole transfer. A `local_path` on this machine can be used
+//! instead of inline content; it must lie inside `McpConfig::transfer_dir`,
+//! relative paths being taken from there.
+//!
+//! Upload params: `session_id`, `remote_path`, and one of `content`,
+//! `local_path`, or `chunk` + `offset` (offset 0 truncates the file).
+//! An optional octal `mode` such as `"600"` is set when the file is created.
+//!
+//! Download params: `session_id`, `remote_path`, and optionally
+//! `local_path`, or `offset` + `length` to read one chunk.
+//!
+//! Listing (`nexus.sftp.list`) params: `session_id` and `path`; the
+//! result holds the directory's `FileEntry` items.
+//!
+//! `remote_path` and `path` are checked against the session's jail, see
+//! `jail`.
+
+use super::{jail, parse_params, McpError};
+use crate::sftp::{self, SftpClient};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Largest file moved in a single message
+pub const MAX_INLINE_BYTES: u64 = 4 * 1024 * 1024;
+
+/// Largest piece of a chunked transfer
+pub const MAX_CHUNK_BYTES: usize = 1024 * 1024;
+
+#[derive(Deserialize)]
+struct UploadParams {
+    session_id: Uuid,
+    remote_path: String,
+    content: Option<String>,
+    local_path: Option<String>,
+    chunk: Option<String>,
+    offset: Option<u64>,
+    mode: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ListParams {
+    session_id: Uuid,
+    path: String,
+}
+
+#[derive(Deserialize)]
+struct DownloadParams {
+    session_id: Uuid,
+    remote_path: String,
+    local_path: Option<String>,
+    offset: Option<u64>,
+    length: Option<usize>,
+}
+
+/// Handle `nexus.ssh.upload`
+pub async fn upload(params: &Value, transfer_dir: Option<&Path>) -> Result<Value, McpError> {
+    let params: UploadParams = parse_params(params)?;
+    let client = sftp_client(params.session_id)?;
+    let remote_path = jail::check(&client, params.session_id, &params.remote_path).await?;
+    let remote_path = remote_path.as_str();
+    let mode = params
+        .mode
+        .as_deref()
+        .map(sftp::perms::parse_mode)
+        .transpose()
+        .map_err(|e| McpError::InvalidRequest(e.to_string()))?;
+
+    match (params.content, params.local_path, params.chunk) {
+        (Some(content), None, None) => {
+            let data = decode(&content, MAX_INLINE_BYTES as usize, "content")?;
+            client
+                .write_file(remote_path, &data, mode)
+                .await
+                .map_err(exec_error)?;
+            tracing::info!("MCP uploaded {} bytes to {}", data.len(), remote_path);
+            Ok(json!({
+                "status": "uploaded",
+                "remote_path": remote_path,
+                "size": data.len(),
+            }))
+        }
+        (None, Some(local_path), None) => {
+            let local_path = local_file(transfer_dir, &local_path)?;
+            let local_path = local_path.to_string_lossy().into_owned();
+            let size = tokio::fs::metadata(&local_path)
+                .await
+                .map_err(|e| McpError::InvalidRequest(format!("{}: {}", local_path, e)))?
+                .len();
+            client
+                .upload(&local_path, remote_path, None, mode, None)
+                .await
+                .map_err(exec_error)?;
+            tracing::info!("MCP uploaded {} to {}", local_path, remote_path);
+            Ok(json!({
+                "status": "uploaded",
+                "remote_path": remote_path,
+                "size": size,
+            }))
+        }
+        (None, None, Some(chunk)) => {
+            let offset = params.offset.unwrap_or(0);
+            let data = decode(&chunk, MAX_CHUNK_BYTES, "chunk")?;
+            client
+                .write_at(remote_path, offset, &data, mode)
+                .await
+                .map_err(exec_error)?;
+            Ok(json!({
+                "status": "chunk_written",
+                "remote_path": remote_path,
+                "offset": offset,
+                "size": data.len(),
+                "next_offset": offset + data.len() as u64,
+            }))
+        }
+        _ => Err(McpError::InvalidRequest(
+            "Exactly one of content, local_path or chunk is required".to_string(),
+        )),
+    }
+}
+
+/// Handle `nexus.ssh.download`
+pub async fn download(params: &Value, transfer_dir: Option<&Path>) -> Result<Value, McpError> {
+    let params: DownloadParams = parse_params(params)?;
+    let client = sftp_client(params.session_id)?;
+    let remote_path = jail::check(&client, params.session_id, &params.remote_path).await?;
+    let remote_path = remote_path.as_str();
+
+    if let Some(local_path) = params.local_path {
+        let local_path = local_file(transfer_dir, &local_path)?;
+        let local_path = local_path.to_string_lossy().into_owned();
+        client
+            .download(remote_path, &local_path, None, None)
+            .await
+            .map_err(exec_error)?;
+        let size = tokio::fs::metadata(&local_path)
+            .await
+            .map(|m| m.len())
+            .unwrap_or(0);
+        tracing::info!("MCP downloaded {} to {}", remote_path, local_path);
+        return Ok(json!({
+            "status": "downloaded",
+            "local_path": local_path,
+            "size": size,
+        }));
+    }
+
+    let total_size = client.stat(remote_path).await.map_err(exec_error)?.size;
+
+    if params.offset.is_some() || params.length.is_some() {
+        let offset = params.offset.unwrap_or(0);
+        let length = params.length.unwrap_or(MAX_CHUNK_BYTES);
+        if length > MAX_CHUNK_BYTES {
+            return Err(McpError::InvalidRequest(format!(
+                "length {} exceeds the chunk limit of {} bytes",
+                length, MAX_CHUNK_BYTES
+            )));
+        }
+        let data = client
+            .read_range(remote_path, offset, length)
+            .await
+            .map_err(exec_error)?;
+        let next_offset = offset + data.len() as u64;
+        return Ok(json!({
+            "chunk": BASE64.encode(&data),
+            "offset": offset,
+            "size": data.len(),
+            "total_size": total_size,
+            "next_offset": next_offset,
+            "eof": next_offset >= total_size,
+        }));
+    }
+
+    if total_size > MAX_INLINE_BYTES {
+        return Err(McpError::InvalidRequest(format!(
+            "{} is {} bytes, over the {} byte inline limit; download it in offset/length chunks or to a local_path",
+            remote_path, total_size, MAX_INLINE_BYTES
+        )));
+    }
+
+    let data = client.read_file(remote_path).await.map_err(exec_error)?;
+    Ok(json!({
+        "content": BASE64.encode(&data),
+        "size": data.len(),
+    }))
+}
+
+/// Handle `nexus.sftp.list`
+pub async fn list(params: &Value) -> Result<Value, McpError> {
+    let params: ListParams = parse_params(params)?;
+    let client = sftp_client(params.session_id)?;
+    let path = jail::check(&client, params.session_id, &params.path).await?;
+    let entries = client.list_dir(&path).await.map_err(exec_error)?;
+    Ok(json!({
+        "path": path,
+        "entries": entries,
+    }))
+}
+
+/// Resolve a `local_path` inside the transfer directory
+///
+/// Refused if no directory is set, or if the path leaves it through `..`,
+/// an absolute path or a symlink.
+fn local_file(root: Option<&Path>, requested: &str) -> Result<PathBuf, McpError> {
+    let root = root.ok_or_else(|| {
+        McpError::PermissionDenied(
+            "local_path needs a transfer directory in the MCP settings".to_string(),
+        )
+    })?;
+    let root = root.canonicalize().map_err(|e| {
+        McpError::ExecutionError(format!("transfer directory {}: {}", root.display(), e))
+    })?;
+    let outside =
+        || McpError::PermissionDenied(format!("{} is outside the transfer directory", requested));
+
+    let requested_path = Path::new(requested);
+    if requested_path
+        .components()
+        .any(|c| c == Component::ParentDir)
+    {
+        return Err(outside());
+    }
+    let path = root.join(requested_path);
+    // Downloads may create the file, so only its directory has to exist
+    let resolved = match path.canonicalize() {
+        Ok(resolved) => resolved,
+        Err(_) => {
+            let name = path.file_name().ok_or_else(outside)?;
+            let parent = path
+                .parent()
+                .and_then(|p| p.canonicalize().ok())
+                .ok_or_else(|| {
+                    McpError::InvalidRequest(format!("{}: no such directory", requested))
+                })?;
+            parent.join(name)
+        }
+    };
+    if resolved.starts_with(&root) && resolved != root {
+        Ok(resolved)
+    } else {
+        Err(outside())
+    }
+}
+
+fn sftp_client(session_id: Uuid) -> Result<Arc<SftpClient>, McpError> {
+    sftp::manager()
+        .read()
+        .get_client(&session_id)
+        .ok_or_else(|| McpError::ExecutionError(format!("No SFTP session {}", session_id)))
+}
+
+/// Decode base64, refusing payloads over `limit` bytes before decoding them
+fn decode(encoded: &str, limit: usize, field: &str) -> Result<Vec<u8>, McpError> {
+    let too_large = || {
+        McpError::InvalidRequest(format!(
+            "{} exceeds the {} byte limit; send it in offset/chunk pieces",
+            field, limit
+        ))
+    };
+    if encoded.len() > limit.div_ceil(3) * 4 {
+        return Err(too_large());
+    }
+    let data = BASE64
+        .decode(encoded)
+        .map_err(|e| McpError::InvalidRequest(format!("{} is not valid base64: {}", field, e)))?;
+    if data.len() > limit {
+        return Err(too_large());
+    }
+    Ok(data)
+}
+
+fn exec_error(err: sftp::SftpError) -> McpError {
+    McpError::ExecutionError(err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_local_file() {
+        let base = std::env::temp_dir().join(format!("nexus-transfer-{}", Uuid::new_v4()));
+        let root = base.join("transfers");
+        fs::create_dir_all(root.join("logs")).unwrap();
+        fs::write(root.join("app.log"), "log").unwrap();
+        fs::write(base.join("secret"), "secret").unwrap();
+        let canonical = root.canonicalize().unwrap();
+
+        assert!(local_file(None, "app.log").is_err());
+        assert_eq!(
+            local_file(Some(&root), "app.log").unwrap(),
+            canonical.join("app.log")
+        );
+        assert_eq!(
+            local_file(Some(&root), "logs/new.log").unwrap(),
+            canonical.join("logs/new.log")
+        );
+        let absolute = root.join("app.log");
+        assert!(local_file(Some(&root), absolute.to_str().unwrap()).is_ok());
+
+        assert!(local_file(Some(&root), "../secret").is_err());
+        assert!(local_file(Some(&root), base.join("secret").to_str().unwrap()).is_err());
+        assert!(local_file(Some(&root), "").is_err());
+        assert!(local_file(Some(&root), "missing/new.log").is_err());
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(base.join("secret"), root.join("link")).unwrap();
+            assert!(local_file(Some(&root), "link").is_err());
+        }
+
+        fs::remove_dir_all(&base).unwrap();
+    }
+}
@@ -0,0 +1,156 @@
+//! Vault abilities (`nexus.datasphere.get` / `nexus.datasphere.set`)
+//!
+//! Entries are addressed by `key`, an entry id or an exact entry name; a
+//! name shared by several entries has to be given as an id instead.
+//!
+//! Get returns the entry with its secret as `value`; the user approves each
+//! get, and the server only listens on loopback. Set replaces the
+//! secret of the entry found by `key`, or creates an entry named `key`
+//! whose `entry_type` defaults to `password`.
+
+use super::{parse_params, McpError};
+use crate::datasphere::{DataSphereStorage, NewVaultEntry, VaultEntry, VaultEntryType};
+use parking_lot::RwLock;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GetParams {
+    key: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SetParams {
+    key: String,
+    value: String,
+    entry_type: Option<VaultEntryType>,
+    username: Option<String>,
+}
+
+/// Handle `nexus.datasphere.get`
+pub fn get(storage: &RwLock<Option<DataSphereStorage>>, params: &Value) -> Result<Value, McpError> {
+    let params: GetParams = parse_params(params)?;
+
+    let storage = storage.read();
+    let storage = storage
+        .as_ref()
+        .ok_or_else(|| McpError::ExecutionError("Storage not initialized".to_string()))?;
+    let entry = find_entry(&storage.get_vault_entries(), &params.key)?
+        .ok_or_else(|| McpError::InvalidRequest(format!("No vault entry {}", params.key)))?;
+    tracing::info!("MCP: read vault entry {}", entry.name);
+
+    Ok(json!({
+        "key": params.key,
+        "id": entry.id,
+        "name": entry.name,
+        "entry_type": entry.entry_type,
+        "username": entry.username,
+        "value": entry.secret,
+    }))
+}
+
+/// Handle `nexus.datasphere.set`
+pub fn set(storage: &RwLock<Option<DataSphereStorage>>, params: &Value) -> Result<Value, McpError> {
+    let params: SetParams = parse_params(params)?;
+    if params.key.trim().is_empty() {
+        return Err(McpError::InvalidRequest("Missing key".to_string()));
+    }
+
+    let mut storage = storage.write();
+    let storage = storage
+        .as_mut()
+        .ok_or_else(|| McpError::ExecutionError("Storage not initialized".to_string()))?;
+    let (entry, status) = match find_entry(&storage.get_vault_entries(), &params.key)? {
+        Some(mut entry) => {
+            entry.secret = params.value;
+            if let Some(entry_type) = params.entry_type {
+                entry.entry_type = entry_type;
+            }
+            if params.username.is_some() {
+                entry.username = params.username;
+            }
+            (storage.update_vault_entry(entry), "updated")
+        }
+        None => {
+            let entry = VaultEntry::from_new(NewVaultEntry {
+                name: params.key.trim().to_string(),
+                entry_type: params.entry_type.unwrap_or(VaultEntryType::Password),
+                username: params.username,
+                secret: params.value,
+                public_key: None,
+                url: None,
+                notes: None,
+                folder: None,
+                tags: vec![],
+            });
+            (storage.add_vault_entry(entry), "created")
+        }
+    };
+    let entry = entry.map_err(|e| McpError::ExecutionError(e.to_string()))?;
+    tracing::info!("MCP: {} vault entry {}", status, entry.name);
+
+    Ok(json!({
+        "status": status,
+        "id": entry.id,
+        "name": entry.name,
+    }))
+}
+
+/// The entry whose id or name is `key`
+pub(super) fn find_entry(
+    entries: &[VaultEntry],
+    key: &str,
+) -> Result<Option<VaultEntry>, McpError> {
+    if let Ok(id) = Uuid::parse_str(key) {
+        if let Some(entry) = entries.iter().find(|e| e.id == id) {
+            return Ok(Some(entry.clone()));
+        }
+    }
+
+    let key = key.trim();
+    let mut named = entries.iter().filter(|e| e.name == key);
+    match (named.next(), named.next()) {
+        (Some(_), Some(_)) => Err(McpError::InvalidRequest(format!(
+            "Several vault entries are named {}; use the entry id",
+            key
+        ))),
+        (entry, _) => Ok(entry.cloned()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str) -> VaultEntry {
+        VaultEntry::from_new(NewVaultEntry {
+            name: name.to_string(),
+            entry_type: VaultEntryType::ApiKey,
+            username: None,
+            secret: "s3cret".to_string(),
+            public_key: None,
+            url: None,
+            notes: None,
+            folder: None,
+            tags: vec![],
+        })
+    }
+
+    #[test]
+    fn test_find_entry() {
+        let entries = vec![entry("github"), entry("dup"), entry("dup")];
+
+        let found = find_entry(&entries, "github").unwrap().unwrap();
+        assert_eq!(found.id, entries[0].id);
+        let by_id = find_entry(&entries, &entries[1].id.to_string())
+            .unwrap()
+            .unwrap();
+        assert_eq!(by_id.id, entries[1].id);
+
+        assert!(find_entry(&entries, "GitHub").unwrap().is_none());
+        assert!(find_entry(&entries, "dup").is_err());
+    }
+}
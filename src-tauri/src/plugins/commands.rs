@@ -0,0 +1,42 @@
+//! Plugin Tauri Commands
+
+use super::{manager, PluginError, PluginInfo};
+use crate::ssh::CommandOutput;
+use uuid::Uuid;
+
+/// List loaded plugins
+#[tauri::command]
+pub fn list_plugins() -> Vec<PluginInfo> {
+    manager()
+        .read()
+        .get_plugins()
+        .iter()
+        .map(PluginInfo::from)
+        .collect()
+}
+
+/// Enable a plugin; the choice is saved in the settings
+#[tauri::command]
+pub fn enable_plugin(id: Uuid) -> Result<PluginInfo, PluginError> {
+    super::set_enabled(id, true)?;
+    manager().read().get(id).map(PluginInfo::from)
+}
+
+/// Disable a plugin so it receives no events; the choice is saved in the settings
+#[tauri::command]
+pub fn disable_plugin(id: Uuid) -> Result<PluginInfo, PluginError> {
+    super::set_enabled(id, false)?;
+    manager().read().get(id).map(PluginInfo::from)
+}
+
+/// Run a command on a session on behalf of a plugin
+///
+/// Refused unless the plugin is enabled and holds `ExecuteCommands`.
+#[tauri::command]
+pub async fn run_plugin_command(
+    plugin_id: Uuid,
+    session_id: Uuid,
+    command: String,
+) -> Result<CommandOutput, PluginError> {
+    super::execute_command(plugin_id, session_id, &command).await
+}
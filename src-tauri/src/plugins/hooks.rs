@@ -0,0 +1,169 @@
+//! Plugin lifecycle and event hooks
+//!
+//! Plugins are in-process trait objects for now. Every hook has a no-op
+//! default, so a plugin only implements what it subscribes to. Contexts
+//! carry no secrets: credentials are never included and commands are
+//! redacted before dispatch.
+
+use super::PluginPermission;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Events a plugin can subscribe to in its manifest
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum PluginEvent {
+    SessionConnect,
+    Command,
+    FileTransfer,
+}
+
+impl PluginEvent {
+    /// Permission a plugin needs to receive this event
+    pub fn required_permission(&self) -> PluginPermission {
+        match self {
+            Self::SessionConnect => PluginPermission::ReadHosts,
+            Self::Command => PluginPermission::ExecuteCommands,
+            Self::FileTransfer => PluginPermission::FileAccess,
+        }
+    }
+}
+
+/// A session finished connecting
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionContext {
+    pub session_id: Uuid,
+    pub host: String,
+    pub port: u16,
+    pub username: String,
+}
+
+/// A command is about to run on a session
+#[derive(Debug, Clone, Serialize)]
+pub struct CommandContext {
+    pub session_id: Uuid,
+    /// The command with secret-looking values masked
+    pub command: String,
+}
+
+impl CommandContext {
+    pub fn new(session_id: Uuid, command: &str) -> Self {
+        Self {
+            session_id,
+            command: redact_command(command),
+        }
+    }
+}
+
+/// Direction of a file transfer, seen from this machine
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TransferDirection {
+    Upload,
+    Download,
+    RemoteToRemote,
+}
+
+/// A file transfer completed
+#[derive(Debug, Clone, Serialize)]
+pub struct TransferContext {
+    pub session_id: Uuid,
+    pub direction: TransferDirection,
+    pub source: String,
+    pub destination: String,
+    pub bytes: u64,
+}
+
+/// A dispatched event with its context
+#[derive(Debug, Clone)]
+pub enum HookEvent {
+    SessionConnect(SessionContext),
+    Command(CommandContext),
+    FileTransfer(TransferContext),
+}
+
+impl HookEvent {
+    pub fn kind(&self) -> PluginEvent {
+        match self {
+            Self::SessionConnect(_) => PluginEvent::SessionConnect,
+            Self::Command(_) => PluginEvent::Command,
+            Self::FileTransfer(_) => PluginEvent::FileTransfer,
+        }
+    }
+}
+
+/// Implemented by plugins to receive lifecycle and event hooks
+///
+/// Hooks run synchronously on the dispatching task, so anything slow
+/// should be spawned. An error is logged and doesn't affect other plugins.
+pub trait PluginHost: Send + Sync {
+    /// Called once when the plugin is registered
+    fn on_load(&self) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Called once when the plugin is removed
+    fn on_unload(&self) {}
+
+    fn on_session_connect(&self, _ctx: &SessionContext) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn on_command(&self, _ctx: &CommandContext) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn on_file_transfer(&self, _ctx: &TransferContext) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// Call the hook matching `event`
+pub(super) fn call(host: &dyn PluginHost, event: &HookEvent) -> Result<(), String> {
+    match event {
+        HookEvent::SessionConnect(ctx) => host.on_session_connect(ctx),
+        HookEvent::Command(ctx) => host.on_command(ctx),
+        HookEvent::FileTransfer(ctx) => host.on_file_transfer(ctx),
+    }
+}
+
+/// `NAME=value` where the name looks like it holds a secret
+static SECRET_ASSIGNMENT: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\b(\w*(?:pass|secret|token|key)\w*=)(\S+)").expect("valid regex")
+});
+
+/// `--password value`, `--token=value` and similar flags
+static SECRET_FLAG: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)(--?(?:password|passwd|pass|secret|token|api-key)(?:=|\s+))(\S+)")
+        .expect("valid regex")
+});
+
+/// Mask values that look like secrets in a command line
+pub fn redact_command(command: &str) -> String {
+    let masked = SECRET_ASSIGNMENT.replace_all(command, "${1}***");
+    SECRET_FLAG.replace_all(&masked, "${1}***").into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_command() {
+        assert_eq!(redact_command("ls -la /tmp"), "ls -la /tmp");
+        assert_eq!(
+            redact_command("DB_PASSWORD=hunter2 ./migrate"),
+            "DB_PASSWORD=*** ./migrate"
+        );
+        assert_eq!(
+            redact_command("mysql --password secret -u root"),
+            "mysql --password *** -u root"
+        );
+        assert_eq!(
+            redact_command("curl --token=abc123 https://x"),
+            "curl --token=*** https://x"
+        );
+    }
+}
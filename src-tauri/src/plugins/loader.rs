@@ -0,0 +1,111 @@
+//! Loading plugin manifests from disk
+//!
+//! Each subdirectory of the plugins directory holding a `manifest.json` is
+//! one plugin. Directories are read in name order, so when two manifests
+//! claim the same id the first one wins and the second is reported.
+
+use super::{manager, Plugin, PluginError, PluginManager, PluginManifest};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// Manifest file expected in every plugin directory
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// A plugin directory that could not be loaded
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginLoadError {
+    pub path: String,
+    pub error: String,
+}
+
+/// Load every plugin in `dir` into the plugin manager
+///
+/// Returns the directories that were skipped and why. Saved enabled state
+/// is applied if the storage is unlocked.
+pub fn load_plugins_from_dir(dir: &Path) -> Vec<PluginLoadError> {
+    let failures = load_into(&mut manager().write(), dir);
+    super::apply_saved_state();
+    failures
+}
+
+fn load_into(manager: &mut PluginManager, dir: &Path) -> Vec<PluginLoadError> {
+    let mut dirs: Vec<_> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| p.join(MANIFEST_FILE).is_file())
+            .collect(),
+        Err(e) => {
+            return vec![PluginLoadError {
+                path: dir.display().to_string(),
+                error: e.to_string(),
+            }]
+        }
+    };
+    dirs.sort();
+
+    let mut failures = Vec::new();
+    for path in dirs {
+        let loaded = read_manifest(&path).and_then(|manifest| {
+            let plugin = Plugin {
+                path: Some(path.clone()),
+                ..Plugin::new(manifest)
+            };
+            manager.add(plugin).map(|p| p.manifest.id.clone())
+        });
+        match loaded {
+            Ok(id) => tracing::info!("Loaded plugin {} from {}", id, path.display()),
+            Err(e) => failures.push(PluginLoadError {
+                path: path.display().to_string(),
+                error: e.to_string(),
+            }),
+        }
+    }
+    failures
+}
+
+fn read_manifest(dir: &Path) -> Result<PluginManifest, PluginError> {
+    let data = fs::read_to_string(dir.join(MANIFEST_FILE))
+        .map_err(|e| PluginError::LoadFailed(e.to_string()))?;
+    serde_json::from_str(&data).map_err(|e| PluginError::InvalidManifest(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_load_rejects_duplicates_and_bad_manifests() {
+        let dir = std::env::temp_dir().join(format!("nexus-plugins-{}", Uuid::new_v4()));
+        let manifest = |id: &str| {
+            format!(
+                r#"{{"id": "{}", "name": "Demo", "version": "1.0.0",
+                    "description": null, "author": null, "homepage": null,
+                    "permissions": []}}"#,
+                id
+            )
+        };
+        for (name, contents) in [
+            ("a-demo", manifest("demo")),
+            ("b-copy", manifest("demo")),
+            ("c-bad", r#"{"id": "bad", "name": "Bad"}"#.to_string()),
+        ] {
+            fs::create_dir_all(dir.join(name)).unwrap();
+            fs::write(dir.join(name).join(MANIFEST_FILE), contents).unwrap();
+        }
+
+        let mut manager = PluginManager::new();
+        let failures = load_into(&mut manager, &dir);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(manager.get_plugins().len(), 1);
+        let plugin = &manager.get_plugins()[0];
+        assert_eq!(plugin.id, Plugin::new(plugin.manifest.clone()).id);
+        assert_eq!(failures.len(), 2);
+        assert!(failures[0].path.ends_with("b-copy"));
+        assert!(failures[0].error.contains("a-demo"));
+        assert!(failures[1].error.contains("version"));
+    }
+}
@@ -1,10 +1,121 @@
 //! Plugin System Module
 //!
-//! Provides an extensible plugin architecture for Nexus
+//! Provides an extensible plugin architecture for Nexus. Plugins are found
+//! in the `plugins` directory of the app data dir, one subdirectory with a
+//! `manifest.json` each. Which plugins are disabled is kept in the
+//! DataSphere settings and applied once the storage is unlocked.
 
+pub mod commands;
+pub mod hooks;
+mod loader;
+
+pub use hooks::{
+    CommandContext, HookEvent, PluginEvent, PluginHost, SessionContext, TransferContext,
+    TransferDirection,
+};
+
+pub use loader::{load_plugins_from_dir, PluginLoadError};
+
+use crate::datasphere;
+use crate::ssh::{self, CommandOutput};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tauri::{AppHandle, Manager};
 use uuid::Uuid;
 
+/// Global plugin manager
+static PLUGIN_MANAGER: Lazy<RwLock<PluginManager>> =
+    Lazy::new(|| RwLock::new(PluginManager::new()));
+
+/// Get the plugin manager
+pub fn manager() -> &'static RwLock<PluginManager> {
+    &PLUGIN_MANAGER
+}
+
+/// Load the plugins in the app data dir
+pub fn init(app: &AppHandle) -> Result<(), PluginError> {
+    let dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| PluginError::LoadFailed(e.to_string()))?
+        .join("plugins");
+    std::fs::create_dir_all(&dir).map_err(|e| PluginError::LoadFailed(e.to_string()))?;
+    for failure in load_plugins_from_dir(&dir) {
+        tracing::warn!("Skipped plugin {}: {}", failure.path, failure.error);
+    }
+    Ok(())
+}
+
+/// Apply the enabled state saved in the settings, e.g. after unlocking
+pub fn apply_saved_state() {
+    let disabled = datasphere::storage()
+        .read()
+        .as_ref()
+        .map(|s| s.get_settings().disabled_plugins)
+        .unwrap_or_default();
+    manager().write().apply_disabled(&disabled);
+}
+
+/// Enable or disable a plugin and save the choice in the settings
+pub fn set_enabled(id: Uuid, enabled: bool) -> Result<(), PluginError> {
+    let plugin_id = {
+        let manager = manager().read();
+        let plugin = manager.get(id)?;
+        plugin.manifest.id.clone()
+    };
+
+    {
+        let mut storage = datasphere::storage().write();
+        let storage = storage
+            .as_mut()
+            .ok_or_else(|| PluginError::Storage("Storage is locked".to_string()))?;
+        let mut settings = storage.get_settings();
+        settings.disabled_plugins.retain(|p| *p != plugin_id);
+        if !enabled {
+            settings.disabled_plugins.push(plugin_id);
+        }
+        storage
+            .update_settings(settings)
+            .map_err(|e| PluginError::Storage(e.to_string()))?;
+    }
+
+    manager().write().set_enabled(id, enabled)
+}
+
+/// Run a command on a session on behalf of a plugin
+///
+/// The plugin must be enabled and hold `ExecuteCommands`. The command is
+/// dispatched to `Command` hooks like any other.
+pub async fn execute_command(
+    plugin: Uuid,
+    session_id: Uuid,
+    command: &str,
+) -> Result<CommandOutput, PluginError> {
+    manager()
+        .read()
+        .require(plugin, PluginPermission::ExecuteCommands)?;
+    let client =
+        ssh::client_for_session(session_id).map_err(|e| PluginError::Execution(e.to_string()))?;
+    dispatch(HookEvent::Command(CommandContext::new(session_id, command)));
+    client
+        .execute(command)
+        .await
+        .map_err(|e| PluginError::Execution(e.to_string()))
+}
+
+/// Send an event to every enabled plugin subscribed to it
+pub fn dispatch(event: HookEvent) {
+    let targets = manager().read().subscribers(event.kind());
+    for (name, host) in targets {
+        if let Err(e) = hooks::call(host.as_ref(), &event) {
+            tracing::warn!("Plugin {} failed on {:?}: {}", name, event.kind(), e);
+        }
+    }
+}
+
 /// Plugin manifest
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PluginManifest {
@@ -15,10 +126,49 @@ pub struct PluginManifest {
     pub author: Option<String>,
     pub homepage: Option<String>,
     pub permissions: Vec<PluginPermission>,
+    /// Events the plugin wants; each needs its matching permission
+    #[serde(default)]
+    pub events: Vec<PluginEvent>,
+}
+
+impl PluginManifest {
+    /// Check required fields, and that every subscribed event is covered
+    /// by a declared permission
+    pub fn validate(&self) -> Result<(), PluginError> {
+        let valid_id = !self.id.is_empty()
+            && self
+                .id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'));
+        if !valid_id {
+            return Err(PluginError::InvalidManifest(format!(
+                "id {:?} must be non-empty and use only letters, digits, '.', '_' and '-'",
+                self.id
+            )));
+        }
+        for (field, value) in [("name", &self.name), ("version", &self.version)] {
+            if value.trim().is_empty() {
+                return Err(PluginError::InvalidManifest(format!(
+                    "{} has an empty {}",
+                    self.id, field
+                )));
+            }
+        }
+        for event in &self.events {
+            let needed = event.required_permission();
+            if !self.permissions.contains(&needed) {
+                return Err(PluginError::PermissionDenied(format!(
+                    "{} subscribes to {:?} without the {:?} permission",
+                    self.id, event, needed
+                )));
+            }
+        }
+        Ok(())
+    }
 }
 
 /// Plugin permissions
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum PluginPermission {
     ReadHosts,
@@ -30,24 +180,70 @@ pub enum PluginPermission {
 }
 
 /// Loaded plugin instance
-#[derive(Debug)]
 pub struct Plugin {
+    /// Derived from the manifest id, so it is the same on every start
+    pub id: Uuid,
+    pub manifest: PluginManifest,
+    pub enabled: bool,
+    /// Directory the plugin was loaded from
+    pub path: Option<PathBuf>,
+    /// Receives hooks; manifest-only plugins have none
+    host: Option<Arc<dyn PluginHost>>,
+}
+
+/// A plugin as shown to the frontend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginInfo {
     pub id: Uuid,
     pub manifest: PluginManifest,
     pub enabled: bool,
+    pub path: Option<String>,
+}
+
+impl From<&Plugin> for PluginInfo {
+    fn from(plugin: &Plugin) -> Self {
+        Self {
+            id: plugin.id,
+            manifest: plugin.manifest.clone(),
+            enabled: plugin.enabled,
+            path: plugin.path.as_ref().map(|p| p.display().to_string()),
+        }
+    }
+}
+
+impl std::fmt::Debug for Plugin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Plugin")
+            .field("id", &self.id)
+            .field("manifest", &self.manifest)
+            .field("enabled", &self.enabled)
+            .field("path", &self.path)
+            .field("has_host", &self.host.is_some())
+            .finish()
+    }
 }
 
 impl Plugin {
     pub fn new(manifest: PluginManifest) -> Self {
         Self {
-            id: Uuid::new_v4(),
+            id: stable_id(&manifest.id),
             manifest,
             enabled: true,
+            path: None,
+            host: None,
         }
     }
 }
 
-/// Plugin manager (placeholder for future implementation)
+/// Namespace for plugin ids (a fixed random UUID)
+const PLUGIN_NAMESPACE: Uuid = Uuid::from_u128(0x5d0c_1e43_8a2f_4f9b_b6e1_7c34_0a9d_2f18);
+
+/// Name-based (v5) UUID for a manifest id
+fn stable_id(manifest_id: &str) -> Uuid {
+    Uuid::new_v5(&PLUGIN_NAMESPACE, manifest_id.as_bytes())
+}
+
+/// Plugin manager
 #[derive(Debug, Default)]
 pub struct PluginManager {
     plugins: Vec<Plugin>,
@@ -58,13 +254,226 @@ impl PluginManager {
         Self { plugins: vec![] }
     }
 
-    pub fn load_plugin(&mut self, manifest: PluginManifest) -> &Plugin {
-        let plugin = Plugin::new(manifest);
+    /// Add a manifest-only plugin
+    pub fn load_plugin(&mut self, manifest: PluginManifest) -> Result<&Plugin, PluginError> {
+        self.add(Plugin::new(manifest))
+    }
+
+    /// Add a plugin after checking its manifest and that its id is free
+    fn add(&mut self, plugin: Plugin) -> Result<&Plugin, PluginError> {
+        plugin.manifest.validate()?;
+        if let Some(existing) = self
+            .plugins
+            .iter()
+            .find(|p| p.manifest.id == plugin.manifest.id)
+        {
+            return Err(PluginError::AlreadyLoaded(match &existing.path {
+                Some(path) => format!(
+                    "{} (already loaded from {})",
+                    plugin.manifest.id,
+                    path.display()
+                ),
+                None => plugin.manifest.id.clone(),
+            }));
+        }
         self.plugins.push(plugin);
-        self.plugins.last().unwrap()
+        Ok(self.plugins.last().expect("just pushed"))
+    }
+
+    /// Register a plugin implementation and run its `on_load` hook
+    pub fn register(
+        &mut self,
+        manifest: PluginManifest,
+        host: Arc<dyn PluginHost>,
+    ) -> Result<Uuid, PluginError> {
+        manifest.validate()?;
+        if self.plugins.iter().any(|p| p.manifest.id == manifest.id) {
+            return Err(PluginError::AlreadyLoaded(manifest.id));
+        }
+        host.on_load()
+            .map_err(|e| PluginError::LoadFailed(format!("{}: {}", manifest.id, e)))?;
+
+        let plugin = Plugin {
+            host: Some(host),
+            ..Plugin::new(manifest)
+        };
+        tracing::info!("Registered plugin {}", plugin.manifest.id);
+        Ok(self.add(plugin)?.id)
+    }
+
+    /// Remove a plugin, running its `on_unload` hook
+    pub fn unload(&mut self, id: Uuid) -> Result<(), PluginError> {
+        let index = self
+            .plugins
+            .iter()
+            .position(|p| p.id == id)
+            .ok_or_else(|| PluginError::NotFound(id.to_string()))?;
+        let plugin = self.plugins.remove(index);
+        if let Some(host) = plugin.host {
+            host.on_unload();
+        }
+        Ok(())
+    }
+
+    /// Enable or disable a plugin; disabled plugins get no events
+    pub fn set_enabled(&mut self, id: Uuid, enabled: bool) -> Result<(), PluginError> {
+        let plugin = self
+            .plugins
+            .iter_mut()
+            .find(|p| p.id == id)
+            .ok_or_else(|| PluginError::NotFound(id.to_string()))?;
+        plugin.enabled = enabled;
+        Ok(())
     }
 
     pub fn get_plugins(&self) -> &[Plugin] {
         &self.plugins
     }
+
+    pub fn get(&self, id: Uuid) -> Result<&Plugin, PluginError> {
+        self.plugins
+            .iter()
+            .find(|p| p.id == id)
+            .ok_or_else(|| PluginError::NotFound(id.to_string()))
+    }
+
+    /// Disable the listed manifest ids and enable every other plugin
+    pub fn apply_disabled(&mut self, disabled: &[String]) {
+        for plugin in &mut self.plugins {
+            plugin.enabled = !disabled.contains(&plugin.manifest.id);
+        }
+    }
+
+    /// Fail unless the plugin is enabled and holds `permission`
+    pub fn require(&self, id: Uuid, permission: PluginPermission) -> Result<(), PluginError> {
+        let plugin = self.get(id)?;
+        if !plugin.enabled {
+            return Err(PluginError::PermissionDenied(format!(
+                "{} is disabled",
+                plugin.manifest.id
+            )));
+        }
+        if !plugin.manifest.permissions.contains(&permission) {
+            return Err(PluginError::PermissionDenied(format!(
+                "{} lacks the {:?} permission",
+                plugin.manifest.id, permission
+            )));
+        }
+        Ok(())
+    }
+
+    /// Enabled plugins subscribed to `event` that hold its permission
+    fn subscribers(&self, event: PluginEvent) -> Vec<(String, Arc<dyn PluginHost>)> {
+        let needed = event.required_permission();
+        self.plugins
+            .iter()
+            .filter(|p| p.enabled && p.manifest.events.contains(&event))
+            .filter(|p| {
+                // Registration checks this too; enforce it where events are delivered
+                let allowed = p.manifest.permissions.contains(&needed);
+                if !allowed {
+                    tracing::warn!(
+                        "Plugin {} lacks {:?} for {:?}",
+                        p.manifest.id,
+                        needed,
+                        event
+                    );
+                }
+                allowed
+            })
+            .filter_map(|p| Some((p.manifest.id.clone(), p.host.clone()?)))
+            .collect()
+    }
+}
+
+/// Plugin Error types
+#[derive(Debug, thiserror::Error)]
+pub enum PluginError {
+    #[error("Plugin not found: {0}")]
+    NotFound(String),
+    #[error("Plugin already loaded: {0}")]
+    AlreadyLoaded(String),
+    #[error("Plugin failed to load: {0}")]
+    LoadFailed(String),
+    #[error("Permission denied: {0}")]
+    PermissionDenied(String),
+    #[error("Invalid manifest: {0}")]
+    InvalidManifest(String),
+    #[error("Storage error: {0}")]
+    Storage(String),
+    #[error("Command failed: {0}")]
+    Execution(String),
+}
+
+impl serde::Serialize for PluginError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(id: &str, permissions: Vec<PluginPermission>) -> PluginManifest {
+        PluginManifest {
+            id: id.to_string(),
+            name: id.to_string(),
+            version: "1.0.0".to_string(),
+            description: None,
+            author: None,
+            homepage: None,
+            permissions,
+            events: vec![],
+        }
+    }
+
+    #[test]
+    fn test_require() {
+        let mut manager = PluginManager::new();
+        let runner = manager
+            .load_plugin(manifest("runner", vec![PluginPermission::ExecuteCommands]))
+            .unwrap()
+            .id;
+        let reader = manager
+            .load_plugin(manifest("reader", vec![PluginPermission::ReadHosts]))
+            .unwrap()
+            .id;
+        assert_eq!(runner, stable_id("runner"));
+
+        assert!(manager
+            .require(runner, PluginPermission::ExecuteCommands)
+            .is_ok());
+        assert!(matches!(
+            manager.require(reader, PluginPermission::ExecuteCommands),
+            Err(PluginError::PermissionDenied(_))
+        ));
+        assert!(matches!(
+            manager.require(Uuid::new_v4(), PluginPermission::ExecuteCommands),
+            Err(PluginError::NotFound(_))
+        ));
+        manager.set_enabled(runner, false).unwrap();
+        assert!(matches!(
+            manager.require(runner, PluginPermission::ExecuteCommands),
+            Err(PluginError::PermissionDenied(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_execute_command_denied() {
+        let id = manager()
+            .write()
+            .load_plugin(manifest("test-no-exec", vec![PluginPermission::ReadHosts]))
+            .unwrap()
+            .id;
+
+        // Refused before any session is looked up
+        let result = execute_command(id, Uuid::new_v4(), "uptime").await;
+        assert!(matches!(result, Err(PluginError::PermissionDenied(_))));
+
+        manager().write().unload(id).unwrap();
+    }
 }
@@ -0,0 +1,22 @@
+//! Retention Tauri Commands
+
+use super::{CategoryUsage, CleanupReport, RetentionError};
+use tauri::{AppHandle, Manager};
+
+/// Get disk usage per storage category
+#[tauri::command]
+pub fn get_storage_usage(app: AppHandle) -> Result<Vec<CategoryUsage>, RetentionError> {
+    super::usage(&data_dir(&app)?)
+}
+
+/// Apply the retention policy immediately
+#[tauri::command]
+pub fn cleanup_now(app: AppHandle) -> Result<CleanupReport, RetentionError> {
+    super::cleanup(&data_dir(&app)?, &super::current_settings())
+}
+
+fn data_dir(app: &AppHandle) -> Result<std::path::PathBuf, RetentionError> {
+    app.path()
+        .app_data_dir()
+        .map_err(|e| RetentionError::Tauri(e.to_string()))
+}
@@ -0,0 +1,364 @@
+//! Retention Module
+//!
+//! Keeps session recordings, audit logs and input logs from growing without
+//! bound. A background task prunes the oldest files in each category once
+//! they exceed the age or size limits from `Settings::retention`.
+
+pub mod commands;
+
+use crate::datasphere::{self, RetentionPolicy, RetentionSettings};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tauri::{AppHandle, Manager};
+
+/// How often the background cleanup runs
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// Files currently being written, with the number of guards held for each;
+/// cleanup never touches these
+static ACTIVE_FILES: Lazy<RwLock<HashMap<PathBuf, usize>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// Categories of files subject to retention
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageCategory {
+    Recordings,
+    AuditLogs,
+    InputLogs,
+}
+
+impl StorageCategory {
+    pub const ALL: [StorageCategory; 3] = [Self::Recordings, Self::AuditLogs, Self::InputLogs];
+
+    /// Directory name below the app data directory
+    pub fn dir_name(&self) -> &'static str {
+        match self {
+            Self::Recordings => "recordings",
+            Self::AuditLogs => "audit_logs",
+            Self::InputLogs => "input_logs",
+        }
+    }
+
+    /// File extensions managed by retention; anything else (e.g. key files) is left alone
+    fn extensions(&self) -> &'static [&'static str] {
+        match self {
+            Self::Recordings => &["cast"],
+            Self::AuditLogs => &["jsonl", "log"],
+            Self::InputLogs => &["log", "head"],
+        }
+    }
+
+    fn policy<'a>(&self, settings: &'a RetentionSettings) -> &'a RetentionPolicy {
+        match self {
+            Self::Recordings => &settings.recordings,
+            Self::AuditLogs => &settings.audit_logs,
+            Self::InputLogs => &settings.input_logs,
+        }
+    }
+}
+
+/// Disk usage of one category
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CategoryUsage {
+    pub category: StorageCategory,
+    pub file_count: usize,
+    pub total_bytes: u64,
+}
+
+/// Result of a cleanup run
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CleanupReport {
+    pub deleted_files: usize,
+    pub freed_bytes: u64,
+}
+
+/// Marks a file as in use for as long as the guard lives
+#[derive(Debug)]
+pub struct ActiveFileGuard {
+    path: PathBuf,
+}
+
+impl Drop for ActiveFileGuard {
+    fn drop(&mut self) {
+        let mut active = ACTIVE_FILES.write();
+        if let Some(count) = active.get_mut(&self.path) {
+            *count -= 1;
+            if *count == 0 {
+                active.remove(&self.path);
+            }
+        }
+    }
+}
+
+/// Protect `path` from cleanup until the returned guard is dropped
+///
+/// Guards for the same path nest: the file stays protected until the last
+/// one is dropped.
+pub fn mark_active(path: &Path) -> ActiveFileGuard {
+    let path = path.to_path_buf();
+    *ACTIVE_FILES.write().entry(path.clone()).or_insert(0) += 1;
+    ActiveFileGuard { path }
+}
+
+fn is_active(path: &Path) -> bool {
+    ACTIVE_FILES.read().contains_key(path)
+}
+
+/// Directory holding the files of a category
+pub fn category_dir(app: &AppHandle, category: StorageCategory) -> Result<PathBuf, RetentionError> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join(category.dir_name()))
+        .map_err(|e| RetentionError::Tauri(e.to_string()))
+}
+
+/// Start the cleanup task: once now, then periodically
+pub fn spawn_cleanup_task(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            match app.path().app_data_dir() {
+                Ok(data_dir) => match cleanup(&data_dir, &current_settings()) {
+                    Ok(report) if report.deleted_files > 0 => tracing::info!(
+                        "Retention cleanup removed {} files ({} bytes)",
+                        report.deleted_files,
+                        report.freed_bytes
+                    ),
+                    Ok(_) => {}
+                    Err(e) => tracing::warn!("Retention cleanup failed: {}", e),
+                },
+                Err(e) => tracing::warn!("Retention cleanup skipped: {}", e),
+            }
+            tokio::time::sleep(CLEANUP_INTERVAL).await;
+        }
+    });
+}
+
+/// Retention settings from DataSphere, or the defaults if it isn't ready
+pub fn current_settings() -> RetentionSettings {
+    datasphere::storage()
+        .read()
+        .as_ref()
+        .map(|s| s.get_settings().retention)
+        .unwrap_or_default()
+}
+
+/// Report disk usage per category
+pub fn usage(data_dir: &Path) -> Result<Vec<CategoryUsage>, RetentionError> {
+    StorageCategory::ALL
+        .iter()
+        .map(|category| {
+            let files = list_files(data_dir, *category)?;
+            Ok(CategoryUsage {
+                category: *category,
+                file_count: files.len(),
+                total_bytes: files.iter().map(|f| f.size).sum(),
+            })
+        })
+        .collect()
+}
+
+/// Prune every category according to `settings`
+pub fn cleanup(
+    data_dir: &Path,
+    settings: &RetentionSettings,
+) -> Result<CleanupReport, RetentionError> {
+    let mut report = CleanupReport::default();
+    for category in StorageCategory::ALL {
+        prune(data_dir, category, category.policy(settings), &mut report)?;
+    }
+    Ok(report)
+}
+
+struct FileEntry {
+    path: PathBuf,
+    size: u64,
+    modified: SystemTime,
+}
+
+fn list_files(
+    data_dir: &Path,
+    category: StorageCategory,
+) -> Result<Vec<FileEntry>, RetentionError> {
+    let dir = data_dir.join(category.dir_name());
+    if !dir.exists() {
+        return Ok(vec![]);
+    }
+
+    let mut files = Vec::new();
+    for entry in fs::read_dir(&dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let managed = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| category.extensions().contains(&e))
+            .unwrap_or(false);
+        let metadata = entry.metadata()?;
+        if !managed || !metadata.is_file() {
+            continue;
+        }
+        files.push(FileEntry {
+            path,
+            size: metadata.len(),
+            modified: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+        });
+    }
+
+    // Oldest first
+    files.sort_by_key(|f| f.modified);
+    Ok(files)
+}
+
+fn prune(
+    data_dir: &Path,
+    category: StorageCategory,
+    policy: &RetentionPolicy,
+    report: &mut CleanupReport,
+) -> Result<(), RetentionError> {
+    let files = list_files(data_dir, category)?;
+    // Limits too large to represent can't be reached, so they don't apply
+    let cutoff = policy.max_age_days.and_then(|days| {
+        SystemTime::now().checked_sub(Duration::from_secs(u64::from(days) * 24 * 60 * 60))
+    });
+    let max_bytes = policy
+        .max_total_mb
+        .and_then(|mb| mb.checked_mul(1024 * 1024));
+    let mut total: u64 = files.iter().map(|f| f.size).sum();
+
+    for file in files {
+        let too_old = cutoff.map(|c| file.modified < c).unwrap_or(false);
+        let over_size = max_bytes.map(|max| total > max).unwrap_or(false);
+        if !too_old && !over_size {
+            continue;
+        }
+        if is_active(&file.path) {
+            continue;
+        }
+
+        match fs::remove_file(&file.path) {
+            Ok(()) => {
+                tracing::debug!("Retention removed {}", file.path.display());
+                total -= file.size;
+                report.deleted_files += 1;
+                report.freed_bytes += file.size;
+            }
+            Err(e) => tracing::warn!("Could not remove {}: {}", file.path.display(), e),
+        }
+    }
+
+    Ok(())
+}
+
+/// Retention Error types
+#[derive(Debug, thiserror::Error)]
+pub enum RetentionError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Tauri error: {0}")]
+    Tauri(String),
+}
+
+impl Serialize for RetentionError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use uuid::Uuid;
+
+    fn data_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("nexus-retention-{}", Uuid::new_v4()));
+        for category in StorageCategory::ALL {
+            fs::create_dir_all(dir.join(category.dir_name())).unwrap();
+        }
+        dir
+    }
+
+    fn write_file(dir: &Path, name: &str, size: usize, age_days: u64) -> PathBuf {
+        let path = dir.join(name);
+        fs::write(&path, vec![0u8; size]).unwrap();
+        let modified = SystemTime::now() - Duration::from_secs(age_days * 24 * 60 * 60);
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+        path
+    }
+
+    fn settings(recordings: RetentionPolicy, audit_logs: RetentionPolicy) -> RetentionSettings {
+        RetentionSettings {
+            recordings,
+            audit_logs,
+            input_logs: RetentionPolicy::default(),
+        }
+    }
+
+    #[test]
+    fn test_cleanup_prunes_oldest_files() {
+        let data_dir = data_dir();
+        let dir = data_dir.join(StorageCategory::Recordings.dir_name());
+        let old = write_file(&dir, "old.cast", 10, 40);
+        let older = write_file(&dir, "older.cast", 1024 * 1024, 20);
+        let newest = write_file(&dir, "newest.cast", 1024 * 1024, 0);
+        let other = write_file(&dir, "notes.txt", 10, 40);
+
+        let policy = RetentionPolicy {
+            max_age_days: Some(30),
+            max_total_mb: Some(1),
+        };
+        let report = cleanup(&data_dir, &settings(policy, RetentionPolicy::default())).unwrap();
+
+        assert_eq!(report.deleted_files, 2);
+        assert!(!old.exists());
+        assert!(!older.exists());
+        assert!(newest.exists());
+        assert!(other.exists());
+
+        fs::remove_dir_all(data_dir).unwrap();
+    }
+
+    #[test]
+    fn test_cleanup_skips_active_files() {
+        let data_dir = data_dir();
+        let recordings = data_dir.join(StorageCategory::Recordings.dir_name());
+        let audit_logs = data_dir.join(StorageCategory::AuditLogs.dir_name());
+        let recording = write_file(&recordings, "session.cast", 10, 40);
+        let rotated_audit_log = write_file(&audit_logs, "audit-20250101-000000.000.jsonl", 10, 400);
+
+        let policy = RetentionPolicy {
+            max_age_days: Some(30),
+            max_total_mb: Some(u64::MAX),
+        };
+        let settings = settings(policy.clone(), policy);
+
+        let first = mark_active(&recording);
+        let second = mark_active(&recording);
+        drop(first);
+        let report = cleanup(&data_dir, &settings).unwrap();
+        assert_eq!(report.deleted_files, 1);
+        assert!(recording.exists());
+        assert!(!rotated_audit_log.exists());
+
+        drop(second);
+        cleanup(&data_dir, &settings).unwrap();
+        assert!(!recording.exists());
+
+        fs::remove_dir_all(data_dir).unwrap();
+    }
+}
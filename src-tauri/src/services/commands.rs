@@ -0,0 +1,24 @@
+//! Services Tauri Commands
+
+use super::{ServiceAction, ServiceError, ServiceResult};
+use crate::ssh;
+use uuid::Uuid;
+use zeroize::Zeroizing;
+
+/// Start, stop, restart, reload or inspect a service on the remote host
+#[tauri::command]
+pub async fn manage_service(
+    session_id: String,
+    name: String,
+    action: ServiceAction,
+    sudo_password: Option<String>,
+) -> Result<ServiceResult, ServiceError> {
+    let sudo_password = sudo_password.map(Zeroizing::new);
+    let session_uuid = Uuid::parse_str(&session_id)
+        .map_err(|_| ServiceError::Failed("Invalid session ID".to_string()))?;
+    let client = ssh::client_for_session(session_uuid).map_err(|_| ServiceError::NotConnected)?;
+
+    tracing::info!("Service {:?} {} for session {}", action, name, session_id);
+
+    super::manage(&client, &name, action, sudo_password).await
+}
@@ -0,0 +1,332 @@
+//! Services Module
+//!
+//! Starts, stops and inspects services on a remote host over an exec
+//! channel. systemd is used when it is running, otherwise the SysV
+//! `service` wrapper. Actions that change state run through `sudo`.
+
+pub mod commands;
+
+use crate::sftp::helper::{run_helper, run_sudo, sudo_refused};
+use crate::sftp::SftpError;
+use crate::ssh::{CommandOutput, SshClient};
+use crate::utils::shell_quote;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use zeroize::Zeroizing;
+
+/// Longest service name accepted
+const MAX_NAME_LEN: usize = 256;
+
+/// What to do with a service
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ServiceAction {
+    Start,
+    Stop,
+    Restart,
+    Reload,
+    Status,
+}
+
+impl ServiceAction {
+    fn verb(&self) -> &'static str {
+        match self {
+            Self::Start => "start",
+            Self::Stop => "stop",
+            Self::Restart => "restart",
+            Self::Reload => "reload",
+            Self::Status => "status",
+        }
+    }
+}
+
+/// Init system found on the host
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum InitSystem {
+    Systemd,
+    /// The SysV `service` wrapper
+    Service,
+}
+
+/// State of a service after an action
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceStatus {
+    pub name: String,
+    pub active: bool,
+    /// systemd `ActiveState`, or "active"/"inactive" for SysV
+    pub state: String,
+    /// systemd `SubState`, e.g. "running" or "exited"
+    pub sub_state: Option<String>,
+    /// Whether the service starts at boot; `None` when unknown
+    pub enabled: Option<bool>,
+    pub main_pid: Option<u32>,
+    /// Human readable status text from the init system
+    pub output: String,
+}
+
+/// Result of `manage_service`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceResult {
+    pub action: ServiceAction,
+    pub init_system: InitSystem,
+    /// Combined output of the action itself; empty for `Status`
+    pub output: String,
+    pub status: ServiceStatus,
+}
+
+/// Service management errors
+#[derive(Debug, thiserror::Error)]
+pub enum ServiceError {
+    #[error("Not connected")]
+    NotConnected,
+    #[error("Invalid service name: {0}")]
+    InvalidName(String),
+    #[error("Service not found: {0}")]
+    NotFound(String),
+    #[error("Neither systemctl nor service is available")]
+    NoInitSystem,
+    #[error("Permission denied: {0}")]
+    PermissionDenied(String),
+    #[error("{0}")]
+    Failed(String),
+}
+
+impl From<SftpError> for ServiceError {
+    fn from(e: SftpError) -> Self {
+        Self::Failed(e.to_string())
+    }
+}
+
+impl Serialize for ServiceError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+/// Run `action` on service `name` and report its state afterwards
+///
+/// Without a sudo password, state changes use `sudo -n`, which only works
+/// as root or with passwordless sudo.
+pub async fn manage(
+    client: &SshClient,
+    name: &str,
+    action: ServiceAction,
+    sudo_password: Option<Zeroizing<String>>,
+) -> Result<ServiceResult, ServiceError> {
+    validate_name(name)?;
+    let init_system = detect_init_system(client).await?;
+
+    let mut output = String::new();
+    if action != ServiceAction::Status {
+        let command = match init_system {
+            InitSystem::Systemd => format!("systemctl {} {}", action.verb(), shell_quote(name)),
+            InitSystem::Service => format!("service {} {}", shell_quote(name), action.verb()),
+        };
+        let result = run_as_root(client, &command, sudo_password).await?;
+        output = combined(&result);
+        if result.exit_code != 0 {
+            return Err(action_error(name, action, &result));
+        }
+        tracing::info!("{} {} on {}", action.verb(), name, client.config.host);
+    }
+
+    let status = status(client, init_system, name).await?;
+    Ok(ServiceResult {
+        action,
+        init_system,
+        output,
+        status,
+    })
+}
+
+/// Accept only characters that appear in systemd unit and SysV script names
+fn validate_name(name: &str) -> Result<(), ServiceError> {
+    let valid = !name.is_empty()
+        && name.len() <= MAX_NAME_LEN
+        && !name.starts_with('-')
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '@' | ':' | '\\'));
+    if valid {
+        Ok(())
+    } else {
+        Err(ServiceError::InvalidName(name.to_string()))
+    }
+}
+
+/// Prefer systemd when it is actually PID 1, not just installed
+async fn detect_init_system(client: &SshClient) -> Result<InitSystem, ServiceError> {
+    let script = "if command -v systemctl >/dev/null 2>&1 && [ -d /run/systemd/system ]; \
+                  then echo systemd; \
+                  elif command -v service >/dev/null 2>&1; then echo service; fi";
+    let output = run_helper(client, &[], script, &[]).await?;
+    match output.stdout.trim() {
+        "systemd" => Ok(InitSystem::Systemd),
+        "service" => Ok(InitSystem::Service),
+        _ => Err(ServiceError::NoInitSystem),
+    }
+}
+
+async fn run_as_root(
+    client: &SshClient,
+    command: &str,
+    sudo_password: Option<Zeroizing<String>>,
+) -> Result<CommandOutput, ServiceError> {
+    let Some(password) = sudo_password else {
+        let script = format!(
+            "if [ \"$(id -u)\" -eq 0 ]; then {c}; else sudo -n {c}; fi",
+            c = command
+        );
+        return Ok(run_helper(client, &[], &script, &[]).await?);
+    };
+
+    Ok(run_sudo(client, command, password).await?)
+}
+
+fn action_error(name: &str, action: ServiceAction, output: &CommandOutput) -> ServiceError {
+    let stderr = output.stderr.trim();
+    if sudo_refused(stderr) {
+        ServiceError::PermissionDenied(format!("sudo: {}", stderr))
+    } else if stderr.contains("not found") || stderr.contains("unrecognized service") {
+        ServiceError::NotFound(name.to_string())
+    } else {
+        ServiceError::Failed(format!(
+            "{} {} exited with {}: {}",
+            action.verb(),
+            name,
+            output.exit_code,
+            stderr
+        ))
+    }
+}
+
+async fn status(
+    client: &SshClient,
+    init_system: InitSystem,
+    name: &str,
+) -> Result<ServiceStatus, ServiceError> {
+    let quoted = shell_quote(name);
+    match init_system {
+        InitSystem::Systemd => {
+            let show = run_helper(
+                client,
+                &[],
+                &format!(
+                    "systemctl show --property=LoadState,ActiveState,SubState,UnitFileState,MainPID {}",
+                    quoted
+                ),
+                &[],
+            )
+            .await?;
+            let text = run_helper(
+                client,
+                &[],
+                &format!("systemctl status --no-pager --lines=10 {}", quoted),
+                &[],
+            )
+            .await?;
+            let status = parse_systemd_show(name, &show.stdout, combined(&text));
+            status.ok_or_else(|| ServiceError::NotFound(name.to_string()))
+        }
+        InitSystem::Service => {
+            let text = run_helper(client, &[], &format!("service {} status", quoted), &[]).await?;
+            let output = combined(&text);
+            if text.exit_code != 0 && output.contains("unrecognized service") {
+                return Err(ServiceError::NotFound(name.to_string()));
+            }
+            // LSB: exit 0 means running, 3 means stopped
+            let active = text.exit_code == 0;
+            Ok(ServiceStatus {
+                name: name.to_string(),
+                active,
+                state: if active { "active" } else { "inactive" }.to_string(),
+                sub_state: None,
+                enabled: None,
+                main_pid: None,
+                output,
+            })
+        }
+    }
+}
+
+/// Parse `systemctl show` key=value output; `None` if the unit doesn't exist
+fn parse_systemd_show(name: &str, show: &str, output: String) -> Option<ServiceStatus> {
+    let props: HashMap<&str, &str> = show
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .collect();
+
+    if props.get("LoadState").copied() == Some("not-found") {
+        return None;
+    }
+
+    let state = props.get("ActiveState").copied().unwrap_or("unknown");
+    let enabled = match props.get("UnitFileState").copied() {
+        Some("enabled") | Some("enabled-runtime") => Some(true),
+        Some("") | None => None,
+        Some(_) => Some(false),
+    };
+
+    Some(ServiceStatus {
+        name: name.to_string(),
+        active: state == "active" || state == "reloading",
+        state: state.to_string(),
+        sub_state: props
+            .get("SubState")
+            .filter(|s| !s.is_empty())
+            .map(|s| s.to_string()),
+        enabled,
+        main_pid: props
+            .get("MainPID")
+            .and_then(|p| p.parse().ok())
+            .filter(|&pid| pid != 0),
+        output,
+    })
+}
+
+fn combined(output: &CommandOutput) -> String {
+    let stdout = output.stdout.trim_end();
+    let stderr = output.stderr.trim_end();
+    match (stdout.is_empty(), stderr.is_empty()) {
+        (_, true) => stdout.to_string(),
+        (true, false) => stderr.to_string(),
+        (false, false) => format!("{}\n{}", stdout, stderr),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_service_name_and_status() {
+        assert!(validate_name("nginx").is_ok());
+        assert!(validate_name("getty@tty1.service").is_ok());
+        assert!(validate_name("nginx; rm -rf /").is_err());
+        assert!(validate_name("$(reboot)").is_err());
+        assert!(validate_name("--global").is_err());
+        assert!(validate_name("").is_err());
+
+        let show = "LoadState=loaded\nActiveState=active\nSubState=running\n\
+                    UnitFileState=enabled\nMainPID=812\n";
+        let status = parse_systemd_show("nginx", show, String::new()).unwrap();
+        assert!(status.active);
+        assert_eq!(status.sub_state.as_deref(), Some("running"));
+        assert_eq!(status.enabled, Some(true));
+        assert_eq!(status.main_pid, Some(812));
+
+        let stopped = "LoadState=loaded\nActiveState=inactive\nSubState=dead\n\
+                       UnitFileState=disabled\nMainPID=0\n";
+        let status = parse_systemd_show("nginx", stopped, String::new()).unwrap();
+        assert!(!status.active);
+        assert_eq!(status.enabled, Some(false));
+        assert_eq!(status.main_pid, None);
+
+        assert!(parse_systemd_show("nope", "LoadState=not-found\n", String::new()).is_none());
+    }
+}
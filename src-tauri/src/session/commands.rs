@@ -1,6 +1,9 @@
 //! Session Tauri Commands
 
-use super::{manager, Session, SessionError};
+use super::{
+    latency, manager, stats, LatencyHistory, ReconnectResult, Session, SessionError, SessionStats,
+};
+use tauri::AppHandle;
 use uuid::Uuid;
 
 /// Get all active sessions
@@ -24,3 +27,29 @@ pub fn close_session(id: Uuid) -> Result<(), SessionError> {
         .map(|_| ())
         .ok_or_else(|| SessionError::NotFound(id.to_string()))
 }
+
+/// Recent latency samples of a session with min/avg/max, jitter and loss
+#[tauri::command]
+pub fn get_latency_history(session_id: Uuid) -> LatencyHistory {
+    latency::history(session_id)
+}
+
+/// Bytes sent and received since the session last connected, and its uptime
+#[tauri::command]
+pub fn get_session_stats(session_id: Uuid) -> Result<SessionStats, SessionError> {
+    stats::stats(session_id)
+}
+
+/// Reconnect a session with its host's stored connection parameters
+///
+/// Does nothing if the session is still connected.
+#[tauri::command]
+pub async fn reconnect_session(app: AppHandle, session_id: Uuid) -> Result<Session, SessionError> {
+    super::reconnect_session(&app, session_id).await
+}
+
+/// Reconnect all dropped sessions, e.g. after the network comes back
+#[tauri::command]
+pub async fn reconnect_all(app: AppHandle) -> Vec<ReconnectResult> {
+    super::reconnect_all(&app).await
+}
@@ -0,0 +1,221 @@
+//! Connection quality history
+//!
+//! The monitor times a channel open/close round trip on every connection
+//! at a fixed interval and keeps a rolling window of samples per session,
+//! so intermittent problems show up as jitter and loss rather than a
+//! single number. It only runs while `Settings.show_latency` is on.
+
+use crate::datasphere;
+use crate::ssh::{self, SshClient};
+use crate::terminal;
+use crate::utils::{emit_app_event, AppEvent};
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::AppHandle;
+use uuid::Uuid;
+
+/// How often each connection is measured
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// A probe taking longer than this counts as lost
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Samples kept per session; half an hour at the default interval
+const MAX_SAMPLES: usize = 360;
+
+/// Sample windows by session id
+static HISTORY: Lazy<RwLock<HashMap<Uuid, VecDeque<LatencySample>>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// One latency measurement
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LatencySample {
+    pub timestamp: DateTime<Utc>,
+    /// Round trip time; `None` if the probe failed or timed out
+    pub latency_ms: Option<u32>,
+}
+
+/// Summary of a sample window
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct LatencyStats {
+    pub min_ms: Option<u32>,
+    pub avg_ms: Option<f64>,
+    pub max_ms: Option<u32>,
+    /// Mean difference between consecutive successful samples
+    pub jitter_ms: Option<f64>,
+    /// Failed probes divided by all probes, 0.0 to 1.0
+    pub loss: f64,
+}
+
+impl LatencyStats {
+    pub fn from_samples(samples: &[LatencySample]) -> Self {
+        let values: Vec<u32> = samples.iter().filter_map(|s| s.latency_ms).collect();
+        if samples.is_empty() {
+            return Self::default();
+        }
+        let loss = (samples.len() - values.len()) as f64 / samples.len() as f64;
+        if values.is_empty() {
+            return Self {
+                loss,
+                ..Default::default()
+            };
+        }
+
+        let avg = values.iter().map(|v| *v as f64).sum::<f64>() / values.len() as f64;
+        let jitter = (values.len() > 1).then(|| {
+            let total: f64 = values
+                .windows(2)
+                .map(|w| (w[1] as f64 - w[0] as f64).abs())
+                .sum();
+            total / (values.len() - 1) as f64
+        });
+
+        Self {
+            min_ms: values.iter().min().copied(),
+            avg_ms: Some(avg),
+            max_ms: values.iter().max().copied(),
+            jitter_ms: jitter,
+            loss,
+        }
+    }
+}
+
+/// Recent samples of a session with their summary
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LatencyHistory {
+    pub session_id: Uuid,
+    /// Oldest first
+    pub samples: Vec<LatencySample>,
+    pub stats: LatencyStats,
+}
+
+/// Samples recorded for a session
+pub fn history(session_id: Uuid) -> LatencyHistory {
+    let samples: Vec<LatencySample> = HISTORY
+        .read()
+        .get(&session_id)
+        .map(|window| window.iter().cloned().collect())
+        .unwrap_or_default();
+    LatencyHistory {
+        session_id,
+        stats: LatencyStats::from_samples(&samples),
+        samples,
+    }
+}
+
+/// Start the background latency monitor
+pub fn spawn_latency_monitor(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(SAMPLE_INTERVAL).await;
+            if enabled() {
+                sample_all(&app).await;
+            }
+        }
+    });
+}
+
+/// Whether the user wants latency shown; on while storage is locked
+fn enabled() -> bool {
+    datasphere::storage()
+        .read()
+        .as_ref()
+        .is_none_or(|s| s.get_settings().show_latency)
+}
+
+/// Measure every connection once and record the results
+async fn sample_all(app: &AppHandle) {
+    let mut sessions: Vec<(Uuid, Arc<SshClient>)> = ssh::clients()
+        .read()
+        .iter()
+        .map(|(id, client)| (*id, client.clone()))
+        .collect();
+    sessions.extend(
+        terminal::manager()
+            .read()
+            .sessions()
+            .filter_map(|s| s.ssh_client().map(|client| (s.id, client))),
+    );
+
+    // Tabs can share a connection, so each client is measured once
+    let mut measured: HashMap<Uuid, Option<u32>> = HashMap::new();
+    for (session_id, client) in &sessions {
+        let latency = match measured.get(&client.id) {
+            Some(latency) => *latency,
+            None => {
+                let latency = measure(client).await;
+                measured.insert(client.id, latency);
+                latency
+            }
+        };
+        record(*session_id, latency);
+
+        if let Some(session) = super::manager().write().get_session_mut(*session_id) {
+            session.latency_ms = latency;
+        }
+        if let Some(latency_ms) = latency {
+            emit_app_event(
+                app,
+                AppEvent::LatencyUpdate {
+                    session_id: session_id.to_string(),
+                    latency_ms,
+                },
+            );
+        }
+    }
+
+    // Forget sessions that are gone
+    HISTORY
+        .write()
+        .retain(|id, _| sessions.iter().any(|(session_id, _)| session_id == id));
+}
+
+async fn measure(client: &SshClient) -> Option<u32> {
+    if client.is_closed() {
+        return None;
+    }
+    client.measure_latency(PROBE_TIMEOUT).await.ok()
+}
+
+fn record(session_id: Uuid, latency_ms: Option<u32>) {
+    let mut history = HISTORY.write();
+    let window = history.entry(session_id).or_default();
+    if window.len() == MAX_SAMPLES {
+        window.pop_front();
+    }
+    window.push_back(LatencySample {
+        timestamp: Utc::now(),
+        latency_ms,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latency_stats() {
+        let samples: Vec<LatencySample> = [Some(10), None, Some(30), Some(20)]
+            .into_iter()
+            .map(|latency_ms| LatencySample {
+                timestamp: Utc::now(),
+                latency_ms,
+            })
+            .collect();
+
+        let stats = LatencyStats::from_samples(&samples);
+        assert_eq!(stats.min_ms, Some(10));
+        assert_eq!(stats.max_ms, Some(30));
+        assert_eq!(stats.avg_ms, Some(20.0));
+        assert_eq!(stats.jitter_ms, Some(15.0));
+        assert_eq!(stats.loss, 0.25);
+
+        assert_eq!(LatencyStats::from_samples(&[]), LatencyStats::default());
+    }
+}
@@ -3,6 +3,15 @@
 //! Handles terminal sessions, tabs, panes, and auto-reconnect
 
 pub mod commands;
+mod latency;
+mod reaper;
+mod reconnect;
+mod stats;
+
+pub use latency::{spawn_latency_monitor, LatencyHistory, LatencySample, LatencyStats};
+pub use reaper::spawn_reaper_task;
+pub use reconnect::{reconnect, reconnect_all, reconnect_session, ReconnectResult};
+pub use stats::{remove_counters, reset_counters, ByteCounters, SessionStats};
 
 use parking_lot::RwLock;
 use once_cell::sync::Lazy;
@@ -27,6 +36,28 @@ pub fn manager() -> &'static RwLock<SessionManager> {
     &SESSION_MANAGER
 }
 
+/// Stored host a session was opened for
+///
+/// Tabs are tracked sessions; terminals opened without one, such as by MCP
+/// clients, are looked up by their terminal, and standalone sessions by the
+/// host they were opened for.
+pub fn host_of(session_id: Uuid) -> Option<Uuid> {
+    let tracked = manager().read().get_session(session_id).map(|s| s.host_id);
+    tracked
+        .or_else(|| {
+            crate::terminal::manager()
+                .read()
+                .get_session(session_id)
+                .map(|t| t.host_id)
+        })
+        .or_else(|| {
+            crate::ssh::clients()
+                .read()
+                .get(&session_id)
+                .and_then(|c| c.host_id)
+        })
+}
+
 /// Session manager
 #[derive(Debug, Default)]
 pub struct SessionManager {
@@ -51,6 +82,7 @@ impl SessionManager {
     }
 
     pub fn close_session(&mut self, id: Uuid) -> Option<Session> {
+        remove_counters(id);
         self.sessions.remove(&id)
     }
 
@@ -61,6 +93,23 @@ impl SessionManager {
     pub fn get_session_mut(&mut self, id: Uuid) -> Option<&mut Session> {
         self.sessions.get_mut(&id)
     }
+
+    /// Record that a terminal session connected, tracking it if it is new
+    pub fn mark_connected(&mut self, id: Uuid, host_id: Uuid, name: &str) {
+        let session = self
+            .sessions
+            .entry(id)
+            .or_insert_with(|| Session::with_id(id, host_id, name.to_string()));
+        session.status = SessionStatus::Connected;
+        session.connected_at = Some(Utc::now());
+    }
+
+    /// Update the status of a tracked session
+    pub fn set_status(&mut self, id: Uuid, status: SessionStatus) {
+        if let Some(session) = self.sessions.get_mut(&id) {
+            session.status = status;
+        }
+    }
 }
 
 /// Terminal session
@@ -77,8 +126,12 @@ pub struct Session {
 
 impl Session {
     pub fn new(host_id: Uuid, name: String) -> Self {
+        Self::with_id(Uuid::new_v4(), host_id, name)
+    }
+
+    pub fn with_id(id: Uuid, host_id: Uuid, name: String) -> Self {
         Self {
-            id: Uuid::new_v4(),
+            id,
             host_id,
             name,
             status: SessionStatus::Disconnected,
@@ -90,7 +143,7 @@ impl Session {
 }
 
 /// Session connection status
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum SessionStatus {
     Disconnected,
@@ -0,0 +1,172 @@
+//! Dead session reaper
+//!
+//! Connections that die without a clean close leave their clients and
+//! sessions behind in the global managers. The reaper probes them
+//! periodically and drops the ones that are confirmed dead.
+
+use super::SessionStatus;
+use crate::sftp;
+use crate::ssh::{self, ConnectionReason, ConnectionState, SshClient};
+use crate::terminal::{self, TerminalEvent};
+use crate::utils::{emit_app_event, AppEvent};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+use uuid::Uuid;
+
+/// How often sessions are checked
+const REAP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How long a liveness probe may take
+const PROBE_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Failed probes in a row before an open but silent connection is dropped
+const MAX_STRIKES: u32 = 2;
+
+/// Start the background reaper
+pub fn spawn_reaper_task(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut reaper = Reaper::default();
+        loop {
+            tokio::time::sleep(REAP_INTERVAL).await;
+            let reaped = reaper.run(&app).await;
+            if reaped > 0 {
+                tracing::info!("Reaped {} dead sessions", reaped);
+            }
+        }
+    });
+}
+
+#[derive(Default)]
+struct Reaper {
+    /// Consecutive failed probes per SSH client id
+    strikes: HashMap<Uuid, u32>,
+}
+
+impl Reaper {
+    /// Check every session once and remove the dead ones
+    async fn run(&mut self, app: &AppHandle) -> usize {
+        // Terminals can share a client, so each one is probed once per round
+        let mut verdicts: HashMap<Uuid, bool> = HashMap::new();
+        let mut reaped = 0;
+
+        let standalone: Vec<(Uuid, Arc<SshClient>)> = ssh::clients()
+            .read()
+            .iter()
+            .map(|(id, client)| (*id, client.clone()))
+            .collect();
+
+        for (id, client) in standalone {
+            if self.is_alive(&client, &mut verdicts).await {
+                continue;
+            }
+            if ssh::clients().write().remove(&id).is_none() {
+                continue;
+            }
+            sftp::manager().write().remove_session(&id);
+            emit_app_event(
+                app,
+                AppEvent::connection_state(
+                    None,
+                    id,
+                    ConnectionState::Disconnected,
+                    Some(ConnectionReason::NetworkLost),
+                ),
+            );
+            tracing::warn!("Removed dead SSH session {}", id);
+            reaped += 1;
+        }
+
+        let terminals: Vec<_> = terminal::manager()
+            .read()
+            .sessions()
+            .filter_map(|s| {
+                s.ssh_client()
+                    .map(|client| (s.id, s.host_id, client, s.io_task_finished()))
+            })
+            .collect();
+
+        for (id, host_id, client, task_finished) in terminals {
+            // A session whose I/O task is gone can never produce output again
+            if !task_finished && self.is_alive(&client, &mut verdicts).await {
+                continue;
+            }
+            drop(client);
+
+            // The session may be checked out by a command right now; try again next round
+            let Some(mut session) = terminal::manager().write().close_session(id) else {
+                continue;
+            };
+            let _ = session.disconnect().await;
+            sftp::manager().write().remove_session(&id);
+            // Keep the session record so reconnect_all can revive it
+            if let Some(tracked) = super::manager().write().get_session_mut(id) {
+                if tracked.status == SessionStatus::Connected {
+                    tracked.status = SessionStatus::Error;
+                }
+            }
+
+            // The I/O task reports its own exit, so only announce the ones it missed
+            if !task_finished {
+                let _ = app.emit(
+                    &format!("terminal-data-{}", id),
+                    TerminalEvent::Disconnected,
+                );
+                emit_app_event(
+                    app,
+                    AppEvent::connection_state(
+                        Some(host_id),
+                        id,
+                        ConnectionState::Disconnected,
+                        Some(ConnectionReason::NetworkLost),
+                    ),
+                );
+            }
+            emit_app_event(
+                app,
+                AppEvent::HostDisconnected {
+                    host_id: host_id.to_string(),
+                    session_id: id.to_string(),
+                },
+            );
+            tracing::warn!("Removed dead terminal session {}", id);
+            reaped += 1;
+        }
+
+        // Forget strikes for clients that are no longer around
+        self.strikes.retain(|id, _| verdicts.contains_key(id));
+        reaped
+    }
+
+    /// Probe a client, reusing this round's verdict if it was already checked
+    async fn is_alive(&mut self, client: &SshClient, verdicts: &mut HashMap<Uuid, bool>) -> bool {
+        if let Some(alive) = verdicts.get(&client.id) {
+            return *alive;
+        }
+
+        let alive = if client.is_closed() {
+            false
+        } else if client.probe(PROBE_TIMEOUT).await {
+            self.strikes.remove(&client.id);
+            true
+        } else {
+            // A slow link can miss one probe; only give up after several
+            let strikes = self.strikes.entry(client.id).or_insert(0);
+            *strikes += 1;
+            tracing::debug!(
+                "Liveness probe failed for {} ({} strikes)",
+                client.id,
+                strikes
+            );
+            *strikes < MAX_STRIKES
+        };
+
+        if !alive {
+            self.strikes.remove(&client.id);
+        }
+        verdicts.insert(client.id, alive);
+        alive
+    }
+}
@@ -0,0 +1,142 @@
+//! Reviving dropped sessions
+//!
+//! Sessions are reconnected with the connection parameters stored on
+//! their host, reusing the terminal session so the tab keeps its id.
+
+use super::{manager, Session, SessionError, SessionStatus};
+use crate::datasphere;
+use crate::terminal::{self, TerminalSession};
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use tauri::AppHandle;
+use uuid::Uuid;
+
+/// How many sessions are reconnected at the same time
+const MAX_CONCURRENT_RECONNECTS: usize = 4;
+
+/// Outcome of reconnecting one session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReconnectResult {
+    pub session_id: Uuid,
+    pub host_id: Uuid,
+    pub name: String,
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+/// Reconnect a session using its host's stored connection parameters
+pub async fn reconnect(app: &AppHandle, session: &Session) -> Result<(), SessionError> {
+    let (host, config) = {
+        let storage = datasphere::storage().read();
+        let storage = storage
+            .as_ref()
+            .ok_or_else(|| SessionError::General("Storage not initialized".to_string()))?;
+        let host = storage
+            .get_host(session.host_id)
+            .ok_or_else(|| SessionError::NotFound(format!("Host {}", session.host_id)))?;
+        let config = storage
+            .host_ssh_config(&host)
+            .map_err(|e| SessionError::General(e.to_string()))?;
+        (host, config)
+    };
+
+    manager()
+        .write()
+        .set_status(session.id, SessionStatus::Reconnecting);
+
+    let mut terminal = terminal::manager()
+        .write()
+        .close_session(session.id)
+        .unwrap_or_else(|| TerminalSession::new_with_id(session.id, host.id, host.name.clone()));
+    // Events and later lookups go by the terminal's host, so it must be the session's
+    terminal.host_id = host.id;
+    terminal.host_name = host.name;
+
+    // Release whatever is left of the dead connection before dialing again
+    let _ = terminal.disconnect().await;
+    let result = terminal.connect(config, app.clone()).await;
+    terminal::manager()
+        .write()
+        .insert_session(session.id, terminal);
+
+    if let Err(e) = result {
+        manager()
+            .write()
+            .set_status(session.id, SessionStatus::Error);
+        return Err(SessionError::General(e.to_string()));
+    }
+    Ok(())
+}
+
+/// Reconnect a tracked session by id
+///
+/// A session that is connected, or already reconnecting, is left alone.
+/// Returns the session as tracked afterwards.
+pub async fn reconnect_session(app: &AppHandle, session_id: Uuid) -> Result<Session, SessionError> {
+    let session = find(session_id)?;
+    let live = terminal::manager()
+        .read()
+        .get_session(session_id)
+        .is_some_and(|t| t.connected);
+    let in_progress = session.status == SessionStatus::Reconnecting;
+    if (session.status == SessionStatus::Connected && live) || in_progress {
+        return Ok(session);
+    }
+
+    reconnect(app, &session).await?;
+    find(session_id)
+}
+
+fn find(session_id: Uuid) -> Result<Session, SessionError> {
+    manager()
+        .read()
+        .get_session(session_id)
+        .cloned()
+        .ok_or_else(|| SessionError::NotFound(format!("Session {}", session_id)))
+}
+
+/// Reconnect every dropped session whose host still exists
+///
+/// Only sessions that were connected before are considered; sessions the
+/// user closed are no longer tracked and are skipped.
+pub async fn reconnect_all(app: &AppHandle) -> Vec<ReconnectResult> {
+    let hosts: HashSet<Uuid> = datasphere::storage()
+        .read()
+        .as_ref()
+        .map(|s| s.get_hosts().into_iter().map(|h| h.id).collect())
+        .unwrap_or_default();
+
+    let dropped: Vec<Session> = manager()
+        .read()
+        .get_sessions()
+        .into_iter()
+        .filter(|s| s.connected_at.is_some() && hosts.contains(&s.host_id))
+        .filter(|s| {
+            matches!(
+                s.status,
+                SessionStatus::Error | SessionStatus::Disconnected | SessionStatus::Reconnecting
+            )
+        })
+        .collect();
+
+    tracing::info!("Reconnecting {} sessions", dropped.len());
+
+    stream::iter(dropped)
+        .map(|session| async move {
+            let result = reconnect(app, &session).await;
+            if let Err(e) = &result {
+                tracing::warn!("Reconnecting session {} failed: {}", session.id, e);
+            }
+            ReconnectResult {
+                session_id: session.id,
+                host_id: session.host_id,
+                name: session.name,
+                success: result.is_ok(),
+                error: result.err().map(|e| e.to_string()),
+            }
+        })
+        .buffer_unordered(MAX_CONCURRENT_RECONNECTS)
+        .collect()
+        .await
+}
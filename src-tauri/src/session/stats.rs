@@ -0,0 +1,122 @@
+//! Data moved per session
+//!
+//! The terminal task counts the bytes it writes to and reads from the SSH
+//! channel, before any decoding. Counters start from zero on every
+//! connect, including reconnects; uptime is derived from the session's
+//! `connected_at`.
+
+use super::{manager, SessionError, SessionStatus};
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Counters by session id
+static COUNTERS: Lazy<RwLock<HashMap<Uuid, Arc<ByteCounters>>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// Bytes moved over one connection
+#[derive(Debug, Default)]
+pub struct ByteCounters {
+    sent: AtomicU64,
+    received: AtomicU64,
+}
+
+impl ByteCounters {
+    pub fn add_sent(&self, bytes: usize) {
+        self.sent.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub fn add_received(&self, bytes: usize) {
+        self.received.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+}
+
+/// Traffic and uptime of a session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionStats {
+    pub session_id: Uuid,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    pub connected_at: Option<DateTime<Utc>>,
+    /// Seconds since `connected_at`; `None` unless connected
+    pub uptime_seconds: Option<u64>,
+}
+
+/// Start counting a new connection of a session from zero
+pub fn reset_counters(session_id: Uuid) -> Arc<ByteCounters> {
+    let counters = Arc::new(ByteCounters::default());
+    COUNTERS.write().insert(session_id, counters.clone());
+    counters
+}
+
+/// Forget a closed session's counters
+pub fn remove_counters(session_id: Uuid) {
+    COUNTERS.write().remove(&session_id);
+}
+
+/// Current traffic and uptime of a tracked session
+pub fn stats(session_id: Uuid) -> Result<SessionStats, SessionError> {
+    let (status, connected_at) = manager()
+        .read()
+        .get_session(session_id)
+        .map(|s| (s.status.clone(), s.connected_at))
+        .ok_or_else(|| SessionError::NotFound(format!("Session {}", session_id)))?;
+    let counters = COUNTERS
+        .read()
+        .get(&session_id)
+        .cloned()
+        .unwrap_or_default();
+
+    Ok(SessionStats {
+        session_id,
+        bytes_sent: counters.sent.load(Ordering::Relaxed),
+        bytes_received: counters.received.load(Ordering::Relaxed),
+        connected_at,
+        uptime_seconds: uptime(&status, connected_at, Utc::now()),
+    })
+}
+
+fn uptime(
+    status: &SessionStatus,
+    connected_at: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+) -> Option<u64> {
+    if *status != SessionStatus::Connected {
+        return None;
+    }
+    connected_at.map(|since| (now - since).num_seconds().max(0) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counters_and_uptime() {
+        let session_id = Uuid::new_v4();
+        let counters = reset_counters(session_id);
+        counters.add_sent(5);
+        counters.add_received(1024);
+        counters.add_received(24);
+        assert_eq!(counters.received.load(Ordering::Relaxed), 1048);
+
+        // A reconnect starts over
+        let counters = reset_counters(session_id);
+        assert_eq!(counters.sent.load(Ordering::Relaxed), 0);
+        remove_counters(session_id);
+
+        let now = Utc::now();
+        let since = now - chrono::Duration::seconds(90);
+        assert_eq!(
+            uptime(&SessionStatus::Connected, Some(since), now),
+            Some(90)
+        );
+        assert_eq!(uptime(&SessionStatus::Error, Some(since), now), None);
+        assert_eq!(uptime(&SessionStatus::Connected, None, now), None);
+    }
+}
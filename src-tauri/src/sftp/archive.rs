@@ -0,0 +1,258 @@
+//! Server-side archive creation and extraction
+//!
+//! Runs `tar`/`zip` over an exec channel so a whole directory can be
+//! transferred as one file instead of many small SFTP round trips.
+
+use super::helper::run_helper;
+use super::SftpError;
+use crate::ssh::SshClient;
+use crate::utils::shell_quote;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Supported archive formats
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ArchiveFormat {
+    Tar,
+    TarGz,
+    Zip,
+}
+
+impl ArchiveFormat {
+    /// Guess the format from an archive file name
+    pub fn from_path(path: &str) -> Option<Self> {
+        let lower = path.to_lowercase();
+        if lower.ends_with(".tar.gz") || lower.ends_with(".tgz") {
+            Some(Self::TarGz)
+        } else if lower.ends_with(".tar") {
+            Some(Self::Tar)
+        } else if lower.ends_with(".zip") {
+            Some(Self::Zip)
+        } else {
+            None
+        }
+    }
+
+    fn create_tool(&self) -> &'static str {
+        match self {
+            Self::Tar | Self::TarGz => "tar",
+            Self::Zip => "zip",
+        }
+    }
+
+    fn extract_tool(&self) -> &'static str {
+        match self {
+            Self::Tar | Self::TarGz => "tar",
+            Self::Zip => "unzip",
+        }
+    }
+}
+
+/// Result of an archive operation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveResult {
+    pub archive_path: String,
+    pub size: u64,
+}
+
+/// Create an archive containing `paths` at `archive_path`
+pub async fn create_archive(
+    client: &SshClient,
+    paths: &[String],
+    archive_path: &str,
+    format: ArchiveFormat,
+) -> Result<ArchiveResult, SftpError> {
+    if paths.is_empty() {
+        return Err(SftpError::InvalidPath("No paths to archive".to_string()));
+    }
+    require_absolute(archive_path)?;
+    for path in paths {
+        require_absolute(path)?;
+    }
+
+    let command = create_command(paths, archive_path, format);
+    run_with_tool(client, format.create_tool(), &command).await?;
+
+    let archive = shell_quote(archive_path);
+    let output = run_helper(client, &["wc"], &format!("wc -c < {}", archive), &[]).await?;
+    let size = parse_size(&output.stdout).ok_or_else(|| {
+        SftpError::TransferFailed(format!(
+            "Could not read the size of {}: {}",
+            archive_path,
+            output.stderr.trim()
+        ))
+    })?;
+
+    tracing::info!("Created archive {} ({} bytes)", archive_path, size);
+
+    Ok(ArchiveResult {
+        archive_path: archive_path.to_string(),
+        size,
+    })
+}
+
+/// Shell command that archives `paths` into `archive_path`
+fn create_command(paths: &[String], archive_path: &str, format: ArchiveFormat) -> String {
+    let archive = shell_quote(archive_path);
+    match format {
+        ArchiveFormat::Tar | ArchiveFormat::TarGz => {
+            let flags = if format == ArchiveFormat::TarGz {
+                "-czf"
+            } else {
+                "-cf"
+            };
+            // One -C per entry keeps archive members relative to their parent
+            let members: Vec<String> = paths
+                .iter()
+                .map(|p| {
+                    let (parent, name) = split_path(p);
+                    format!(
+                        "-C {} {}",
+                        shell_quote(&parent),
+                        shell_quote(&member(&name))
+                    )
+                })
+                .collect();
+            format!("tar {} {} {}", flags, archive, members.join(" "))
+        }
+        ArchiveFormat::Zip => paths
+            .iter()
+            .map(|p| {
+                let (parent, name) = split_path(p);
+                format!(
+                    "(cd -- {} && zip -qr {} {})",
+                    shell_quote(&parent),
+                    archive,
+                    shell_quote(&member(&name))
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(" && "),
+    }
+}
+
+/// Extract an archive into `dest`, creating it if needed
+pub async fn extract_archive(
+    client: &SshClient,
+    archive_path: &str,
+    dest: &str,
+) -> Result<(), SftpError> {
+    require_absolute(archive_path)?;
+    require_absolute(dest)?;
+
+    let format = ArchiveFormat::from_path(archive_path)
+        .ok_or_else(|| SftpError::InvalidPath(format!("Unknown archive type: {}", archive_path)))?;
+
+    run_with_tool(
+        client,
+        format.extract_tool(),
+        &extract_command(archive_path, dest, format),
+    )
+    .await?;
+
+    tracing::info!("Extracted {} into {}", archive_path, dest);
+    Ok(())
+}
+
+/// Shell command that extracts `archive_path` into `dest`
+fn extract_command(archive_path: &str, dest: &str, format: ArchiveFormat) -> String {
+    let archive = shell_quote(archive_path);
+    let target = shell_quote(dest);
+    let extract = match format {
+        ArchiveFormat::Tar => format!("tar -xf {} -C {}", archive, target),
+        ArchiveFormat::TarGz => format!("tar -xzf {} -C {}", archive, target),
+        ArchiveFormat::Zip => format!("unzip -qo {} -d {}", archive, target),
+    };
+    format!("mkdir -p -- {} && {}", target, extract)
+}
+
+/// Size printed by `wc -c`, `None` if the output isn't a number
+fn parse_size(stdout: &str) -> Option<u64> {
+    stdout.trim().parse().ok()
+}
+
+/// Run `command` after checking that `tool` exists on the server
+async fn run_with_tool(client: &SshClient, tool: &str, command: &str) -> Result<(), SftpError> {
+    let output = run_helper(client, &[tool], command, &[]).await?;
+
+    match output.exit_code {
+        0 => Ok(()),
+        code => Err(SftpError::TransferFailed(format!(
+            "{} exited with {}: {}",
+            tool,
+            code,
+            output.stderr.trim()
+        ))),
+    }
+}
+
+fn require_absolute(path: &str) -> Result<(), SftpError> {
+    if path.starts_with('/') {
+        Ok(())
+    } else {
+        Err(SftpError::InvalidPath(format!(
+            "Path must be absolute: {}",
+            path
+        )))
+    }
+}
+
+/// Archive member for a file name that can't be mistaken for an option
+///
+/// tar stops reading `-C` after `--`, so a leading `-` is escaped with a
+/// `./` prefix instead, which extracts to the same place.
+fn member(name: &str) -> String {
+    if name.starts_with('-') {
+        format!("./{}", name)
+    } else {
+        name.to_string()
+    }
+}
+
+fn split_path(path: &str) -> (String, String) {
+    let path = Path::new(path.trim_end_matches('/'));
+    let parent = path
+        .parent()
+        .map(|p| p.to_string_lossy().to_string())
+        .filter(|p| !p.is_empty())
+        .unwrap_or_else(|| "/".to_string());
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| ".".to_string());
+    (parent, name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_command_guards_names() {
+        let paths = vec!["/srv/app/".to_string(), "/tmp/-rf".to_string()];
+        assert_eq!(
+            create_command(&paths, "/tmp/out.tar.gz", ArchiveFormat::TarGz),
+            "tar -czf '/tmp/out.tar.gz' -C '/srv' 'app' -C '/tmp' './-rf'"
+        );
+        assert_eq!(
+            create_command(&paths[1..], "/tmp/it's.zip", ArchiveFormat::Zip),
+            "(cd -- '/tmp' && zip -qr '/tmp/it'\\''s.zip' './-rf')"
+        );
+    }
+
+    #[test]
+    fn test_extract_command() {
+        assert_eq!(
+            extract_command("/tmp/a.tar", "/srv/-x", ArchiveFormat::Tar),
+            "mkdir -p -- '/srv/-x' && tar -xf '/tmp/a.tar' -C '/srv/-x'"
+        );
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size(" 1024\n"), Some(1024));
+        assert_eq!(parse_size(""), None);
+        assert_eq!(parse_size("wc: /tmp/a.tar: No such file"), None);
+    }
+}
@@ -1,10 +1,28 @@
 //! SFTP Client Implementation
 
-use super::{FileEntry, SftpError, TransferProgress};
+use super::fsstats::{self, FsStats};
+use super::perms::validate_mode;
+use super::sync::join_relative;
+use super::transfers;
+use super::{DirTransferResult, FileEntry, SftpError, TransferFileError, TransferProgress};
+use russh_sftp::client::fs::{File, Metadata};
 use russh_sftp::client::SftpSession;
-use std::path::Path;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use russh_sftp::protocol::{OpenFlags, StatusCode};
+use std::collections::HashSet;
+use std::io::SeekFrom;
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
 use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+/// Bytes read from the source per round trip when copying between servers
+const COPY_CHUNK_SIZE: usize = 256 * 1024;
+
+/// Bytes per read/write when uploading or downloading
+const TRANSFER_CHUNK_SIZE: usize = 32 * 1024;
+
+/// Symlink targets read at once while listing a directory
+const LINK_READS_IN_FLIGHT: usize = 32;
 
 /// SFTP Client for file operations
 pub struct SftpClient {
@@ -19,63 +37,191 @@ impl SftpClient {
 
     /// List directory contents
     pub async fn list_dir(&self, path: &str) -> Result<Vec<FileEntry>, SftpError> {
-        tracing::debug!("Listing directory: {}", path);
+        self.list_dir_page(path, 0, None).await
+    }
 
-        let entries_result = self.sftp.read_dir(path).await
-            .map_err(|e| SftpError::Ssh(format!("Failed to read directory: {}", e)))?;
+    /// List one page of a directory
+    ///
+    /// Entries are sorted (directories first, then by name) so pages are
+    /// stable. The `..` entry is prepended to the first page and does not
+    /// count towards `offset`/`limit`.
+    pub async fn list_dir_page(
+        &self,
+        path: &str,
+        offset: usize,
+        limit: Option<usize>,
+    ) -> Result<Vec<FileEntry>, SftpError> {
+        tracing::debug!(
+            "Listing directory: {} (offset {}, limit {:?})",
+            path,
+            offset,
+            limit
+        );
+
+        let names = self.read_dir_sorted(path).await?;
+        let end = limit
+            .map(|l| offset.saturating_add(l).min(names.len()))
+            .unwrap_or(names.len());
 
         let mut entries = Vec::new();
+        if offset == 0 {
+            entries.extend(parent_entry(path));
+        }
+        let page = names.get(offset..end).unwrap_or_default();
+        entries.extend(self.listed_entries(path, page).await);
 
-        for entry in entries_result {
-            let metadata = entry.metadata();
-            let is_dir = metadata.is_dir();
-            let size = metadata.len();
-
-            let modified = metadata.modified().ok().and_then(|system_time| {
-                system_time
-                    .duration_since(std::time::UNIX_EPOCH)
-                    .ok()
-                    .and_then(|duration| {
-                        chrono::DateTime::from_timestamp(duration.as_secs() as i64, 0)
-                    })
-            });
-
-            let permissions = if is_dir {
-                Some("drwxr-xr-x".to_string())
-            } else {
-                Some("-rw-r--r--".to_string())
-            };
+        Ok(entries)
+    }
 
-            entries.push(FileEntry {
-                name: entry.file_name().to_string(),
-                path: format!("{}/{}", path.trim_end_matches('/'), entry.file_name()),
-                is_dir,
-                size,
-                modified,
-                permissions,
-                owner: None,
-                group: None,
-            });
-        }
-
-        // Add parent directory entry if not root
-        if path != "/" {
-            entries.insert(
-                0,
-                FileEntry {
-                    name: "..".to_string(),
-                    path: get_parent_path(path),
-                    is_dir: true,
-                    size: 0,
-                    modified: None,
-                    permissions: Some("drwxr-xr-x".to_string()),
-                    owner: None,
-                    group: None,
-                },
-            );
+    /// List a directory in batches, calling `on_batch` for each one
+    ///
+    /// Returns the number of entries (excluding `..`).
+    pub async fn list_dir_batches<F>(
+        &self,
+        path: &str,
+        batch_size: usize,
+        mut on_batch: F,
+    ) -> Result<usize, SftpError>
+    where
+        F: FnMut(Vec<FileEntry>),
+    {
+        let names = self.read_dir_sorted(path).await?;
+        let total = names.len();
+
+        let batch_size = batch_size.max(1);
+        let mut batch: Vec<FileEntry> = parent_entry(path).into_iter().collect();
+        let mut rest = names.as_slice();
+        while !rest.is_empty() {
+            let take = batch_size.saturating_sub(batch.len()).clamp(1, rest.len());
+            let (chunk, tail) = rest.split_at(take);
+            batch.extend(self.listed_entries(path, chunk).await);
+            rest = tail;
+            if batch.len() >= batch_size {
+                on_batch(std::mem::take(&mut batch));
+            }
+        }
+        if !batch.is_empty() {
+            on_batch(batch);
         }
 
-        Ok(entries)
+        Ok(total)
+    }
+
+    /// Read raw directory entries, sorted directories first then by name
+    ///
+    /// russh-sftp's high-level session always reads the directory to the end,
+    /// so paging happens after the read; only the per-entry conversion and
+    /// the IPC payload are bounded.
+    pub(super) async fn read_dir_sorted(
+        &self,
+        path: &str,
+    ) -> Result<Vec<(String, Metadata)>, SftpError> {
+        let read_dir = self
+            .sftp
+            .read_dir(path)
+            .await
+            .map_err(|e| SftpError::Ssh(format!("Failed to read directory: {}", e)))?;
+
+        let mut names: Vec<(String, Metadata)> = read_dir
+            .map(|entry| (entry.file_name(), entry.metadata()))
+            .collect();
+        names.sort_by(|(a_name, a), (b_name, b)| {
+            b.is_dir().cmp(&a.is_dir()).then_with(|| a_name.cmp(b_name))
+        });
+
+        Ok(names)
+    }
+
+    /// Listing entries for `names`, in order
+    ///
+    /// Symlink targets are read a few at a time rather than one round trip
+    /// after another, so a directory full of links still lists quickly.
+    async fn listed_entries(&self, dir: &str, names: &[(String, Metadata)]) -> Vec<FileEntry> {
+        let mut entries = Vec::with_capacity(names.len());
+        for chunk in names.chunks(LINK_READS_IN_FLIGHT) {
+            let listed = chunk
+                .iter()
+                .map(|(name, metadata)| self.listed_entry(dir, name, metadata));
+            entries.extend(futures::future::join_all(listed).await);
+        }
+        entries
+    }
+
+    /// A listing entry, with the target filled in for symlinks
+    ///
+    /// A link whose target can't be read is still listed, just without it.
+    async fn listed_entry(&self, dir: &str, name: &str, metadata: &Metadata) -> FileEntry {
+        let mut entry = file_entry(dir, name, metadata);
+        if metadata.is_symlink() {
+            match self.read_link(&entry.path).await {
+                Ok(target) => entry.symlink_target = Some(target),
+                Err(e) => tracing::debug!("Could not read link {}: {}", entry.path, e),
+            }
+        }
+        entry
+    }
+
+    /// Pick the directory a new session starts in
+    ///
+    /// Uses `initial` if it is an existing directory, otherwise the login
+    /// directory, otherwise `/`.
+    pub async fn resolve_start_path(&self, initial: Option<&str>) -> String {
+        if let Some(path) = initial.map(str::trim).filter(|p| !p.is_empty()) {
+            // SFTP paths are relative to the login directory already
+            let path = path.strip_prefix("~/").unwrap_or(path);
+            match self.sftp.metadata(path).await {
+                Ok(metadata) if metadata.is_dir() => {
+                    return self
+                        .sftp
+                        .canonicalize(path)
+                        .await
+                        .unwrap_or_else(|_| path.to_string());
+                }
+                Ok(_) => tracing::warn!("Initial path {} is not a directory", path),
+                Err(e) => tracing::warn!("Initial path {} is not usable: {}", path, e),
+            }
+        }
+
+        self.sftp
+            .canonicalize(".")
+            .await
+            .unwrap_or_else(|_| "/".to_string())
+    }
+
+    /// Absolute path with symlinks and `..` resolved by the server
+    pub async fn canonicalize(&self, path: &str) -> Result<String, SftpError> {
+        self.sftp
+            .canonicalize(path)
+            .await
+            .map_err(|e| SftpError::PathNotFound(format!("{}: {}", path, e)))
+    }
+
+    /// Same as `canonicalize`, named after the SFTP `realpath` request
+    pub async fn realpath(&self, path: &str) -> Result<String, SftpError> {
+        self.canonicalize(path).await
+    }
+
+    /// Create a symbolic link at `link_path` pointing to `target`
+    ///
+    /// `target` is stored as given, so a relative target is resolved from
+    /// the link's directory.
+    pub async fn symlink(&self, target: &str, link_path: &str) -> Result<(), SftpError> {
+        tracing::info!("Linking {} to {}", link_path, target);
+
+        // OpenSSH reads the two paths of SSH_FXP_SYMLINK in swapped order,
+        // and nearly every server is OpenSSH, so the target goes first
+        self.sftp
+            .symlink(target, link_path)
+            .await
+            .map_err(|e| status_error(link_path, e))
+    }
+
+    /// Read where a symbolic link points, without resolving it further
+    pub async fn read_link(&self, path: &str) -> Result<String, SftpError> {
+        self.sftp
+            .read_link(path)
+            .await
+            .map_err(|e| status_error(path, e))
     }
 
     /// Get file/directory info
@@ -95,9 +241,7 @@ impl SftpClient {
             system_time
                 .duration_since(std::time::UNIX_EPOCH)
                 .ok()
-                .and_then(|duration| {
-                    chrono::DateTime::from_timestamp(duration.as_secs() as i64, 0)
-                })
+                .and_then(|duration| chrono::DateTime::from_timestamp(duration.as_secs() as i64, 0))
         });
 
         let permissions = if is_dir {
@@ -120,9 +264,103 @@ impl SftpClient {
             permissions,
             owner: None,
             group: None,
+            symlink_target: None,
+        })
+    }
+
+    /// Get metadata, following symlinks
+    pub async fn metadata(&self, path: &str) -> Result<Metadata, SftpError> {
+        self.sftp
+            .metadata(path)
+            .await
+            .map_err(|e| status_error(path, e))
+    }
+
+    /// Get metadata without following a final symlink
+    pub async fn symlink_metadata(&self, path: &str) -> Result<Metadata, SftpError> {
+        self.sftp
+            .symlink_metadata(path)
+            .await
+            .map_err(|e| SftpError::PathNotFound(format!("{}: {}", path, e)))
+    }
+
+    /// Filesystem stats via `statvfs@openssh.com`; `None` if unsupported
+    pub async fn fs_stats(&self, path: &str) -> Result<Option<FsStats>, SftpError> {
+        let Some(info) = self
+            .sftp
+            .fs_info(path)
+            .await
+            .map_err(|e| SftpError::Ssh(format!("statvfs failed: {}", e)))?
+        else {
+            return Ok(None);
+        };
+
+        fsstats::from_statvfs(path, &info).map(Some).ok_or_else(|| {
+            SftpError::Unsupported(format!("statvfs sizes of {} are out of range", path))
         })
     }
 
+    /// Set the permission bits of a file or directory
+    pub async fn set_mode(&self, path: &str, mode: u32) -> Result<(), SftpError> {
+        tracing::debug!("Setting mode of {} to {:o}", path, mode);
+
+        let mut attrs = Metadata::empty();
+        attrs.permissions = Some(mode);
+        self.sftp
+            .set_metadata(path, attrs)
+            .await
+            .map_err(|e| SftpError::PermissionDenied(format!("{}: {}", path, e)))?;
+
+        Ok(())
+    }
+
+    /// Change the permission bits, rejecting anything beyond `0o7777`
+    pub async fn chmod(&self, path: &str, mode: u32) -> Result<(), SftpError> {
+        self.set_mode(path, validate_mode(mode)?).await
+    }
+
+    /// Change the owner and/or group of a path
+    ///
+    /// SFTP always sets both ids, so an id that isn't given is read from
+    /// the path first and written back unchanged.
+    pub async fn chown(
+        &self,
+        path: &str,
+        uid: Option<u32>,
+        gid: Option<u32>,
+    ) -> Result<(), SftpError> {
+        tracing::debug!("Setting owner of {} to {:?}:{:?}", path, uid, gid);
+        if uid.is_none() && gid.is_none() {
+            return Err(SftpError::InvalidPath(
+                "No owner or group given".to_string(),
+            ));
+        }
+
+        let mut attrs = Metadata::empty();
+        attrs.uid = uid;
+        attrs.gid = gid;
+        if uid.is_none() || gid.is_none() {
+            let current = self
+                .sftp
+                .metadata(path)
+                .await
+                .map_err(|e| status_error(path, e))?;
+            attrs.uid = attrs.uid.or(current.uid);
+            attrs.gid = attrs.gid.or(current.gid);
+            if attrs.uid.is_none() || attrs.gid.is_none() {
+                return Err(SftpError::Unsupported(format!(
+                    "{}: server doesn't report ownership",
+                    path
+                )));
+            }
+        }
+
+        self.sftp
+            .set_metadata(path, attrs)
+            .await
+            .map_err(|e| status_error(path, e))
+    }
+
     /// Create directory
     pub async fn mkdir(&self, path: &str) -> Result<(), SftpError> {
         tracing::info!("Creating directory: {}", path);
@@ -172,90 +410,134 @@ impl SftpClient {
     }
 
     /// Upload file with progress
+    ///
+    /// With `mode` the remote file gets those permissions before any data
+    /// is written. Once `cancel` fires the upload stops after at most one
+    /// chunk and fails with `TransferCancelled`.
     pub async fn upload(
         &self,
         local_path: &str,
         remote_path: &str,
         progress_tx: Option<mpsc::Sender<TransferProgress>>,
+        mode: Option<u32>,
+        cancel: Option<&CancellationToken>,
     ) -> Result<(), SftpError> {
         tracing::info!("Uploading {} to {}", local_path, remote_path);
 
-        // Read local file in chunks
         let mut local_file = tokio::fs::File::open(local_path).await?;
-        let metadata = local_file.metadata().await?;
-        let total_bytes = metadata.len();
+        let total_bytes = local_file.metadata().await?.len();
+        let mut remote_file = self.create_with_mode(remote_path, mode).await?;
 
-        let mut buffer = vec![0u8; 32768]; // 32KB chunks
-        let mut all_data = Vec::new();
+        let mut buffer = vec![0u8; TRANSFER_CHUNK_SIZE];
         let mut bytes_transferred = 0u64;
 
         loop {
-            let n = local_file.read(&mut buffer).await?;
+            let chunk = async {
+                let n = local_file.read(&mut buffer).await?;
+                remote_file
+                    .write_all(&buffer[..n])
+                    .await
+                    .map_err(|e| SftpError::TransferFailed(format!("Write failed: {}", e)))?;
+                Ok::<_, SftpError>(n)
+            };
+            let n = tokio::select! {
+                biased;
+                _ = transfers::cancelled(cancel) => None,
+                n = chunk => Some(n?),
+            };
+            let Some(n) = n else {
+                let _ = remote_file.shutdown().await;
+                if transfers::remove_partial() {
+                    let _ = self.sftp.remove_file(remote_path).await;
+                }
+                tracing::info!("Upload of {} cancelled", remote_path);
+                return Err(SftpError::TransferCancelled(remote_path.to_string()));
+            };
             if n == 0 {
                 break;
             }
-
-            all_data.extend_from_slice(&buffer[..n]);
             bytes_transferred += n as u64;
 
-            // Send progress update
             if let Some(ref tx) = progress_tx {
-                let progress = TransferProgress {
-                    path: remote_path.to_string(),
-                    bytes_transferred,
-                    total_bytes,
-                    percent: (bytes_transferred as f32 / total_bytes as f32) * 100.0,
-                };
+                let progress = file_progress(remote_path, bytes_transferred, total_bytes, false);
                 let _ = tx.send(progress).await;
             }
         }
+        remote_file.shutdown().await?;
 
-        // Write all data to remote file
-        self.sftp
-            .write(remote_path, &all_data)
-            .await
-            .map_err(|e| SftpError::TransferFailed(format!("Write failed: {}", e)))?;
+        if let Some(ref tx) = progress_tx {
+            let progress = file_progress(remote_path, bytes_transferred, total_bytes, true);
+            let _ = tx.send(progress).await;
+        }
 
         tracing::info!("Upload complete: {} bytes", bytes_transferred);
         Ok(())
     }
 
-    /// Download file with progress
+    /// Download file with progress; cancelled like `upload`
     pub async fn download(
         &self,
         remote_path: &str,
         local_path: &str,
         progress_tx: Option<mpsc::Sender<TransferProgress>>,
+        cancel: Option<&CancellationToken>,
     ) -> Result<(), SftpError> {
         tracing::info!("Downloading {} to {}", remote_path, local_path);
 
         // Get remote file size
-        let attrs = self.sftp.metadata(remote_path).await
+        let attrs = self
+            .sftp
+            .metadata(remote_path)
+            .await
             .map_err(|e| SftpError::PathNotFound(format!("{}: {}", remote_path, e)))?;
         let total_bytes = attrs.size.unwrap_or(0);
 
-        // Read remote file
-        let data = self
+        let mut remote_file = self
             .sftp
-            .read(remote_path)
+            .open(remote_path)
             .await
             .map_err(|e| SftpError::PathNotFound(format!("{}: {}", remote_path, e)))?;
-
-        // Write to local file
         let mut local_file = tokio::fs::File::create(local_path).await?;
-        local_file.write_all(&data).await?;
-        local_file.flush().await?;
 
-        let bytes_transferred = data.len() as u64;
+        let mut buffer = vec![0u8; TRANSFER_CHUNK_SIZE];
+        let mut bytes_transferred = 0u64;
 
-        // Send final progress update
-        if let Some(ref tx) = progress_tx {
-            let progress = TransferProgress {
-                path: remote_path.to_string(),
-                bytes_transferred,
-                total_bytes,
-                percent: 100.0,
+        loop {
+            let chunk = async {
+                let n = remote_file
+                    .read(&mut buffer)
+                    .await
+                    .map_err(|e| SftpError::TransferFailed(format!("Read failed: {}", e)))?;
+                local_file.write_all(&buffer[..n]).await?;
+                Ok::<_, SftpError>(n)
             };
+            let n = tokio::select! {
+                biased;
+                _ = transfers::cancelled(cancel) => None,
+                n = chunk => Some(n?),
+            };
+            let Some(n) = n else {
+                drop(local_file);
+                if transfers::remove_partial() {
+                    let _ = tokio::fs::remove_file(local_path).await;
+                }
+                tracing::info!("Download of {} cancelled", remote_path);
+                return Err(SftpError::TransferCancelled(remote_path.to_string()));
+            };
+            if n == 0 {
+                break;
+            }
+            bytes_transferred += n as u64;
+
+            if let Some(ref tx) = progress_tx {
+                let progress = file_progress(remote_path, bytes_transferred, total_bytes, false);
+                let _ = tx.send(progress).await;
+            }
+        }
+        local_file.flush().await?;
+
+        if let Some(ref tx) = progress_tx {
+            let progress = file_progress(remote_path, bytes_transferred, total_bytes, true);
             let _ = tx.send(progress).await;
         }
 
@@ -263,6 +545,288 @@ impl SftpClient {
         Ok(())
     }
 
+    /// Upload a directory tree, creating directories as needed
+    ///
+    /// Progress is reported across all files. Symlinks are listed in the
+    /// result rather than followed unless `follow_symlinks` is set. A file
+    /// that fails is recorded in `errors` and the transfer carries on; only
+    /// failing to create `remote_dir` itself, or `cancel`, aborts it.
+    pub async fn upload_dir(
+        &self,
+        local_dir: &str,
+        remote_dir: &str,
+        progress_tx: Option<mpsc::Sender<TransferProgress>>,
+        follow_symlinks: bool,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<DirTransferResult, SftpError> {
+        tracing::info!("Uploading directory {} to {}", local_dir, remote_dir);
+
+        let root = Path::new(local_dir);
+        if !tokio::fs::metadata(root).await?.is_dir() {
+            return Err(SftpError::NotDirectory(local_dir.to_string()));
+        }
+        let plan = walk_local(root, follow_symlinks);
+        self.ensure_dir(remote_dir).await?;
+
+        let mut result = DirTransferResult {
+            symlinks: plan.symlinks,
+            errors: plan.errors,
+            ..Default::default()
+        };
+        for dir in &plan.dirs {
+            if let Err(e) = self.ensure_dir(&remote_join(remote_dir, dir)).await {
+                result.errors.push(TransferFileError {
+                    path: dir.clone(),
+                    error: e.to_string(),
+                });
+            }
+        }
+
+        let mut progress = DirProgress::new(progress_tx, &plan.files);
+        for file in &plan.files {
+            let local = root.join(&file.path);
+            let remote = remote_join(remote_dir, &file.path);
+            let local = local.to_string_lossy();
+            let (file_tx, forward) = progress.forward(&remote);
+            let (outcome, ()) = tokio::join!(
+                self.upload(&local, &remote, file_tx, file.mode, cancel),
+                forward
+            );
+            if let Err(SftpError::TransferCancelled(_)) = outcome {
+                return Err(SftpError::TransferCancelled(remote_dir.to_string()));
+            }
+            progress.finish(&remote, file, outcome, &mut result).await;
+        }
+
+        tracing::info!(
+            "Directory upload complete: {} files, {} errors",
+            result.files_transferred,
+            result.errors.len()
+        );
+        Ok(result)
+    }
+
+    /// Download a directory tree; the counterpart of `upload_dir`
+    pub async fn download_dir(
+        &self,
+        remote_dir: &str,
+        local_dir: &str,
+        progress_tx: Option<mpsc::Sender<TransferProgress>>,
+        follow_symlinks: bool,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<DirTransferResult, SftpError> {
+        tracing::info!("Downloading directory {} to {}", remote_dir, local_dir);
+
+        if !self.symlink_metadata(remote_dir).await?.is_dir() {
+            return Err(SftpError::NotDirectory(remote_dir.to_string()));
+        }
+        let plan = self.walk_remote(remote_dir, follow_symlinks).await;
+        let root = Path::new(local_dir);
+        tokio::fs::create_dir_all(root).await?;
+
+        let mut result = DirTransferResult {
+            symlinks: plan.symlinks,
+            errors: plan.errors,
+            ..Default::default()
+        };
+        for dir in &plan.dirs {
+            if let Err(e) = tokio::fs::create_dir_all(root.join(dir)).await {
+                result.errors.push(TransferFileError {
+                    path: dir.clone(),
+                    error: e.to_string(),
+                });
+            }
+        }
+
+        let mut progress = DirProgress::new(progress_tx, &plan.files);
+        for file in &plan.files {
+            let remote = remote_join(remote_dir, &file.path);
+            let local = root.join(&file.path);
+            let local = local.to_string_lossy();
+            let (file_tx, forward) = progress.forward(&remote);
+            let (outcome, ()) =
+                tokio::join!(self.download(&remote, &local, file_tx, cancel), forward);
+            if let Err(SftpError::TransferCancelled(_)) = outcome {
+                return Err(SftpError::TransferCancelled(remote_dir.to_string()));
+            }
+            progress.finish(&remote, file, outcome, &mut result).await;
+        }
+
+        tracing::info!(
+            "Directory download complete: {} files, {} errors",
+            result.files_transferred,
+            result.errors.len()
+        );
+        Ok(result)
+    }
+
+    /// Create `path` unless it is already a directory
+    async fn ensure_dir(&self, path: &str) -> Result<(), SftpError> {
+        match self.sftp.metadata(path).await {
+            Ok(metadata) if metadata.is_dir() => Ok(()),
+            Ok(_) => Err(SftpError::NotDirectory(path.to_string())),
+            Err(_) => self.mkdir(path).await,
+        }
+    }
+
+    /// Collect what a download of `root` has to transfer
+    async fn walk_remote(&self, root: &str, follow_symlinks: bool) -> TreePlan {
+        let mut plan = TreePlan::default();
+        let mut visited = HashSet::new();
+        if let Ok(canonical) = self.canonicalize(root).await {
+            visited.insert(canonical);
+        }
+
+        let mut pending = vec![String::new()];
+        while let Some(relative) = pending.pop() {
+            let dir = remote_join(root, &relative);
+            let entries = match self.read_dir_sorted(&dir).await {
+                Ok(entries) => entries,
+                Err(e) => {
+                    plan.error(&relative, e);
+                    continue;
+                }
+            };
+            for (name, mut metadata) in entries {
+                if name == "." || name == ".." {
+                    continue;
+                }
+                let child = join_relative(&relative, &name);
+                if metadata.is_symlink() {
+                    if !follow_symlinks {
+                        plan.symlinks.push(child);
+                        continue;
+                    }
+                    let path = remote_join(root, &child);
+                    match self.sftp.metadata(&path).await {
+                        Ok(target) => metadata = target,
+                        Err(e) => {
+                            plan.error(&child, e);
+                            continue;
+                        }
+                    }
+                    if metadata.is_dir() {
+                        let canonical = self.canonicalize(&path).await.unwrap_or(path);
+                        if !visited.insert(canonical) {
+                            plan.symlinks.push(child);
+                            continue;
+                        }
+                    }
+                }
+                if metadata.is_dir() {
+                    plan.dirs.push(child.clone());
+                    pending.push(child);
+                } else if metadata.is_regular() {
+                    plan.files.push(PlannedFile {
+                        path: child,
+                        size: metadata.size.unwrap_or(0),
+                        mode: None,
+                    });
+                }
+            }
+        }
+        plan
+    }
+
+    /// Stream a file from this server to `dest`, one chunk at a time
+    ///
+    /// Nothing is held locally beyond the current chunk. The destination
+    /// gets the source's permission bits. `on_progress` receives the bytes
+    /// copied so far and the total. The data goes to a file beside
+    /// `dst_path` that replaces it once complete (see `replace_file`), so
+    /// copying a file onto itself, even through another account, can't
+    /// truncate the source. If either side fails, the partial file is
+    /// removed when the destination is still reachable.
+    pub async fn copy_to<F>(
+        &self,
+        src_path: &str,
+        dest: &SftpClient,
+        dst_path: &str,
+        mut on_progress: F,
+    ) -> Result<u64, SftpError>
+    where
+        F: FnMut(u64, u64),
+    {
+        let attrs = self
+            .sftp
+            .metadata(src_path)
+            .await
+            .map_err(|e| SftpError::PathNotFound(format!("{}: {}", src_path, e)))?;
+        if attrs.is_dir() {
+            return Err(SftpError::NotFile(src_path.to_string()));
+        }
+        let total_bytes = attrs.size.unwrap_or(0);
+
+        let mut src = self
+            .sftp
+            .open(src_path)
+            .await
+            .map_err(|e| SftpError::PathNotFound(format!("{}: {}", src_path, e)))?;
+        let part_path = format!("{}.nexus-part-{}", dst_path, uuid::Uuid::new_v4().simple());
+        let mut dst = dest
+            .create_with_mode(&part_path, attrs.permissions.map(|p| p & 0o7777))
+            .await?;
+
+        let result = async {
+            let mut buffer = vec![0u8; COPY_CHUNK_SIZE];
+            let mut copied = 0u64;
+            loop {
+                let n = src.read(&mut buffer).await.map_err(|e| {
+                    SftpError::TransferFailed(format!("Reading {} failed: {}", src_path, e))
+                })?;
+                if n == 0 {
+                    break;
+                }
+                dst.write_all(&buffer[..n]).await.map_err(|e| {
+                    SftpError::TransferFailed(format!("Writing {} failed: {}", dst_path, e))
+                })?;
+                copied += n as u64;
+                on_progress(copied, total_bytes);
+            }
+            dst.shutdown().await.map_err(|e| {
+                SftpError::TransferFailed(format!("Closing {} failed: {}", dst_path, e))
+            })?;
+            dest.replace_file(&part_path, dst_path).await?;
+            Ok(copied)
+        }
+        .await;
+
+        if result.is_err() {
+            let _ = dest.remove(&part_path).await;
+        }
+        result
+    }
+
+    /// Move `part_path` to `dst_path`, replacing any file there
+    ///
+    /// Plain SFTP renames don't overwrite, so an existing file is renamed
+    /// aside and only removed once the new one is in place. If the new one
+    /// can't be moved in, the old one is put back, so `dst_path` is never
+    /// missing its data.
+    async fn replace_file(&self, part_path: &str, dst_path: &str) -> Result<(), SftpError> {
+        if self.rename(part_path, dst_path).await.is_ok() {
+            return Ok(());
+        }
+
+        let backup_path = format!("{}.nexus-old-{}", dst_path, uuid::Uuid::new_v4().simple());
+        self.rename(dst_path, &backup_path).await?;
+        if let Err(e) = self.rename(part_path, dst_path).await {
+            if let Err(restore) = self.rename(&backup_path, dst_path).await {
+                tracing::error!(
+                    "Could not move {} back to {}: {}",
+                    backup_path,
+                    dst_path,
+                    restore
+                );
+            }
+            return Err(e);
+        }
+        if let Err(e) = self.remove(&backup_path).await {
+            tracing::warn!("Could not remove {}: {}", backup_path, e);
+        }
+        Ok(())
+    }
+
     /// Read file contents
     pub async fn read_file(&self, path: &str) -> Result<Vec<u8>, SftpError> {
         tracing::debug!("Reading file: {}", path);
@@ -276,17 +840,375 @@ impl SftpClient {
         Ok(data)
     }
 
-    /// Write file contents
-    pub async fn write_file(&self, path: &str, data: &[u8]) -> Result<(), SftpError> {
+    /// Read up to `len` bytes starting at `offset`
+    ///
+    /// Returns fewer bytes only at the end of the file.
+    pub async fn read_range(
+        &self,
+        path: &str,
+        offset: u64,
+        len: usize,
+    ) -> Result<Vec<u8>, SftpError> {
+        let mut file = self
+            .sftp
+            .open(path)
+            .await
+            .map_err(|e| SftpError::PathNotFound(format!("{}: {}", path, e)))?;
+        file.seek(SeekFrom::Start(offset)).await?;
+
+        let mut data = Vec::with_capacity(len);
+        (&mut file).take(len as u64).read_to_end(&mut data).await?;
+        Ok(data)
+    }
+
+    /// Write `data` at `offset`, creating or truncating the file when `offset` is 0
+    ///
+    /// `mode` is only applied when the file is created, i.e. at offset 0.
+    pub async fn write_at(
+        &self,
+        path: &str,
+        offset: u64,
+        data: &[u8],
+        mode: Option<u32>,
+    ) -> Result<(), SftpError> {
+        let mut file = if offset == 0 {
+            self.create_with_mode(path, mode).await?
+        } else {
+            self.sftp
+                .open_with_flags(path, OpenFlags::WRITE)
+                .await
+                .map_err(|e| SftpError::TransferFailed(format!("Open failed: {}", e)))?
+        };
+
+        file.seek(SeekFrom::Start(offset)).await?;
+        file.write_all(data).await?;
+        file.shutdown().await?;
+        Ok(())
+    }
+
+    /// Write file contents, replacing the file
+    ///
+    /// With `mode` the file gets those permissions before any data is written.
+    pub async fn write_file(
+        &self,
+        path: &str,
+        data: &[u8],
+        mode: Option<u32>,
+    ) -> Result<(), SftpError> {
         tracing::debug!("Writing {} bytes to {}", data.len(), path);
 
-        self.sftp
-            .write(path, data)
+        let mut file = self.create_with_mode(path, mode).await?;
+        file.write_all(data)
             .await
             .map_err(|e| SftpError::TransferFailed(format!("Write failed: {}", e)))?;
+        file.shutdown().await?;
 
         Ok(())
     }
+
+    /// Create or truncate `path` for writing, with `mode` if given
+    ///
+    /// The mode is requested at creation and set again on the handle, since
+    /// servers apply their umask to the former and ignore it for files that
+    /// already exist. Both happen before the caller writes anything.
+    async fn create_with_mode(&self, path: &str, mode: Option<u32>) -> Result<File, SftpError> {
+        let flags = OpenFlags::CREATE | OpenFlags::TRUNCATE | OpenFlags::WRITE;
+        let Some(mode) = mode else {
+            return self
+                .sftp
+                .open_with_flags(path, flags)
+                .await
+                .map_err(|e| SftpError::TransferFailed(format!("Open failed: {}", e)));
+        };
+        let mode = validate_mode(mode)?;
+
+        let mut attrs = Metadata::empty();
+        attrs.permissions = Some(mode);
+        let file = self
+            .sftp
+            .open_with_flags_and_attributes(path, flags, attrs.clone())
+            .await
+            .map_err(|e| SftpError::TransferFailed(format!("Open failed: {}", e)))?;
+        // Nothing has been written yet, so failing here leaks no content
+        if let Err(e) = file.set_metadata(attrs).await {
+            return Err(SftpError::PermissionDenied(format!(
+                "Could not set mode {:o} on {}: {}",
+                mode, path, e
+            )));
+        }
+        Ok(file)
+    }
+}
+
+/// Progress of a single-file transfer; `done` once the file is complete
+fn file_progress(
+    path: &str,
+    bytes_transferred: u64,
+    total_bytes: u64,
+    done: bool,
+) -> TransferProgress {
+    // The size can change while we read, so never report more than 100%
+    let total_bytes = total_bytes.max(bytes_transferred);
+    TransferProgress {
+        path: path.to_string(),
+        bytes_transferred,
+        total_bytes,
+        percent: if total_bytes == 0 {
+            100.0
+        } else {
+            bytes_transferred as f32 / total_bytes as f32 * 100.0
+        },
+        files_completed: u32::from(done),
+        files_total: 1,
+    }
+}
+
+/// A file to transfer as part of a directory
+struct PlannedFile {
+    /// Path relative to the source directory, `/`-separated
+    path: String,
+    size: u64,
+    /// Permission bits to give the uploaded file
+    mode: Option<u32>,
+}
+
+/// Everything a directory transfer has to do, parents before children
+#[derive(Default)]
+struct TreePlan {
+    dirs: Vec<String>,
+    files: Vec<PlannedFile>,
+    symlinks: Vec<String>,
+    errors: Vec<TransferFileError>,
+}
+
+impl TreePlan {
+    fn error(&mut self, path: &str, error: impl std::fmt::Display) {
+        let path = if path.is_empty() { "." } else { path };
+        self.errors.push(TransferFileError {
+            path: path.to_string(),
+            error: error.to_string(),
+        });
+    }
+}
+
+/// Collect what an upload of `root` has to transfer
+fn walk_local(root: &Path, follow_symlinks: bool) -> TreePlan {
+    let mut plan = TreePlan::default();
+    let mut visited: HashSet<PathBuf> = root.canonicalize().into_iter().collect();
+
+    let mut pending = vec![String::new()];
+    while let Some(relative) = pending.pop() {
+        let entries = match std::fs::read_dir(root.join(&relative)) {
+            Ok(entries) => entries,
+            Err(e) => {
+                plan.error(&relative, e);
+                continue;
+            }
+        };
+        let mut entries: Vec<_> = entries.filter_map(Result::ok).collect();
+        entries.sort_by_key(|entry| entry.file_name());
+
+        for entry in entries {
+            let child = join_relative(&relative, &entry.file_name().to_string_lossy());
+            let is_symlink = entry.file_type().is_ok_and(|t| t.is_symlink());
+            if is_symlink && !follow_symlinks {
+                plan.symlinks.push(child);
+                continue;
+            }
+            // Follows the link when there is one
+            let metadata = match std::fs::metadata(entry.path()) {
+                Ok(metadata) => metadata,
+                Err(e) => {
+                    plan.error(&child, e);
+                    continue;
+                }
+            };
+            if metadata.is_dir() {
+                if is_symlink {
+                    let canonical = entry.path().canonicalize().unwrap_or_else(|_| entry.path());
+                    if !visited.insert(canonical) {
+                        plan.symlinks.push(child);
+                        continue;
+                    }
+                }
+                plan.dirs.push(child.clone());
+                pending.push(child);
+            } else if metadata.is_file() {
+                plan.files.push(PlannedFile {
+                    path: child,
+                    size: metadata.len(),
+                    mode: local_mode(&metadata),
+                });
+            }
+        }
+    }
+    plan
+}
+
+#[cfg(unix)]
+fn local_mode(metadata: &std::fs::Metadata) -> Option<u32> {
+    use std::os::unix::fs::PermissionsExt;
+    Some(metadata.permissions().mode() & 0o777)
+}
+
+#[cfg(not(unix))]
+fn local_mode(_metadata: &std::fs::Metadata) -> Option<u32> {
+    None
+}
+
+/// `relative` below the remote directory `base`
+fn remote_join(base: &str, relative: &str) -> String {
+    if relative.is_empty() {
+        base.to_string()
+    } else {
+        format!("{}/{}", base.trim_end_matches('/'), relative)
+    }
+}
+
+/// Progress of a directory transfer, summed over its files
+struct DirProgress {
+    tx: Option<mpsc::Sender<TransferProgress>>,
+    done_bytes: u64,
+    total_bytes: u64,
+    files_completed: u32,
+    files_total: u32,
+}
+
+impl DirProgress {
+    fn new(tx: Option<mpsc::Sender<TransferProgress>>, files: &[PlannedFile]) -> Self {
+        Self {
+            tx,
+            done_bytes: 0,
+            total_bytes: files.iter().map(|f| f.size).sum(),
+            files_completed: 0,
+            files_total: files.len() as u32,
+        }
+    }
+
+    fn snapshot(&self, path: &str, bytes_transferred: u64) -> TransferProgress {
+        TransferProgress {
+            path: path.to_string(),
+            bytes_transferred,
+            total_bytes: self.total_bytes,
+            percent: if self.total_bytes == 0 {
+                100.0
+            } else {
+                bytes_transferred as f32 / self.total_bytes as f32 * 100.0
+            },
+            files_completed: self.files_completed,
+            files_total: self.files_total,
+        }
+    }
+
+    /// A sender for one file's progress and the task turning it into totals
+    fn forward(
+        &self,
+        path: &str,
+    ) -> (
+        Option<mpsc::Sender<TransferProgress>>,
+        impl std::future::Future<Output = ()>,
+    ) {
+        let outer = self.tx.clone();
+        let (file_tx, mut file_rx) = match outer {
+            Some(_) => {
+                let (tx, rx) = mpsc::channel::<TransferProgress>(16);
+                (Some(tx), Some(rx))
+            }
+            None => (None, None),
+        };
+        let base = self.snapshot(path, self.done_bytes);
+        let forward = async move {
+            let (Some(outer), Some(rx)) = (outer, file_rx.as_mut()) else {
+                return;
+            };
+            while let Some(file) = rx.recv().await {
+                // Count completion only once the file's result is known
+                let mut total = base.clone();
+                total.bytes_transferred += file.bytes_transferred;
+                total.percent = if total.total_bytes == 0 {
+                    100.0
+                } else {
+                    total.bytes_transferred as f32 / total.total_bytes as f32 * 100.0
+                };
+                let _ = outer.send(total).await;
+            }
+        };
+        (file_tx, forward)
+    }
+
+    /// Record one file's outcome and report the new totals
+    async fn finish(
+        &mut self,
+        path: &str,
+        file: &PlannedFile,
+        outcome: Result<(), SftpError>,
+        result: &mut DirTransferResult,
+    ) {
+        match outcome {
+            Ok(()) => {
+                self.files_completed += 1;
+                result.files_transferred += 1;
+                result.bytes_transferred += file.size;
+            }
+            Err(e) => result.errors.push(TransferFileError {
+                path: file.path.clone(),
+                error: e.to_string(),
+            }),
+        }
+        // Failed files count as done so the total still reaches 100%
+        self.done_bytes += file.size;
+        if let Some(tx) = &self.tx {
+            let _ = tx.send(self.snapshot(path, self.done_bytes)).await;
+        }
+    }
+}
+
+fn file_entry(dir: &str, name: &str, metadata: &Metadata) -> FileEntry {
+    let is_dir = metadata.is_dir();
+
+    let modified = metadata.modified().ok().and_then(|system_time| {
+        system_time
+            .duration_since(std::time::UNIX_EPOCH)
+            .ok()
+            .and_then(|duration| chrono::DateTime::from_timestamp(duration.as_secs() as i64, 0))
+    });
+
+    let permissions = if is_dir {
+        Some("drwxr-xr-x".to_string())
+    } else {
+        Some("-rw-r--r--".to_string())
+    };
+
+    FileEntry {
+        name: name.to_string(),
+        path: format!("{}/{}", dir.trim_end_matches('/'), name),
+        is_dir,
+        size: metadata.len(),
+        modified,
+        permissions,
+        owner: None,
+        group: None,
+        symlink_target: None,
+    }
+}
+
+/// `..` entry for every directory except the root
+fn parent_entry(path: &str) -> Option<FileEntry> {
+    if path == "/" {
+        return None;
+    }
+
+    Some(FileEntry {
+        name: "..".to_string(),
+        path: get_parent_path(path),
+        is_dir: true,
+        size: 0,
+        modified: None,
+        permissions: Some("drwxr-xr-x".to_string()),
+        owner: None,
+        group: None,
+        symlink_target: None,
+    })
 }
 
 fn get_parent_path(path: &str) -> String {
@@ -329,3 +1251,469 @@ fn format_permissions(mode: u32, is_dir: bool) -> String {
 
     format!("{}{}{}{}", file_type, user, group, other)
 }
+
+/// Map an SFTP failure on `path` by its status code
+fn status_error(path: &str, error: russh_sftp::client::error::Error) -> SftpError {
+    use russh_sftp::client::error::Error;
+
+    match &error {
+        Error::Status(status) if status.status_code == StatusCode::PermissionDenied => {
+            SftpError::PermissionDenied(format!("{}: {}", path, status.error_message))
+        }
+        Error::Status(status) if status.status_code == StatusCode::NoSuchFile => {
+            SftpError::PathNotFound(path.to_string())
+        }
+        _ => SftpError::Ssh(format!("{}: {}", path, error)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_walk_local_reports_symlinks() {
+        let dir = std::env::temp_dir().join(format!("nexus-walk-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("a/b")).unwrap();
+        std::fs::write(dir.join("top.txt"), "12").unwrap();
+        std::fs::write(dir.join("a/b/deep.txt"), "3456").unwrap();
+        std::os::unix::fs::symlink(&dir, dir.join("a/loop")).unwrap();
+
+        let plan = walk_local(&dir, false);
+        assert_eq!(plan.dirs, vec!["a", "a/b"]);
+        let files: Vec<_> = plan
+            .files
+            .iter()
+            .map(|f| (f.path.as_str(), f.size))
+            .collect();
+        assert_eq!(files, vec![("top.txt", 2), ("a/b/deep.txt", 4)]);
+        assert_eq!(plan.symlinks, vec!["a/loop"]);
+
+        // Followed, the link points back at the root and is still not descended into
+        let plan = walk_local(&dir, true);
+        assert_eq!(plan.symlinks, vec!["a/loop"]);
+        assert_eq!(plan.files.len(), 2);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_status_error_mapping() {
+        let status = |status_code| {
+            russh_sftp::client::error::Error::Status(russh_sftp::protocol::Status {
+                id: 1,
+                status_code,
+                error_message: "Permission denied".to_string(),
+                language_tag: "en-US".to_string(),
+            })
+        };
+        assert!(matches!(
+            status_error("/etc/shadow", status(StatusCode::PermissionDenied)),
+            SftpError::PermissionDenied(_)
+        ));
+        assert!(matches!(
+            status_error("/nope", status(StatusCode::NoSuchFile)),
+            SftpError::PathNotFound(_)
+        ));
+        assert!(matches!(
+            status_error("/x", status(StatusCode::Failure)),
+            SftpError::Ssh(_)
+        ));
+    }
+
+    /// Just enough of an OpenSSH server for links: one directory of symlinks
+    #[derive(Default)]
+    struct LinkServer {
+        links: std::collections::BTreeMap<String, String>,
+        listed: bool,
+    }
+
+    fn status(id: u32, status_code: StatusCode) -> russh_sftp::protocol::Status {
+        russh_sftp::protocol::Status {
+            id,
+            status_code,
+            error_message: String::new(),
+            language_tag: "en-US".to_string(),
+        }
+    }
+
+    impl russh_sftp::server::Handler for LinkServer {
+        type Error = StatusCode;
+
+        fn unimplemented(&self) -> Self::Error {
+            StatusCode::OpUnsupported
+        }
+
+        async fn init(
+            &mut self,
+            _version: u32,
+            _extensions: std::collections::HashMap<String, String>,
+        ) -> Result<russh_sftp::protocol::Version, Self::Error> {
+            Ok(russh_sftp::protocol::Version::new())
+        }
+
+        async fn symlink(
+            &mut self,
+            id: u32,
+            linkpath: String,
+            targetpath: String,
+        ) -> Result<russh_sftp::protocol::Status, Self::Error> {
+            // Reads the fields swapped, the way OpenSSH does
+            self.links.insert(targetpath, linkpath);
+            Ok(status(id, StatusCode::Ok))
+        }
+
+        async fn readlink(
+            &mut self,
+            id: u32,
+            path: String,
+        ) -> Result<russh_sftp::protocol::Name, Self::Error> {
+            let target = self.links.get(&path).ok_or(StatusCode::NoSuchFile)?;
+            Ok(russh_sftp::protocol::Name {
+                id,
+                files: vec![russh_sftp::protocol::File::dummy(target)],
+            })
+        }
+
+        async fn realpath(
+            &mut self,
+            id: u32,
+            path: String,
+        ) -> Result<russh_sftp::protocol::Name, Self::Error> {
+            let mut parts: Vec<&str> = Vec::new();
+            let absolute = if path.starts_with('/') {
+                path.clone()
+            } else {
+                format!("/home/test/{}", path)
+            };
+            for part in absolute.split('/') {
+                match part {
+                    "" | "." => {}
+                    ".." => {
+                        parts.pop();
+                    }
+                    part => parts.push(part),
+                }
+            }
+            Ok(russh_sftp::protocol::Name {
+                id,
+                files: vec![russh_sftp::protocol::File::dummy(format!(
+                    "/{}",
+                    parts.join("/")
+                ))],
+            })
+        }
+
+        async fn opendir(
+            &mut self,
+            id: u32,
+            path: String,
+        ) -> Result<russh_sftp::protocol::Handle, Self::Error> {
+            self.listed = false;
+            Ok(russh_sftp::protocol::Handle { id, handle: path })
+        }
+
+        async fn readdir(
+            &mut self,
+            id: u32,
+            _handle: String,
+        ) -> Result<russh_sftp::protocol::Name, Self::Error> {
+            if std::mem::replace(&mut self.listed, true) {
+                return Err(StatusCode::Eof);
+            }
+            let link = russh_sftp::protocol::FileAttributes {
+                permissions: Some(0o120777),
+                ..Default::default()
+            };
+            let files = self
+                .links
+                .keys()
+                .filter_map(|path| path.strip_prefix("/links/"))
+                .map(|name| russh_sftp::protocol::File::new(name, link.clone()))
+                .collect();
+            Ok(russh_sftp::protocol::Name { id, files })
+        }
+
+        async fn close(
+            &mut self,
+            id: u32,
+            _handle: String,
+        ) -> Result<russh_sftp::protocol::Status, Self::Error> {
+            Ok(status(id, StatusCode::Ok))
+        }
+    }
+
+    /// Files kept in memory by `FileServer`
+    #[derive(Default)]
+    struct MemFs {
+        files: std::collections::BTreeMap<String, Vec<u8>>,
+        handles: std::collections::HashMap<String, String>,
+        /// Refuse to rename partial copies, as a full or read-only disk would
+        refuse_part_renames: bool,
+    }
+
+    /// Just enough of an OpenSSH server for copying regular files
+    struct FileServer {
+        fs: std::sync::Arc<parking_lot::Mutex<MemFs>>,
+    }
+
+    impl russh_sftp::server::Handler for FileServer {
+        type Error = StatusCode;
+
+        fn unimplemented(&self) -> Self::Error {
+            StatusCode::OpUnsupported
+        }
+
+        async fn init(
+            &mut self,
+            _version: u32,
+            _extensions: std::collections::HashMap<String, String>,
+        ) -> Result<russh_sftp::protocol::Version, Self::Error> {
+            Ok(russh_sftp::protocol::Version::new())
+        }
+
+        async fn stat(
+            &mut self,
+            id: u32,
+            path: String,
+        ) -> Result<russh_sftp::protocol::Attrs, Self::Error> {
+            let fs = self.fs.lock();
+            let data = fs.files.get(&path).ok_or(StatusCode::NoSuchFile)?;
+            let attrs = russh_sftp::protocol::FileAttributes {
+                size: Some(data.len() as u64),
+                permissions: Some(0o100644),
+                ..Default::default()
+            };
+            Ok(russh_sftp::protocol::Attrs { id, attrs })
+        }
+
+        async fn open(
+            &mut self,
+            id: u32,
+            filename: String,
+            pflags: OpenFlags,
+            _attrs: russh_sftp::protocol::FileAttributes,
+        ) -> Result<russh_sftp::protocol::Handle, Self::Error> {
+            let mut fs = self.fs.lock();
+            if pflags.contains(OpenFlags::CREATE) {
+                fs.files.insert(filename.clone(), Vec::new());
+            } else if !fs.files.contains_key(&filename) {
+                return Err(StatusCode::NoSuchFile);
+            }
+            let handle = uuid::Uuid::new_v4().to_string();
+            fs.handles.insert(handle.clone(), filename);
+            Ok(russh_sftp::protocol::Handle { id, handle })
+        }
+
+        async fn read(
+            &mut self,
+            id: u32,
+            handle: String,
+            offset: u64,
+            len: u32,
+        ) -> Result<russh_sftp::protocol::Data, Self::Error> {
+            let fs = self.fs.lock();
+            let path = fs.handles.get(&handle).ok_or(StatusCode::Failure)?;
+            let data = &fs.files[path];
+            let start = offset as usize;
+            if start >= data.len() {
+                return Err(StatusCode::Eof);
+            }
+            let end = data.len().min(start + len as usize);
+            Ok(russh_sftp::protocol::Data {
+                id,
+                data: data[start..end].to_vec(),
+            })
+        }
+
+        async fn write(
+            &mut self,
+            id: u32,
+            handle: String,
+            offset: u64,
+            data: Vec<u8>,
+        ) -> Result<russh_sftp::protocol::Status, Self::Error> {
+            let mut fs = self.fs.lock();
+            let path = fs.handles.get(&handle).ok_or(StatusCode::Failure)?.clone();
+            let file = fs.files.get_mut(&path).ok_or(StatusCode::NoSuchFile)?;
+            let start = offset as usize;
+            if file.len() < start + data.len() {
+                file.resize(start + data.len(), 0);
+            }
+            file[start..start + data.len()].copy_from_slice(&data);
+            Ok(status(id, StatusCode::Ok))
+        }
+
+        async fn fsetstat(
+            &mut self,
+            id: u32,
+            _handle: String,
+            _attrs: russh_sftp::protocol::FileAttributes,
+        ) -> Result<russh_sftp::protocol::Status, Self::Error> {
+            Ok(status(id, StatusCode::Ok))
+        }
+
+        async fn close(
+            &mut self,
+            id: u32,
+            handle: String,
+        ) -> Result<russh_sftp::protocol::Status, Self::Error> {
+            self.fs.lock().handles.remove(&handle);
+            Ok(status(id, StatusCode::Ok))
+        }
+
+        async fn rename(
+            &mut self,
+            id: u32,
+            oldpath: String,
+            newpath: String,
+        ) -> Result<russh_sftp::protocol::Status, Self::Error> {
+            let mut fs = self.fs.lock();
+            // Like SFTP v3, an existing file is never overwritten
+            if fs.files.contains_key(&newpath)
+                || (fs.refuse_part_renames && oldpath.contains(".nexus-part-"))
+            {
+                return Err(StatusCode::Failure);
+            }
+            let data = fs.files.remove(&oldpath).ok_or(StatusCode::NoSuchFile)?;
+            fs.files.insert(newpath, data);
+            Ok(status(id, StatusCode::Ok))
+        }
+
+        async fn remove(
+            &mut self,
+            id: u32,
+            filename: String,
+        ) -> Result<russh_sftp::protocol::Status, Self::Error> {
+            self.fs
+                .lock()
+                .files
+                .remove(&filename)
+                .ok_or(StatusCode::NoSuchFile)?;
+            Ok(status(id, StatusCode::Ok))
+        }
+    }
+
+    async fn file_client(fs: &std::sync::Arc<parking_lot::Mutex<MemFs>>) -> SftpClient {
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        russh_sftp::server::run(server, FileServer { fs: fs.clone() }).await;
+        SftpClient::new(SftpSession::new(client).await.unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_copy_over_existing_file() {
+        let fs = std::sync::Arc::new(parking_lot::Mutex::new(MemFs::default()));
+        fs.lock()
+            .files
+            .insert("/a.txt".to_string(), b"new contents".to_vec());
+        fs.lock()
+            .files
+            .insert("/b.txt".to_string(), b"old".to_vec());
+        let client = file_client(&fs).await;
+
+        let copied = client
+            .copy_to("/a.txt", &client, "/b.txt", |_, _| {})
+            .await
+            .unwrap();
+        assert_eq!(copied, 12);
+        let files = fs.lock().files.clone();
+        assert_eq!(files.keys().collect::<Vec<_>>(), vec!["/a.txt", "/b.txt"]);
+        assert_eq!(files["/b.txt"], b"new contents");
+
+        // Onto itself the file comes out the same
+        client
+            .copy_to("/a.txt", &client, "/a.txt", |_, _| {})
+            .await
+            .unwrap();
+        assert_eq!(fs.lock().files["/a.txt"], b"new contents");
+        assert_eq!(fs.lock().files.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_failed_replace_keeps_the_old_file() {
+        let fs = std::sync::Arc::new(parking_lot::Mutex::new(MemFs::default()));
+        fs.lock()
+            .files
+            .insert("/a.txt".to_string(), b"contents".to_vec());
+        fs.lock().refuse_part_renames = true;
+        let client = file_client(&fs).await;
+
+        assert!(client
+            .copy_to("/a.txt", &client, "/a.txt", |_, _| {})
+            .await
+            .is_err());
+        let files = fs.lock().files.clone();
+        assert_eq!(files.keys().collect::<Vec<_>>(), vec!["/a.txt"]);
+        assert_eq!(files["/a.txt"], b"contents");
+    }
+
+    async fn link_client() -> SftpClient {
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        russh_sftp::server::run(server, LinkServer::default()).await;
+        SftpClient::new(SftpSession::new(client).await.unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_symlink_and_read_link() {
+        let client = link_client().await;
+
+        client
+            .symlink("../data/app.log", "/links/app.log")
+            .await
+            .unwrap();
+        assert_eq!(
+            client.read_link("/links/app.log").await.unwrap(),
+            "../data/app.log"
+        );
+        assert!(matches!(
+            client.read_link("/links/missing").await,
+            Err(SftpError::PathNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_realpath() {
+        let client = link_client().await;
+
+        assert_eq!(client.realpath(".").await.unwrap(), "/home/test");
+        assert_eq!(
+            client.realpath("/var/log/../tmp/./x").await.unwrap(),
+            "/var/tmp/x"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_listing_reads_every_link_target() {
+        let client = link_client().await;
+        for i in 0..(LINK_READS_IN_FLIGHT + 5) {
+            client
+                .symlink(&format!("/target/{:02}", i), &format!("/links/{:02}", i))
+                .await
+                .unwrap();
+        }
+
+        let entries = client.list_dir("/links").await.unwrap();
+        let links: Vec<_> = entries.iter().filter(|e| e.name != "..").collect();
+        assert_eq!(links.len(), LINK_READS_IN_FLIGHT + 5);
+        for (i, entry) in links.iter().enumerate() {
+            assert_eq!(entry.name, format!("{:02}", i));
+            assert_eq!(
+                entry.symlink_target.as_deref(),
+                Some(format!("/target/{:02}", i).as_str())
+            );
+        }
+
+        let mut batches = Vec::new();
+        let total = client
+            .list_dir_batches("/links", 10, |batch| batches.push(batch))
+            .await
+            .unwrap();
+        assert_eq!(total, LINK_READS_IN_FLIGHT + 5);
+        assert!(batches.iter().all(|b| b.len() <= 10));
+        assert!(batches
+            .iter()
+            .flatten()
+            .all(|e| e.name == ".." || e.symlink_target.is_some()));
+    }
+}
@@ -1,11 +1,96 @@
 //! SFTP Tauri Commands
 
-use super::{manager, FileEntry, SftpError};
+use super::editor::{self, RemoteFileContent, RemoteFileStat};
+use super::find::{self, FindResult};
+use super::perms::{self, ChmodModes};
+use super::{
+    archive, fsstats, manager, progress, remote, sudo, sync, transfers, xattr, AclEntry,
+    ArchiveFormat, ArchiveResult, ChmodResult, DirTransferResult, DirectoryBatch,
+    ExtendedAttributes, FileEntry, FsStats, RemoteTransferResult, SftpError, SftpSessionInfo,
+    SyncDiff, TransferProgress,
+};
+use crate::plugins::{self, HookEvent, TransferContext, TransferDirection};
+use crate::utils::{emit_app_event, AppEvent, AuditAction, BulkResult};
+use crate::{audit, datasphere, ssh, terminal};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use std::collections::BTreeMap;
+use tauri::{AppHandle, Emitter};
 use uuid::Uuid;
+use zeroize::Zeroizing;
+
+/// Entries per event when streaming a directory listing
+const DEFAULT_BATCH_SIZE: usize = 500;
+
+/// Open an SFTP session on a connected terminal or SSH session
+///
+/// For terminals the host's `initial_path` decides where browsing starts.
+#[tauri::command]
+pub async fn open_sftp_session(session_id: String) -> Result<SftpSessionInfo, SftpError> {
+    let session_uuid = Uuid::parse_str(&session_id)
+        .map_err(|_| SftpError::Ssh("Invalid session ID".to_string()))?;
+
+    if let Some(info) = manager().read().get_info(&session_uuid) {
+        return Ok(info);
+    }
+
+    let client = ssh::client_for_session(session_uuid).map_err(|_| SftpError::NotConnected)?;
+    let initial_path = terminal::manager()
+        .read()
+        .get_session(session_uuid)
+        .and_then(|terminal| {
+            datasphere::storage()
+                .read()
+                .as_ref()
+                .and_then(|s| s.get_host(terminal.host_id))
+        })
+        .and_then(|host| host.initial_path);
+
+    tracing::info!("Opening SFTP session for {}", session_id);
+    super::manager::connect(session_uuid, &client, initial_path.as_deref()).await
+}
+
+/// Open SFTP on several sessions at once, e.g. both ends of a transfer
+///
+/// Sessions that already have SFTP are returned as they are. A session
+/// that fails to open doesn't stop the others.
+#[tauri::command]
+pub async fn open_sftp_sessions(session_ids: Vec<String>) -> BulkResult<SftpSessionInfo> {
+    let opened = futures::future::join_all(
+        session_ids
+            .iter()
+            .map(|session_id| open_sftp_session(session_id.clone())),
+    )
+    .await;
+    let mut result = BulkResult::new();
+    for (session_id, outcome) in session_ids.iter().zip(opened) {
+        result.push(session_id, outcome);
+    }
+    result
+}
+
+/// Get info about an open SFTP session
+#[tauri::command]
+pub fn get_sftp_session(session_id: String) -> Result<SftpSessionInfo, SftpError> {
+    let session_uuid = Uuid::parse_str(&session_id)
+        .map_err(|_| SftpError::Ssh("Invalid session ID".to_string()))?;
+
+    manager()
+        .read()
+        .get_info(&session_uuid)
+        .ok_or(SftpError::NotConnected)
+}
 
 /// List directory contents
+///
+/// `offset`/`limit` page through large directories; without them the whole
+/// directory is returned.
 #[tauri::command]
-pub async fn list_directory(session_id: String, path: String) -> Result<Vec<FileEntry>, SftpError> {
+pub async fn list_directory(
+    session_id: String,
+    path: String,
+    offset: Option<usize>,
+    limit: Option<usize>,
+) -> Result<Vec<FileEntry>, SftpError> {
     let session_uuid = Uuid::parse_str(&session_id)
         .map_err(|_| SftpError::Ssh("Invalid session ID".to_string()))?;
 
@@ -19,18 +104,131 @@ pub async fn list_directory(session_id: String, path: String) -> Result<Vec<File
             .ok_or_else(|| SftpError::NotConnected)?
     }; // Lock is dropped here
 
-    client.list_dir(&path).await
+    client
+        .list_dir_page(&path, offset.unwrap_or(0), limit)
+        .await
+}
+
+/// Stream a directory listing as `sftp-list-{request_id}` events
+///
+/// Each event carries a `DirectoryBatch`; the last one has `done` set.
+/// Returns the number of entries listed.
+#[tauri::command]
+pub async fn list_directory_stream(
+    app: AppHandle,
+    session_id: String,
+    path: String,
+    request_id: Uuid,
+    batch_size: Option<usize>,
+) -> Result<usize, SftpError> {
+    let session_uuid = Uuid::parse_str(&session_id)
+        .map_err(|_| SftpError::Ssh("Invalid session ID".to_string()))?;
+
+    let client = {
+        let sftp_mgr = manager().read();
+        sftp_mgr
+            .get_client(&session_uuid)
+            .ok_or(SftpError::NotConnected)?
+    };
+
+    let event = format!("sftp-list-{}", request_id);
+    let total = client
+        .list_dir_batches(&path, batch_size.unwrap_or(DEFAULT_BATCH_SIZE), |entries| {
+            let _ = app.emit(
+                &event,
+                DirectoryBatch {
+                    entries,
+                    done: false,
+                },
+            );
+        })
+        .await?;
+
+    let _ = app.emit(
+        &event,
+        DirectoryBatch {
+            entries: vec![],
+            done: true,
+        },
+    );
+    Ok(total)
+}
+
+/// Find files below `root` whose names match a `*`/`?` glob
+///
+/// Search stops `max_depth` levels down or after `max_results` matches.
+/// With a `request_id`, matches are also streamed as `sftp-find-{request_id}`
+/// events carrying a `DirectoryBatch`, the last one with `done` set.
+#[tauri::command]
+pub async fn sftp_find(
+    app: AppHandle,
+    session_id: String,
+    root: String,
+    pattern: String,
+    max_depth: Option<usize>,
+    max_results: Option<usize>,
+    request_id: Option<Uuid>,
+) -> Result<FindResult, SftpError> {
+    let session_uuid = Uuid::parse_str(&session_id)
+        .map_err(|_| SftpError::Ssh("Invalid session ID".to_string()))?;
+
+    let client = {
+        let sftp_mgr = manager().read();
+        sftp_mgr
+            .get_client(&session_uuid)
+            .ok_or(SftpError::NotConnected)?
+    };
+
+    let event = request_id.map(|id| format!("sftp-find-{}", id));
+    let result = find::find(
+        &client,
+        &root,
+        &pattern,
+        max_depth.unwrap_or(find::DEFAULT_MAX_DEPTH),
+        max_results.unwrap_or(find::DEFAULT_MAX_RESULTS),
+        |entries| {
+            if let Some(event) = &event {
+                let _ = app.emit(
+                    event,
+                    DirectoryBatch {
+                        entries: entries.to_vec(),
+                        done: false,
+                    },
+                );
+            }
+        },
+    )
+    .await;
+
+    if let Some(event) = &event {
+        let _ = app.emit(
+            event,
+            DirectoryBatch {
+                entries: vec![],
+                done: true,
+            },
+        );
+    }
+    result
 }
 
 /// Upload a file to the remote server
+///
+/// `mode` is an octal string such as `600`, applied before any data lands.
+/// Progress is emitted as `FileTransferProgress` app events, followed by
+/// `FileTransferComplete` once the upload succeeded. `cancel_transfer` with
+/// the remote path stops it.
 #[tauri::command]
 pub async fn upload_file(
+    app: AppHandle,
     session_id: String,
     local_path: String,
     remote_path: String,
+    mode: Option<String>,
 ) -> Result<(), SftpError> {
     let session_uuid = Uuid::parse_str(&session_id)
         .map_err(|_| SftpError::Ssh("Invalid session ID".to_string()))?;
+    let mode = mode.as_deref().map(perms::parse_mode).transpose()?;
 
     tracing::info!(
         "Uploading {} to {} for session {}",
@@ -46,12 +244,59 @@ pub async fn upload_file(
             .ok_or_else(|| SftpError::NotConnected)?
     };
 
-    client.upload(&local_path, &remote_path, None).await
+    // Fail fast instead of filling the disk mid-transfer, where the server can tell us
+    let size = tokio::fs::metadata(&local_path).await?.len();
+    let remote_dir = match remote_path.rsplit_once('/') {
+        Some(("", _)) => "/",
+        Some((dir, _)) => dir,
+        None => ".",
+    };
+    if let Ok(Some(stats)) = client.fs_stats(remote_dir).await {
+        stats.ensure_room(size)?;
+    }
+
+    let (tx, forward) = progress::forward_app_events(app.clone(), session_id.clone());
+    let transfer = transfers::register(session_uuid, &remote_path);
+    let result = client
+        .upload(
+            &local_path,
+            &remote_path,
+            Some(tx),
+            mode,
+            Some(transfer.token()),
+        )
+        .await;
+    let _ = forward.await;
+    result?;
+
+    emit_app_event(
+        &app,
+        AppEvent::FileTransferComplete {
+            session_id,
+            path: remote_path.clone(),
+        },
+    );
+    audit::log_action(
+        AuditAction::FileUploaded,
+        format!("{} -> {} ({} bytes)", local_path, remote_path, size),
+        Some(session_uuid),
+    );
+    plugins::dispatch(HookEvent::FileTransfer(TransferContext {
+        session_id: session_uuid,
+        direction: TransferDirection::Upload,
+        source: local_path,
+        destination: remote_path,
+        bytes: size,
+    }));
+    Ok(())
 }
 
 /// Download a file from the remote server
+///
+/// Reports progress the same way as `upload_file`.
 #[tauri::command]
 pub async fn download_file(
+    app: AppHandle,
     session_id: String,
     remote_path: String,
     local_path: String,
@@ -73,7 +318,194 @@ pub async fn download_file(
             .ok_or_else(|| SftpError::NotConnected)?
     };
 
-    client.download(&remote_path, &local_path, None).await
+    let (tx, forward) = progress::forward_app_events(app.clone(), session_id.clone());
+    let transfer = transfers::register(session_uuid, &remote_path);
+    let result = client
+        .download(&remote_path, &local_path, Some(tx), Some(transfer.token()))
+        .await;
+    let _ = forward.await;
+    result?;
+
+    emit_app_event(
+        &app,
+        AppEvent::FileTransferComplete {
+            session_id,
+            path: remote_path.clone(),
+        },
+    );
+    let bytes = tokio::fs::metadata(&local_path)
+        .await
+        .map(|m| m.len())
+        .unwrap_or(0);
+    audit::log_action(
+        AuditAction::FileDownloaded,
+        format!("{} -> {} ({} bytes)", remote_path, local_path, bytes),
+        Some(session_uuid),
+    );
+    plugins::dispatch(HookEvent::FileTransfer(TransferContext {
+        session_id: session_uuid,
+        direction: TransferDirection::Download,
+        source: remote_path,
+        destination: local_path,
+        bytes,
+    }));
+    Ok(())
+}
+
+/// Copy a file from one remote session to another
+///
+/// Data is streamed between the servers without being stored locally;
+/// progress is emitted on `remote-transfer-progress-{dst_session}`. If both
+/// sessions are the same account on the same server, `cp` runs there.
+#[tauri::command]
+pub async fn transfer_remote_to_remote(
+    app: AppHandle,
+    src_session: String,
+    src_path: String,
+    dst_session: String,
+    dst_path: String,
+) -> Result<RemoteTransferResult, SftpError> {
+    let src_uuid = Uuid::parse_str(&src_session)
+        .map_err(|_| SftpError::Ssh("Invalid session ID".to_string()))?;
+    let dst_uuid = Uuid::parse_str(&dst_session)
+        .map_err(|_| SftpError::Ssh("Invalid session ID".to_string()))?;
+
+    tracing::info!(
+        "Transferring {}:{} to {}:{}",
+        src_session,
+        src_path,
+        dst_session,
+        dst_path
+    );
+
+    let event = format!("remote-transfer-progress-{}", dst_session);
+    let result = remote::transfer(src_uuid, &src_path, dst_uuid, &dst_path, |copied, total| {
+        let progress = TransferProgress {
+            path: dst_path.clone(),
+            bytes_transferred: copied,
+            total_bytes: total,
+            percent: if total == 0 {
+                100.0
+            } else {
+                (copied as f32 / total as f32) * 100.0
+            },
+            files_completed: u32::from(copied >= total),
+            files_total: 1,
+        };
+        let _ = app.emit(&event, progress);
+    })
+    .await?;
+
+    plugins::dispatch(HookEvent::FileTransfer(TransferContext {
+        session_id: dst_uuid,
+        direction: TransferDirection::RemoteToRemote,
+        source: format!("{}:{}", src_session, src_path),
+        destination: dst_path,
+        bytes: result.bytes,
+    }));
+    Ok(result)
+}
+
+/// Upload a local directory tree
+///
+/// Progress across all files is emitted on `dir-transfer-progress-{session_id}`.
+/// Files that fail are listed in the result instead of stopping the upload.
+#[tauri::command]
+pub async fn upload_directory(
+    app: AppHandle,
+    session_id: String,
+    local_dir: String,
+    remote_dir: String,
+    follow_symlinks: Option<bool>,
+) -> Result<DirTransferResult, SftpError> {
+    let session_uuid = Uuid::parse_str(&session_id)
+        .map_err(|_| SftpError::Ssh("Invalid session ID".to_string()))?;
+
+    let client = {
+        let sftp_mgr = manager().read();
+        sftp_mgr
+            .get_client(&session_uuid)
+            .ok_or(SftpError::NotConnected)?
+    };
+
+    let event = format!("dir-transfer-progress-{}", session_id);
+    let (tx, forward) = progress::forward_event(app, event);
+    let transfer = transfers::register(session_uuid, &remote_dir);
+    let result = client
+        .upload_dir(
+            &local_dir,
+            &remote_dir,
+            Some(tx),
+            follow_symlinks.unwrap_or(false),
+            Some(transfer.token()),
+        )
+        .await;
+    let _ = forward.await;
+    let result = result?;
+
+    plugins::dispatch(HookEvent::FileTransfer(TransferContext {
+        session_id: session_uuid,
+        direction: TransferDirection::Upload,
+        source: local_dir,
+        destination: remote_dir,
+        bytes: result.bytes_transferred,
+    }));
+    Ok(result)
+}
+
+/// Download a remote directory tree; the counterpart of `upload_directory`
+#[tauri::command]
+pub async fn download_directory(
+    app: AppHandle,
+    session_id: String,
+    remote_dir: String,
+    local_dir: String,
+    follow_symlinks: Option<bool>,
+) -> Result<DirTransferResult, SftpError> {
+    let session_uuid = Uuid::parse_str(&session_id)
+        .map_err(|_| SftpError::Ssh("Invalid session ID".to_string()))?;
+
+    let client = {
+        let sftp_mgr = manager().read();
+        sftp_mgr
+            .get_client(&session_uuid)
+            .ok_or(SftpError::NotConnected)?
+    };
+
+    let event = format!("dir-transfer-progress-{}", session_id);
+    let (tx, forward) = progress::forward_event(app, event);
+    let transfer = transfers::register(session_uuid, &remote_dir);
+    let result = client
+        .download_dir(
+            &remote_dir,
+            &local_dir,
+            Some(tx),
+            follow_symlinks.unwrap_or(false),
+            Some(transfer.token()),
+        )
+        .await;
+    let _ = forward.await;
+    let result = result?;
+
+    plugins::dispatch(HookEvent::FileTransfer(TransferContext {
+        session_id: session_uuid,
+        direction: TransferDirection::Download,
+        source: remote_dir,
+        destination: local_dir,
+        bytes: result.bytes_transferred,
+    }));
+    Ok(result)
+}
+
+/// Cancel a running upload or download by its remote path
+///
+/// The transfer's command fails with `TransferCancelled`. Returns whether
+/// a transfer of `path` was running on the session.
+#[tauri::command]
+pub fn cancel_transfer(session_id: String, path: String) -> Result<bool, SftpError> {
+    let session_uuid = Uuid::parse_str(&session_id)
+        .map_err(|_| SftpError::Ssh("Invalid session ID".to_string()))?;
+    Ok(transfers::cancel(session_uuid, &path))
 }
 
 /// Delete a file or directory
@@ -92,10 +524,12 @@ pub async fn delete_path(session_id: String, path: String, is_dir: bool) -> Resu
     };
 
     if is_dir {
-        client.rmdir(&path).await
+        client.rmdir(&path).await?;
     } else {
-        client.remove(&path).await
+        client.remove(&path).await?;
     }
+    audit::log_action(AuditAction::FileDeleted, path, Some(session_uuid));
+    Ok(())
 }
 
 /// Create a directory
@@ -116,6 +550,327 @@ pub async fn create_directory(session_id: String, path: String) -> Result<(), Sf
     client.mkdir(&path).await
 }
 
+/// Change the permissions of a remote path
+///
+/// `mode` applies to everything; `dir_mode`/`file_mode` override it for
+/// directories and files. With `recursive` the whole tree is changed.
+#[tauri::command]
+pub async fn chmod_path(
+    session_id: String,
+    path: String,
+    mode: Option<String>,
+    recursive: bool,
+    dir_mode: Option<String>,
+    file_mode: Option<String>,
+) -> Result<ChmodResult, SftpError> {
+    let session_uuid = Uuid::parse_str(&session_id)
+        .map_err(|_| SftpError::Ssh("Invalid session ID".to_string()))?;
+    let modes = ChmodModes::parse(mode.as_deref(), dir_mode.as_deref(), file_mode.as_deref())?;
+
+    tracing::info!(
+        "Changing mode of {} (recursive: {}) for session {}",
+        path,
+        recursive,
+        session_id
+    );
+
+    // Prefer SFTP, fall back to a single find/chmod over exec
+    let sftp_client = manager().read().get_client(&session_uuid);
+    if let Some(client) = sftp_client {
+        return perms::chmod_sftp(&client, &path, modes, recursive).await;
+    }
+
+    let client = ssh::client_for_session(session_uuid).map_err(|_| SftpError::NotConnected)?;
+    perms::chmod_exec(&client, &path, modes, recursive).await
+}
+
+/// Set the permissions of a single remote path from an octal mode like `644`
+#[tauri::command]
+pub async fn set_permissions(
+    session_id: String,
+    path: String,
+    mode: String,
+) -> Result<(), SftpError> {
+    let session_uuid = Uuid::parse_str(&session_id)
+        .map_err(|_| SftpError::Ssh("Invalid session ID".to_string()))?;
+    let mode = perms::parse_mode(mode.trim().trim_start_matches("0o"))?;
+
+    let client = {
+        let sftp_mgr = manager().read();
+        sftp_mgr
+            .get_client(&session_uuid)
+            .ok_or(SftpError::NotConnected)?
+    };
+
+    client.chmod(&path, mode).await
+}
+
+/// Change the numeric owner and/or group of a remote path
+///
+/// Servers usually only let root give files away, which fails with
+/// `PermissionDenied`.
+#[tauri::command]
+pub async fn set_owner(
+    session_id: String,
+    path: String,
+    uid: Option<u32>,
+    gid: Option<u32>,
+) -> Result<(), SftpError> {
+    let session_uuid = Uuid::parse_str(&session_id)
+        .map_err(|_| SftpError::Ssh("Invalid session ID".to_string()))?;
+
+    let client = {
+        let sftp_mgr = manager().read();
+        sftp_mgr
+            .get_client(&session_uuid)
+            .ok_or(SftpError::NotConnected)?
+    };
+
+    client.chown(&path, uid, gid).await
+}
+
+/// Create a remote symbolic link at `link_path` pointing to `target`
+#[tauri::command]
+pub async fn create_symlink(
+    session_id: String,
+    target: String,
+    link_path: String,
+) -> Result<(), SftpError> {
+    let session_uuid = Uuid::parse_str(&session_id)
+        .map_err(|_| SftpError::Ssh("Invalid session ID".to_string()))?;
+
+    let client = {
+        let sftp_mgr = manager().read();
+        sftp_mgr
+            .get_client(&session_uuid)
+            .ok_or(SftpError::NotConnected)?
+    };
+
+    client.symlink(&target, &link_path).await
+}
+
+/// Read the target of a remote symbolic link
+#[tauri::command]
+pub async fn read_symlink(session_id: String, path: String) -> Result<String, SftpError> {
+    let session_uuid = Uuid::parse_str(&session_id)
+        .map_err(|_| SftpError::Ssh("Invalid session ID".to_string()))?;
+
+    let client = {
+        let sftp_mgr = manager().read();
+        sftp_mgr
+            .get_client(&session_uuid)
+            .ok_or(SftpError::NotConnected)?
+    };
+
+    client.read_link(&path).await
+}
+
+/// Resolve a remote path to an absolute path without symlinks or `..`
+#[tauri::command]
+pub async fn resolve_remote_path(session_id: String, path: String) -> Result<String, SftpError> {
+    let session_uuid = Uuid::parse_str(&session_id)
+        .map_err(|_| SftpError::Ssh("Invalid session ID".to_string()))?;
+
+    let client = {
+        let sftp_mgr = manager().read();
+        sftp_mgr
+            .get_client(&session_uuid)
+            .ok_or(SftpError::NotConnected)?
+    };
+
+    client.realpath(&path).await
+}
+
+/// Get the POSIX ACL and extended attributes of a remote path
+///
+/// Fails with `Unsupported` when the server has neither `getfacl` nor
+/// `getfattr`; if only one is missing, that field is `null`.
+#[tauri::command]
+pub async fn get_extended_attributes(
+    session_id: String,
+    path: String,
+) -> Result<ExtendedAttributes, SftpError> {
+    let session_uuid = Uuid::parse_str(&session_id)
+        .map_err(|_| SftpError::Ssh("Invalid session ID".to_string()))?;
+    let client = ssh::client_for_session(session_uuid).map_err(|_| SftpError::NotConnected)?;
+    xattr::get_attributes(&client, &path).await
+}
+
+/// Replace the ACL and/or set extended attributes of a remote path
+///
+/// `acl` replaces the whole ACL. An `xattrs` entry with a `null` value
+/// removes that attribute.
+#[tauri::command]
+pub async fn set_extended_attributes(
+    session_id: String,
+    path: String,
+    acl: Option<Vec<AclEntry>>,
+    xattrs: Option<BTreeMap<String, Option<String>>>,
+) -> Result<ExtendedAttributes, SftpError> {
+    let session_uuid = Uuid::parse_str(&session_id)
+        .map_err(|_| SftpError::Ssh("Invalid session ID".to_string()))?;
+    let client = ssh::client_for_session(session_uuid).map_err(|_| SftpError::NotConnected)?;
+
+    tracing::info!(
+        "Setting extended attributes of {} for session {}",
+        path,
+        session_id
+    );
+    xattr::set_attributes(&client, &path, acl.as_deref(), &xattrs.unwrap_or_default()).await
+}
+
+/// Get the size and free space of the remote filesystem holding `path`
+#[tauri::command]
+pub async fn get_remote_fs_stats(session_id: String, path: String) -> Result<FsStats, SftpError> {
+    let session_uuid = Uuid::parse_str(&session_id)
+        .map_err(|_| SftpError::Ssh("Invalid session ID".to_string()))?;
+
+    let sftp_client = manager().read().get_client(&session_uuid);
+    let ssh_client = ssh::client_for_session(session_uuid).ok();
+    if sftp_client.is_none() && ssh_client.is_none() {
+        return Err(SftpError::NotConnected);
+    }
+
+    fsstats::fs_stats(sftp_client.as_deref(), ssh_client.as_deref(), &path).await
+}
+
+/// Preview a directory sync without transferring anything
+#[tauri::command]
+pub async fn compare_directories(
+    session_id: String,
+    local_dir: String,
+    remote_dir: String,
+) -> Result<Vec<SyncDiff>, SftpError> {
+    let session_uuid = Uuid::parse_str(&session_id)
+        .map_err(|_| SftpError::Ssh("Invalid session ID".to_string()))?;
+
+    let client = manager()
+        .read()
+        .get_client(&session_uuid)
+        .ok_or(SftpError::NotConnected)?;
+
+    sync::compare_directories(&client, &local_dir, &remote_dir).await
+}
+
+/// Create an archive of remote paths on the server
+#[tauri::command]
+pub async fn create_remote_archive(
+    session_id: String,
+    paths: Vec<String>,
+    archive_path: String,
+    format: ArchiveFormat,
+) -> Result<ArchiveResult, SftpError> {
+    let session_uuid = Uuid::parse_str(&session_id)
+        .map_err(|_| SftpError::Ssh("Invalid session ID".to_string()))?;
+
+    tracing::info!(
+        "Creating archive {} for session {}",
+        archive_path,
+        session_id
+    );
+
+    let client = ssh::client_for_session(session_uuid).map_err(|_| SftpError::NotConnected)?;
+
+    archive::create_archive(&client, &paths, &archive_path, format).await
+}
+
+/// Extract a remote archive on the server
+#[tauri::command]
+pub async fn extract_remote_archive(
+    session_id: String,
+    archive_path: String,
+    dest: String,
+) -> Result<(), SftpError> {
+    let session_uuid = Uuid::parse_str(&session_id)
+        .map_err(|_| SftpError::Ssh("Invalid session ID".to_string()))?;
+
+    tracing::info!(
+        "Extracting archive {} for session {}",
+        archive_path,
+        session_id
+    );
+
+    let client = ssh::client_for_session(session_uuid).map_err(|_| SftpError::NotConnected)?;
+
+    archive::extract_archive(&client, &archive_path, &dest).await
+}
+
+/// Load a remote file for the editor
+///
+/// Files above the `editor_max_file_bytes` setting fail with `FileTooLarge`.
+#[tauri::command]
+pub async fn read_remote_file(
+    session_id: String,
+    path: String,
+) -> Result<RemoteFileContent, SftpError> {
+    let session_uuid = Uuid::parse_str(&session_id)
+        .map_err(|_| SftpError::Ssh("Invalid session ID".to_string()))?;
+
+    let client = {
+        let sftp_mgr = manager().read();
+        sftp_mgr
+            .get_client(&session_uuid)
+            .ok_or(SftpError::NotConnected)?
+    };
+
+    editor::read(&client, &path, editor::configured_max_bytes()).await
+}
+
+/// Save the editor's content over a remote file
+///
+/// `expected` holds the `mtime`, `size` and `sha256` from
+/// `read_remote_file`, or `null` for a new file. Fails with `Conflict` if
+/// the file changed in the meantime. Returns the version to pass as
+/// `expected` on the next save.
+#[tauri::command]
+pub async fn write_remote_file(
+    session_id: String,
+    path: String,
+    content_base64: String,
+    expected: Option<RemoteFileStat>,
+) -> Result<RemoteFileStat, SftpError> {
+    let session_uuid = Uuid::parse_str(&session_id)
+        .map_err(|_| SftpError::Ssh("Invalid session ID".to_string()))?;
+    let content = BASE64
+        .decode(content_base64.as_bytes())
+        .map_err(|e| SftpError::TransferFailed(format!("Invalid content: {}", e)))?;
+
+    let client = {
+        let sftp_mgr = manager().read();
+        sftp_mgr
+            .get_client(&session_uuid)
+            .ok_or(SftpError::NotConnected)?
+    };
+
+    editor::write(&client, &path, &content, expected.as_ref()).await
+}
+
+/// Save an edited local file over a remote file that needs root
+///
+/// `temp_path` is the local file holding the edited content.
+#[tauri::command]
+pub async fn save_remote_edit_sudo(
+    session_id: String,
+    temp_path: String,
+    remote_path: String,
+    sudo_password: String,
+) -> Result<(), SftpError> {
+    let sudo_password = Zeroizing::new(sudo_password);
+    let session_uuid = Uuid::parse_str(&session_id)
+        .map_err(|_| SftpError::Ssh("Invalid session ID".to_string()))?;
+
+    tracing::info!(
+        "Saving {} with sudo for session {}",
+        remote_path,
+        session_id
+    );
+
+    let data = tokio::fs::read(&temp_path).await?;
+    let client = ssh::client_for_session(session_uuid).map_err(|_| SftpError::NotConnected)?;
+
+    sudo::save_with_sudo(&client, &data, &remote_path, sudo_password).await
+}
+
 /// List local directory contents
 #[tauri::command]
 pub async fn list_local_directory(path: String) -> Result<Vec<FileEntry>, SftpError> {
@@ -143,6 +898,7 @@ pub async fn list_local_directory(path: String) -> Result<Vec<FileEntry>, SftpEr
             permissions: Some("drwxr-xr-x".to_string()),
             owner: None,
             group: None,
+            symlink_target: None,
         });
     }
 
@@ -171,6 +927,15 @@ pub async fn list_local_directory(path: String) -> Result<Vec<FileEntry>, SftpEr
             Some(if is_dir { "drwxrwxrwx" } else { "-rw-rw-rw-" }.to_string())
         };
 
+        let symlink_target = if entry.file_type().await?.is_symlink() {
+            tokio::fs::read_link(entry.path())
+                .await
+                .ok()
+                .map(|target| target.to_string_lossy().to_string())
+        } else {
+            None
+        };
+
         entries.push(FileEntry {
             name: entry.file_name().to_string_lossy().to_string(),
             path: entry.path().to_string_lossy().to_string(),
@@ -180,6 +945,7 @@ pub async fn list_local_directory(path: String) -> Result<Vec<FileEntry>, SftpEr
             permissions,
             owner: None,
             group: None,
+            symlink_target,
         });
     }
 
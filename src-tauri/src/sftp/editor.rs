@@ -0,0 +1,189 @@
+//! Reading and saving remote files for the built-in editor
+//!
+//! Saving checks the file's modification time and size against the ones the
+//! editor loaded and refuses with `SftpError::Conflict` when someone else
+//! changed the file in between. Modification times have one-second
+//! resolution, so two changes within the same second that keep the size
+//! can't be told apart. Servers that report no modification time or size
+//! get the file's content compared instead.
+
+use super::{SftpClient, SftpError};
+use crate::datasphere;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Largest file opened in the editor unless configured otherwise
+pub const EDITOR_MAX_BYTES: u64 = 5 * 1024 * 1024;
+
+/// A remote file loaded for editing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoteFileContent {
+    pub content_base64: String,
+    /// Modification time in seconds since the epoch, to pass back on save
+    pub mtime: Option<u32>,
+    pub size: u64,
+    /// Hex SHA-256 of the content, to pass back on save
+    pub sha256: String,
+}
+
+/// Version of a file as loaded or saved, to check for changes on save
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoteFileStat {
+    pub mtime: Option<u32>,
+    pub size: u64,
+    /// Hex SHA-256 of the content
+    pub sha256: String,
+}
+
+/// Configured size limit for files opened in the editor
+pub fn configured_max_bytes() -> u64 {
+    datasphere::storage()
+        .read()
+        .as_ref()
+        .map_or(EDITOR_MAX_BYTES, |s| s.get_settings().editor_max_file_bytes)
+}
+
+/// Load a regular file of at most `max_bytes`
+pub async fn read(
+    client: &SftpClient,
+    path: &str,
+    max_bytes: u64,
+) -> Result<RemoteFileContent, SftpError> {
+    let metadata = client.metadata(path).await?;
+    if metadata.is_dir() {
+        return Err(SftpError::NotFile(path.to_string()));
+    }
+    let too_large = || {
+        SftpError::FileTooLarge(format!(
+            "{} is larger than the editor limit of {} bytes",
+            path, max_bytes
+        ))
+    };
+    if metadata.len() > max_bytes {
+        return Err(too_large());
+    }
+
+    // The file may have grown since the stat; never read past the limit
+    let data = client
+        .read_range(path, 0, max_bytes.saturating_add(1) as usize)
+        .await?;
+    if data.len() as u64 > max_bytes {
+        return Err(too_large());
+    }
+
+    Ok(RemoteFileContent {
+        content_base64: BASE64.encode(&data),
+        mtime: metadata.mtime,
+        size: data.len() as u64,
+        sha256: sha256_hex(&data),
+    })
+}
+
+/// Save `content` over `path` unless it changed since `expected`
+///
+/// `expected` is `None` for a new file, which then must not exist yet. An
+/// existing file keeps its permission bits.
+pub async fn write(
+    client: &SftpClient,
+    path: &str,
+    content: &[u8],
+    expected: Option<&RemoteFileStat>,
+) -> Result<RemoteFileStat, SftpError> {
+    let current = match client.metadata(path).await {
+        Ok(metadata) => Some(metadata),
+        Err(SftpError::PathNotFound(_)) => None,
+        Err(e) => return Err(e),
+    };
+    let compare_content =
+        check_unchanged(path, current.as_ref().map(|m| (m.mtime, m.size)), expected)?;
+    if let Some(expected) = expected.filter(|_| compare_content) {
+        let data = client
+            .read_range(path, 0, expected.size.saturating_add(1) as usize)
+            .await?;
+        if sha256_hex(&data) != expected.sha256 {
+            return Err(changed(path));
+        }
+    }
+
+    let mode = current.and_then(|m| m.permissions).map(|p| p & 0o7777);
+    client.write_file(path, content, mode).await?;
+
+    let saved = client.metadata(path).await?;
+    Ok(RemoteFileStat {
+        mtime: saved.mtime,
+        size: content.len() as u64,
+        sha256: sha256_hex(content),
+    })
+}
+
+/// `current` is `None` if the file doesn't exist, else its mtime and size
+///
+/// Returns whether the content has to be compared as well, because an mtime
+/// or the current size is missing.
+fn check_unchanged(
+    path: &str,
+    current: Option<(Option<u32>, Option<u64>)>,
+    expected: Option<&RemoteFileStat>,
+) -> Result<bool, SftpError> {
+    match (current, expected) {
+        (None, None) => Ok(false),
+        (None, Some(_)) => Err(SftpError::Conflict(format!(
+            "{} was deleted since it was opened",
+            path
+        ))),
+        (Some(_), None) => Err(SftpError::Conflict(format!("{} already exists", path))),
+        (Some((_, Some(size))), Some(expected)) if size != expected.size => Err(changed(path)),
+        (Some((Some(mtime), _)), Some(expected))
+            if expected.mtime.is_some_and(|expected| expected != mtime) =>
+        {
+            Err(changed(path))
+        }
+        (Some((mtime, size)), Some(expected)) => {
+            Ok(mtime.is_none() || expected.mtime.is_none() || size.is_none())
+        }
+    }
+}
+
+fn changed(path: &str) -> SftpError {
+    SftpError::Conflict(format!("{} was changed since it was opened", path))
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_unchanged() {
+        let stat = |mtime| RemoteFileStat {
+            mtime,
+            size: 10,
+            sha256: String::new(),
+        };
+        let current = |mtime, size| Some((mtime, size));
+        assert!(
+            !check_unchanged("a", current(Some(100), Some(10)), Some(&stat(Some(100)))).unwrap()
+        );
+        assert!(!check_unchanged("a", None, None).unwrap());
+        // Without an mtime or size the content decides
+        assert!(check_unchanged("a", current(None, Some(10)), Some(&stat(None))).unwrap());
+        assert!(check_unchanged("a", current(Some(100), Some(10)), Some(&stat(None))).unwrap());
+        assert!(check_unchanged("a", current(Some(100), None), Some(&stat(Some(100)))).unwrap());
+        for (current, expected) in [
+            (current(Some(101), Some(10)), Some(stat(Some(100)))),
+            (current(Some(100), Some(11)), Some(stat(Some(100)))),
+            (current(None, Some(11)), Some(stat(None))),
+            (None, Some(stat(Some(100)))),
+            (current(Some(100), Some(10)), None),
+        ] {
+            assert!(matches!(
+                check_unchanged("a", current, expected.as_ref()),
+                Err(SftpError::Conflict(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,101 @@
+//! Finding remote files by name
+//!
+//! Walks the tree breadth first with `SftpClient::list_dir`, matching each
+//! name against a glob where `*` matches any run of characters and `?` a
+//! single one. Symbolic links are matched but never followed, so link
+//! loops can't keep the walk going. Directories that can't be read are
+//! skipped and reported.
+
+use super::{FileEntry, SftpClient, SftpError};
+use crate::utils::wildcard_match;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Levels below the root searched when no depth is given
+pub const DEFAULT_MAX_DEPTH: usize = 8;
+
+/// Deepest search allowed
+pub const MAX_DEPTH_LIMIT: usize = 64;
+
+/// Matches returned when no limit is given
+pub const DEFAULT_MAX_RESULTS: usize = 500;
+
+/// Most matches one search may return
+pub const MAX_RESULTS_LIMIT: usize = 10_000;
+
+/// Outcome of a search
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FindResult {
+    pub matches: Vec<FileEntry>,
+    /// Stopped at `max_results`; more matches may exist
+    pub truncated: bool,
+    /// Directories that could not be listed
+    pub skipped: Vec<String>,
+}
+
+/// Search below `root` for names matching `pattern`
+///
+/// `max_depth` 1 searches only the entries of `root` itself. `on_matches`
+/// gets each directory's matches as soon as they are found. Fails only if
+/// `root` can't be listed.
+pub async fn find<F>(
+    client: &SftpClient,
+    root: &str,
+    pattern: &str,
+    max_depth: usize,
+    max_results: usize,
+    mut on_matches: F,
+) -> Result<FindResult, SftpError>
+where
+    F: FnMut(&[FileEntry]),
+{
+    if pattern.is_empty() {
+        return Err(SftpError::InvalidPath("Empty search pattern".to_string()));
+    }
+    let max_depth = max_depth.clamp(1, MAX_DEPTH_LIMIT);
+    let max_results = max_results.clamp(1, MAX_RESULTS_LIMIT);
+
+    let mut result = FindResult::default();
+    let mut queue = VecDeque::from([(root.to_string(), 1)]);
+    while let Some((dir, depth)) = queue.pop_front() {
+        let entries = match client.list_dir(&dir).await {
+            Ok(entries) => entries,
+            Err(e) if dir == root => return Err(e),
+            Err(e) => {
+                tracing::debug!("Skipping {} in search: {}", dir, e);
+                result.skipped.push(dir);
+                continue;
+            }
+        };
+
+        let mut found = Vec::new();
+        for entry in entries.into_iter().filter(|e| e.name != "..") {
+            if entry.is_dir && depth < max_depth {
+                queue.push_back((entry.path.clone(), depth + 1));
+            }
+            if wildcard_match(pattern, &entry.name) {
+                found.push(entry);
+                if result.matches.len() + found.len() >= max_results {
+                    result.truncated = true;
+                    break;
+                }
+            }
+        }
+
+        if !found.is_empty() {
+            on_matches(&found);
+            result.matches.extend(found);
+        }
+        if result.truncated {
+            break;
+        }
+    }
+
+    tracing::debug!(
+        "Search for {} below {} found {} matches",
+        pattern,
+        root,
+        result.matches.len()
+    );
+    Ok(result)
+}
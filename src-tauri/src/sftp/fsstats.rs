@@ -0,0 +1,181 @@
+//! Remote filesystem capacity
+//!
+//! Uses the `statvfs@openssh.com` SFTP extension when the server offers it
+//! and falls back to POSIX `df -P -k` over exec otherwise.
+
+use super::helper::run_helper;
+use super::{SftpClient, SftpError};
+use crate::ssh::SshClient;
+use crate::utils::shell_quote;
+use russh_sftp::extensions::Statvfs;
+use serde::{Deserialize, Serialize};
+
+/// Where filesystem figures came from
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum FsStatsSource {
+    /// The `statvfs@openssh.com` SFTP extension
+    Statvfs,
+    /// `df` run over exec
+    Df,
+}
+
+/// Size and free space of the filesystem holding a path
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FsStats {
+    pub path: String,
+    pub total_bytes: u64,
+    /// Free space including blocks reserved for root
+    pub free_bytes: u64,
+    /// Free space usable by the login user
+    pub available_bytes: u64,
+    pub block_size: u64,
+    pub source: FsStatsSource,
+}
+
+impl FsStats {
+    /// Fail with `QuotaExceeded` if `bytes` won't fit
+    pub fn ensure_room(&self, bytes: u64) -> Result<(), SftpError> {
+        if bytes > self.available_bytes {
+            return Err(SftpError::QuotaExceeded(format!(
+                "{} needs {} bytes but only {} are available",
+                self.path, bytes, self.available_bytes
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Get filesystem stats, trying SFTP first and `df` second
+pub async fn fs_stats(
+    sftp: Option<&SftpClient>,
+    ssh: Option<&SshClient>,
+    path: &str,
+) -> Result<FsStats, SftpError> {
+    if let Some(sftp) = sftp {
+        match sftp.fs_stats(path).await {
+            Ok(Some(stats)) => return Ok(stats),
+            Ok(None) => tracing::debug!("Server has no statvfs extension, trying df"),
+            Err(e) => tracing::debug!("statvfs on {} failed ({}), trying df", path, e),
+        }
+    }
+
+    let Some(ssh) = ssh else {
+        return Err(SftpError::Unsupported(
+            "filesystem stats need the statvfs extension or exec access".to_string(),
+        ));
+    };
+    let output = run_helper(
+        ssh,
+        &["df"],
+        &format!("df -P -k {}", shell_quote(path)),
+        &[],
+    )
+    .await?;
+    if output.exit_code != 0 {
+        return Err(SftpError::PathNotFound(format!(
+            "{}: {}",
+            path,
+            output.stderr.trim()
+        )));
+    }
+    parse_df(path, &output.stdout).ok_or_else(|| {
+        SftpError::Unsupported(format!("unexpected df output: {}", output.stdout.trim()))
+    })
+}
+
+/// Convert a `statvfs@openssh.com` reply, `None` if the sizes overflow
+///
+/// Block counts are in units of the fragment size; servers that leave it
+/// at 0 count in blocks instead.
+pub(super) fn from_statvfs(path: &str, info: &Statvfs) -> Option<FsStats> {
+    let unit = if info.fragment_size == 0 {
+        info.block_size
+    } else {
+        info.fragment_size
+    };
+    Some(FsStats {
+        path: path.to_string(),
+        total_bytes: info.blocks.checked_mul(unit)?,
+        free_bytes: info.blocks_free.checked_mul(unit)?,
+        available_bytes: info.blocks_avail.checked_mul(unit)?,
+        block_size: info.block_size,
+        source: FsStatsSource::Statvfs,
+    })
+}
+
+/// Parse `df -P -k` output
+///
+/// The filesystem and mount point may contain spaces, so the numbers are
+/// located relative to the capacity column (the one ending in `%`).
+fn parse_df(path: &str, output: &str) -> Option<FsStats> {
+    let line = output.lines().nth(1)?;
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let capacity = fields.iter().position(|f| f.ends_with('%'))?;
+    if capacity < 3 {
+        return None;
+    }
+
+    let kib = |i: usize| fields[i].parse::<u64>().ok()?.checked_mul(1024);
+    let total_bytes = kib(capacity - 3)?;
+    let used_bytes = kib(capacity - 2)?;
+    let available_bytes = kib(capacity - 1)?;
+
+    Some(FsStats {
+        path: path.to_string(),
+        total_bytes,
+        free_bytes: total_bytes.saturating_sub(used_bytes),
+        available_bytes,
+        block_size: 1024,
+        source: FsStatsSource::Df,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_df() {
+        let output = "Filesystem     1024-blocks     Used Available Capacity Mounted on\n\
+                      /dev/my disk      10000000  4000000   5500000      43% /mnt/my data\n";
+        let stats = parse_df("/mnt/my data", output).unwrap();
+        assert_eq!(stats.total_bytes, 10_000_000 * 1024);
+        assert_eq!(stats.free_bytes, 6_000_000 * 1024);
+        assert_eq!(stats.available_bytes, 5_500_000 * 1024);
+        assert_eq!(stats.source, FsStatsSource::Df);
+
+        assert!(parse_df("/", "df: /nope: No such file or directory\n").is_none());
+    }
+
+    fn statvfs(fragment_size: u64, blocks: u64) -> Statvfs {
+        Statvfs {
+            block_size: 4096,
+            fragment_size,
+            blocks,
+            blocks_free: blocks / 2,
+            blocks_avail: blocks / 4,
+            inodes: 0,
+            inodes_free: 0,
+            inodes_avail: 0,
+            fs_id: 0,
+            flags: 0,
+            name_max: 255,
+        }
+    }
+
+    #[test]
+    fn test_from_statvfs() {
+        let stats = from_statvfs("/", &statvfs(1024, 1000)).unwrap();
+        assert_eq!(stats.total_bytes, 1_024_000);
+        assert_eq!(stats.free_bytes, 512_000);
+        assert_eq!(stats.available_bytes, 256_000);
+        assert_eq!(stats.block_size, 4096);
+
+        // No fragment size: counts are in blocks
+        let stats = from_statvfs("/", &statvfs(0, 1000)).unwrap();
+        assert_eq!(stats.total_bytes, 4_096_000);
+
+        assert!(from_statvfs("/", &statvfs(4096, u64::MAX / 1024)).is_none());
+    }
+}
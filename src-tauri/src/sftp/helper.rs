@@ -0,0 +1,131 @@
+//! Running helper commands for exec-based SFTP fallbacks
+//!
+//! Exec channels get a non-login shell whose `PATH` can be much smaller
+//! than in an interactive session. Every helper command goes through
+//! `run_helper`, which prefixes the host's `exec_profile` and checks that
+//! the tools it needs exist, so a missing binary is reported as
+//! `SftpError::ToolNotFound` and the caller can fall back to pure SFTP.
+//! The script is adapted to the login shell, and refused with
+//! `SftpError::Unsupported` where it can't run.
+
+use super::SftpError;
+use crate::ssh::{CommandOutput, SshClient, SshError};
+use crate::utils::shell_quote;
+use zeroize::Zeroizing;
+
+/// Exit code the shell uses for "command not found"
+pub const EXIT_NOT_FOUND: i32 = 127;
+
+/// Printed to stderr when a required tool is missing
+const MISSING_TOOL_PREFIX: &str = "nexus: missing tool: ";
+
+/// Run `command` after the host's exec profile, requiring `tools`
+///
+/// Returns the output whatever the exit code, except that a missing tool
+/// becomes `ToolNotFound`.
+pub async fn run_helper(
+    client: &SshClient,
+    tools: &[&str],
+    command: &str,
+    input: &[u8],
+) -> Result<CommandOutput, SftpError> {
+    let script = helper_script(client.config.exec_profile.as_deref(), tools, command);
+    let script = client.posix_command(&script).await.map_err(|e| match e {
+        SshError::UnsupportedShell(_) => SftpError::Unsupported(e.to_string()),
+        e => SftpError::Ssh(e.to_string()),
+    })?;
+
+    let output = client
+        .execute_with_input(&script, input)
+        .await
+        .map_err(|e| SftpError::Ssh(e.to_string()))?;
+
+    if output.exit_code == EXIT_NOT_FOUND {
+        let tool = missing_tool(&output).unwrap_or_else(|| output.stderr.trim().to_string());
+        tracing::warn!("Helper tool not found on {}: {}", client.config.host, tool);
+        return Err(SftpError::ToolNotFound(tool));
+    }
+    Ok(output)
+}
+
+/// Run `command` as root with `sudo -S`, writing `password` to its stdin
+///
+/// The password only lives in a buffer that is wiped on drop.
+pub async fn run_sudo(
+    client: &SshClient,
+    command: &str,
+    password: Zeroizing<String>,
+) -> Result<CommandOutput, SftpError> {
+    let mut input = Zeroizing::new(Vec::with_capacity(password.len() + 1));
+    input.extend_from_slice(password.as_bytes());
+    input.push(b'\n');
+    drop(password);
+
+    let script = format!("sudo -S -p '' {}", command);
+    run_helper(client, &["sudo"], &script, &input).await
+}
+
+/// Whether sudo's stderr says it refused the password or the user
+pub fn sudo_refused(stderr: &str) -> bool {
+    [
+        "incorrect password",
+        "Sorry, try again",
+        "a password is required",
+        "not in the sudoers",
+    ]
+    .iter()
+    .any(|message| stderr.contains(message))
+}
+
+/// The tool a `helper_script` preflight check found missing, if any
+pub fn missing_tool(output: &CommandOutput) -> Option<String> {
+    if output.exit_code != EXIT_NOT_FOUND {
+        return None;
+    }
+    output
+        .stderr
+        .lines()
+        .find_map(|line| line.strip_prefix(MISSING_TOOL_PREFIX))
+        .map(str::to_string)
+}
+
+/// Prefix `command` with the exec profile and checks for `tools`
+pub fn helper_script(profile: Option<&str>, tools: &[&str], command: &str) -> String {
+    let mut script = String::new();
+
+    if let Some(profile) = profile.map(str::trim).filter(|p| !p.is_empty()) {
+        script.push_str(profile);
+        if !profile.ends_with(';') && !profile.ends_with("&&") {
+            script.push(';');
+        }
+        script.push(' ');
+    }
+
+    for tool in tools {
+        let message = shell_quote(&format!("{}{}", MISSING_TOOL_PREFIX, tool));
+        script.push_str(&format!(
+            "command -v {} >/dev/null 2>&1 || {{ echo {} >&2; exit {}; }}; ",
+            shell_quote(tool),
+            message,
+            EXIT_NOT_FOUND
+        ));
+    }
+
+    script.push_str(command);
+    script
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_helper_script() {
+        assert_eq!(helper_script(None, &[], "ls"), "ls");
+
+        let script = helper_script(Some(" . /etc/profile "), &["tar"], "tar -cf a.tar b");
+        assert!(script.starts_with(". /etc/profile; command -v 'tar' "));
+        assert!(script.contains("'nexus: missing tool: tar' >&2; exit 127; }; "));
+        assert!(script.ends_with("tar -cf a.tar b"));
+    }
+}
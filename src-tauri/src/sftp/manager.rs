@@ -1,6 +1,7 @@
 //! SFTP Session Manager
 
-use super::SftpClient;
+use super::{SftpClient, SftpError, SftpSessionInfo};
+use crate::ssh::SshClient;
 use once_cell::sync::Lazy;
 use parking_lot::RwLock;
 use russh_sftp::client::SftpSession;
@@ -17,9 +18,38 @@ pub fn manager() -> &'static RwLock<SftpManager> {
     &SFTP_MANAGER
 }
 
+/// Open an SFTP session on an SSH connection and register it
+///
+/// The session starts in `initial_path` when that is an existing directory.
+pub async fn connect(
+    session_id: Uuid,
+    ssh_client: &SshClient,
+    initial_path: Option<&str>,
+) -> Result<SftpSessionInfo, SftpError> {
+    let channel = ssh_client
+        .open_sftp_channel()
+        .await
+        .map_err(|e| SftpError::Ssh(e.to_string()))?;
+    let sftp_session = SftpSession::new(channel.into_stream())
+        .await
+        .map_err(|e| SftpError::Ssh(format!("Failed to start SFTP: {}", e)))?;
+
+    let client = SftpClient::new(sftp_session);
+    let start_path = client.resolve_start_path(initial_path).await;
+
+    Ok(manager()
+        .write()
+        .add_session(session_id, client, start_path))
+}
+
+struct SftpEntry {
+    client: Arc<SftpClient>,
+    info: SftpSessionInfo,
+}
+
 /// SFTP Session Manager
 pub struct SftpManager {
-    sessions: HashMap<Uuid, Arc<SftpClient>>,
+    sessions: HashMap<Uuid, SftpEntry>,
 }
 
 impl SftpManager {
@@ -29,16 +59,40 @@ impl SftpManager {
         }
     }
 
-    /// Create SFTP session from an existing SFTP session
-    pub fn add_session(&mut self, session_id: Uuid, sftp_session: SftpSession) {
-        tracing::info!("Adding SFTP session for terminal {}", session_id);
-        let client = Arc::new(SftpClient::new(sftp_session));
-        self.sessions.insert(session_id, client);
+    /// Register a connected SFTP client
+    pub fn add_session(
+        &mut self,
+        session_id: Uuid,
+        client: SftpClient,
+        start_path: String,
+    ) -> SftpSessionInfo {
+        tracing::info!(
+            "Adding SFTP session for terminal {} at {}",
+            session_id,
+            start_path
+        );
+        let info = SftpSessionInfo {
+            session_id,
+            start_path,
+        };
+        self.sessions.insert(
+            session_id,
+            SftpEntry {
+                client: Arc::new(client),
+                info: info.clone(),
+            },
+        );
+        info
     }
 
     /// Get SFTP client for a session
     pub fn get_client(&self, session_id: &Uuid) -> Option<Arc<SftpClient>> {
-        self.sessions.get(session_id).cloned()
+        self.sessions.get(session_id).map(|e| e.client.clone())
+    }
+
+    /// Get session info
+    pub fn get_info(&self, session_id: &Uuid) -> Option<SftpSessionInfo> {
+        self.sessions.get(session_id).map(|e| e.info.clone())
     }
 
     /// Remove SFTP session
@@ -2,15 +2,35 @@
 //!
 //! Provides SFTP file operations using russh-sftp
 
+pub mod archive;
 pub mod commands;
+pub mod editor;
+pub mod find;
 mod client;
+pub mod fsstats;
+pub(crate) mod helper;
 pub mod manager;
+pub mod perms;
+pub mod progress;
+pub mod remote;
+mod sudo;
+pub mod sync;
+pub mod transfers;
+pub mod xattr;
 
+pub use archive::{ArchiveFormat, ArchiveResult};
 pub use client::SftpClient;
+pub use find::FindResult;
+pub use fsstats::{FsStats, FsStatsSource};
 pub use manager::manager;
+pub use perms::{ChmodFailure, ChmodResult};
+pub use remote::RemoteTransferResult;
+pub use sync::{SyncDiff, SyncStatus};
+pub use xattr::{AclEntry, ExtendedAttributes};
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
 /// File entry in a directory listing
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -23,6 +43,24 @@ pub struct FileEntry {
     pub permissions: Option<String>,
     pub owner: Option<String>,
     pub group: Option<String>,
+    /// Where a symbolic link points, as stored in the link
+    #[serde(default)]
+    pub symlink_target: Option<String>,
+}
+
+/// An open SFTP session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SftpSessionInfo {
+    pub session_id: Uuid,
+    /// Directory the file browser should open in
+    pub start_path: String,
+}
+
+/// A batch of entries from a streamed directory listing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DirectoryBatch {
+    pub entries: Vec<FileEntry>,
+    pub done: bool,
 }
 
 /// File transfer progress
@@ -32,6 +70,30 @@ pub struct TransferProgress {
     pub bytes_transferred: u64,
     pub total_bytes: u64,
     pub percent: f32,
+    /// Files finished so far; directory transfers count all their files
+    #[serde(default)]
+    pub files_completed: u32,
+    #[serde(default)]
+    pub files_total: u32,
+}
+
+/// A file that failed during a directory transfer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferFileError {
+    /// Path relative to the source directory
+    pub path: String,
+    pub error: String,
+}
+
+/// Outcome of a directory upload or download
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DirTransferResult {
+    pub files_transferred: u32,
+    pub bytes_transferred: u64,
+    /// Symlinks that were not followed, relative to the source directory
+    pub symlinks: Vec<String>,
+    /// Files and directories that failed; the rest were still transferred
+    pub errors: Vec<TransferFileError>,
 }
 
 /// SFTP Error types
@@ -53,8 +115,40 @@ pub enum SftpError {
     Io(#[from] std::io::Error),
     #[error("Transfer failed: {0}")]
     TransferFailed(String),
+    #[error("Transfer cancelled: {0}")]
+    TransferCancelled(String),
     #[error("SSH error: {0}")]
     Ssh(String),
+    #[error("Invalid path: {0}")]
+    InvalidPath(String),
+    #[error("Required tool not found on server: {0}")]
+    ToolNotFound(String),
+    #[error("Not enough space: {0}")]
+    QuotaExceeded(String),
+    #[error("Not supported by server: {0}")]
+    Unsupported(String),
+    #[error("File too large: {0}")]
+    FileTooLarge(String),
+    /// The file changed on the server since it was read
+    #[error("Conflict: {0}")]
+    Conflict(String),
+}
+
+impl crate::utils::ErrorCode for SftpError {
+    fn code(&self) -> &'static str {
+        match self {
+            SftpError::NotConnected => "not_connected",
+            SftpError::PathNotFound(_) => "not_found",
+            SftpError::PermissionDenied(_) => "permission_denied",
+            SftpError::AlreadyExists(_) => "already_exists",
+            SftpError::QuotaExceeded(_) => "quota_exceeded",
+            SftpError::TransferCancelled(_) => "cancelled",
+            SftpError::ToolNotFound(_) | SftpError::Unsupported(_) => "unsupported",
+            SftpError::FileTooLarge(_) => "too_large",
+            SftpError::Conflict(_) => "conflict",
+            _ => "error",
+        }
+    }
 }
 
 impl Serialize for SftpError {
@@ -0,0 +1,191 @@
+//! Changing permissions on remote paths
+//!
+//! Uses SFTP `setstat` when the session has an SFTP channel, otherwise a
+//! single `find ... -exec chmod` over exec. Directories and files can get
+//! different modes, like the classic `find -type d` / `-type f` pair.
+
+use super::helper::run_helper;
+use super::{SftpClient, SftpError};
+use crate::ssh::SshClient;
+use crate::utils::shell_quote;
+use serde::{Deserialize, Serialize};
+
+/// Modes to apply per entry type; `None` leaves that type unchanged
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChmodModes {
+    pub dir: Option<u32>,
+    pub file: Option<u32>,
+}
+
+impl ChmodModes {
+    /// Build from octal strings; `dir_mode`/`file_mode` override `mode`
+    pub fn parse(
+        mode: Option<&str>,
+        dir_mode: Option<&str>,
+        file_mode: Option<&str>,
+    ) -> Result<Self, SftpError> {
+        let mode = mode.map(parse_mode).transpose()?;
+        let modes = Self {
+            dir: dir_mode.map(parse_mode).transpose()?.or(mode),
+            file: file_mode.map(parse_mode).transpose()?.or(mode),
+        };
+        if modes.dir.is_none() && modes.file.is_none() {
+            return Err(SftpError::InvalidPath("No mode given".to_string()));
+        }
+        Ok(modes)
+    }
+
+    fn for_entry(&self, is_dir: bool) -> Option<u32> {
+        if is_dir {
+            self.dir
+        } else {
+            self.file
+        }
+    }
+}
+
+/// A path whose mode could not be changed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChmodFailure {
+    pub path: String,
+    pub error: String,
+}
+
+/// Outcome of a chmod request
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChmodResult {
+    pub changed: u64,
+    pub failures: Vec<ChmodFailure>,
+}
+
+/// Parse an octal mode such as `755` or `2775`
+pub fn parse_mode(mode: &str) -> Result<u32, SftpError> {
+    let valid = (3..=4).contains(&mode.len()) && mode.chars().all(|c| ('0'..='7').contains(&c));
+    if !valid {
+        return Err(SftpError::InvalidPath(format!("Invalid mode: {}", mode)));
+    }
+    u32::from_str_radix(mode, 8)
+        .map_err(|_| SftpError::InvalidPath(format!("Invalid mode: {}", mode)))
+}
+
+/// Check that `mode` only holds permission, setuid/setgid and sticky bits
+pub fn validate_mode(mode: u32) -> Result<u32, SftpError> {
+    if mode > 0o7777 {
+        return Err(SftpError::InvalidPath(format!("Invalid mode: {:o}", mode)));
+    }
+    Ok(mode)
+}
+
+/// Change modes over SFTP, walking the tree if `recursive`
+///
+/// Symlinks inside the tree are skipped rather than followed.
+pub async fn chmod_sftp(
+    client: &SftpClient,
+    path: &str,
+    modes: ChmodModes,
+    recursive: bool,
+) -> Result<ChmodResult, SftpError> {
+    let root = client.symlink_metadata(path).await?;
+    let mut result = ChmodResult::default();
+    let mut pending = vec![(path.to_string(), root.is_dir())];
+
+    while let Some((current, is_dir)) = pending.pop() {
+        if let Some(mode) = modes.for_entry(is_dir) {
+            match client.set_mode(&current, mode).await {
+                Ok(()) => result.changed += 1,
+                Err(e) => result.failures.push(ChmodFailure {
+                    path: current.clone(),
+                    error: e.to_string(),
+                }),
+            }
+        }
+
+        if !(recursive && is_dir) {
+            continue;
+        }
+        match client.read_dir_sorted(&current).await {
+            Ok(entries) => {
+                for (name, metadata) in entries {
+                    if name == "." || name == ".." || metadata.is_symlink() {
+                        continue;
+                    }
+                    let child = format!("{}/{}", current.trim_end_matches('/'), name);
+                    pending.push((child, metadata.is_dir()));
+                }
+            }
+            Err(e) => result.failures.push(ChmodFailure {
+                path: current,
+                error: e.to_string(),
+            }),
+        }
+    }
+
+    tracing::info!(
+        "chmod {}: {} changed, {} failed",
+        path,
+        result.changed,
+        result.failures.len()
+    );
+    Ok(result)
+}
+
+/// Change modes with one `find ... -exec chmod` over exec
+pub async fn chmod_exec(
+    client: &SshClient,
+    path: &str,
+    modes: ChmodModes,
+    recursive: bool,
+) -> Result<ChmodResult, SftpError> {
+    if !path.starts_with('/') {
+        return Err(SftpError::InvalidPath(format!(
+            "Path must be absolute: {}",
+            path
+        )));
+    }
+
+    // Each matched entry is printed so the changes can be counted
+    let actions: Vec<String> = [("d", modes.dir), ("f", modes.file)]
+        .into_iter()
+        .filter_map(|(kind, mode)| {
+            mode.map(|m| format!("-type {} -exec chmod {:o} {{}} + -print", kind, m))
+        })
+        .collect();
+    let command = format!(
+        "find {}{} \\( {} \\)",
+        shell_quote(path),
+        if recursive { "" } else { " -maxdepth 0" },
+        actions.join(" -o ")
+    );
+
+    let output = run_helper(client, &["find", "chmod"], &command, &[]).await?;
+
+    let failures: Vec<ChmodFailure> = output
+        .stderr
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| ChmodFailure {
+            path: quoted_path(line).unwrap_or(path).to_string(),
+            error: line.trim().to_string(),
+        })
+        .collect();
+    // Entries are printed whether or not their chmod succeeded
+    let printed = output.stdout.lines().filter(|l| !l.is_empty()).count() as u64;
+    let chmod_failures = failures
+        .iter()
+        .filter(|f| f.error.starts_with("chmod:"))
+        .count() as u64;
+
+    Ok(ChmodResult {
+        changed: printed.saturating_sub(chmod_failures),
+        failures,
+    })
+}
+
+/// Pull the path out of a GNU-style `chmod: ... 'path': reason` message
+fn quoted_path(line: &str) -> Option<&str> {
+    let start = line.find(['\'', '‘'])?;
+    let rest = &line[start..];
+    let open = rest.chars().next()?.len_utf8();
+    let end = rest[open..].find(['\'', '’'])?;
+    Some(&rest[open..open + end])
+}
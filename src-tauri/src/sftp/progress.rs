@@ -0,0 +1,107 @@
+//! Forwarding transfer progress to the frontend
+//!
+//! Transfers report progress for every chunk, which on a fast link is far
+//! more often than the UI can use. Updates are thinned out to at most one
+//! per `MIN_INTERVAL`; the final update of a transfer is always sent.
+
+use super::TransferProgress;
+use crate::utils::{emit_app_event, AppEvent};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// Shortest gap between two progress events (~20 per second)
+pub const MIN_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Decides which progress updates are worth emitting
+#[derive(Debug, Default)]
+pub struct Throttle {
+    last: Option<Instant>,
+}
+
+impl Throttle {
+    /// Whether an update at `now` should be sent
+    pub fn ready(&mut self, progress: &TransferProgress, now: Instant) -> bool {
+        let finished = progress.bytes_transferred >= progress.total_bytes;
+        let due = self
+            .last
+            .is_none_or(|last| now.duration_since(last) >= MIN_INTERVAL);
+        if finished || due {
+            self.last = Some(now);
+        }
+        finished || due
+    }
+}
+
+/// Emit a single-file transfer's progress as `AppEvent::FileTransferProgress`
+///
+/// The task ends once the returned sender is dropped.
+pub fn forward_app_events(
+    app: AppHandle,
+    session_id: String,
+) -> (mpsc::Sender<TransferProgress>, JoinHandle<()>) {
+    spawn(move |progress| {
+        emit_app_event(
+            &app,
+            AppEvent::FileTransferProgress {
+                session_id: session_id.clone(),
+                path: progress.path,
+                progress: progress.percent,
+                bytes_transferred: progress.bytes_transferred,
+                total_bytes: progress.total_bytes,
+            },
+        )
+    })
+}
+
+/// Emit progress on a plain event name, e.g. for directory transfers
+pub fn forward_event(
+    app: AppHandle,
+    event: String,
+) -> (mpsc::Sender<TransferProgress>, JoinHandle<()>) {
+    spawn(move |progress| {
+        let _ = app.emit(&event, progress);
+    })
+}
+
+fn spawn(
+    mut emit: impl FnMut(TransferProgress) + Send + 'static,
+) -> (mpsc::Sender<TransferProgress>, JoinHandle<()>) {
+    let (tx, mut rx) = mpsc::channel::<TransferProgress>(64);
+    let task = tokio::spawn(async move {
+        let mut throttle = Throttle::default();
+        while let Some(progress) = rx.recv().await {
+            if throttle.ready(&progress, Instant::now()) {
+                emit(progress);
+            }
+        }
+    });
+    (tx, task)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn progress(bytes_transferred: u64) -> TransferProgress {
+        TransferProgress {
+            path: "/tmp/file".to_string(),
+            bytes_transferred,
+            total_bytes: 100,
+            percent: bytes_transferred as f32,
+            files_completed: u32::from(bytes_transferred >= 100),
+            files_total: 1,
+        }
+    }
+
+    #[test]
+    fn test_throttle_keeps_final_update() {
+        let start = Instant::now();
+        let mut throttle = Throttle::default();
+        assert!(throttle.ready(&progress(10), start));
+        assert!(!throttle.ready(&progress(20), start + Duration::from_millis(10)));
+        assert!(throttle.ready(&progress(30), start + MIN_INTERVAL));
+        assert!(throttle.ready(&progress(100), start + MIN_INTERVAL));
+    }
+}
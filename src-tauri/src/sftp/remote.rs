@@ -0,0 +1,113 @@
+//! Copying files between two remote servers
+//!
+//! Data is streamed from one SFTP session to the other without landing on
+//! the local disk. When both sessions log into the same account on the
+//! same server, `cp` runs there instead and the data never leaves it.
+
+use super::helper::run_helper;
+use super::{manager, SftpError};
+use crate::ssh::{self, SshClient};
+use crate::utils::shell_quote;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Outcome of a remote-to-remote transfer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoteTransferResult {
+    pub bytes: u64,
+    /// Copied on the server itself rather than streamed through this app
+    pub server_side: bool,
+}
+
+/// Copy `src_path` on one session to `dst_path` on another
+///
+/// `on_progress` receives the bytes copied so far and the total; a
+/// server-side copy reports once, at the end.
+pub async fn transfer<F>(
+    src_session: Uuid,
+    src_path: &str,
+    dst_session: Uuid,
+    dst_path: &str,
+    mut on_progress: F,
+) -> Result<RemoteTransferResult, SftpError>
+where
+    F: FnMut(u64, u64),
+{
+    let src_ssh = ssh::client_for_session(src_session).ok();
+    let dst_ssh = ssh::client_for_session(dst_session).ok();
+
+    if let (Some(src), Some(dst)) = (&src_ssh, &dst_ssh) {
+        if Arc::ptr_eq(src, dst) || src.config.same_target(&dst.config) {
+            match server_copy(src, src_path, dst_path).await {
+                Ok(()) => {
+                    let dst_sftp = manager().read().get_client(&dst_session);
+                    let bytes = match dst_sftp {
+                        Some(client) => client.stat(dst_path).await.map(|e| e.size).unwrap_or(0),
+                        None => 0,
+                    };
+                    on_progress(bytes, bytes);
+                    return Ok(RemoteTransferResult {
+                        bytes,
+                        server_side: true,
+                    });
+                }
+                Err(SftpError::ToolNotFound(tool)) => {
+                    tracing::debug!("{} not available, streaming instead", tool)
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    let (src, dst) = {
+        let sftp = manager().read();
+        (
+            sftp.get_client(&src_session)
+                .ok_or(SftpError::NotConnected)?,
+            sftp.get_client(&dst_session)
+                .ok_or(SftpError::NotConnected)?,
+        )
+    };
+
+    match src.copy_to(src_path, &dst, dst_path, on_progress).await {
+        Ok(bytes) => Ok(RemoteTransferResult {
+            bytes,
+            server_side: false,
+        }),
+        // Name the side that went away rather than the failed read or write
+        Err(e) => Err(
+            match (
+                disconnected(src_ssh.as_deref()),
+                disconnected(dst_ssh.as_deref()),
+            ) {
+                (true, _) => SftpError::TransferFailed(format!(
+                    "Source session disconnected during transfer ({})",
+                    e
+                )),
+                (_, true) => SftpError::TransferFailed(format!(
+                    "Destination session disconnected during transfer ({})",
+                    e
+                )),
+                _ => e,
+            },
+        ),
+    }
+}
+
+fn disconnected(client: Option<&SshClient>) -> bool {
+    client.is_some_and(|c| c.is_closed())
+}
+
+async fn server_copy(client: &SshClient, src_path: &str, dst_path: &str) -> Result<(), SftpError> {
+    let command = format!("cp -- {} {}", shell_quote(src_path), shell_quote(dst_path));
+    let output = run_helper(client, &["cp"], &command, &[]).await?;
+    if output.exit_code != 0 {
+        return Err(SftpError::TransferFailed(format!(
+            "cp exited with {}: {}",
+            output.exit_code,
+            output.stderr.trim()
+        )));
+    }
+    Ok(())
+}
@@ -0,0 +1,139 @@
+//! Saving edits to files that need root
+//!
+//! The new content is written to a temp file as the login user, then
+//! `sudo` copies it over the target in place. Writing through the existing
+//! file keeps its inode, so a symlink stays a link and the owner, mode and
+//! SELinux context don't change. A new file is created with mode 644.
+
+use super::helper::{run_helper, run_sudo, sudo_refused};
+use super::SftpError;
+use crate::ssh::SshClient;
+use crate::utils::shell_quote;
+use zeroize::Zeroizing;
+
+/// Write `data` to `remote_path` via a temp file and `sudo`
+pub async fn save_with_sudo(
+    client: &SshClient,
+    data: &[u8],
+    remote_path: &str,
+    sudo_password: Zeroizing<String>,
+) -> Result<(), SftpError> {
+    if !remote_path.starts_with('/') {
+        return Err(SftpError::InvalidPath(format!(
+            "Path must be absolute: {}",
+            remote_path
+        )));
+    }
+
+    let output = run_helper(client, &["mktemp", "sudo"], "mktemp", &[]).await?;
+    let temp = output.stdout.trim().to_string();
+    if output.exit_code != 0 || temp.is_empty() {
+        return Err(SftpError::TransferFailed(format!(
+            "Could not create temp file: {}",
+            output.stderr.trim()
+        )));
+    }
+    let temp_q = shell_quote(&temp);
+
+    let upload = run_helper(client, &[], &format!("cat > {}", temp_q), data).await;
+    if let Err(e) = check(upload, "Upload to temp file") {
+        cleanup(client, &temp_q).await;
+        return Err(e);
+    }
+
+    let command = format!("sh -c {}", shell_quote(&write_script(remote_path, &temp)));
+    let written = run_sudo(client, &command, sudo_password).await;
+    cleanup(client, &temp_q).await;
+
+    let output = written?;
+    let stderr = output.stderr.trim();
+    if output.exit_code == 0 {
+        tracing::info!("Saved {} with sudo", remote_path);
+        Ok(())
+    } else if sudo_refused(stderr) {
+        Err(SftpError::PermissionDenied(format!("sudo: {}", stderr)))
+    } else {
+        Err(SftpError::TransferFailed(format!(
+            "sudo write exited with {}: {}",
+            output.exit_code, stderr
+        )))
+    }
+}
+
+/// Script copying `temp` over `target`, following a link at `target`
+fn write_script(target: &str, temp: &str) -> String {
+    format!(
+        "if [ -e {t} ]; then cat {tmp} > {t}; else cat {tmp} > {t} && chmod 644 {t}; fi",
+        t = shell_quote(target),
+        tmp = shell_quote(temp)
+    )
+}
+
+fn check(
+    result: Result<crate::ssh::CommandOutput, SftpError>,
+    step: &str,
+) -> Result<(), SftpError> {
+    let output = result?;
+    if output.exit_code == 0 {
+        Ok(())
+    } else {
+        Err(SftpError::TransferFailed(format!(
+            "{} failed: {}",
+            step,
+            output.stderr.trim()
+        )))
+    }
+}
+
+async fn cleanup(client: &SshClient, temp_quoted: &str) {
+    let _ = run_helper(client, &[], &format!("rm -f {}", temp_quoted), &[]).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_script_keeps_links_and_modes() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("nexus-sudo-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let run = |target: &std::path::Path, content: &str| {
+            let temp = dir.join("upload");
+            std::fs::write(&temp, content).unwrap();
+            let script = write_script(target.to_str().unwrap(), temp.to_str().unwrap());
+            let status = std::process::Command::new("sh")
+                .arg("-c")
+                .arg(script)
+                .status()
+                .unwrap();
+            assert!(status.success());
+        };
+
+        // An existing file behind a link is written through the link
+        let real = dir.join("site.conf");
+        std::fs::write(&real, "old").unwrap();
+        std::fs::set_permissions(&real, std::fs::Permissions::from_mode(0o600)).unwrap();
+        let link = dir.join("enabled.conf");
+        std::os::unix::fs::symlink(&real, &link).unwrap();
+        run(&link, "new");
+        assert!(std::fs::symlink_metadata(&link)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!(std::fs::read_to_string(&real).unwrap(), "new");
+        let mode = std::fs::metadata(&real).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        // A new file gets 644
+        let created = dir.join("new file.conf");
+        run(&created, "hello");
+        assert_eq!(std::fs::read_to_string(&created).unwrap(), "hello");
+        let mode = std::fs::metadata(&created).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o644);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,238 @@
+//! Comparing local and remote directory trees
+//!
+//! `compare_directories` produces the diff a directory sync acts on. It
+//! only reads metadata, so it doubles as the dry-run preview; a sync must
+//! use the same function so the preview matches what it will do.
+
+use super::{SftpClient, SftpError};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+/// Modification times closer than this count as equal (SFTP has 1s resolution)
+const MTIME_TOLERANCE_SECS: i64 = 1;
+
+/// Metadata of one entry in a scanned tree
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TreeEntry {
+    pub is_dir: bool,
+    pub size: u64,
+    /// Seconds since the Unix epoch
+    pub modified: Option<i64>,
+}
+
+/// Entries of a tree keyed by `/`-separated path relative to its root
+pub type Tree = BTreeMap<String, TreeEntry>;
+
+/// How an entry differs between the two sides
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncStatus {
+    LocalOnly,
+    RemoteOnly,
+    Different,
+    Identical,
+}
+
+/// One entry of a directory comparison
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncDiff {
+    /// Path relative to both roots, `/`-separated
+    pub path: String,
+    pub status: SyncStatus,
+    pub is_dir: bool,
+    pub local_size: Option<u64>,
+    pub remote_size: Option<u64>,
+    pub local_modified: Option<i64>,
+    pub remote_modified: Option<i64>,
+}
+
+/// Compare a local and a remote directory tree without transferring anything
+pub async fn compare_directories(
+    client: &SftpClient,
+    local_dir: &str,
+    remote_dir: &str,
+) -> Result<Vec<SyncDiff>, SftpError> {
+    let local_root = local_dir.to_string();
+    let local = tokio::task::spawn_blocking(move || scan_local(Path::new(&local_root)))
+        .await
+        .map_err(|e| SftpError::TransferFailed(e.to_string()))??;
+    let remote = scan_remote(client, remote_dir).await?;
+
+    let diffs = compare(&local, &remote);
+    tracing::info!(
+        "Compared {} with {}: {} entries, {} not identical",
+        local_dir,
+        remote_dir,
+        diffs.len(),
+        diffs
+            .iter()
+            .filter(|d| d.status != SyncStatus::Identical)
+            .count()
+    );
+    Ok(diffs)
+}
+
+/// Diff two scanned trees, sorted by path
+pub fn compare(local: &Tree, remote: &Tree) -> Vec<SyncDiff> {
+    let paths: BTreeSet<&String> = local.keys().chain(remote.keys()).collect();
+
+    paths
+        .into_iter()
+        .map(|path| {
+            let (l, r) = (local.get(path), remote.get(path));
+            let status = match (l, r) {
+                (Some(_), None) => SyncStatus::LocalOnly,
+                (None, Some(_)) => SyncStatus::RemoteOnly,
+                (Some(l), Some(r)) if entries_match(l, r) => SyncStatus::Identical,
+                _ => SyncStatus::Different,
+            };
+            SyncDiff {
+                path: path.clone(),
+                status,
+                is_dir: l.or(r).is_some_and(|e| e.is_dir),
+                local_size: l.map(|e| e.size),
+                remote_size: r.map(|e| e.size),
+                local_modified: l.and_then(|e| e.modified),
+                remote_modified: r.and_then(|e| e.modified),
+            }
+        })
+        .collect()
+}
+
+/// Directories match by type alone; files by size and modification time
+fn entries_match(local: &TreeEntry, remote: &TreeEntry) -> bool {
+    if local.is_dir || remote.is_dir {
+        return local.is_dir == remote.is_dir;
+    }
+    let same_mtime = match (local.modified, remote.modified) {
+        (Some(l), Some(r)) => (l - r).abs() <= MTIME_TOLERANCE_SECS,
+        _ => true,
+    };
+    local.size == remote.size && same_mtime
+}
+
+/// Walk a local directory; symlinks are skipped
+fn scan_local(root: &Path) -> Result<Tree, SftpError> {
+    if !root.is_dir() {
+        return Err(SftpError::NotDirectory(root.to_string_lossy().to_string()));
+    }
+
+    let mut tree = Tree::new();
+    let mut pending = vec![String::new()];
+    while let Some(relative) = pending.pop() {
+        for entry in std::fs::read_dir(root.join(&relative))? {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            if entry.file_type()?.is_symlink() {
+                continue;
+            }
+            let path = join_relative(&relative, &entry.file_name().to_string_lossy());
+            let modified = metadata
+                .modified()
+                .ok()
+                .map(|t| chrono::DateTime::<chrono::Utc>::from(t).timestamp());
+            if metadata.is_dir() {
+                pending.push(path.clone());
+            }
+            tree.insert(
+                path,
+                TreeEntry {
+                    is_dir: metadata.is_dir(),
+                    size: if metadata.is_dir() { 0 } else { metadata.len() },
+                    modified,
+                },
+            );
+        }
+    }
+    Ok(tree)
+}
+
+/// Walk a remote directory over SFTP; symlinks are skipped
+async fn scan_remote(client: &SftpClient, root: &str) -> Result<Tree, SftpError> {
+    if !client.symlink_metadata(root).await?.is_dir() {
+        return Err(SftpError::NotDirectory(root.to_string()));
+    }
+
+    let root = root.trim_end_matches('/');
+    let mut tree = Tree::new();
+    let mut pending = vec![String::new()];
+    while let Some(relative) = pending.pop() {
+        let dir = if relative.is_empty() {
+            root.to_string()
+        } else {
+            format!("{}/{}", root, relative)
+        };
+        for (name, metadata) in client.read_dir_sorted(&dir).await? {
+            if name == "." || name == ".." || metadata.is_symlink() {
+                continue;
+            }
+            let path = join_relative(&relative, &name);
+            if metadata.is_dir() {
+                pending.push(path.clone());
+            }
+            tree.insert(
+                path,
+                TreeEntry {
+                    is_dir: metadata.is_dir(),
+                    size: if metadata.is_dir() {
+                        0
+                    } else {
+                        metadata.size.unwrap_or(0)
+                    },
+                    modified: metadata.mtime.map(i64::from),
+                },
+            );
+        }
+    }
+    Ok(tree)
+}
+
+pub(super) fn join_relative(parent: &str, name: &str) -> String {
+    if parent.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}/{}", parent, name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(size: u64, modified: i64) -> TreeEntry {
+        TreeEntry {
+            is_dir: false,
+            size,
+            modified: Some(modified),
+        }
+    }
+
+    #[test]
+    fn test_compare_trees() {
+        let local = Tree::from([
+            ("a.txt".to_string(), file(10, 1000)),
+            ("b.txt".to_string(), file(10, 1000)),
+            ("only_local".to_string(), file(1, 1000)),
+        ]);
+        let remote = Tree::from([
+            ("a.txt".to_string(), file(10, 1001)),
+            ("b.txt".to_string(), file(12, 1000)),
+            ("only_remote".to_string(), file(1, 1000)),
+        ]);
+
+        let statuses: Vec<_> = compare(&local, &remote)
+            .into_iter()
+            .map(|d| (d.path, d.status))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ("a.txt".to_string(), SyncStatus::Identical),
+                ("b.txt".to_string(), SyncStatus::Different),
+                ("only_local".to_string(), SyncStatus::LocalOnly),
+                ("only_remote".to_string(), SyncStatus::RemoteOnly),
+            ]
+        );
+    }
+}
@@ -0,0 +1,107 @@
+//! Cancellation of in-flight uploads and downloads
+//!
+//! Each transfer registers its own token under its session and remote path,
+//! so cancelling one never touches another transfer on the same session.
+//! The registration is dropped with the returned handle.
+
+use crate::datasphere;
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use std::collections::HashMap;
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+/// Running transfers by transfer id
+static ACTIVE: Lazy<RwLock<HashMap<Uuid, ActiveTransfer>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+struct ActiveTransfer {
+    session_id: Uuid,
+    path: String,
+    token: CancellationToken,
+}
+
+/// A registered transfer; unregisters itself when dropped
+pub struct TransferHandle {
+    id: Uuid,
+    token: CancellationToken,
+}
+
+impl TransferHandle {
+    pub fn token(&self) -> &CancellationToken {
+        &self.token
+    }
+}
+
+impl Drop for TransferHandle {
+    fn drop(&mut self) {
+        ACTIVE.write().remove(&self.id);
+    }
+}
+
+/// Register a transfer of the remote `path` on a session
+pub fn register(session_id: Uuid, path: &str) -> TransferHandle {
+    let id = Uuid::new_v4();
+    let token = CancellationToken::new();
+    ACTIVE.write().insert(
+        id,
+        ActiveTransfer {
+            session_id,
+            path: path.to_string(),
+            token: token.clone(),
+        },
+    );
+    TransferHandle { id, token }
+}
+
+/// Cancel the transfers of `path` on a session; returns whether any was running
+pub fn cancel(session_id: Uuid, path: &str) -> bool {
+    let active = ACTIVE.read();
+    let mut found = false;
+    for transfer in active
+        .values()
+        .filter(|t| t.session_id == session_id && t.path == path)
+    {
+        transfer.token.cancel();
+        found = true;
+    }
+    if found {
+        tracing::info!("Cancelling transfer of {} on session {}", path, session_id);
+    }
+    found
+}
+
+/// Resolves once `token` is cancelled; never without one
+pub async fn cancelled(token: Option<&CancellationToken>) {
+    match token {
+        Some(token) => token.cancelled().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Whether a cancelled transfer's partial file should be deleted
+pub fn remove_partial() -> bool {
+    datasphere::storage()
+        .read()
+        .as_ref()
+        .is_none_or(|s| s.get_settings().remove_partial_transfers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cancel_only_matching_transfer() {
+        let session = Uuid::new_v4();
+        let first = register(session, "/srv/a.bin");
+        let second = register(session, "/srv/b.bin");
+
+        assert!(cancel(session, "/srv/a.bin"));
+        assert!(first.token().is_cancelled());
+        assert!(!second.token().is_cancelled());
+
+        drop(second);
+        assert!(!cancel(session, "/srv/b.bin"));
+    }
+}
@@ -0,0 +1,327 @@
+//! POSIX ACLs and extended attributes
+//!
+//! Read with `getfacl`/`getfattr` and written with `setfacl`/`setfattr`
+//! over exec. The two halves are independent: a server without the `attr`
+//! tools still reports ACLs and vice versa. Only when neither works is the
+//! request `Unsupported`.
+
+use super::helper::run_helper;
+use super::SftpError;
+use crate::ssh::{CommandOutput, SshClient};
+use crate::utils::shell_quote;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// One entry of a POSIX ACL, e.g. `user:alice:rw-`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AclEntry {
+    /// `user`, `group`, `mask` or `other`
+    pub tag: String,
+    /// User or group name; `None` for the owner, owning group, mask and other
+    #[serde(default)]
+    pub qualifier: Option<String>,
+    /// `rwx` with `-` for missing permissions
+    pub perms: String,
+    /// Part of a directory's default ACL
+    #[serde(default)]
+    pub default: bool,
+}
+
+impl AclEntry {
+    fn spec(&self) -> String {
+        format!(
+            "{}{}:{}:{}",
+            if self.default { "default:" } else { "" },
+            self.tag,
+            self.qualifier.as_deref().unwrap_or(""),
+            self.perms
+        )
+    }
+
+    fn validate(&self) -> Result<(), SftpError> {
+        let tag_ok = matches!(self.tag.as_str(), "user" | "group" | "mask" | "other");
+        let perms_ok = !self.perms.is_empty() && self.perms.chars().all(|c| "rwxX-".contains(c));
+        let qualifier_ok = self
+            .qualifier
+            .as_deref()
+            .is_none_or(|q| !q.is_empty() && !q.contains([':', ',', '\n']));
+        if tag_ok && perms_ok && qualifier_ok {
+            Ok(())
+        } else {
+            Err(SftpError::InvalidPath(format!(
+                "Invalid ACL entry: {}",
+                self.spec()
+            )))
+        }
+    }
+}
+
+/// ACL and extended attributes of a path
+///
+/// A `None` field means the server can't report that kind of attribute.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExtendedAttributes {
+    pub path: String,
+    pub acl: Option<Vec<AclEntry>>,
+    /// Attribute values as text, or as `0x` hex when they aren't plain
+    /// text; `set_attributes` reads values the same way
+    pub xattrs: Option<BTreeMap<String, String>>,
+}
+
+/// Read the ACL and extended attributes of `path`
+pub async fn get_attributes(
+    client: &SshClient,
+    path: &str,
+) -> Result<ExtendedAttributes, SftpError> {
+    let quoted = shell_quote(path);
+
+    let acl = match run_helper(
+        client,
+        &["getfacl"],
+        &format!("getfacl -p --omit-header -- {}", quoted),
+        &[],
+    )
+    .await
+    {
+        Ok(output) => check(path, &output)?.map(|out| parse_getfacl(&out)),
+        Err(SftpError::ToolNotFound(_)) => None,
+        Err(e) => return Err(e),
+    };
+
+    let xattrs = match run_helper(
+        client,
+        &["getfattr"],
+        &format!("getfattr -h -d -m - -e hex -- {}", quoted),
+        &[],
+    )
+    .await
+    {
+        Ok(output) => check(path, &output)?.map(|out| parse_getfattr(&out)),
+        Err(SftpError::ToolNotFound(_)) => None,
+        Err(e) => return Err(e),
+    };
+
+    if acl.is_none() && xattrs.is_none() {
+        return Err(SftpError::Unsupported(
+            "ACLs and extended attributes need getfacl or getfattr on the server".to_string(),
+        ));
+    }
+    Ok(ExtendedAttributes {
+        path: path.to_string(),
+        acl,
+        xattrs,
+    })
+}
+
+/// Replace the ACL and/or change extended attributes of `path`
+///
+/// `acl` replaces the whole ACL, so it must include the `user`, `group`
+/// and `other` base entries. In `xattrs` a `None` value removes the
+/// attribute, and values use the encoding of `ExtendedAttributes::xattrs`.
+/// Returns the attributes as they are afterwards.
+pub async fn set_attributes(
+    client: &SshClient,
+    path: &str,
+    acl: Option<&[AclEntry]>,
+    xattrs: &BTreeMap<String, Option<String>>,
+) -> Result<ExtendedAttributes, SftpError> {
+    let quoted = shell_quote(path);
+
+    if let Some(acl) = acl {
+        for entry in acl {
+            entry.validate()?;
+        }
+        let spec = acl.iter().map(AclEntry::spec).collect::<Vec<_>>().join(",");
+        let output = run_helper(
+            client,
+            &["setfacl"],
+            &format!("setfacl --set {} -- {}", shell_quote(&spec), quoted),
+            &[],
+        )
+        .await?;
+        check_set(path, &output, "setfacl")?;
+    }
+
+    if !xattrs.is_empty() {
+        let commands: Vec<String> = xattrs
+            .iter()
+            .map(|(name, value)| match value {
+                Some(value) => format!(
+                    "setfattr -h -n {} -v {} -- {}",
+                    shell_quote(name),
+                    setfattr_value(&value_bytes(value)),
+                    quoted
+                ),
+                None => format!("setfattr -h -x {} -- {}", shell_quote(name), quoted),
+            })
+            .collect();
+        let output = run_helper(client, &["setfattr"], &commands.join(" && "), &[]).await?;
+        check_set(path, &output, "setfattr")?;
+    }
+
+    get_attributes(client, path).await
+}
+
+/// Stdout of a successful read, `None` if the filesystem lacks support
+fn check(path: &str, output: &CommandOutput) -> Result<Option<String>, SftpError> {
+    let stderr = output.stderr.trim();
+    if output.exit_code == 0 || !output.stdout.trim().is_empty() {
+        // getfattr exits non-zero when one namespace is unreadable but
+        // still prints the rest
+        return Ok(Some(output.stdout.clone()));
+    }
+    if stderr.contains("Operation not supported") {
+        Ok(None)
+    } else if stderr.contains("No such file") {
+        Err(SftpError::PathNotFound(path.to_string()))
+    } else if stderr.contains("Permission denied") {
+        Err(SftpError::PermissionDenied(path.to_string()))
+    } else if stderr.is_empty() {
+        // No attributes at all
+        Ok(Some(String::new()))
+    } else {
+        Err(SftpError::Ssh(stderr.to_string()))
+    }
+}
+
+fn check_set(path: &str, output: &CommandOutput, tool: &str) -> Result<(), SftpError> {
+    if output.exit_code == 0 {
+        return Ok(());
+    }
+    let stderr = output.stderr.trim();
+    if stderr.contains("Operation not supported") {
+        Err(SftpError::Unsupported(format!(
+            "{} on {}: {}",
+            tool, path, stderr
+        )))
+    } else if stderr.contains("Permission denied") || stderr.contains("not permitted") {
+        Err(SftpError::PermissionDenied(format!("{}: {}", path, stderr)))
+    } else {
+        Err(SftpError::Ssh(format!("{} failed: {}", tool, stderr)))
+    }
+}
+
+/// Parse `getfacl --omit-header` output
+fn parse_getfacl(output: &str) -> Vec<AclEntry> {
+    output
+        .lines()
+        .filter_map(|line| {
+            // Drop `#effective:` annotations and comment lines
+            let line = line.split('#').next()?.trim();
+            let (default, line) = match line.strip_prefix("default:") {
+                Some(rest) => (true, rest),
+                None => (false, line),
+            };
+            let mut parts = line.splitn(3, ':');
+            let tag = parts.next()?;
+            let qualifier = parts.next()?;
+            let perms = parts.next()?;
+            Some(AclEntry {
+                tag: tag.to_string(),
+                qualifier: (!qualifier.is_empty()).then(|| qualifier.to_string()),
+                perms: perms.to_string(),
+                default,
+            })
+        })
+        .collect()
+}
+
+/// Parse `getfattr -d -e hex` output
+fn parse_getfattr(output: &str) -> BTreeMap<String, String> {
+    output
+        .lines()
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let (name, raw) = line.split_once('=').unwrap_or((line, ""));
+            let value = match raw.strip_prefix("0x").map(hex::decode) {
+                Some(Ok(bytes)) => display_value(&bytes),
+                _ => raw.trim_matches('"').to_string(),
+            };
+            (name.to_string(), value)
+        })
+        .collect()
+}
+
+/// Show an attribute value as text if it survives the trip back intact
+///
+/// Anything else, including C strings with their trailing NUL and text
+/// that itself starts with `0x`, is shown as `0x` hex.
+fn display_value(bytes: &[u8]) -> String {
+    match std::str::from_utf8(bytes) {
+        Ok(text) if !text.starts_with("0x") && !text.contains('\0') => text.to_string(),
+        _ => format!("0x{}", hex::encode(bytes)),
+    }
+}
+
+/// The bytes a value from `display_value` stands for
+fn value_bytes(value: &str) -> Vec<u8> {
+    match value.strip_prefix("0x").map(hex::decode) {
+        Some(Ok(bytes)) => bytes,
+        _ => value.as_bytes().to_vec(),
+    }
+}
+
+/// `setfattr -v` argument for `bytes`; it rejects a bare `0x`
+fn setfattr_value(bytes: &[u8]) -> String {
+    if bytes.is_empty() {
+        "''".to_string()
+    } else {
+        format!("0x{}", hex::encode(bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_attributes() {
+        let acl = parse_getfacl(
+            "user::rw-\nuser:alice:rwx\t\t#effective:r-x\ngroup::r-x\nmask::r-x\nother::---\n\ndefault:user::rwx\n",
+        );
+        assert_eq!(acl.len(), 6);
+        assert_eq!(acl[1].qualifier.as_deref(), Some("alice"));
+        assert_eq!(acl[1].perms, "rwx");
+        assert_eq!(acl[0].qualifier, None);
+        assert!(acl[5].default);
+        assert_eq!(acl[1].spec(), "user:alice:rwx");
+
+        let xattrs = parse_getfattr(
+            "# file: /srv/a\nuser.comment=0x68656c6c6f00\nuser.blob=0xff00\nuser.empty\n",
+        );
+        assert_eq!(xattrs["user.comment"], "0x68656c6c6f00");
+        assert_eq!(xattrs["user.blob"], "0xff00");
+        assert_eq!(xattrs["user.empty"], "");
+
+        let bad = AclEntry {
+            tag: "user".to_string(),
+            qualifier: Some("a,b".to_string()),
+            perms: "rw-".to_string(),
+            default: false,
+        };
+        assert!(bad.validate().is_err());
+    }
+
+    #[test]
+    fn test_xattr_value_round_trip() {
+        let values: [&[u8]; 6] = [
+            b"hello",
+            b"hello\0",
+            &[0xff, 0x00],
+            b"",
+            b"0xab",
+            "d\u{e9}j\u{e0}".as_bytes(),
+        ];
+        for bytes in values {
+            let shown =
+                parse_getfattr(&format!("user.v=0x{}\n", hex::encode(bytes)))["user.v"].clone();
+            assert_eq!(value_bytes(&shown), bytes, "{:?} shown as {}", bytes, shown);
+        }
+
+        assert_eq!(display_value(b"hello"), "hello");
+        assert_eq!(display_value(b"0xab"), "0x30786162");
+        assert_eq!(value_bytes("0xzz"), b"0xzz");
+        assert_eq!(setfattr_value(b""), "''");
+        assert_eq!(setfattr_value(b"hi"), "0x6869");
+    }
+}
@@ -0,0 +1,80 @@
+//! Authentication through a running SSH agent
+//!
+//! On Unix the agent is found through `SSH_AUTH_SOCK`. On Windows the
+//! OpenSSH agent's named pipe is used, unless `SSH_AUTH_SOCK` names another
+//! pipe (e.g. the one Pageant writes into its `--openssh-config` file).
+
+use super::client::ClientHandler;
+use super::fingerprint::fingerprint_sha256;
+use super::SshError;
+use russh::client::Handle;
+use russh_keys::agent::client::AgentClient;
+use tokio::io::{AsyncRead, AsyncWrite};
+
+/// Pipe of the Windows OpenSSH agent service
+#[cfg(windows)]
+const OPENSSH_AGENT_PIPE: &str = r"\\.\pipe\openssh-ssh-agent";
+
+/// Try every identity the agent holds until the server accepts one
+///
+/// Identities the server rejects or the agent can't sign with are skipped.
+/// Fails with `AgentUnavailable` when no agent can be reached.
+pub async fn authenticate(
+    session: &mut Handle<ClientHandler>,
+    username: &str,
+) -> Result<bool, SshError> {
+    let agent = connect().await?;
+    try_identities(session, username, agent).await
+}
+
+#[cfg(unix)]
+async fn connect() -> Result<AgentClient<tokio::net::UnixStream>, SshError> {
+    let path = std::env::var("SSH_AUTH_SOCK")
+        .map_err(|_| SshError::AgentUnavailable("SSH_AUTH_SOCK is not set".to_string()))?;
+    AgentClient::connect_uds(&path)
+        .await
+        .map_err(|e| SshError::AgentUnavailable(format!("{}: {}", path, e)))
+}
+
+#[cfg(windows)]
+async fn connect() -> Result<AgentClient<tokio::net::windows::named_pipe::NamedPipeClient>, SshError>
+{
+    let path = std::env::var("SSH_AUTH_SOCK").unwrap_or_else(|_| OPENSSH_AGENT_PIPE.to_string());
+    tokio::net::windows::named_pipe::ClientOptions::new()
+        .open(&path)
+        .map(AgentClient::connect)
+        .map_err(|e| SshError::AgentUnavailable(format!("{}: {}", path, e)))
+}
+
+async fn try_identities<S>(
+    session: &mut Handle<ClientHandler>,
+    username: &str,
+    mut agent: AgentClient<S>,
+) -> Result<bool, SshError>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let identities = agent
+        .request_identities()
+        .await
+        .map_err(|e| SshError::AgentUnavailable(e.to_string()))?;
+    if identities.is_empty() {
+        tracing::info!("SSH agent holds no identities");
+        return Ok(false);
+    }
+
+    for key in identities {
+        let fingerprint = fingerprint_sha256(&key);
+        let (returned, result) = session.authenticate_future(username, key, agent).await;
+        agent = returned;
+        match result {
+            Ok(true) => {
+                tracing::info!("Authenticated with agent key {}", fingerprint);
+                return Ok(true);
+            }
+            Ok(false) => tracing::debug!("Server rejected agent key {}", fingerprint),
+            Err(e) => tracing::warn!("Agent could not sign with {}: {}", fingerprint, e),
+        }
+    }
+    Ok(false)
+}
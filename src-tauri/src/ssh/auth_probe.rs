@@ -0,0 +1,123 @@
+//! Discovering which authentication methods a server accepts
+//!
+//! russh keeps the method list from a USERAUTH_FAILURE reply to itself, so
+//! the probe runs the local OpenSSH client with only "none" auth allowed
+//! and reads the list the server sends back from its log.
+
+use super::SshError;
+use std::process::Stdio;
+use std::time::Duration;
+
+/// Connect timeout handed to `ssh`
+const PROBE_TIMEOUT_SECS: u64 = 10;
+
+/// Ask a server which authentication methods it offers
+///
+/// Returns `["none"]` if the server lets anyone in, and an empty list if it
+/// offers nothing. Connection problems are returned as errors.
+pub async fn probe_auth_methods(
+    host: &str,
+    port: u16,
+    username: Option<&str>,
+) -> Result<Vec<String>, SshError> {
+    let null_file = if cfg!(windows) { "NUL" } else { "/dev/null" };
+
+    let mut command = tokio::process::Command::new("ssh");
+    command
+        .args(["-v", "-T", "-F", null_file])
+        .args(["-o", "BatchMode=yes"])
+        .args(["-o", "PreferredAuthentications=none"])
+        .args(["-o", "StrictHostKeyChecking=no"])
+        .args(["-o", &format!("UserKnownHostsFile={}", null_file)])
+        .args(["-o", &format!("ConnectTimeout={}", PROBE_TIMEOUT_SECS)])
+        .args(["-p", &port.to_string()]);
+    if let Some(user) = username {
+        command.args(["-l", user]);
+    }
+    command
+        .args(["--", host, "true"])
+        .stdin(Stdio::null())
+        .kill_on_drop(true);
+
+    // Allow for the handshake on top of the TCP connect timeout
+    let limit = Duration::from_secs(PROBE_TIMEOUT_SECS * 2);
+    let output = tokio::time::timeout(limit, command.output())
+        .await
+        .map_err(|_| SshError::Timeout)?
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => {
+                SshError::ConnectionFailed("OpenSSH client (ssh) is not installed".to_string())
+            }
+            _ => SshError::Io(e),
+        })?;
+
+    parse_probe_log(&String::from_utf8_lossy(&output.stderr))
+}
+
+/// Extract the offered methods, or the connection error, from `ssh -v` output
+fn parse_probe_log(log: &str) -> Result<Vec<String>, SshError> {
+    for line in log.lines() {
+        if let Some((_, list)) = line.split_once("Authentications that can continue:") {
+            return Ok(split_methods(list));
+        }
+        if line.contains("Authentication succeeded (none)") {
+            return Ok(vec!["none".to_string()]);
+        }
+    }
+
+    // Without -v output, the final error still carries the list
+    if let Some((_, rest)) = log.split_once("Permission denied (") {
+        if let Some((list, _)) = rest.split_once(')') {
+            return Ok(split_methods(list));
+        }
+    }
+
+    let error = log
+        .lines()
+        .rev()
+        .find(|line| !line.starts_with("debug") && !line.trim().is_empty())
+        .unwrap_or("ssh exited without output")
+        .trim()
+        .to_string();
+
+    Err(if error.contains("Could not resolve hostname") {
+        SshError::DnsResolutionFailed(error)
+    } else if error.contains("Connection refused") {
+        SshError::ConnectionRefused(error)
+    } else if error.contains("timed out") {
+        SshError::Timeout
+    } else {
+        SshError::ConnectionFailed(error)
+    })
+}
+
+fn split_methods(list: &str) -> Vec<String> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|m| !m.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_probe_log() {
+        let log = "debug1: Next authentication method: none\n\
+                   debug1: Authentications that can continue: publickey,password\n\
+                   user@host: Permission denied (publickey,password).\n";
+        assert_eq!(
+            parse_probe_log(log).unwrap(),
+            vec!["publickey".to_string(), "password".to_string()]
+        );
+
+        let log = "debug1: Connecting to nowhere.invalid port 22.\n\
+                   ssh: Could not resolve hostname nowhere.invalid: Name or service not known\n";
+        assert!(matches!(
+            parse_probe_log(log),
+            Err(SshError::DnsResolutionFailed(_))
+        ));
+    }
+}
@@ -0,0 +1,109 @@
+//! Installing public keys on a server, like `ssh-copy-id`
+//!
+//! Runs one shell script over exec: it creates `~/.ssh` (700) and
+//! `~/.ssh/authorized_keys` (600) when missing and appends the key unless
+//! the same key, whatever its options or comment, is already listed on a
+//! line that isn't commented out.
+
+use super::{SshClient, SshError};
+use crate::utils::shell_quote;
+use serde::{Deserialize, Serialize};
+
+/// Exit code of the install script when the home directory can't be written
+const EXIT_NOT_WRITABLE: i32 = 3;
+
+/// What installing a key did
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyInstallOutcome {
+    Added,
+    AlreadyPresent,
+}
+
+/// The `type base64` part of an `authorized_keys` line, checked to parse
+///
+/// The key must be a single line: anything after a line break would be
+/// installed as another entry.
+fn key_blob(public_key: &str) -> Result<String, SshError> {
+    if public_key.contains(['\n', '\r']) {
+        return Err(SshError::KeyError(
+            "A public key must be a single line".to_string(),
+        ));
+    }
+    let mut fields = public_key.split_whitespace();
+    let (Some(key_type), Some(data)) = (fields.next(), fields.next()) else {
+        return Err(SshError::KeyError(
+            "Expected a public key like \"ssh-ed25519 AAAA... comment\"".to_string(),
+        ));
+    };
+    russh::keys::parse_public_key_base64(data)
+        .map_err(|e| SshError::KeyError(format!("Invalid public key: {}", e)))?;
+    Ok(format!("{} {}", key_type, data))
+}
+
+fn install_script(blob: &str, line: &str) -> String {
+    let file = ".ssh/authorized_keys";
+    format!(
+        "umask 077
+cd || exit {fail}
+[ -d .ssh ] || mkdir .ssh || exit {fail}
+[ -f {file} ] || {{ : > {file} && chmod 600 {file}; }} || exit {fail}
+if KEY={blob} awk '!/^[[:space:]]*#/ {{ for (i = 1; i < NF; i++) if ($i \" \" $(i + 1) == ENVIRON[\"KEY\"]) found = 1 }} END {{ exit !found }}' {file}; then echo already_present; exit 0; fi
+if [ -s {file} ] && [ -n \"$(tail -c 1 {file})\" ]; then echo >> {file} || exit {fail}; fi
+printf '%s\\n' {line} >> {file} || exit {fail}
+echo added",
+        fail = EXIT_NOT_WRITABLE,
+        file = file,
+        blob = shell_quote(blob),
+        line = shell_quote(line),
+    )
+}
+
+/// Add `public_key` to the connected user's `~/.ssh/authorized_keys`
+pub async fn install(client: &SshClient, public_key: &str) -> Result<KeyInstallOutcome, SshError> {
+    let line = public_key.trim();
+    let blob = key_blob(line)?;
+    let command = client.posix_command(&install_script(&blob, line)).await?;
+    let output = client.execute(&command).await?;
+
+    match (output.exit_code, output.stdout.trim()) {
+        (0, "added") => {
+            tracing::info!("Installed public key on {}", client.config.host);
+            Ok(KeyInstallOutcome::Added)
+        }
+        (0, "already_present") => Ok(KeyInstallOutcome::AlreadyPresent),
+        (EXIT_NOT_WRITABLE, _) => Err(SshError::CommandFailed(format!(
+            "Home directory is not writable: {}",
+            output.stderr.trim()
+        ))),
+        (code, _) => Err(SshError::CommandFailed(format!(
+            "Installing the key failed with exit code {}: {}",
+            code,
+            output.stderr.trim()
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ssh::keygen::{self, KeyAlgorithm};
+
+    #[test]
+    fn test_key_blob() {
+        let key = keygen::generate(&KeyAlgorithm::Ed25519, "", None)
+            .unwrap()
+            .public_key;
+        assert_eq!(key_blob(&format!("{}  me@laptop ", key)).unwrap(), key);
+        assert_eq!(key_blob(&key).unwrap(), key);
+        assert!(key_blob("ssh-ed25519").is_err());
+        assert!(key_blob("ssh-ed25519 not-base64!").is_err());
+        // A second line would be installed as a key of its own
+        assert!(key_blob(&format!("{} me\n{}", key, key)).is_err());
+        assert!(key_blob(&format!("{} me\rcommand=\"sh\" {}", key, key)).is_err());
+
+        let script = install_script(&key, &format!("{} it's me", key));
+        assert!(script.contains(&format!("KEY='{}' awk ", key)));
+        assert!(script.contains("it'\\''s me'"));
+    }
+}
@@ -1,14 +1,33 @@
 //! SSH Client Implementation using russh
 
+use super::agent;
+use super::known_hosts;
+use super::ppk;
+use super::profile::SecurityProfile;
+use super::prompt::{InteractiveOutput, PromptDetector};
+use super::remote_info::{self, RemoteInfo};
+use super::resolve::{self, AddressFamily};
+use super::script;
+use super::shell::{self, ShellKind};
+use super::state::{ConnectionReason, ConnectionState, StateListener};
+use super::transport::{self, Transport};
+use super::tunnel::{self, RemoteTargets, Tunnel, Tunnels};
 use async_trait::async_trait;
 use russh::client::{self, Config, Handle, Handler};
 use russh::keys::key::PublicKey;
-use russh::{ChannelId, Disconnect};
+use russh::{Channel, ChannelId, Disconnect, Sig};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use tokio::sync::{mpsc, Mutex};
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot, Mutex, RwLock};
 use uuid::Uuid;
 
+/// Unanswered keepalives tolerated unless configured otherwise
+pub const DEFAULT_KEEPALIVE_MAX: usize = 3;
+
 /// SSH connection configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SshConfig {
@@ -18,12 +37,82 @@ pub struct SshConfig {
     pub auth_method: AuthMethod,
     #[serde(default = "default_timeout")]
     pub timeout_seconds: u64,
+    #[serde(default)]
+    pub address_family: AddressFamily,
+    /// Keep the login banner/MOTD out of PTY output
+    ///
+    /// Exec channels never run a login shell, so they are clean either way.
+    #[serde(default)]
+    pub suppress_motd: bool,
+    /// Shell snippet run before exec helper commands, e.g. `. /etc/profile;`
+    #[serde(default)]
+    pub exec_profile: Option<String>,
+    /// TCP unless the server is reached through a local socket or pipe
+    #[serde(default)]
+    pub transport: Transport,
+    /// Encoding of terminal output, e.g. `latin1`; UTF-8 when unset
+    #[serde(default)]
+    pub encoding: Option<String>,
+    /// Encoding of terminal input; defaults to `encoding`
+    #[serde(default)]
+    pub send_encoding: Option<String>,
+    /// Turn bare CR or LF line endings in terminal output into CRLF
+    #[serde(default)]
+    pub normalize_newlines: bool,
+    /// Resolved security profile; russh defaults when unset
+    #[serde(default)]
+    pub security: Option<SecurityProfile>,
+    /// Send a keepalive after this much silence; overrides the profile, off when unset
+    #[serde(default)]
+    pub keepalive_seconds: Option<u64>,
+    /// Unanswered keepalives before the connection is dropped
+    #[serde(default = "default_keepalive_max")]
+    pub keepalive_max: usize,
+    /// Bastions to pass through, outermost first; each hop uses its own auth
+    ///
+    /// With jump hosts the target is always reached over TCP from the last hop.
+    #[serde(default)]
+    pub jump_hosts: Vec<SshConfig>,
+    /// Variables set on shell channels; see `open_shell`
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    /// Typed into terminal shells once they show a prompt
+    #[serde(default)]
+    pub init_commands: Vec<String>,
 }
 
 fn default_timeout() -> u64 {
     30
 }
 
+fn default_keepalive_max() -> usize {
+    DEFAULT_KEEPALIVE_MAX
+}
+
+impl SshConfig {
+    /// Idle time after which the connection is dropped
+    pub fn inactivity_timeout_seconds(&self) -> u64 {
+        self.timeout_seconds * 2
+    }
+
+    /// Apply a security profile, including its connect timeout
+    pub fn with_profile(mut self, profile: SecurityProfile) -> Self {
+        if let Some(timeout) = profile.connect_timeout_seconds {
+            self.timeout_seconds = timeout;
+        }
+        self.security = Some(profile);
+        self
+    }
+
+    /// Whether both configs log in as the same user on the same server
+    pub fn same_target(&self, other: &SshConfig) -> bool {
+        self.host.eq_ignore_ascii_case(&other.host)
+            && self.port == other.port
+            && self.username == other.username
+            && self.transport == other.transport
+    }
+}
+
 /// Authentication method for SSH
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", content = "data")]
@@ -46,12 +135,43 @@ pub enum TerminalOutput {
     Error(String),
 }
 
+/// How long a cancelled command gets to exit on SIGINT before its channel is closed
+const CANCEL_GRACE: Duration = Duration::from_secs(2);
+
+/// How long the server may take to accept or refuse an environment variable
+const ENV_REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+
 /// Command execution output
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CommandOutput {
     pub stdout: String,
     pub stderr: String,
     pub exit_code: i32,
+    /// Both streams in arrival order; only filled for interleaved execution
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub chunks: Vec<OutputChunk>,
+    /// Interrupted by `cancel_command`; the output is what arrived until then
+    #[serde(default)]
+    pub cancelled: bool,
+}
+
+/// Which stream a piece of command output came from
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+/// A run of output from one stream
+///
+/// Consecutive data on the same stream is merged into one chunk, so `seq`
+/// increases each time the output switches streams.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutputChunk {
+    pub seq: u64,
+    pub stream: OutputStream,
+    pub data: String,
 }
 
 /// SSH Error types
@@ -61,6 +181,10 @@ pub enum SshError {
     NotConnected,
     #[error("Connection failed: {0}")]
     ConnectionFailed(String),
+    #[error("DNS resolution failed: {0}")]
+    DnsResolutionFailed(String),
+    #[error("Connection refused: {0}")]
+    ConnectionRefused(String),
     #[error("Authentication failed")]
     AuthenticationFailed,
     #[error("Command execution failed: {0}")]
@@ -75,11 +199,43 @@ pub enum SshError {
     Russh(String),
     #[error("Timeout")]
     Timeout,
+    /// The command was stopped at its deadline; serialized with the output so far
+    #[error("Command timed out after {seconds}s")]
+    CommandTimeout {
+        seconds: u64,
+        output: Box<CommandOutput>,
+    },
+    #[error("Interpreter not found on server: {0}")]
+    InterpreterNotFound(String),
+    #[error("Invalid security profile: {0}")]
+    InvalidProfile(String),
+    #[error("Unknown host key: {0}")]
+    HostKeyUnknown(String),
+    #[error("Host key changed: {0}")]
+    HostKeyChanged(String),
+    #[error("Exec helpers need a POSIX shell; server uses {0}")]
+    UnsupportedShell(String),
+    #[error("SSH agent unavailable: {0}")]
+    AgentUnavailable(String),
+    #[error("Server stopped answering keepalives")]
+    KeepaliveTimeout,
+    #[error("Invalid address: {0}")]
+    InvalidAddress(String),
+    #[error("Jump host {hop} ({host}) failed: {source}")]
+    JumpHostFailed {
+        /// 1-based position in `jump_hosts`
+        hop: usize,
+        host: String,
+        source: Box<SshError>,
+    },
 }
 
 impl From<russh::Error> for SshError {
     fn from(err: russh::Error) -> Self {
-        SshError::Russh(err.to_string())
+        match err {
+            russh::Error::KeepaliveTimeout => SshError::KeepaliveTimeout,
+            err => SshError::Russh(err.to_string()),
+        }
     }
 }
 
@@ -89,30 +245,81 @@ impl From<russh_keys::Error> for SshError {
     }
 }
 
+impl crate::utils::ErrorCode for SshError {
+    fn code(&self) -> &'static str {
+        match self {
+            SshError::NotConnected => "not_connected",
+            SshError::DnsResolutionFailed(_) => "dns_failed",
+            SshError::ConnectionRefused(_) => "connection_refused",
+            SshError::Timeout | SshError::CommandTimeout { .. } => "timeout",
+            SshError::AuthenticationFailed => "auth_failed",
+            SshError::KeyError(_) => "key_error",
+            SshError::HostKeyUnknown(_) => "host_key_unknown",
+            SshError::HostKeyChanged(_) => "host_key_changed",
+            SshError::UnsupportedShell(_) => "unsupported_shell",
+            SshError::AgentUnavailable(_) => "agent_unavailable",
+            SshError::KeepaliveTimeout => "keepalive_timeout",
+            SshError::JumpHostFailed { source, .. } => source.code(),
+            _ => "error",
+        }
+    }
+}
+
+/// Timeouts go to the frontend as `{ code, message, output }` so the partial
+/// output survives (`CommandTimeoutError` in src/types); everything else is
+/// its message string
 impl Serialize for SshError {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
     {
-        serializer.serialize_str(&self.to_string())
+        use serde::ser::SerializeStruct;
+
+        match self {
+            SshError::CommandTimeout { output, .. } => {
+                let mut error = serializer.serialize_struct("SshError", 3)?;
+                error.serialize_field("code", "timeout")?;
+                error.serialize_field("message", &self.to_string())?;
+                error.serialize_field("output", output)?;
+                error.end()
+            }
+            _ => serializer.serialize_str(&self.to_string()),
+        }
     }
 }
 
 /// Client handler for russh events
 pub struct ClientHandler {
     output_tx: Arc<Mutex<Option<mpsc::Sender<TerminalOutput>>>>,
+    auth_banner: Arc<parking_lot::Mutex<Option<String>>>,
+    /// Host and port the host key is checked against
+    target: (String, u16),
+    /// Set once the connection is dropped for missed keepalives
+    keepalive_lost: Arc<AtomicBool>,
+    state_listener: Option<StateListener>,
+    remote_targets: RemoteTargets,
 }
 
 impl ClientHandler {
     pub fn new() -> Self {
         Self {
             output_tx: Arc::new(Mutex::new(None)),
+            auth_banner: Arc::default(),
+            target: (String::new(), 0),
+            keepalive_lost: Arc::default(),
+            state_listener: None,
+            remote_targets: RemoteTargets::default(),
         }
     }
 
     pub fn with_output(tx: mpsc::Sender<TerminalOutput>) -> Self {
         Self {
             output_tx: Arc::new(Mutex::new(Some(tx))),
+            auth_banner: Arc::default(),
+            target: (String::new(), 0),
+            keepalive_lost: Arc::default(),
+            state_listener: None,
+            remote_targets: RemoteTargets::default(),
         }
     }
 }
@@ -123,13 +330,75 @@ impl Handler for ClientHandler {
 
     async fn check_server_key(
         &mut self,
-        _server_public_key: &PublicKey,
+        server_public_key: &PublicKey,
     ) -> Result<bool, Self::Error> {
-        // TODO: Implement proper host key verification with known_hosts
-        tracing::warn!("Host key verification skipped - implement proper verification!");
+        let (host, port) = &self.target;
+        known_hosts::verify(host, *port, server_public_key)?;
         Ok(true)
     }
 
+    async fn disconnected(
+        &mut self,
+        reason: client::DisconnectReason<Self::Error>,
+    ) -> Result<(), Self::Error> {
+        match reason {
+            client::DisconnectReason::ReceivedDisconnect(_) => Ok(()),
+            client::DisconnectReason::Error(SshError::KeepaliveTimeout) => {
+                let (host, port) = &self.target;
+                tracing::warn!("{}:{} stopped answering keepalives", host, port);
+                self.keepalive_lost.store(true, Ordering::Relaxed);
+                if let Some(listener) = &self.state_listener {
+                    listener(
+                        ConnectionState::Disconnected,
+                        Some(ConnectionReason::KeepaliveTimeout),
+                    );
+                }
+                Err(SshError::KeepaliveTimeout)
+            }
+            client::DisconnectReason::Error(e) => Err(e),
+        }
+    }
+
+    async fn server_channel_open_forwarded_tcpip(
+        &mut self,
+        channel: Channel<client::Msg>,
+        connected_address: &str,
+        connected_port: u32,
+        _originator_address: &str,
+        _originator_port: u32,
+        _session: &mut client::Session,
+    ) -> Result<(), Self::Error> {
+        let target = self
+            .remote_targets
+            .lock()
+            .get(&(connected_address.to_string(), connected_port))
+            .cloned();
+        match target {
+            Some(target) => tunnel::serve_forwarded(channel, target),
+            None => {
+                tracing::warn!(
+                    "Server forwarded {}:{}, which no tunnel asked for",
+                    connected_address,
+                    connected_port
+                );
+                tokio::spawn(async move {
+                    let _ = channel.close().await;
+                });
+            }
+        }
+        Ok(())
+    }
+
+    async fn auth_banner(
+        &mut self,
+        banner: &str,
+        _session: &mut client::Session,
+    ) -> Result<(), Self::Error> {
+        let mut stored = self.auth_banner.lock();
+        stored.get_or_insert_with(String::new).push_str(banner);
+        Ok(())
+    }
+
     async fn data(
         &mut self,
         _channel: ChannelId,
@@ -167,16 +436,35 @@ impl Handler for ClientHandler {
 pub struct SshClient {
     pub id: Uuid,
     pub config: SshConfig,
-    session: Option<Handle<ClientHandler>>,
+    /// Stored host the session was opened for, when it came from one
+    pub host_id: Option<Uuid>,
+    /// Shared with tunnel tasks; only requesting a remote forward needs write access
+    session: Option<Arc<RwLock<Handle<ClientHandler>>>>,
+    /// Connected jump hosts, outermost first; kept open for the session
+    jumps: Vec<SshClient>,
+    tunnels: Tunnels,
     output_tx: Option<mpsc::Sender<TerminalOutput>>,
+    connected_addr: Option<SocketAddr>,
+    state_listener: Option<StateListener>,
+    auth_banner: Arc<parking_lot::Mutex<Option<String>>>,
+    keepalive_lost: Arc<AtomicBool>,
+    active_execs: parking_lot::Mutex<HashMap<Uuid, CancelSender>>,
+    /// Login shell, probed on first use
+    shell: tokio::sync::OnceCell<ShellKind>,
+    /// Remote system details, probed on first use
+    remote_info: tokio::sync::OnceCell<RemoteInfo>,
 }
 
+/// Cancels a running command; cancelling hands back the output received so far
+type CancelSender = oneshot::Sender<oneshot::Sender<CommandOutput>>;
+
 impl std::fmt::Debug for SshClient {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("SshClient")
             .field("id", &self.id)
             .field("config", &self.config)
             .field("connected", &self.session.is_some())
+            .field("connected_addr", &self.connected_addr)
             .finish()
     }
 }
@@ -187,8 +475,18 @@ impl SshClient {
         Self {
             id: Uuid::new_v4(),
             config,
+            host_id: None,
             session: None,
+            jumps: Vec::new(),
+            tunnels: Tunnels::default(),
             output_tx: None,
+            connected_addr: None,
+            state_listener: None,
+            auth_banner: Arc::default(),
+            keepalive_lost: Arc::default(),
+            active_execs: parking_lot::Mutex::default(),
+            shell: tokio::sync::OnceCell::new(),
+            remote_info: tokio::sync::OnceCell::new(),
         }
     }
 
@@ -197,40 +495,180 @@ impl SshClient {
         self.output_tx = Some(tx);
     }
 
+    /// Be notified of each stage while connecting
+    pub fn set_state_listener(&mut self, listener: StateListener) {
+        self.state_listener = Some(listener);
+    }
+
+    fn report_state(&self, state: ConnectionState) {
+        if let Some(listener) = &self.state_listener {
+            listener(state, None);
+        }
+    }
+
     /// Check if connected
     pub fn is_connected(&self) -> bool {
-        self.session.is_some()
+        self.session.is_some() && !self.lost_keepalive()
+    }
+
+    /// Whether the connection was dropped because keepalives went unanswered
+    pub fn lost_keepalive(&self) -> bool {
+        self.keepalive_lost.load(Ordering::Relaxed)
+    }
+
+    /// Flag behind `lost_keepalive`, for tasks that outlive a borrow of the client
+    pub fn keepalive_flag(&self) -> Arc<AtomicBool> {
+        self.keepalive_lost.clone()
+    }
+
+    /// Whether the transport behind this client has shut down
+    pub fn is_closed(&self) -> bool {
+        match &self.session {
+            // Only busy while a remote forward is being requested
+            Some(session) => session.try_read().is_ok_and(|s| s.is_closed()),
+            None => true,
+        }
+    }
+
+    /// Check that the server still answers by opening and closing a channel
+    pub async fn probe(&self, timeout: Duration) -> bool {
+        self.measure_latency(timeout).await.is_ok()
+    }
+
+    /// Banner the server sent during authentication, if any
+    pub fn auth_banner(&self) -> Option<String> {
+        self.auth_banner.lock().clone()
+    }
+
+    /// Address the current connection was established to
+    pub fn connected_addr(&self) -> Option<SocketAddr> {
+        self.connected_addr
     }
 
-    /// Connect to the SSH server
+    /// Connect to the SSH server, through its jump hosts if it has any
     pub async fn connect(&mut self) -> Result<(), SshError> {
-        tracing::info!("Connecting to {}:{}", self.config.host, self.config.port);
+        let mut jumps: Vec<SshClient> = Vec::new();
+        for (index, hop) in self.config.jump_hosts.iter().enumerate() {
+            let mut client = SshClient::new(SshConfig {
+                jump_hosts: Vec::new(),
+                ..hop.clone()
+            });
+            if let Err(e) = client.connect_via(jumps.last()).await {
+                disconnect_all(jumps).await;
+                return Err(SshError::JumpHostFailed {
+                    hop: index + 1,
+                    host: hop.host.clone(),
+                    source: Box::new(e),
+                });
+            }
+            jumps.push(client);
+        }
 
-        let config = Arc::new(Config {
+        if let Err(e) = self.connect_via(jumps.last()).await {
+            disconnect_all(jumps).await;
+            return Err(e);
+        }
+        self.jumps = jumps;
+        Ok(())
+    }
+
+    /// Connect directly, or through a channel of the previous hop
+    async fn connect_via(&mut self, via: Option<&SshClient>) -> Result<(), SshError> {
+        match (via, self.config.transport.describe()) {
+            (Some(via), _) => tracing::info!(
+                "Connecting to {}:{} through {}",
+                self.config.host,
+                self.config.port,
+                via.config.host
+            ),
+            (None, Some(target)) => {
+                tracing::info!("Connecting to {} via {}", self.config.host, target)
+            }
+            (None, None) => {
+                tracing::info!("Connecting to {}:{}", self.config.host, self.config.port)
+            }
+        }
+
+        let mut config = Config {
             inactivity_timeout: Some(std::time::Duration::from_secs(
-                self.config.timeout_seconds * 2,
+                self.config.inactivity_timeout_seconds(),
             )),
             ..Default::default()
-        });
+        };
+        if let Some(profile) = &self.config.security {
+            config.preferred = profile.preferred()?;
+            config.keepalive_interval = profile.keepalive_interval();
+            config.keepalive_max = profile.keepalive_max;
+        }
+        if let Some(seconds) = self.config.keepalive_seconds.filter(|s| *s > 0) {
+            config.keepalive_interval = Some(Duration::from_secs(seconds));
+            config.keepalive_max = self.config.keepalive_max;
+        }
+        let config = Arc::new(config);
 
-        let handler = if let Some(tx) = self.output_tx.clone() {
+        let mut handler = if let Some(tx) = self.output_tx.clone() {
             ClientHandler::with_output(tx)
         } else {
             ClientHandler::new()
         };
+        *self.auth_banner.lock() = None;
+        handler.auth_banner = self.auth_banner.clone();
+        handler.target = (self.config.host.clone(), self.config.port);
+        self.keepalive_lost.store(false, Ordering::Relaxed);
+        handler.keepalive_lost = self.keepalive_lost.clone();
+        handler.state_listener = self.state_listener.clone();
+        handler.remote_targets = self.tunnels.remote_targets();
 
-        let addr = format!("{}:{}", self.config.host, self.config.port);
-
-        let connect_future = client::connect(config, &addr, handler);
         let timeout = std::time::Duration::from_secs(self.config.timeout_seconds);
 
-        let mut session = match tokio::time::timeout(timeout, connect_future).await {
-            Ok(Ok(session)) => session,
-            Ok(Err(e)) => return Err(SshError::ConnectionFailed(e.to_string())),
-            Err(_) => return Err(SshError::Timeout),
+        let (mut session, addr) = match (via, &self.config.transport) {
+            (Some(via), _) => {
+                self.report_state(ConnectionState::Connecting);
+                let channel = tokio::time::timeout(
+                    timeout,
+                    via.open_direct_tcpip(&self.config.host, self.config.port),
+                )
+                .await
+                .map_err(|_| SshError::Timeout)??;
+                (
+                    handshake(config, channel.into_stream(), handler, timeout).await?,
+                    None,
+                )
+            }
+            (None, Transport::Tcp) => {
+                self.report_state(ConnectionState::Resolving);
+                let addrs = resolve::resolve(
+                    &self.config.host,
+                    self.config.port,
+                    self.config.address_family,
+                )
+                .await?;
+
+                self.report_state(ConnectionState::Connecting);
+                let (stream, addr) = resolve::connect_tcp(&addrs, timeout).await?;
+                (
+                    handshake(config, stream, handler, timeout).await?,
+                    Some(addr),
+                )
+            }
+            (None, Transport::UnixSocket { path }) => {
+                self.report_state(ConnectionState::Connecting);
+                let stream = tokio::time::timeout(timeout, transport::connect_unix(path))
+                    .await
+                    .map_err(|_| SshError::Timeout)??;
+                (handshake(config, stream, handler, timeout).await?, None)
+            }
+            (None, Transport::NamedPipe { path }) => {
+                self.report_state(ConnectionState::Connecting);
+                let stream = tokio::time::timeout(timeout, transport::connect_pipe(path))
+                    .await
+                    .map_err(|_| SshError::Timeout)??;
+                (handshake(config, stream, handler, timeout).await?, None)
+            }
         };
 
         // Authenticate
+        self.report_state(ConnectionState::Authenticating);
         let authenticated = match &self.config.auth_method {
             AuthMethod::Password(password) => {
                 session
@@ -241,7 +679,9 @@ impl SshClient {
                 key_path,
                 passphrase,
             } => {
-                let key = if let Some(pass) = passphrase {
+                let key = if key_path.to_lowercase().ends_with(".ppk") {
+                    ppk::load_ppk(key_path, passphrase.as_deref())?
+                } else if let Some(pass) = passphrase {
                     russh_keys::load_secret_key(key_path, Some(pass))?
                 } else {
                     russh_keys::load_secret_key(key_path, None)?
@@ -250,40 +690,329 @@ impl SshClient {
                     .authenticate_publickey(&self.config.username, Arc::new(key))
                     .await?
             }
-            AuthMethod::Agent => {
-                // TODO: Implement SSH agent authentication
-                return Err(SshError::AuthenticationFailed);
-            }
+            AuthMethod::Agent => agent::authenticate(&mut session, &self.config.username).await?,
         };
 
         if !authenticated {
             return Err(SshError::AuthenticationFailed);
         }
 
-        tracing::info!("Successfully connected to {}", self.config.host);
-        self.session = Some(session);
+        let target = match addr {
+            Some(addr) => addr.to_string(),
+            None => self.config.transport.describe().unwrap_or_default(),
+        };
+        tracing::info!(
+            "Successfully connected to {} ({})",
+            self.config.host,
+            target
+        );
+        self.session = Some(Arc::new(RwLock::new(session)));
+        self.connected_addr = addr;
         Ok(())
     }
 
+    /// The login shell that runs exec commands, probed once per connection
+    pub async fn shell_kind(&self) -> Result<ShellKind, SshError> {
+        self.shell
+            .get_or_try_init(|| async {
+                let output = self.execute(shell::PROBE_COMMAND).await?;
+                let kind = ShellKind::from_probe(&output.stdout, &output.stderr);
+                tracing::info!(
+                    "{} runs exec commands with a {} shell",
+                    self.config.host,
+                    kind.name()
+                );
+                Ok(kind)
+            })
+            .await
+            .copied()
+    }
+
+    /// OS, distribution and shell of the server, probed once per connection
+    ///
+    /// Servers the probe doesn't work on are reported as `unknown`.
+    pub async fn remote_info(&self) -> Result<RemoteInfo, SshError> {
+        self.remote_info
+            .get_or_try_init(|| async {
+                let output = self.execute(remote_info::PROBE_COMMAND).await?;
+                let info = RemoteInfo::from_probe(&output.stdout);
+                tracing::info!(
+                    "{} runs {}",
+                    self.config.host,
+                    info.distro.as_deref().unwrap_or(&info.os)
+                );
+                Ok(info)
+            })
+            .await
+            .cloned()
+    }
+
+    /// Adapt a POSIX `sh` script to the server's login shell
+    ///
+    /// Fails with `UnsupportedShell` where the script can't run safely.
+    pub async fn posix_command(&self, script: &str) -> Result<String, SshError> {
+        self.shell_kind().await?.adapt(script)
+    }
+
     /// Execute a single command (non-interactive)
-    pub async fn execute(&mut self, command: &str) -> Result<CommandOutput, SshError> {
-        let session = self.session.as_mut().ok_or(SshError::NotConnected)?;
+    pub async fn execute(&self, command: &str) -> Result<CommandOutput, SshError> {
+        self.execute_with_input(command, &[]).await
+    }
+
+    /// Execute a command, writing `input` to its stdin before closing it
+    pub async fn execute_with_input(
+        &self,
+        command: &str,
+        input: &[u8],
+    ) -> Result<CommandOutput, SshError> {
+        self.run_command(command, input, false, None, None, |_, _| {})
+            .await
+    }
+
+    /// Execute a command, stopping it once `timeout` has passed
+    ///
+    /// A command still running at the deadline gets SIGKILL and its channel
+    /// is closed; the error carries the output received until then.
+    pub async fn execute_with_timeout(
+        &self,
+        command: &str,
+        timeout: Duration,
+    ) -> Result<CommandOutput, SshError> {
+        self.run_command(command, &[], false, None, Some(timeout), |_, _| {})
+            .await
+    }
 
-        let mut channel = session.channel_open_session().await?;
+    /// Execute a command, passing output to `on_data` as it arrives
+    ///
+    /// The returned output still holds everything that was received.
+    pub async fn execute_with_callback<F>(
+        &self,
+        command: &str,
+        input: &[u8],
+        on_data: F,
+    ) -> Result<CommandOutput, SshError>
+    where
+        F: FnMut(OutputStream, &[u8]) + Send,
+    {
+        self.run_command(command, input, false, None, None, on_data)
+            .await
+    }
+
+    /// Execute a command, sending its output to `tx` as it arrives
+    ///
+    /// Sends `Stdout`/`Stderr` chunks, never splitting a character, and a
+    /// final `Exit`. Nothing is buffered, so this suits commands that run
+    /// indefinitely; the command is closed once `tx`'s receiver goes away.
+    pub async fn execute_streaming(
+        &self,
+        command: &str,
+        tx: mpsc::Sender<TerminalOutput>,
+    ) -> Result<i32, SshError> {
+        let mut channel = self.open_channel().await?;
         channel.exec(true, command).await?;
+        channel.eof().await?;
 
         let mut stdout = Vec::new();
         let mut stderr = Vec::new();
         let mut exit_code = 0;
+        // The exit status may follow EOF, so read on until it or the close
+        let (mut exited, mut eof) = (false, false);
+        loop {
+            let output = match channel.wait().await {
+                Some(russh::ChannelMsg::Data { data }) => {
+                    stdout.extend_from_slice(&data);
+                    script::take_complete(&mut stdout).map(TerminalOutput::Stdout)
+                }
+                Some(russh::ChannelMsg::ExtendedData { data, ext: 1 }) => {
+                    stderr.extend_from_slice(&data);
+                    script::take_complete(&mut stderr).map(TerminalOutput::Stderr)
+                }
+                Some(russh::ChannelMsg::ExitStatus { exit_status }) => {
+                    exit_code = exit_status as i32;
+                    exited = true;
+                    if eof {
+                        break;
+                    }
+                    None
+                }
+                Some(russh::ChannelMsg::Eof) => {
+                    eof = true;
+                    if exited {
+                        break;
+                    }
+                    None
+                }
+                Some(russh::ChannelMsg::Close) | None => break,
+                _ => None,
+            };
+            if let Some(output) = output {
+                if tx.send(output).await.is_err() {
+                    tracing::debug!("Output receiver dropped, closing command");
+                    let _ = channel.close().await;
+                    return Ok(exit_code);
+                }
+            }
+        }
 
+        // Whatever is left can't be completed any more
+        if !stdout.is_empty() {
+            let text = String::from_utf8_lossy(&stdout).to_string();
+            let _ = tx.send(TerminalOutput::Stdout(text)).await;
+        }
+        if !stderr.is_empty() {
+            let text = String::from_utf8_lossy(&stderr).to_string();
+            let _ = tx.send(TerminalOutput::Stderr(text)).await;
+        }
+        let _ = tx.send(TerminalOutput::Exit(exit_code)).await;
+        Ok(exit_code)
+    }
+
+    /// Execute a command, also recording stdout and stderr in arrival order
+    ///
+    /// The split `stdout`/`stderr` fields are filled as usual; `chunks`
+    /// additionally shows how the two streams were interleaved.
+    pub async fn execute_interleaved(&self, command: &str) -> Result<CommandOutput, SshError> {
+        self.run_command(command, &[], true, None, None, |_, _| {})
+            .await
+    }
+
+    /// Execute a command that `cancel_exec` can interrupt by `id`
+    ///
+    /// Any number of cancellable commands can run at once; `id` must not be
+    /// in use by another one. With `timeout`, the command is stopped like in
+    /// `execute_with_timeout`.
+    pub async fn execute_cancellable(
+        &self,
+        id: Uuid,
+        command: &str,
+        interleaved: bool,
+        timeout: Option<Duration>,
+    ) -> Result<CommandOutput, SshError> {
+        let (cancel, cancelled) = oneshot::channel();
+        match self.active_execs.lock().entry(id) {
+            std::collections::hash_map::Entry::Occupied(_) => {
+                return Err(SshError::CommandFailed(format!(
+                    "Command {} is already running",
+                    id
+                )));
+            }
+            std::collections::hash_map::Entry::Vacant(slot) => {
+                slot.insert(cancel);
+            }
+        }
+
+        let result = self
+            .run_command(
+                command,
+                &[],
+                interleaved,
+                Some(cancelled),
+                timeout,
+                |_, _| {},
+            )
+            .await;
+
+        self.active_execs.lock().remove(&id);
+        result
+    }
+
+    /// Interrupt the cancellable command started with `id`
+    ///
+    /// Sends SIGINT, then SIGKILL and closes the channel if the command
+    /// hasn't exited shortly after. Returns the output received so far, or
+    /// `None` when no such command was running.
+    ///
+    /// Commands run without a PTY, so closing the channel sends them no
+    /// SIGHUP; on servers that ignore signal requests (OpenSSH before 7.9)
+    /// the remote process keeps running until it next writes output.
+    pub async fn cancel_exec(&self, id: Uuid) -> Option<CommandOutput> {
+        let cancel = self.active_execs.lock().remove(&id)?;
+        let (reply, output) = oneshot::channel();
+        cancel.send(reply).ok()?;
+        output.await.ok()
+    }
+
+    async fn run_command<F>(
+        &self,
+        command: &str,
+        input: &[u8],
+        interleaved: bool,
+        mut cancel: Option<oneshot::Receiver<oneshot::Sender<CommandOutput>>>,
+        timeout: Option<Duration>,
+        mut on_data: F,
+    ) -> Result<CommandOutput, SshError>
+    where
+        F: FnMut(OutputStream, &[u8]) + Send,
+    {
+        let deadline = timeout.map(|t| tokio::time::Instant::now() + t);
+        let mut channel = self.open_channel().await?;
+        channel.exec(true, command).await?;
+
+        if !input.is_empty() {
+            channel.data(input).await?;
+        }
+        channel.eof().await?;
+
+        let mut stdout = Vec::new();
+        let mut stderr = Vec::new();
+        let mut exit_code = 0;
+        let mut runs: Vec<(OutputStream, Vec<u8>)> = Vec::new();
+        let mut record = |stream: OutputStream, data: &[u8]| {
+            if !interleaved {
+                return;
+            }
+            match runs.last_mut() {
+                Some((last, buf)) if *last == stream => buf.extend_from_slice(data),
+                _ => runs.push((stream, data.to_vec())),
+            }
+        };
+
+        let mut reply = None;
+        let mut timed_out = false;
         loop {
-            match channel.wait().await {
+            let msg = match cancel.as_mut() {
+                Some(rx) if reply.is_none() => tokio::select! {
+                    msg = wait_until(&mut channel, deadline) => msg,
+                    Ok(tx) = rx => {
+                        reply = Some(tx);
+                        if let Err(e) = channel.signal(Sig::INT).await {
+                            tracing::debug!("Sending SIGINT failed: {}", e);
+                        }
+                        continue;
+                    }
+                },
+                // Give the command a moment to exit on SIGINT before killing it
+                Some(_) => match tokio::time::timeout(CANCEL_GRACE, channel.wait()).await {
+                    Ok(msg) => Ok(msg),
+                    Err(_) => {
+                        if let Err(e) = channel.signal(Sig::KILL).await {
+                            tracing::debug!("Sending SIGKILL failed: {}", e);
+                        }
+                        let _ = channel.close().await;
+                        break;
+                    }
+                },
+                None => wait_until(&mut channel, deadline).await,
+            };
+            let Ok(msg) = msg else {
+                timed_out = true;
+                if let Err(e) = channel.signal(Sig::KILL).await {
+                    tracing::debug!("Sending SIGKILL failed: {}", e);
+                }
+                let _ = channel.close().await;
+                break;
+            };
+            match msg {
                 Some(russh::ChannelMsg::Data { data }) => {
                     stdout.extend_from_slice(&data);
+                    record(OutputStream::Stdout, &data);
+                    on_data(OutputStream::Stdout, &data);
                 }
                 Some(russh::ChannelMsg::ExtendedData { data, ext }) => {
                     if ext == 1 {
                         stderr.extend_from_slice(&data);
+                        record(OutputStream::Stderr, &data);
+                        on_data(OutputStream::Stderr, &data);
                     }
                 }
                 Some(russh::ChannelMsg::ExitStatus { exit_status }) => {
@@ -296,40 +1025,120 @@ impl SshClient {
             }
         }
 
-        Ok(CommandOutput {
+        let output = CommandOutput {
             stdout: String::from_utf8_lossy(&stdout).to_string(),
             stderr: String::from_utf8_lossy(&stderr).to_string(),
             exit_code,
-        })
+            chunks: runs
+                .into_iter()
+                .enumerate()
+                .map(|(seq, (stream, data))| OutputChunk {
+                    seq: seq as u64,
+                    stream,
+                    data: String::from_utf8_lossy(&data).to_string(),
+                })
+                .collect(),
+            cancelled: reply.is_some(),
+        };
+        if let Some(reply) = reply {
+            let _ = reply.send(output.clone());
+        }
+        if timed_out {
+            return Err(SshError::CommandTimeout {
+                seconds: timeout.unwrap_or_default().as_secs(),
+                output: Box::new(output),
+            });
+        }
+        Ok(output)
     }
 
     /// Open an interactive shell session and return the channel
+    ///
+    /// `config.env` is set on the channel first. Many servers only accept a
+    /// few variables (`AcceptEnv`); the refused ones are returned so the
+    /// caller can export them in the shell instead.
     pub async fn open_shell(
-        &mut self,
+        &self,
         cols: u32,
         rows: u32,
-    ) -> Result<russh::Channel<client::Msg>, SshError> {
-        let session = self.session.as_mut().ok_or(SshError::NotConnected)?;
-
-        let channel = session.channel_open_session().await?;
+    ) -> Result<(russh::Channel<client::Msg>, Vec<(String, String)>), SshError> {
+        let mut channel = self.open_channel().await?;
 
         // Request PTY
         channel
             .request_pty(false, "xterm-256color", cols, rows, 0, 0, &[])
             .await?;
 
+        let mut env: Vec<_> = self.config.env.iter().collect();
+        env.sort();
+        let mut refused = Vec::new();
+        for (name, value) in env {
+            channel.set_env(true, name.as_str(), value.as_str()).await?;
+            let accepted = tokio::time::timeout(ENV_REPLY_TIMEOUT, async {
+                loop {
+                    match channel.wait().await {
+                        Some(russh::ChannelMsg::Success) => return true,
+                        Some(russh::ChannelMsg::Failure) | None => return false,
+                        Some(_) => {}
+                    }
+                }
+            })
+            .await
+            .unwrap_or(false);
+            if !accepted {
+                refused.push((name.clone(), value.clone()));
+            }
+        }
+        if !refused.is_empty() {
+            tracing::debug!(
+                "{} refused {} environment variables",
+                self.config.host,
+                refused.len()
+            );
+        }
+
         // Request shell
         channel.request_shell(false).await?;
 
         tracing::info!("Shell opened for {}", self.config.host);
-        Ok(channel)
+        Ok((channel, refused))
     }
 
-    /// Open an SFTP channel
-    pub async fn open_sftp_channel(&mut self) -> Result<russh::Channel<client::Msg>, SshError> {
-        let session = self.session.as_mut().ok_or(SshError::NotConnected)?;
+    /// Run a command through a PTY shell, using `detector` to tell when it is done
+    ///
+    /// For programs that behave differently without a terminal. Prefer
+    /// `PromptDetector::marker()`; regex detection is a fallback for shells
+    /// where the marker cannot be injected.
+    pub async fn execute_interactive(
+        &self,
+        command: &str,
+        mut detector: PromptDetector,
+        timeout: Duration,
+    ) -> Result<InteractiveOutput, SshError> {
+        let (mut channel, _) = self.open_shell(200, 50).await?;
+
+        let result = tokio::time::timeout(timeout, async {
+            // Skip past login banners and the first prompt
+            if let Some(sync) = detector.sync_command() {
+                channel.data(sync.as_bytes()).await?;
+            }
+            read_until_complete(&mut channel, &mut detector).await?;
 
-        let channel = session.channel_open_session().await?;
+            let line = detector.wrap(command);
+            channel.data(line.as_bytes()).await?;
+            read_until_complete(&mut channel, &mut detector).await
+        })
+        .await;
+
+        let _ = channel.eof().await;
+        let _ = channel.close().await;
+
+        result.map_err(|_| SshError::Timeout)?
+    }
+
+    /// Open an SFTP channel
+    pub async fn open_sftp_channel(&self) -> Result<russh::Channel<client::Msg>, SshError> {
+        let channel = self.open_channel().await?;
 
         // Request SFTP subsystem
         channel.request_subsystem(true, "sftp").await?;
@@ -338,23 +1147,135 @@ impl SshClient {
         Ok(channel)
     }
 
+    /// Open a channel to `host:port` as seen from the server
+    async fn open_direct_tcpip(
+        &self,
+        host: &str,
+        port: u16,
+    ) -> Result<Channel<client::Msg>, SshError> {
+        let session = self.session.as_ref().ok_or(SshError::NotConnected)?;
+        let channel = session
+            .read()
+            .await
+            .channel_open_direct_tcpip(host, port as u32, "127.0.0.1", 0)
+            .await?;
+        Ok(channel)
+    }
+
+    /// Open a session channel without holding on to the connection
+    async fn open_channel(&self) -> Result<Channel<client::Msg>, SshError> {
+        let session = self.session.as_ref().ok_or(SshError::NotConnected)?;
+        let channel = session.read().await.channel_open_session().await?;
+        Ok(channel)
+    }
+
+    /// Forward connections to `local_bind` on to `remote_host`, like `ssh -L`
+    pub async fn open_local_forward(
+        &self,
+        local_bind: &str,
+        remote_host: &str,
+        remote_port: u16,
+    ) -> Result<Tunnel, SshError> {
+        let session = self.session.as_ref().ok_or(SshError::NotConnected)?;
+        let listener = tokio::net::TcpListener::bind(local_bind).await?;
+        let tunnel = self.tunnels.add_local(
+            listener,
+            Arc::downgrade(session),
+            (remote_host.to_string(), remote_port),
+        )?;
+        tracing::info!(
+            "Tunnel {} -> {} via {}",
+            tunnel.bind_addr,
+            tunnel.target,
+            self.config.host
+        );
+        Ok(tunnel)
+    }
+
+    /// Have the server listen on `remote_bind` and forward here, like `ssh -R`
+    ///
+    /// Port 0 lets the server pick one; the tunnel's `bind_addr` has the result.
+    pub async fn open_remote_forward(
+        &self,
+        remote_bind: &str,
+        local_host: &str,
+        local_port: u16,
+    ) -> Result<Tunnel, SshError> {
+        let session = self.session.as_ref().ok_or(SshError::NotConnected)?;
+        let (address, port) = tunnel::split_host_port(remote_bind)?;
+        let assigned = session
+            .write()
+            .await
+            .tcpip_forward(address.as_str(), port as u32)
+            .await?;
+        let port = if port == 0 { assigned } else { port as u32 };
+        let tunnel = self
+            .tunnels
+            .add_remote(address, port, (local_host.to_string(), local_port));
+        tracing::info!(
+            "Tunnel {} on {} -> {}",
+            tunnel.bind_addr,
+            self.config.host,
+            tunnel.target
+        );
+        Ok(tunnel)
+    }
+
+    /// Tunnels open on this connection
+    pub fn tunnels(&self) -> Vec<Tunnel> {
+        self.tunnels.list()
+    }
+
+    /// Close a tunnel; returns whether it existed
+    pub async fn close_tunnel(&self, id: uuid::Uuid) -> Result<bool, SshError> {
+        let Some(remote) = self.tunnels.remove(id) else {
+            return Ok(false);
+        };
+        if let (Some((address, port)), Some(session)) = (remote, &self.session) {
+            session
+                .read()
+                .await
+                .cancel_tcpip_forward(address.as_str(), port)
+                .await?;
+        }
+        tracing::info!("Closed tunnel {}", id);
+        Ok(true)
+    }
+
     /// Disconnect from the SSH server
     pub async fn disconnect(&mut self) -> Result<(), SshError> {
+        for id in self.tunnels.ids() {
+            // The connection is going away anyway, so a refused cancel doesn't matter
+            let _ = self.close_tunnel(id).await;
+        }
         if let Some(session) = self.session.take() {
             session
+                .read()
+                .await
                 .disconnect(Disconnect::ByApplication, "User disconnected", "en")
                 .await?;
         }
+        disconnect_all(std::mem::take(&mut self.jumps)).await;
+        self.connected_addr = None;
 
         tracing::info!("Disconnected from {}", self.config.host);
         Ok(())
     }
 
     /// Measure connection latency (ping)
-    pub async fn measure_latency(&mut self) -> Result<u32, SshError> {
+    ///
+    /// Times a channel open/close round trip. Nothing runs on the server, so
+    /// interactive shells on the same connection never see it.
+    pub async fn measure_latency(&self, timeout: Duration) -> Result<u32, SshError> {
+        if self.session.is_none() {
+            return Err(SshError::NotConnected);
+        }
         let start = std::time::Instant::now();
-        let _ = self.execute("echo ping").await?;
+        let channel = tokio::time::timeout(timeout, self.open_channel())
+            .await
+            .map_err(|_| SshError::Timeout)??;
         let latency = start.elapsed().as_millis() as u32;
+        let _ = channel.close().await;
         Ok(latency)
     }
 }
@@ -366,3 +1287,65 @@ impl Drop for SshClient {
         }
     }
 }
+
+/// Close jump host connections, innermost first
+async fn disconnect_all(jumps: Vec<SshClient>) {
+    for mut hop in jumps.into_iter().rev() {
+        // Boxed: `disconnect` calls back into here for its own jump hosts
+        if let Err(e) = Box::pin(hop.disconnect()).await {
+            tracing::debug!("Closing jump host {} failed: {}", hop.config.host, e);
+        }
+    }
+}
+
+/// Run the SSH handshake over an established stream
+async fn handshake<S>(
+    config: Arc<Config>,
+    stream: S,
+    handler: ClientHandler,
+    timeout: Duration,
+) -> Result<Handle<ClientHandler>, SshError>
+where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + 'static,
+{
+    match tokio::time::timeout(timeout, client::connect_stream(config, stream, handler)).await {
+        Ok(Ok(session)) => Ok(session),
+        Ok(Err(e @ (SshError::HostKeyUnknown(_) | SshError::HostKeyChanged(_)))) => Err(e),
+        Ok(Err(e)) => Err(SshError::ConnectionFailed(e.to_string())),
+        Err(_) => Err(SshError::Timeout),
+    }
+}
+
+/// Feed shell output to the detector until it reports completion
+async fn read_until_complete(
+    channel: &mut russh::Channel<client::Msg>,
+    detector: &mut PromptDetector,
+) -> Result<InteractiveOutput, SshError> {
+    loop {
+        match channel.wait().await {
+            Some(russh::ChannelMsg::Data { data })
+            | Some(russh::ChannelMsg::ExtendedData { data, .. }) => {
+                if let Some(done) = detector.feed(&String::from_utf8_lossy(&data)) {
+                    return Ok(done);
+                }
+            }
+            Some(russh::ChannelMsg::Eof) | Some(russh::ChannelMsg::Close) | None => {
+                return Err(SshError::ChannelError(
+                    "Shell closed before the command completed".to_string(),
+                ));
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Next channel message, or `Elapsed` once `deadline` has passed
+async fn wait_until(
+    channel: &mut russh::Channel<client::Msg>,
+    deadline: Option<tokio::time::Instant>,
+) -> Result<Option<russh::ChannelMsg>, tokio::time::error::Elapsed> {
+    match deadline {
+        Some(deadline) => tokio::time::timeout_at(deadline, channel.wait()).await,
+        None => Ok(channel.wait().await),
+    }
+}
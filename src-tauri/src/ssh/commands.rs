@@ -1,48 +1,333 @@
 //! SSH Tauri Commands
 
-use super::{clients, SshClient, SshConfig, SshError, CommandOutput};
+use super::{
+    client_for_session, clients, tunnel, CommandOutput, ConnectionReason, ConnectionState,
+    HostFingerprint, InteractiveOutput, KeyInstallOutcome, PromptDetector, RemoteInfo, ShellKind,
+    SshClient, SshConfig, SshError, TerminalOutput, Tunnel, TunnelKind,
+};
+use crate::audit;
+use crate::plugins::{self, CommandContext, HookEvent, SessionContext};
+use crate::utils::{emit_app_event, AppEvent, AuditAction};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::mpsc;
 use uuid::Uuid;
 
 /// Connect to an SSH server
 #[tauri::command]
-pub async fn connect(config: SshConfig) -> Result<Uuid, SshError> {
+pub async fn connect(app: AppHandle, config: SshConfig) -> Result<Uuid, SshError> {
     let mut client = SshClient::new(config);
-    client.connect().await?;
-
     let id = client.id;
-    clients().write().insert(id, client);
+
+    let listener_app = app.clone();
+    client.set_state_listener(Arc::new(move |state, reason| {
+        emit_app_event(
+            &listener_app,
+            AppEvent::connection_state(None, id, state, reason),
+        );
+    }));
+
+    if let Err(e) = client.connect().await {
+        let reason = ConnectionReason::from_error(&e);
+        emit_app_event(
+            &app,
+            AppEvent::connection_state(None, id, ConnectionState::Failed, Some(reason)),
+        );
+        return Err(e);
+    }
+
+    audit::log_action(
+        AuditAction::Connect,
+        format!(
+            "{}@{}:{}",
+            client.config.username, client.config.host, client.config.port
+        ),
+        Some(id),
+    );
+    let context = SessionContext {
+        session_id: id,
+        host: client.config.host.clone(),
+        port: client.config.port,
+        username: client.config.username.clone(),
+    };
+    clients().write().insert(id, Arc::new(client));
+    emit_app_event(
+        &app,
+        AppEvent::connection_state(None, id, ConnectionState::Connected, None),
+    );
+    plugins::dispatch(HookEvent::SessionConnect(context));
 
     Ok(id)
 }
 
 /// Disconnect from an SSH server
 #[tauri::command]
-pub async fn disconnect(session_id: Uuid) -> Result<(), SshError> {
+pub async fn disconnect(app: AppHandle, session_id: Uuid) -> Result<(), SshError> {
     // Remove client from map first, then disconnect
     // This avoids holding the lock across await
     let client = clients().write().remove(&session_id);
 
-    if let Some(mut client) = client {
-        client.disconnect().await?;
+    if let Some(client) = client {
+        audit::log_action(
+            AuditAction::Disconnect,
+            format!(
+                "{}@{}:{}",
+                client.config.username, client.config.host, client.config.port
+            ),
+            Some(session_id),
+        );
+        // Only tear down the connection if nobody else is using it
+        match Arc::try_unwrap(client) {
+            Ok(mut client) => client.disconnect().await?,
+            Err(client) => tracing::info!(
+                "Connection of session {} is still shared by {} users; leaving it open",
+                session_id,
+                Arc::strong_count(&client) - 1
+            ),
+        }
+        emit_app_event(
+            &app,
+            AppEvent::connection_state(
+                None,
+                session_id,
+                ConnectionState::Disconnected,
+                Some(ConnectionReason::UserRequested),
+            ),
+        );
     }
 
     Ok(())
 }
 
 /// Send a command to the SSH server
+///
+/// With `interleaved`, the output also carries both streams in arrival order.
+/// A command still running after `timeout_seconds` is killed; the `timeout`
+/// error then carries the output received so far. Passing an `exec_id`
+/// lets `cancel_command` interrupt the command while it runs.
 #[tauri::command]
-pub async fn send_command(session_id: Uuid, command: String) -> Result<CommandOutput, SshError> {
-    // Take client out, execute, then put back
-    // This avoids holding lock across await
-    let mut client = clients()
-        .write()
-        .remove(&session_id)
+pub async fn send_command(
+    session_id: Uuid,
+    command: String,
+    interleaved: Option<bool>,
+    timeout_seconds: Option<u64>,
+    exec_id: Option<Uuid>,
+) -> Result<CommandOutput, SshError> {
+    // Clone the handle out so the lock isn't held across await
+    let client = clients()
+        .read()
+        .get(&session_id)
+        .cloned()
         .ok_or(SshError::NotConnected)?;
 
-    let result = client.execute(&command).await;
+    audit::log_action(
+        AuditAction::CommandExecuted,
+        command.as_str(),
+        Some(session_id),
+    );
+    plugins::dispatch(HookEvent::Command(CommandContext::new(
+        session_id, &command,
+    )));
+    client
+        .execute_cancellable(
+            exec_id.unwrap_or_else(Uuid::new_v4),
+            &command,
+            interleaved.unwrap_or(false),
+            timeout_seconds.map(Duration::from_secs),
+        )
+        .await
+}
+
+/// Send a command and stream its output while it runs
+///
+/// Output is emitted as `TerminalOutput` on `command-output-{session_id}`,
+/// ending with `exit` (or `error` if the command could not be run). Returns
+/// the exit code.
+#[tauri::command]
+pub async fn send_command_streaming(
+    app: AppHandle,
+    session_id: Uuid,
+    command: String,
+) -> Result<i32, SshError> {
+    let client = client_for_session(session_id)?;
+    let event = format!("command-output-{}", session_id);
 
-    // Put the client back
-    clients().write().insert(session_id, client);
+    let (tx, mut rx) = mpsc::channel::<TerminalOutput>(64);
+    let forward_app = app.clone();
+    let forward_event = event.clone();
+    let forward = tokio::spawn(async move {
+        while let Some(output) = rx.recv().await {
+            let _ = forward_app.emit(&forward_event, output);
+        }
+    });
 
+    audit::log_action(
+        AuditAction::CommandExecuted,
+        command.as_str(),
+        Some(session_id),
+    );
+    plugins::dispatch(HookEvent::Command(CommandContext::new(
+        session_id, &command,
+    )));
+    let result = client.execute_streaming(&command, tx).await;
+    let _ = forward.await;
+    if let Err(e) = &result {
+        let _ = app.emit(&event, TerminalOutput::Error(e.to_string()));
+    }
     result
 }
+
+/// Interrupt the command `send_command` is running with `exec_id`
+///
+/// Returns the output received before the interrupt, or `None` when no
+/// such command was running. The pending `send_command` call returns the
+/// same output with `cancelled` set.
+#[tauri::command]
+pub async fn cancel_command(
+    session_id: Uuid,
+    exec_id: Uuid,
+) -> Result<Option<CommandOutput>, SshError> {
+    let client = client_for_session(session_id)?;
+    Ok(client.cancel_exec(exec_id).await)
+}
+
+/// Run a local script file on the server
+///
+/// Output is streamed as `OutputChunk`s on `script-output-{session_id}`;
+/// the full output and exit code are returned when it finishes.
+#[tauri::command]
+pub async fn run_local_script(
+    app: AppHandle,
+    session_id: Uuid,
+    local_path: String,
+    interpreter: Option<String>,
+    args: Option<Vec<String>>,
+) -> Result<CommandOutput, SshError> {
+    let client = client_for_session(session_id)?;
+    let interpreter = interpreter.unwrap_or_else(|| "bash".to_string());
+    let event = format!("script-output-{}", session_id);
+
+    super::script::run_local_script(
+        &client,
+        Path::new(&local_path),
+        &interpreter,
+        &args.unwrap_or_default(),
+        |chunk| {
+            let _ = app.emit(&event, chunk);
+        },
+    )
+    .await
+}
+
+/// Get the OS, distribution, kernel and shell of a session's server
+///
+/// Probed on the first call and cached for the connection.
+#[tauri::command]
+pub async fn detect_remote_info(session_id: Uuid) -> Result<RemoteInfo, SshError> {
+    client_for_session(session_id)?.remote_info().await
+}
+
+/// Run a command in a PTY shell and wait for it to finish
+///
+/// Completion is detected with a unique marker unless `prompt_pattern` is
+/// given, in which case the shell prompt is matched instead.
+#[tauri::command]
+pub async fn send_command_interactive(
+    session_id: Uuid,
+    command: String,
+    prompt_pattern: Option<String>,
+    timeout_seconds: Option<u64>,
+) -> Result<InteractiveOutput, SshError> {
+    let client = client_for_session(session_id)?;
+    let detector = match prompt_pattern {
+        Some(pattern) => PromptDetector::with_regex(&pattern)?,
+        // The PTY runs the same login shell as exec channels
+        None => PromptDetector::marker_for(client.shell_kind().await.unwrap_or(ShellKind::Posix)),
+    };
+    let timeout = Duration::from_secs(timeout_seconds.unwrap_or(30));
+
+    plugins::dispatch(HookEvent::Command(CommandContext::new(
+        session_id, &command,
+    )));
+    client
+        .execute_interactive(&command, detector, timeout)
+        .await
+}
+
+/// Detect the login shell that runs exec commands on a session
+///
+/// Exec-based helpers only run on `posix`, `fish` and `csh` shells.
+#[tauri::command]
+pub async fn detect_remote_shell(session_id: Uuid) -> Result<ShellKind, SshError> {
+    let client = client_for_session(session_id)?;
+    client.shell_kind().await
+}
+
+/// List the authentication methods a server offers
+///
+/// Sends only a "none" auth request, so no credentials are needed.
+#[tauri::command]
+pub async fn probe_auth_methods(
+    host: String,
+    port: u16,
+    username: Option<String>,
+) -> Result<Vec<String>, SshError> {
+    super::probe_auth_methods(&host, port, username.as_deref()).await
+}
+
+/// Authorize a public key for the session's user, like `ssh-copy-id`
+///
+/// Creates `~/.ssh` and `authorized_keys` if needed; a key that is already
+/// listed is left alone.
+#[tauri::command]
+pub async fn install_public_key(
+    session_id: Uuid,
+    public_key: String,
+) -> Result<KeyInstallOutcome, SshError> {
+    let client = client_for_session(session_id)?;
+    super::authorized_keys::install(&client, &public_key).await
+}
+
+/// Read a server's host key fingerprint without authenticating
+///
+/// For checking a key out of band before the first connect; the key is not
+/// added to the known hosts.
+#[tauri::command]
+pub async fn get_host_fingerprint(host: String, port: u16) -> Result<HostFingerprint, SshError> {
+    super::fingerprint::fetch(&host, port).await
+}
+
+/// Open a port forward on a session
+///
+/// `bind_addr` and `target` are `host:port`. A `local` tunnel listens on
+/// `bind_addr` here and connects to `target` from the server; a `remote`
+/// tunnel listens on the server and connects to `target` from here.
+#[tauri::command]
+pub async fn create_tunnel(
+    session_id: Uuid,
+    tunnel_type: TunnelKind,
+    bind_addr: String,
+    target: String,
+) -> Result<Tunnel, SshError> {
+    let client = client_for_session(session_id)?;
+    let (host, port) = tunnel::split_host_port(&target)?;
+    match tunnel_type {
+        TunnelKind::Local => client.open_local_forward(&bind_addr, &host, port).await,
+        TunnelKind::Remote => client.open_remote_forward(&bind_addr, &host, port).await,
+    }
+}
+
+/// Close a tunnel; returns whether it was open
+#[tauri::command]
+pub async fn close_tunnel(session_id: Uuid, tunnel_id: Uuid) -> Result<bool, SshError> {
+    let client = client_for_session(session_id)?;
+    client.close_tunnel(tunnel_id).await
+}
+
+/// Tunnels open on a session's connection
+#[tauri::command]
+pub fn list_tunnels(session_id: Uuid) -> Result<Vec<Tunnel>, SshError> {
+    Ok(client_for_session(session_id)?.tunnels())
+}
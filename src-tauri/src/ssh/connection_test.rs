@@ -0,0 +1,127 @@
+//! Testing a host's connection settings
+//!
+//! Connects and logs in with a throwaway client that is never registered
+//! in `clients()`, so no session is left behind. The client is
+//! disconnected before returning, also when a step times out.
+
+use super::{SshClient, SshConfig, SshError};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// Text echoed back to check that commands run
+const ECHO_TOKEN: &str = "nexus-connection-test";
+
+/// How long the echo may take once logged in
+const ECHO_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Outcome of a connection test
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectionTestResult {
+    /// Logged in, and the echo came back if one was run
+    pub success: bool,
+    /// The server accepted the credentials
+    pub auth_ok: bool,
+    /// Time to connect and log in
+    pub latency_ms: Option<u32>,
+    pub error: Option<String>,
+}
+
+/// Connect and log in with `config`, optionally run `echo`, then disconnect
+pub async fn test_connection(config: SshConfig, run_echo: bool) -> ConnectionTestResult {
+    // Each step has its own timeout; this bounds jump hosts and retries as a whole
+    let limit = Duration::from_secs(config.timeout_seconds.max(1) * 2);
+    let mut client = SshClient::new(config);
+
+    let started = Instant::now();
+    let connected = tokio::time::timeout(limit, client.connect())
+        .await
+        .unwrap_or(Err(SshError::Timeout));
+    let latency_ms = started.elapsed().as_millis() as u32;
+
+    if let Err(e) = connected {
+        // A timed-out connect is dropped along with its half-open session
+        return ConnectionTestResult {
+            success: false,
+            auth_ok: false,
+            latency_ms: None,
+            error: Some(e.to_string()),
+        };
+    }
+
+    let echoed = if run_echo {
+        echo(&client).await
+    } else {
+        Ok(())
+    };
+    if let Err(e) = client.disconnect().await {
+        tracing::debug!("Closing test connection failed: {}", e);
+    }
+
+    ConnectionTestResult {
+        success: echoed.is_ok(),
+        auth_ok: true,
+        latency_ms: Some(latency_ms),
+        error: echoed.err().map(|e| e.to_string()),
+    }
+}
+
+/// Run `echo` and check that its output comes back
+async fn echo(client: &SshClient) -> Result<(), SshError> {
+    let output = tokio::time::timeout(
+        ECHO_TIMEOUT,
+        client.execute(&format!("echo {}", ECHO_TOKEN)),
+    )
+    .await
+    .map_err(|_| SshError::Timeout)??;
+    if output.stdout.contains(ECHO_TOKEN) {
+        Ok(())
+    } else {
+        Err(SshError::CommandFailed(format!(
+            "echo exited with {}: {}",
+            output.exit_code,
+            output.stderr.trim()
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ssh::{AddressFamily, AuthMethod, Transport, DEFAULT_KEEPALIVE_MAX};
+
+    #[tokio::test]
+    async fn test_refused_connection() {
+        // Grab a free port, then close it so connecting is refused
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let config = SshConfig {
+            host: "127.0.0.1".into(),
+            port,
+            username: "root".into(),
+            auth_method: AuthMethod::Agent,
+            timeout_seconds: 2,
+            address_family: AddressFamily::default(),
+            suppress_motd: false,
+            exec_profile: None,
+            transport: Transport::Tcp,
+            encoding: None,
+            send_encoding: None,
+            normalize_newlines: false,
+            security: None,
+            keepalive_seconds: None,
+            keepalive_max: DEFAULT_KEEPALIVE_MAX,
+            jump_hosts: Vec::new(),
+            env: Default::default(),
+            init_commands: Vec::new(),
+        };
+
+        let result = test_connection(config, true).await;
+        assert!(!result.success);
+        assert!(!result.auth_ok);
+        assert!(result.latency_ms.is_none());
+        assert!(result.error.is_some());
+    }
+}
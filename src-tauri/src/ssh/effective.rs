@@ -0,0 +1,194 @@
+//! Effective connection settings for a host
+//!
+//! Reports what a connection to a host will actually use and where each
+//! value came from, with secrets redacted. Only sources the connect path
+//! applies are listed here.
+
+use super::client::{AuthMethod, SshConfig};
+use super::profile::SecurityProfile;
+use super::resolve::AddressFamily;
+use super::transport::Transport;
+use crate::datasphere::Host;
+use russh::Preferred;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Where a resolved value came from
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigSource {
+    /// Set on the host itself
+    Explicit,
+    /// Set by the host's security profile
+    Profile,
+    /// Not configurable yet; the value built into the app
+    BuiltinDefault,
+}
+
+/// A value together with its provenance
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Resolved<T> {
+    pub value: T,
+    pub source: ConfigSource,
+}
+
+impl<T> Resolved<T> {
+    fn explicit(value: T) -> Self {
+        Self {
+            value,
+            source: ConfigSource::Explicit,
+        }
+    }
+
+    fn builtin(value: T) -> Self {
+        Self {
+            value,
+            source: ConfigSource::BuiltinDefault,
+        }
+    }
+
+    fn profile(value: T) -> Self {
+        Self {
+            value,
+            source: ConfigSource::Profile,
+        }
+    }
+}
+
+/// Authentication method without its secrets
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RedactedAuth {
+    Password {
+        password_set: bool,
+    },
+    PrivateKey {
+        key_path: String,
+        passphrase_set: bool,
+    },
+    Agent,
+}
+
+impl From<&AuthMethod> for RedactedAuth {
+    fn from(auth: &AuthMethod) -> Self {
+        match auth {
+            AuthMethod::Password(password) => Self::Password {
+                password_set: !password.is_empty(),
+            },
+            AuthMethod::PrivateKey {
+                key_path,
+                passphrase,
+            } => Self::PrivateKey {
+                key_path: key_path.clone(),
+                passphrase_set: passphrase.is_some(),
+            },
+            AuthMethod::Agent => Self::Agent,
+        }
+    }
+}
+
+/// Algorithm preferences offered to the server, most preferred first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Algorithms {
+    pub kex: Vec<String>,
+    pub host_key: Vec<String>,
+    pub cipher: Vec<String>,
+    pub mac: Vec<String>,
+    pub compression: Vec<String>,
+}
+
+impl From<&Preferred> for Algorithms {
+    fn from(preferred: &Preferred) -> Self {
+        fn names<N: AsRef<str>>(list: &[N]) -> Vec<String> {
+            list.iter().map(|n| n.as_ref().to_string()).collect()
+        }
+        Self {
+            kex: names(&preferred.kex),
+            host_key: names(&preferred.key),
+            cipher: names(&preferred.cipher),
+            mac: names(&preferred.mac),
+            compression: names(&preferred.compression),
+        }
+    }
+}
+
+/// Fully resolved connection settings for a host
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResolvedConfig {
+    pub host_id: Uuid,
+    pub name: String,
+    pub host: Resolved<String>,
+    pub port: Resolved<u16>,
+    pub transport: Resolved<Transport>,
+    pub username: Resolved<String>,
+    pub auth: Resolved<RedactedAuth>,
+    pub connect_timeout_seconds: Resolved<u64>,
+    pub inactivity_timeout_seconds: Resolved<u64>,
+    pub address_family: Resolved<AddressFamily>,
+    /// Name of the security profile in effect
+    pub security_profile: Option<String>,
+    pub algorithms: Resolved<Algorithms>,
+    pub keepalive_interval_seconds: Resolved<Option<u64>>,
+    pub keepalive_max: Resolved<usize>,
+    pub suppress_motd: Resolved<bool>,
+    pub exec_profile: Resolved<Option<String>>,
+    pub encoding: Resolved<String>,
+    pub send_encoding: Resolved<String>,
+    pub normalize_newlines: Resolved<bool>,
+}
+
+/// Resolve the settings a connection to `host` will use
+///
+/// `profile` is the security profile named by the host, if any.
+pub fn resolve_host(host: &Host, profile: Option<&SecurityProfile>) -> ResolvedConfig {
+    let config: SshConfig = host.ssh_config();
+    let connect_timeout_seconds = match profile.and_then(|p| p.connect_timeout_seconds) {
+        Some(seconds) => Resolved::profile(seconds),
+        None => Resolved::builtin(config.timeout_seconds),
+    };
+    let inactivity_timeout_seconds = Resolved {
+        value: connect_timeout_seconds.value * 2,
+        source: connect_timeout_seconds.source,
+    };
+    // An invalid stored profile fails at connect time; report the defaults
+    let algorithms = match profile.map(|p| p.preferred()) {
+        Some(Ok(preferred)) => Resolved::profile(Algorithms::from(&preferred)),
+        _ => Resolved::builtin(Algorithms::from(&Preferred::default())),
+    };
+    let (keepalive_interval_seconds, keepalive_max) = match profile {
+        Some(p) => (
+            Resolved::profile(p.keepalive_interval().map(|d| d.as_secs())),
+            Resolved::profile(p.keepalive_max),
+        ),
+        None => (Resolved::builtin(None), Resolved::builtin(0)),
+    };
+    let encoding = match &config.encoding {
+        Some(label) => Resolved::explicit(label.clone()),
+        None => Resolved::builtin("utf-8".to_string()),
+    };
+
+    ResolvedConfig {
+        host_id: host.id,
+        name: host.name.clone(),
+        host: Resolved::explicit(config.host.clone()),
+        port: Resolved::explicit(config.port),
+        transport: Resolved::explicit(config.transport.clone()),
+        username: Resolved::explicit(config.username.clone()),
+        auth: Resolved::explicit(RedactedAuth::from(&config.auth_method)),
+        connect_timeout_seconds,
+        inactivity_timeout_seconds,
+        address_family: Resolved::builtin(config.address_family),
+        security_profile: profile.map(|p| p.name.clone()),
+        algorithms,
+        keepalive_interval_seconds,
+        keepalive_max,
+        suppress_motd: Resolved::explicit(config.suppress_motd),
+        exec_profile: Resolved::explicit(config.exec_profile.clone()),
+        encoding: encoding.clone(),
+        send_encoding: match &config.send_encoding {
+            Some(label) => Resolved::explicit(label.clone()),
+            None => encoding,
+        },
+        normalize_newlines: Resolved::explicit(config.normalize_newlines),
+    }
+}
@@ -0,0 +1,119 @@
+//! Host key fingerprints for display
+//!
+//! `get_host_fingerprint` reads a server's key by starting a handshake and
+//! rejecting the key as soon as it is offered, so nothing is authenticated
+//! and the key is not added to the known hosts.
+
+use super::SshError;
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use russh::client::{self, Config, Handler};
+use russh::keys::key::PublicKey;
+use russh::keys::PublicKeyBase64;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How long reading a server key may take
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A server's host key, in the forms `ssh-keygen -l` prints
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HostFingerprint {
+    pub host: String,
+    pub port: u16,
+    /// Key algorithm, e.g. `ssh-ed25519`
+    pub key_type: String,
+    /// `SHA256:` followed by unpadded base64
+    pub sha256: String,
+    /// `MD5:` followed by colon-separated hex, for older tools
+    pub md5: String,
+}
+
+impl HostFingerprint {
+    pub fn new(host: &str, port: u16, key: &PublicKey) -> Self {
+        Self {
+            host: host.to_string(),
+            port,
+            key_type: key.name().to_string(),
+            sha256: fingerprint_sha256(key),
+            md5: fingerprint_md5(key),
+        }
+    }
+}
+
+/// OpenSSH-style `SHA256:` fingerprint
+pub fn fingerprint_sha256(key: &PublicKey) -> String {
+    format!("SHA256:{}", key.fingerprint())
+}
+
+/// Legacy `MD5:` fingerprint
+pub fn fingerprint_md5(key: &PublicKey) -> String {
+    let digest = md5::compute(key.public_key_bytes());
+    let hex: Vec<String> = digest.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("MD5:{}", hex.join(":"))
+}
+
+/// Records the offered key and refuses it, ending the handshake
+struct KeyCatcher {
+    key: Arc<Mutex<Option<PublicKey>>>,
+}
+
+#[async_trait]
+impl Handler for KeyCatcher {
+    type Error = SshError;
+
+    async fn check_server_key(
+        &mut self,
+        server_public_key: &PublicKey,
+    ) -> Result<bool, Self::Error> {
+        *self.key.lock() = Some(server_public_key.clone());
+        Ok(false)
+    }
+}
+
+/// Read the host key of `host:port` without authenticating
+pub async fn fetch(host: &str, port: u16) -> Result<HostFingerprint, SshError> {
+    let key = Arc::new(Mutex::new(None));
+    let handler = KeyCatcher { key: key.clone() };
+    let config = Arc::new(Config::default());
+
+    let result = tokio::time::timeout(
+        FETCH_TIMEOUT,
+        client::connect(config, (host, port), handler),
+    )
+    .await
+    .map_err(|_| SshError::Timeout)?;
+
+    let captured = key.lock().take();
+    match (captured, result) {
+        (Some(key), _) => Ok(HostFingerprint::new(host, port, &key)),
+        (None, Err(e)) => Err(e),
+        (None, Ok(_)) => Err(SshError::ConnectionFailed(
+            "Server did not offer a host key".to_string(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fingerprints_match_ssh_keygen() {
+        let key = russh::keys::parse_public_key_base64(
+            "AAAAC3NzaC1lZDI1NTE5AAAAIATHRlO4mdyqg1m4eswS9SG5M5jYbtay+W2AseLb4nHm",
+        )
+        .unwrap();
+        let fingerprint = HostFingerprint::new("example.com", 22, &key);
+        assert_eq!(fingerprint.key_type, "ssh-ed25519");
+        assert_eq!(
+            fingerprint.sha256,
+            "SHA256:K1PEr1zBlebZjnVauIZI9lq7rinpfMpDotbCnKvUzkQ"
+        );
+        assert_eq!(
+            fingerprint.md5,
+            "MD5:f9:f2:70:e9:ab:39:b9:a8:8d:cc:2b:a6:53:84:d4:e9"
+        );
+    }
+}
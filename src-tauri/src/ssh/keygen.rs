@@ -0,0 +1,134 @@
+//! SSH key generation
+//!
+//! Private keys are written as PKCS#8 PEM, encrypted with the passphrase
+//! when one is given. Both russh and OpenSSH load that format.
+
+use super::fingerprint::fingerprint_sha256;
+use super::SshError;
+use russh_keys::key::{KeyPair, SignatureHash};
+use russh_keys::PublicKeyBase64;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use zeroize::Zeroizing;
+
+/// PBKDF2 rounds protecting a passphrase-encrypted key
+const PBKDF2_ROUNDS: u32 = 100_000;
+
+/// Smallest RSA key that is still accepted
+pub const MIN_RSA_BITS: usize = 2048;
+
+/// Largest RSA key generated
+pub const MAX_RSA_BITS: usize = 8192;
+
+/// Kind of key to generate
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum KeyAlgorithm {
+    Ed25519,
+    Rsa { bits: usize },
+}
+
+impl KeyAlgorithm {
+    /// Key type as written in `authorized_keys`
+    pub fn ssh_name(&self) -> &'static str {
+        match self {
+            Self::Ed25519 => "ssh-ed25519",
+            Self::Rsa { .. } => "ssh-rsa",
+        }
+    }
+}
+
+/// A freshly generated key pair
+pub struct KeyMaterial {
+    /// PKCS#8 PEM private key
+    pub private_pem: Zeroizing<String>,
+    /// OpenSSH `authorized_keys` line
+    pub public_key: String,
+    /// `SHA256:` fingerprint of the public key
+    pub fingerprint: String,
+}
+
+/// What the frontend learns about a generated key; never the private key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeneratedSshKey {
+    /// Vault entry holding the private key
+    pub entry_id: Uuid,
+    /// OpenSSH `authorized_keys` line
+    pub public_key: String,
+    pub fingerprint: String,
+}
+
+/// Generate a key pair
+///
+/// RSA generation takes a while; call it off the async workers.
+pub fn generate(
+    algorithm: &KeyAlgorithm,
+    comment: &str,
+    passphrase: Option<&str>,
+) -> Result<KeyMaterial, SshError> {
+    let key = match algorithm {
+        KeyAlgorithm::Ed25519 => KeyPair::generate_ed25519(),
+        KeyAlgorithm::Rsa { bits } => {
+            if !(MIN_RSA_BITS..=MAX_RSA_BITS).contains(bits) {
+                return Err(SshError::KeyError(format!(
+                    "RSA keys must have {} to {} bits",
+                    MIN_RSA_BITS, MAX_RSA_BITS
+                )));
+            }
+            KeyPair::generate_rsa(*bits, SignatureHash::SHA2_512)
+        }
+    }
+    .ok_or_else(|| SshError::KeyError("Key generation failed".to_string()))?;
+
+    let mut pem = Zeroizing::new(Vec::new());
+    match passphrase.filter(|p| !p.is_empty()) {
+        Some(passphrase) => russh_keys::encode_pkcs8_pem_encrypted(
+            &key,
+            passphrase.as_bytes(),
+            PBKDF2_ROUNDS,
+            &mut *pem,
+        )?,
+        None => russh_keys::encode_pkcs8_pem(&key, &mut *pem)?,
+    }
+    let private_pem = Zeroizing::new(String::from_utf8(pem.to_vec()).expect("PEM is ASCII"));
+
+    let public = key.clone_public_key()?;
+    let public_key = format!(
+        "{} {} {}",
+        algorithm.ssh_name(),
+        public.public_key_base64(),
+        comment.trim()
+    )
+    .trim_end()
+    .to_string();
+
+    Ok(KeyMaterial {
+        private_pem,
+        public_key,
+        fingerprint: fingerprint_sha256(&public),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_ed25519() {
+        let key = generate(&KeyAlgorithm::Ed25519, "me@laptop", Some("hunter2")).unwrap();
+        assert!(key.public_key.starts_with("ssh-ed25519 AAAA"));
+        assert!(key.public_key.ends_with(" me@laptop"));
+        assert!(key.fingerprint.starts_with("SHA256:"));
+        assert!(key.private_pem.contains("ENCRYPTED PRIVATE KEY"));
+
+        let decoded = russh_keys::decode_secret_key(&key.private_pem, Some("hunter2")).unwrap();
+        let public = decoded.clone_public_key().unwrap();
+        assert_eq!(fingerprint_sha256(&public), key.fingerprint);
+
+        let plain = generate(&KeyAlgorithm::Ed25519, "", None).unwrap();
+        assert!(russh_keys::decode_secret_key(&plain.private_pem, None).is_ok());
+        assert_eq!(plain.public_key.split(' ').count(), 2);
+
+        assert!(generate(&KeyAlgorithm::Rsa { bits: 1024 }, "", None).is_err());
+    }
+}
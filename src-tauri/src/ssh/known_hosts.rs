@@ -0,0 +1,271 @@
+//! Host key verification
+//!
+//! Trusted host keys are stored in DataSphere. What happens on the first
+//! connection to a host depends on the `TrustPolicy` from the settings; a
+//! key that differs from the trusted one fails under every policy except
+//! `AcceptAll`.
+
+use super::fingerprint::fingerprint_sha256;
+use super::SshError;
+use crate::datasphere;
+use crate::retention::StorageCategory;
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use russh::keys::key::PublicKey;
+use russh::keys::PublicKeyBase64;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+/// How to treat a host whose key isn't trusted yet
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TrustPolicy {
+    /// Refuse until the key is accepted with `accept_host_key`
+    Strict,
+    /// Trust and store the first key seen, writing it to the audit log
+    TofuLogged,
+    /// Don't verify host keys at all
+    #[default]
+    AcceptAll,
+}
+
+/// A trusted host key
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct KnownHost {
+    pub host: String,
+    pub port: u16,
+    pub key_type: String,
+    /// `SHA256:` fingerprint, as printed by `ssh-keygen -l`
+    pub fingerprint: String,
+    /// Base64 public key blob, as in an OpenSSH `known_hosts` line
+    pub public_key: String,
+    pub added_at: DateTime<Utc>,
+    /// Trusted on first use rather than accepted by the user
+    #[serde(default)]
+    pub auto_trusted: bool,
+}
+
+impl KnownHost {
+    pub fn new(host: &str, port: u16, key: &PublicKey) -> Self {
+        Self {
+            host: host.to_string(),
+            port,
+            key_type: key.name().to_string(),
+            fingerprint: fingerprint_sha256(key),
+            public_key: key.public_key_base64(),
+            added_at: Utc::now(),
+            auto_trusted: false,
+        }
+    }
+
+    fn is_for(&self, host: &str, port: u16) -> bool {
+        self.host == host && self.port == port
+    }
+}
+
+/// Outcome of checking a key against the trusted ones
+#[derive(Debug, PartialEq)]
+enum KeyStatus {
+    Trusted,
+    Unknown,
+    /// Another key of the same type is trusted for this host
+    Changed {
+        expected: String,
+    },
+}
+
+/// Keys refused under `Strict`, waiting for the user to accept them
+static PENDING: Lazy<RwLock<HashMap<(String, u16), KnownHost>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// Check a server's host key according to the configured policy
+pub fn verify(host: &str, port: u16, key: &PublicKey) -> Result<(), SshError> {
+    let candidate = KnownHost::new(host, port, key);
+    let mut storage = datasphere::storage().write();
+    let policy = storage
+        .as_ref()
+        .map(|s| s.get_settings().host_key_policy)
+        .unwrap_or_default();
+
+    if policy == TrustPolicy::AcceptAll {
+        tracing::warn!(
+            "Host key for {}:{} not verified ({})",
+            host,
+            port,
+            candidate.fingerprint
+        );
+        return Ok(());
+    }
+
+    let known = storage
+        .as_ref()
+        .map(|s| s.get_known_hosts())
+        .unwrap_or_default();
+    match check(&known, &candidate) {
+        KeyStatus::Trusted => Ok(()),
+        KeyStatus::Changed { expected } => {
+            tracing::error!(
+                "Host key for {}:{} changed: got {}, trusted {}",
+                host,
+                port,
+                candidate.fingerprint,
+                expected
+            );
+            Err(SshError::HostKeyChanged(format!(
+                "{}:{} presented {} {} but {} is trusted",
+                host, port, candidate.key_type, candidate.fingerprint, expected
+            )))
+        }
+        KeyStatus::Unknown if policy == TrustPolicy::TofuLogged => {
+            let Some(storage) = storage.as_mut() else {
+                return Err(SshError::HostKeyUnknown(
+                    "DataSphere is not initialized; cannot store the host key".to_string(),
+                ));
+            };
+            let entry = KnownHost {
+                auto_trusted: true,
+                ..candidate
+            };
+            storage
+                .add_known_host(entry.clone())
+                .map_err(|e| SshError::HostKeyUnknown(format!("storing host key: {}", e)))?;
+            if let Err(e) = audit_auto_trust(storage.data_dir(), &entry) {
+                tracing::warn!("Could not write host key audit entry: {}", e);
+            }
+            tracing::info!(
+                "Trusted {} key {} for {}:{} on first use",
+                entry.key_type,
+                entry.fingerprint,
+                host,
+                port
+            );
+            Ok(())
+        }
+        KeyStatus::Unknown => {
+            let message = format!(
+                "{}:{} presented {} {}",
+                host, port, candidate.key_type, candidate.fingerprint
+            );
+            PENDING.write().insert((host.to_string(), port), candidate);
+            Err(SshError::HostKeyUnknown(message))
+        }
+    }
+}
+
+/// Take a key refused under `Strict` if its fingerprint is `fingerprint`
+pub fn take_pending(host: &str, port: u16, fingerprint: &str) -> Option<KnownHost> {
+    let mut pending = PENDING.write();
+    let key = (host.to_string(), port);
+    if pending.get(&key)?.fingerprint != fingerprint {
+        return None;
+    }
+    pending.remove(&key).map(|entry| KnownHost {
+        added_at: Utc::now(),
+        ..entry
+    })
+}
+
+/// A host with any trusted key must present one of them; a key of a type
+/// not trusted yet could be an attacker's as easily as a new host key
+fn check(known: &[KnownHost], candidate: &KnownHost) -> KeyStatus {
+    let for_host: Vec<&KnownHost> = known
+        .iter()
+        .filter(|k| k.is_for(&candidate.host, candidate.port))
+        .collect();
+    if for_host.is_empty() {
+        return KeyStatus::Unknown;
+    }
+    if for_host
+        .iter()
+        .any(|k| k.key_type == candidate.key_type && k.public_key == candidate.public_key)
+    {
+        return KeyStatus::Trusted;
+    }
+    let expected = for_host
+        .iter()
+        .find(|k| k.key_type == candidate.key_type)
+        .unwrap_or(&for_host[0]);
+    KeyStatus::Changed {
+        expected: expected.fingerprint.clone(),
+    }
+}
+
+/// Append an auto-trust event to `audit_logs/host_keys.jsonl`
+fn audit_auto_trust(data_dir: &Path, entry: &KnownHost) -> std::io::Result<()> {
+    let dir = data_dir.join(StorageCategory::AuditLogs.dir_name());
+    fs::create_dir_all(&dir)?;
+    let line = serde_json::json!({
+        "timestamp": entry.added_at,
+        "event": "host_key_auto_trusted",
+        "host": entry.host,
+        "port": entry.port,
+        "key_type": entry.key_type,
+        "fingerprint": entry.fingerprint,
+    });
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join("host_keys.jsonl"))?;
+    writeln!(file, "{}", line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(key_type: &str, public_key: &str) -> KnownHost {
+        KnownHost {
+            host: "example.com".to_string(),
+            port: 22,
+            key_type: key_type.to_string(),
+            fingerprint: format!("SHA256:{}", public_key),
+            public_key: public_key.to_string(),
+            added_at: Utc::now(),
+            auto_trusted: false,
+        }
+    }
+
+    #[test]
+    fn test_check() {
+        let known = vec![entry("ssh-ed25519", "AAAA")];
+
+        assert_eq!(
+            check(&known, &entry("ssh-ed25519", "AAAA")),
+            KeyStatus::Trusted
+        );
+        assert_eq!(
+            check(&known, &entry("ssh-ed25519", "BBBB")),
+            KeyStatus::Changed {
+                expected: "SHA256:AAAA".to_string()
+            }
+        );
+        // A known host offering a key type it has no trusted key for
+        assert_eq!(
+            check(&known, &entry("rsa-sha2-256", "CCCC")),
+            KeyStatus::Changed {
+                expected: "SHA256:AAAA".to_string()
+            }
+        );
+        let both = vec![entry("rsa-sha2-256", "CCCC"), entry("ssh-ed25519", "AAAA")];
+        assert_eq!(
+            check(&both, &entry("rsa-sha2-256", "CCCC")),
+            KeyStatus::Trusted
+        );
+        assert_eq!(
+            check(&both, &entry("ssh-ed25519", "DDDD")),
+            KeyStatus::Changed {
+                expected: "SHA256:AAAA".to_string()
+            }
+        );
+
+        let other_port = KnownHost {
+            port: 2222,
+            ..entry("ssh-ed25519", "BBBB")
+        };
+        assert_eq!(check(&known, &other_port), KeyStatus::Unknown);
+    }
+}
@@ -3,20 +3,74 @@
 //! Provides SSH connection management using russh (to be implemented)
 
 pub mod commands;
+mod agent;
+mod auth_probe;
+pub mod authorized_keys;
 mod client;
+pub mod connection_test;
+pub mod effective;
+pub mod fingerprint;
+pub mod keygen;
+pub mod known_hosts;
+pub mod ppk;
+pub mod profile;
+mod prompt;
+pub mod reachability;
+pub mod remote_info;
+mod resolve;
+pub mod script;
+pub mod shell;
+mod state;
+mod transport;
+pub mod tunnel;
 
-pub use client::{SshClient, SshConfig, SshError, AuthMethod, CommandOutput};
+pub use auth_probe::probe_auth_methods;
+pub use authorized_keys::KeyInstallOutcome;
+pub use client::{
+    AuthMethod, CommandOutput, OutputChunk, OutputStream, SshClient, SshConfig, SshError,
+    TerminalOutput, DEFAULT_KEEPALIVE_MAX,
+};
+pub use connection_test::ConnectionTestResult;
+pub use effective::ResolvedConfig;
+pub use fingerprint::{fingerprint_md5, fingerprint_sha256, HostFingerprint};
+pub use known_hosts::{KnownHost, TrustPolicy};
+pub use profile::SecurityProfile;
+pub use prompt::{InteractiveOutput, PromptDetector};
+pub use reachability::HostProbe;
+pub use remote_info::RemoteInfo;
+pub use resolve::AddressFamily;
+pub(crate) use script::ChunkDecoder;
+pub use shell::ShellKind;
+pub use state::{ConnectionReason, ConnectionState, StateListener};
+pub use transport::Transport;
+pub use tunnel::{Tunnel, TunnelKind};
 
 use std::collections::HashMap;
+use std::sync::Arc;
 use parking_lot::RwLock;
 use once_cell::sync::Lazy;
 use uuid::Uuid;
 
 /// Global SSH client manager
-static SSH_CLIENTS: Lazy<RwLock<HashMap<Uuid, SshClient>>> =
+static SSH_CLIENTS: Lazy<RwLock<HashMap<Uuid, Arc<SshClient>>>> =
     Lazy::new(|| RwLock::new(HashMap::new()));
 
 /// Get a reference to the SSH clients map
-pub fn clients() -> &'static RwLock<HashMap<Uuid, SshClient>> {
+pub fn clients() -> &'static RwLock<HashMap<Uuid, Arc<SshClient>>> {
     &SSH_CLIENTS
 }
+
+/// Find the connected SSH client behind a session id
+///
+/// Looks at standalone clients first, then at terminal sessions, so
+/// exec-based helpers work for either kind of session.
+pub fn client_for_session(session_id: Uuid) -> Result<Arc<SshClient>, SshError> {
+    if let Some(client) = clients().read().get(&session_id) {
+        return Ok(client.clone());
+    }
+
+    crate::terminal::manager()
+        .read()
+        .get_ssh_client(session_id)
+        .ok_or(SshError::NotConnected)
+}
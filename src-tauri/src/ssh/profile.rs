@@ -0,0 +1,289 @@
+//! Named security profiles
+//!
+//! A profile bundles algorithm preferences, the connect timeout and
+//! keepalive settings so many hosts can share one security posture. Empty
+//! algorithm lists keep russh's defaults for that category.
+
+use super::SshError;
+use russh::{cipher, compression, kex, keys::key, mac, Preferred};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::time::Duration;
+
+/// Reusable connection security settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct SecurityProfile {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Shipped with the app; can't be changed or deleted
+    #[serde(default)]
+    pub builtin: bool,
+    #[serde(default)]
+    pub kex: Vec<String>,
+    #[serde(default)]
+    pub host_key: Vec<String>,
+    #[serde(default)]
+    pub cipher: Vec<String>,
+    #[serde(default)]
+    pub mac: Vec<String>,
+    #[serde(default)]
+    pub compression: Vec<String>,
+    /// Overrides the host's connect timeout
+    #[serde(default)]
+    pub connect_timeout_seconds: Option<u64>,
+    /// Send a keepalive after this much silence from the server
+    #[serde(default)]
+    pub keepalive_interval_seconds: Option<u64>,
+    /// Unanswered keepalives before the connection is dropped; 0 never drops
+    #[serde(default)]
+    pub keepalive_max: usize,
+}
+
+impl SecurityProfile {
+    /// Profiles shipped with the app
+    pub fn builtins() -> Vec<SecurityProfile> {
+        vec![
+            SecurityProfile {
+                name: "modern".to_string(),
+                description: Some("Current OpenSSH defaults without SHA-1".to_string()),
+                builtin: true,
+                kex: names(&[
+                    "curve25519-sha256",
+                    "curve25519-sha256@libssh.org",
+                    "diffie-hellman-group16-sha512",
+                    "diffie-hellman-group14-sha256",
+                ]),
+                host_key: names(&[
+                    "ssh-ed25519",
+                    "ecdsa-sha2-nistp256",
+                    "ecdsa-sha2-nistp384",
+                    "ecdsa-sha2-nistp521",
+                    "rsa-sha2-512",
+                    "rsa-sha2-256",
+                ]),
+                cipher: names(&[
+                    "chacha20-poly1305@openssh.com",
+                    "aes256-gcm@openssh.com",
+                    "aes256-ctr",
+                    "aes128-ctr",
+                ]),
+                mac: names(&[
+                    "hmac-sha2-512-etm@openssh.com",
+                    "hmac-sha2-256-etm@openssh.com",
+                ]),
+                compression: names(&["none"]),
+                connect_timeout_seconds: None,
+                keepalive_interval_seconds: Some(30),
+                keepalive_max: 3,
+            },
+            SecurityProfile {
+                name: "legacy-compat".to_string(),
+                description: Some(
+                    "Adds SHA-1, CBC and ssh-rsa for old appliances; avoid where possible"
+                        .to_string(),
+                ),
+                builtin: true,
+                kex: names(&[
+                    "curve25519-sha256",
+                    "curve25519-sha256@libssh.org",
+                    "ecdh-sha2-nistp256",
+                    "ecdh-sha2-nistp384",
+                    "ecdh-sha2-nistp521",
+                    "diffie-hellman-group16-sha512",
+                    "diffie-hellman-group14-sha256",
+                    "diffie-hellman-group14-sha1",
+                    "diffie-hellman-group1-sha1",
+                ]),
+                host_key: names(&[
+                    "ssh-ed25519",
+                    "ecdsa-sha2-nistp256",
+                    "ecdsa-sha2-nistp384",
+                    "ecdsa-sha2-nistp521",
+                    "rsa-sha2-512",
+                    "rsa-sha2-256",
+                    "ssh-rsa",
+                ]),
+                cipher: names(&[
+                    "chacha20-poly1305@openssh.com",
+                    "aes256-gcm@openssh.com",
+                    "aes256-ctr",
+                    "aes192-ctr",
+                    "aes128-ctr",
+                    "aes256-cbc",
+                    "aes192-cbc",
+                    "aes128-cbc",
+                    "3des-cbc",
+                ]),
+                mac: names(&[
+                    "hmac-sha2-512-etm@openssh.com",
+                    "hmac-sha2-256-etm@openssh.com",
+                    "hmac-sha2-512",
+                    "hmac-sha2-256",
+                    "hmac-sha1-etm@openssh.com",
+                    "hmac-sha1",
+                ]),
+                compression: Vec::new(),
+                connect_timeout_seconds: Some(60),
+                keepalive_interval_seconds: Some(60),
+                keepalive_max: 5,
+            },
+            SecurityProfile {
+                name: "fips".to_string(),
+                description: Some("Only FIPS 140 approved algorithms".to_string()),
+                builtin: true,
+                kex: names(&[
+                    "ecdh-sha2-nistp256",
+                    "ecdh-sha2-nistp384",
+                    "ecdh-sha2-nistp521",
+                    "diffie-hellman-group16-sha512",
+                    "diffie-hellman-group14-sha256",
+                ]),
+                host_key: names(&[
+                    "ecdsa-sha2-nistp256",
+                    "ecdsa-sha2-nistp384",
+                    "ecdsa-sha2-nistp521",
+                    "rsa-sha2-512",
+                    "rsa-sha2-256",
+                ]),
+                cipher: names(&["aes256-gcm@openssh.com", "aes256-ctr", "aes128-ctr"]),
+                mac: names(&[
+                    "hmac-sha2-512-etm@openssh.com",
+                    "hmac-sha2-256-etm@openssh.com",
+                    "hmac-sha2-512",
+                    "hmac-sha2-256",
+                ]),
+                compression: names(&["none"]),
+                connect_timeout_seconds: None,
+                keepalive_interval_seconds: Some(30),
+                keepalive_max: 3,
+            },
+        ]
+    }
+
+    /// Look up a built-in profile by name
+    pub fn builtin(name: &str) -> Option<SecurityProfile> {
+        Self::builtins().into_iter().find(|p| p.name == name)
+    }
+
+    /// Check the name and that every algorithm is one russh implements
+    pub fn validate(&self) -> Result<(), SshError> {
+        let name = self.name.trim();
+        if name.is_empty() || name != self.name {
+            return Err(SshError::InvalidProfile(
+                "Profile name must be non-empty without surrounding spaces".to_string(),
+            ));
+        }
+        self.preferred().map(|_| ())
+    }
+
+    /// Algorithm preferences to offer, in russh's form
+    pub fn preferred(&self) -> Result<Preferred, SshError> {
+        let defaults = Preferred::default();
+        let mut kex = parse(&self.kex, "key exchange", &defaults.kex, |n| {
+            kex::Name::try_from(n).ok()
+        })?;
+        if !self.kex.is_empty() {
+            // Extension markers aren't real algorithms but russh relies on
+            // them for ext-info and strict kex (CVE-2023-48795)
+            kex.extend([
+                kex::EXTENSION_SUPPORT_AS_CLIENT,
+                kex::EXTENSION_OPENSSH_STRICT_KEX_AS_CLIENT,
+            ]);
+        }
+
+        Ok(Preferred {
+            kex: Cow::Owned(kex),
+            key: Cow::Owned(parse(
+                &self.host_key,
+                "host key",
+                &defaults.key,
+                host_key_name,
+            )?),
+            cipher: Cow::Owned(parse(&self.cipher, "cipher", &defaults.cipher, |n| {
+                cipher::Name::try_from(n).ok()
+            })?),
+            mac: Cow::Owned(parse(&self.mac, "MAC", &defaults.mac, |n| {
+                mac::Name::try_from(n).ok()
+            })?),
+            compression: Cow::Owned(parse(
+                &self.compression,
+                "compression",
+                &defaults.compression,
+                |n| compression::Name::try_from(n).ok(),
+            )?),
+        })
+    }
+
+    pub fn keepalive_interval(&self) -> Option<Duration> {
+        self.keepalive_interval_seconds
+            .filter(|s| *s > 0)
+            .map(Duration::from_secs)
+    }
+}
+
+fn names(list: &[&str]) -> Vec<String> {
+    list.iter().map(|s| s.to_string()).collect()
+}
+
+/// `key::Name::try_from` doesn't know `ssh-ed25519`
+fn host_key_name(name: &str) -> Option<key::Name> {
+    if name == key::ED25519.0 {
+        Some(key::ED25519)
+    } else {
+        key::Name::try_from(name).ok()
+    }
+}
+
+/// Map algorithm names to russh names, keeping `defaults` for an empty list
+fn parse<N: Copy>(
+    list: &[String],
+    kind: &str,
+    defaults: &[N],
+    lookup: impl Fn(&str) -> Option<N>,
+) -> Result<Vec<N>, SshError> {
+    if list.is_empty() {
+        return Ok(defaults.to_vec());
+    }
+    list.iter()
+        .map(|name| {
+            lookup(name).ok_or_else(|| {
+                SshError::InvalidProfile(format!("Unsupported {} algorithm: {}", kind, name))
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profiles_resolve() {
+        for profile in SecurityProfile::builtins() {
+            profile.validate().unwrap();
+        }
+
+        let modern = SecurityProfile::builtin("modern").unwrap();
+        let preferred = modern.preferred().unwrap();
+        assert_eq!(preferred.kex[0].as_ref(), "curve25519-sha256");
+        assert!(preferred
+            .kex
+            .contains(&kex::EXTENSION_OPENSSH_STRICT_KEX_AS_CLIENT));
+        assert!(!preferred.mac.iter().any(|m| m.as_ref() == "hmac-sha1"));
+
+        let custom = SecurityProfile {
+            name: "custom".to_string(),
+            cipher: names(&["rot13"]),
+            ..Default::default()
+        };
+        assert!(custom.validate().is_err());
+
+        // Empty lists fall back to russh's defaults
+        let empty = SecurityProfile {
+            name: "empty".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(empty.preferred().unwrap().kex, Preferred::default().kex);
+    }
+}
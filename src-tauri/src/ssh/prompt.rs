@@ -0,0 +1,293 @@
+//! Prompt Detection
+//!
+//! Decides when a command running in a PTY has finished. Two strategies:
+//! - `Marker`: send a `printf` of a random nonce (and the exit status) on
+//!   its own line after the command and wait for it to appear. The nonce is
+//!   split in the command text so the terminal echo of the input line can
+//!   never match.
+//! - `Regex`: wait until the buffered output ends in something that looks
+//!   like a shell prompt.
+
+use super::{ShellKind, SshError};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// Pattern for the first prompt of a login shell: a line ending in a typical
+/// prompt character
+const DEFAULT_PROMPT_PATTERN: &str = r"[$#>%]\s*$";
+
+/// Output of a command run through a prompt detector
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InteractiveOutput {
+    pub output: String,
+    /// Only known when the marker strategy is used
+    pub exit_code: Option<i32>,
+}
+
+enum Strategy {
+    Marker {
+        pending: Option<PendingMarker>,
+        /// Variable holding the last exit status in the remote shell
+        status_var: &'static str,
+    },
+    Regex(Regex),
+}
+
+/// Marker of the command currently running
+struct PendingMarker {
+    /// Matches the printed marker
+    regex: Regex,
+    /// Part of the echoed `printf` line, to drop it from the output
+    echo: String,
+}
+
+/// Detects command completion in PTY output
+pub struct PromptDetector {
+    strategy: Strategy,
+    buffer: String,
+}
+
+impl PromptDetector {
+    /// Detector using the unique-marker technique, for a POSIX shell
+    pub fn marker() -> Self {
+        Self::marker_for(ShellKind::Posix)
+    }
+
+    /// Detector using the unique-marker technique in `shell`
+    pub fn marker_for(shell: ShellKind) -> Self {
+        Self {
+            strategy: Strategy::Marker {
+                pending: None,
+                status_var: shell.status_var(),
+            },
+            buffer: String::new(),
+        }
+    }
+
+    /// Detector matching the prompt with a custom regex
+    pub fn with_regex(pattern: &str) -> Result<Self, SshError> {
+        let regex = Regex::new(pattern)
+            .map_err(|e| SshError::CommandFailed(format!("Invalid prompt pattern: {}", e)))?;
+        Ok(Self {
+            strategy: Strategy::Regex(regex),
+            buffer: String::new(),
+        })
+    }
+
+    /// Detector for the first prompt of a fresh login shell
+    pub fn login() -> Self {
+        Self::with_regex(DEFAULT_PROMPT_PATTERN).expect("default prompt pattern is valid")
+    }
+
+    /// Feed login output until the first prompt appears
+    ///
+    /// Returns the banner printed before the prompt and the prompt line
+    /// itself. Never completes for the marker strategy, which has to send a
+    /// command first.
+    pub fn feed_login(&mut self, chunk: &str) -> Option<(String, String)> {
+        self.buffer.push_str(chunk);
+
+        let Strategy::Regex(regex) = &self.strategy else {
+            return None;
+        };
+        let last_line = self.buffer.rsplit('\n').next().unwrap_or_default();
+        if !regex.is_match(last_line) {
+            return None;
+        }
+
+        let prompt = last_line.to_string();
+        self.buffer.truncate(self.buffer.len() - prompt.len());
+        let banner = std::mem::take(&mut self.buffer);
+        Some((banner, prompt))
+    }
+
+    /// Number of bytes fed but not yet consumed
+    pub fn buffered_len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Take everything fed so far, e.g. when no prompt ever appears
+    pub fn take_buffer(&mut self) -> String {
+        std::mem::take(&mut self.buffer)
+    }
+
+    /// Command to send before the first real command to skip banners/MOTD.
+    ///
+    /// The regex strategy simply waits for the first prompt.
+    pub fn sync_command(&mut self) -> Option<String> {
+        match self.strategy {
+            Strategy::Marker { .. } => Some(self.wrap("true")),
+            Strategy::Regex(_) => None,
+        }
+    }
+
+    /// Prepare a command line for sending, arming the detector for it
+    pub fn wrap(&mut self, command: &str) -> String {
+        self.buffer.clear();
+        match &mut self.strategy {
+            Strategy::Marker {
+                pending,
+                status_var,
+            } => {
+                let nonce = uuid::Uuid::new_v4().simple().to_string();
+                let (head, tail) = nonce.split_at(8);
+                *pending = Some(PendingMarker {
+                    regex: Regex::new(&format!(r"__NX_{}{}__:(\d+)", head, tail))
+                        .expect("marker regex is valid"),
+                    echo: format!("'__NX_{}'", head),
+                });
+                // On a line of its own, so a trailing comment in the command
+                // can't swallow it
+                format!(
+                    "{}\nprintf '%s%s:%d\\n' '__NX_{}' '{}__' \"{}\"\n",
+                    command, head, tail, status_var
+                )
+            }
+            Strategy::Regex(_) => format!("{}\n", command),
+        }
+    }
+
+    /// Feed PTY output; returns the completed command's output once done
+    pub fn feed(&mut self, chunk: &str) -> Option<InteractiveOutput> {
+        self.buffer.push_str(chunk);
+
+        let (end, exit_code, echo) = match &mut self.strategy {
+            Strategy::Marker { pending, .. } => {
+                let captures = pending.as_ref()?.regex.captures(&self.buffer)?;
+                let whole = captures.get(0)?;
+                let exit_code = captures.get(1).and_then(|m| m.as_str().parse().ok());
+                // Output without a trailing newline shares the marker's line
+                let echo = pending.take().map(|p| p.echo);
+                (whole.start(), exit_code, echo)
+            }
+            Strategy::Regex(regex) => {
+                let last_line = self.buffer.rsplit('\n').next().unwrap_or_default();
+                if !regex.is_match(last_line) {
+                    return None;
+                }
+                (self.buffer.len() - last_line.len(), None, None)
+            }
+        };
+
+        let mut output = clean_output(&self.buffer[..end]);
+        // The terminal echoes the marker line either before or after the
+        // command's output, depending on when the shell reads it
+        if let Some(echo) = echo {
+            output = output
+                .split('\n')
+                .filter(|line| !line.contains(&echo))
+                .collect::<Vec<_>>()
+                .join("\n");
+        }
+        self.buffer.clear();
+
+        Some(InteractiveOutput { output, exit_code })
+    }
+}
+
+/// Normalize line endings and drop the echoed command line
+fn clean_output(raw: &str) -> String {
+    let normalized = raw.replace("\r\n", "\n");
+    match normalized.split_once('\n') {
+        Some((_echo, rest)) => rest.trim_end_matches(['\r', '\n']).to_string(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_marker_ignores_echoed_input() {
+        let mut detector = PromptDetector::marker();
+        let line = detector.wrap("ls");
+
+        // The PTY echoes the input line first; that must not complete the command
+        assert!(detector.feed(&line.replace('\n', "\r\n")).is_none());
+
+        let nonce_line = line
+            .split('\'')
+            .filter(|part| part.starts_with("__NX_") || part.ends_with("__"))
+            .collect::<String>();
+        let done = detector
+            .feed(&format!("a.txt\r\nb.txt\r\n{}:2\r\n$ ", nonce_line))
+            .unwrap();
+
+        assert_eq!(done.output, "a.txt\nb.txt");
+        assert_eq!(done.exit_code, Some(2));
+    }
+
+    #[test]
+    fn test_marker_keeps_output_on_its_line() {
+        let mut detector = PromptDetector::marker();
+        let line = detector.wrap("printf done");
+        let nonce_line = line
+            .split('\'')
+            .filter(|part| part.starts_with("__NX_") || part.ends_with("__"))
+            .collect::<String>();
+
+        let echo = line.replace('\n', "\r\n");
+        let done = detector
+            .feed(&format!("{}done{}:0\r\n$ ", echo, nonce_line))
+            .unwrap();
+        assert_eq!(done.output, "done");
+        assert_eq!(done.exit_code, Some(0));
+    }
+
+    #[test]
+    fn test_marker_survives_trailing_comment() {
+        let mut detector = PromptDetector::marker();
+        let line = detector.wrap("ls # list files");
+        let (command, marker) = line.trim_end().split_once('\n').unwrap();
+        assert_eq!(command, "ls # list files");
+        assert!(marker.starts_with("printf ") && marker.ends_with("\"$?\""));
+
+        let nonce_line = line
+            .split('\'')
+            .filter(|part| part.starts_with("__NX_") || part.ends_with("__"))
+            .collect::<String>();
+        let echo = line.replace('\n', "\r\n");
+        let done = detector
+            .feed(&format!("{}a.txt\r\n{}:0\r\n$ ", echo, nonce_line))
+            .unwrap();
+        assert_eq!(done.output, "a.txt");
+        assert_eq!(done.exit_code, Some(0));
+
+        // A shell that echoes each line only when it reads it
+        let line = detector.wrap("false");
+        let nonce_line = line
+            .split('\'')
+            .filter(|part| part.starts_with("__NX_") || part.ends_with("__"))
+            .collect::<String>();
+        let (command, marker) = line.trim_end().split_once('\n').unwrap();
+        let done = detector
+            .feed(&format!(
+                "{}\r\n$ {}\r\n{}:1\r\n$ ",
+                command, marker, nonce_line
+            ))
+            .unwrap();
+        assert_eq!(done.output, "");
+        assert_eq!(done.exit_code, Some(1));
+    }
+
+    #[test]
+    fn test_marker_uses_the_shells_status_variable() {
+        let line = PromptDetector::marker_for(ShellKind::Fish).wrap("ls");
+        assert!(line.ends_with("\"$status\"\n"));
+        let line = PromptDetector::marker_for(ShellKind::Csh).wrap("ls");
+        assert!(line.ends_with("\"$status\"\n"));
+    }
+
+    #[test]
+    fn test_feed_login_splits_banner_from_prompt() {
+        let mut detector = PromptDetector::login();
+        assert!(detector.feed_login("Welcome to Ubuntu\r\n").is_none());
+
+        let (banner, prompt) = detector
+            .feed_login("Last login: Mon\r\nuser@host:~$ ")
+            .unwrap();
+        assert_eq!(banner, "Welcome to Ubuntu\r\nLast login: Mon\r\n");
+        assert_eq!(prompt, "user@host:~$ ");
+    }
+}
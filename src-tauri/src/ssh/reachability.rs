@@ -0,0 +1,180 @@
+//! Reachability checks for hosts
+//!
+//! A probe only opens the transport and reads the server's identification
+//! line; it never starts a handshake. Hosts that are only reachable
+//! through jump hosts are probed directly and so show as unreachable.
+
+use super::transport::{self, Transport};
+use super::SshError;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::net::TcpStream;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use uuid::Uuid;
+
+/// How long connecting may take
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// How long to wait for the identification line once connected
+const BANNER_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Most bytes read looking for the identification line
+const MAX_BANNER_BYTES: usize = 1024;
+
+/// Probes running at once in `probe_many`
+pub const MAX_CONCURRENT_PROBES: usize = 16;
+
+/// Where and how to probe a host
+#[derive(Debug, Clone)]
+pub struct ProbeTarget {
+    pub host_id: Uuid,
+    pub hostname: String,
+    pub port: u16,
+    pub transport: Transport,
+}
+
+/// Result of probing one host
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HostProbe {
+    pub host_id: Uuid,
+    pub reachable: bool,
+    /// Identification line, e.g. `SSH-2.0-OpenSSH_9.6`
+    pub banner: Option<String>,
+    /// Time to establish the connection
+    pub rtt_ms: Option<u32>,
+    /// Why the host is unreachable
+    pub error: Option<String>,
+}
+
+/// Probe one host, reading its banner if `read_banner` is set
+pub async fn probe(target: &ProbeTarget, read_banner: bool) -> HostProbe {
+    let started = Instant::now();
+    let result = match &target.transport {
+        Transport::Tcp => {
+            let connect = TcpStream::connect((target.hostname.as_str(), target.port));
+            match tokio::time::timeout(CONNECT_TIMEOUT, connect).await {
+                Ok(Ok(stream)) => finish(stream, started, read_banner).await,
+                Ok(Err(e)) => Err(SshError::ConnectionFailed(e.to_string())),
+                Err(_) => Err(SshError::Timeout),
+            }
+        }
+        Transport::UnixSocket { path } => {
+            match tokio::time::timeout(CONNECT_TIMEOUT, transport::connect_unix(path)).await {
+                Ok(Ok(stream)) => finish(stream, started, read_banner).await,
+                Ok(Err(e)) => Err(e),
+                Err(_) => Err(SshError::Timeout),
+            }
+        }
+        Transport::NamedPipe { path } => {
+            match tokio::time::timeout(CONNECT_TIMEOUT, transport::connect_pipe(path)).await {
+                Ok(Ok(stream)) => finish(stream, started, read_banner).await,
+                Ok(Err(e)) => Err(e),
+                Err(_) => Err(SshError::Timeout),
+            }
+        }
+    };
+
+    match result {
+        Ok((rtt, banner)) => HostProbe {
+            host_id: target.host_id,
+            reachable: true,
+            banner,
+            rtt_ms: Some(rtt.as_millis().min(u32::MAX as u128) as u32),
+            error: None,
+        },
+        Err(e) => HostProbe {
+            host_id: target.host_id,
+            reachable: false,
+            banner: None,
+            rtt_ms: None,
+            error: Some(e.to_string()),
+        },
+    }
+}
+
+/// Probe many hosts, at most `MAX_CONCURRENT_PROBES` at a time
+///
+/// Results are in the order of `targets`.
+pub async fn probe_many(targets: Vec<ProbeTarget>, read_banner: bool) -> Vec<HostProbe> {
+    let permits = Arc::new(Semaphore::new(MAX_CONCURRENT_PROBES));
+    let mut tasks = JoinSet::new();
+    for (index, target) in targets.into_iter().enumerate() {
+        let permits = permits.clone();
+        tasks.spawn(async move {
+            // The semaphore is never closed
+            let _permit = permits.acquire_owned().await;
+            (index, probe(&target, read_banner).await)
+        });
+    }
+
+    let mut results = Vec::with_capacity(tasks.len());
+    while let Some(joined) = tasks.join_next().await {
+        match joined {
+            Ok(result) => results.push(result),
+            Err(e) => tracing::warn!("Host probe task failed: {}", e),
+        }
+    }
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, probe)| probe).collect()
+}
+
+async fn finish<S>(
+    mut stream: S,
+    started: Instant,
+    read_banner: bool,
+) -> Result<(Duration, Option<String>), SshError>
+where
+    S: AsyncRead + Unpin,
+{
+    let rtt = started.elapsed();
+    if !read_banner {
+        return Ok((rtt, None));
+    }
+
+    // A missing banner doesn't make the host unreachable
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 256];
+    let read = async {
+        while buf.len() < MAX_BANNER_BYTES {
+            match stream.read(&mut chunk).await {
+                Ok(0) | Err(_) => break,
+                Ok(n) => buf.extend_from_slice(&chunk[..n]),
+            }
+            if banner_line(&buf).is_some() {
+                break;
+            }
+        }
+    };
+    let _ = tokio::time::timeout(BANNER_TIMEOUT, read).await;
+    Ok((rtt, banner_line(&buf)))
+}
+
+/// The `SSH-` identification line in `data`, skipping lines sent before it
+fn banner_line(data: &[u8]) -> Option<String> {
+    let text = String::from_utf8_lossy(data);
+    let mut lines = text.split('\n');
+    // Only complete lines count
+    lines.next_back();
+    lines
+        .map(|line| line.trim_end_matches('\r'))
+        .find(|line| line.starts_with("SSH-"))
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_banner_line() {
+        assert_eq!(
+            banner_line(b"Welcome\r\nSSH-2.0-OpenSSH_9.6\r\n"),
+            Some("SSH-2.0-OpenSSH_9.6".to_string())
+        );
+        assert_eq!(banner_line(b"SSH-2.0-partial"), None);
+        assert_eq!(banner_line(b"HTTP/1.1 400 Bad Request\r\n"), None);
+    }
+}
@@ -0,0 +1,128 @@
+//! What a connection leads to: OS, distribution, kernel and shell
+//!
+//! One exec command prints the `uname` fields, `$SHELL` and
+//! `/etc/os-release` after a marker line. Servers where that doesn't work,
+//! such as Windows with cmd.exe or PowerShell, are reported as `unknown`.
+
+use serde::{Deserialize, Serialize};
+
+/// Marker printed before the probe's output
+const MARKER: &str = "nexus-remote-info";
+
+/// Command whose output `RemoteInfo::from_probe` parses
+pub const PROBE_COMMAND: &str = "printf '%s\\n' nexus-remote-info; uname -s; uname -n; uname -r; uname -m; printf '%s\\n' \"$SHELL\"; cat /etc/os-release 2>/dev/null";
+
+/// Facts about the remote system
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RemoteInfo {
+    /// `linux`, `macos`, `freebsd`, ... or `unknown`
+    pub os: String,
+    /// `PRETTY_NAME` from `/etc/os-release`, e.g. `Debian GNU/Linux 12 (bookworm)`
+    pub distro: Option<String>,
+    pub kernel: Option<String>,
+    pub arch: Option<String>,
+    /// Login shell from `$SHELL`
+    pub shell: Option<String>,
+    pub hostname: Option<String>,
+}
+
+impl RemoteInfo {
+    pub fn unknown() -> Self {
+        Self {
+            os: "unknown".to_string(),
+            distro: None,
+            kernel: None,
+            arch: None,
+            shell: None,
+            hostname: None,
+        }
+    }
+
+    /// Parse the output of `PROBE_COMMAND`
+    pub fn from_probe(stdout: &str) -> Self {
+        let mut lines = stdout
+            .lines()
+            .map(str::trim)
+            .skip_while(|l| *l != MARKER)
+            .skip(1);
+        let mut field = || lines.next().filter(|l| !l.is_empty()).map(str::to_string);
+        let Some(system) = field() else {
+            return Self::unknown();
+        };
+        let (hostname, kernel, arch, shell) = (field(), field(), field(), field());
+
+        let mut name = None;
+        let mut pretty_name = None;
+        for line in lines {
+            match line.split_once('=') {
+                Some(("PRETTY_NAME", value)) => pretty_name = Some(unquote(value)),
+                Some(("NAME", value)) => name = Some(unquote(value)),
+                _ => {}
+            }
+        }
+
+        Self {
+            os: os_name(&system),
+            distro: pretty_name.or(name),
+            kernel,
+            arch,
+            shell,
+            hostname,
+        }
+    }
+}
+
+/// Short OS name for `uname -s`
+fn os_name(system: &str) -> String {
+    let lower = system.to_lowercase();
+    match lower.as_str() {
+        "darwin" => "macos".to_string(),
+        "sunos" => "solaris".to_string(),
+        _ if lower.starts_with("mingw")
+            || lower.starts_with("msys")
+            || lower.starts_with("cygwin") =>
+        {
+            "windows".to_string()
+        }
+        _ => lower,
+    }
+}
+
+fn unquote(value: &str) -> String {
+    value.trim().trim_matches(['"', '\'']).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_probe() {
+        let debian = "nexus-remote-info\nLinux\nweb-1\n6.1.0-18-amd64\nx86_64\n/bin/bash\n\
+            PRETTY_NAME=\"Debian GNU/Linux 12 (bookworm)\"\nNAME=\"Debian GNU/Linux\"\nID=debian\n";
+        assert_eq!(
+            RemoteInfo::from_probe(debian),
+            RemoteInfo {
+                os: "linux".into(),
+                distro: Some("Debian GNU/Linux 12 (bookworm)".into()),
+                kernel: Some("6.1.0-18-amd64".into()),
+                arch: Some("x86_64".into()),
+                shell: Some("/bin/bash".into()),
+                hostname: Some("web-1".into()),
+            }
+        );
+
+        let mac = RemoteInfo::from_probe(
+            "Last login: today\nnexus-remote-info\nDarwin\nmbp\n23.4.0\narm64\n/bin/zsh\n",
+        );
+        assert_eq!(mac.os, "macos");
+        assert_eq!(mac.distro, None);
+        assert_eq!(mac.arch.as_deref(), Some("arm64"));
+
+        // cmd.exe and PowerShell don't know printf
+        assert_eq!(
+            RemoteInfo::from_probe("'printf' is not recognized as an internal or external command"),
+            RemoteInfo::unknown()
+        );
+    }
+}
@@ -0,0 +1,280 @@
+//! Address resolution and TCP dialing for SSH connections
+//!
+//! Resolves the target explicitly so dual-stack behavior can be controlled,
+//! then dials the candidates Happy Eyeballs style (RFC 8305): in order, with
+//! the next one started once the previous fails or hasn't connected within
+//! `ATTEMPT_DELAY`. Each attempt has its own timeout, and all of them end
+//! with the connection timeout.
+
+use super::SshError;
+use futures::stream::{FuturesUnordered, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio::time::Instant;
+
+/// Number of DNS lookup attempts before giving up
+const DNS_ATTEMPTS: u32 = 3;
+
+/// Delay between DNS lookup attempts
+const DNS_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// Upper bound for a single address attempt
+const PER_ADDRESS_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long an attempt runs alone before the next address is dialed too
+const ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
+/// Address family preference for outgoing connections
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum AddressFamily {
+    /// Use both families, alternating between them
+    #[default]
+    Auto,
+    /// IPv4 only
+    V4,
+    /// IPv6 only
+    V6,
+}
+
+/// Resolve `host:port` into an ordered list of candidate addresses
+pub async fn resolve(
+    host: &str,
+    port: u16,
+    family: AddressFamily,
+) -> Result<Vec<SocketAddr>, SshError> {
+    let mut last_error = String::new();
+
+    for attempt in 1..=DNS_ATTEMPTS {
+        match tokio::net::lookup_host((host, port)).await {
+            Ok(addrs) => {
+                let addrs = order_addresses(addrs.collect(), family);
+                if addrs.is_empty() {
+                    return Err(SshError::DnsResolutionFailed(format!(
+                        "{}: no {} addresses",
+                        host,
+                        match family {
+                            AddressFamily::V4 => "IPv4",
+                            AddressFamily::V6 => "IPv6",
+                            AddressFamily::Auto => "usable",
+                        }
+                    )));
+                }
+                return Ok(addrs);
+            }
+            Err(e) => {
+                tracing::debug!(
+                    "DNS lookup for {} failed (attempt {}): {}",
+                    host,
+                    attempt,
+                    e
+                );
+                last_error = e.to_string();
+                if attempt < DNS_ATTEMPTS {
+                    tokio::time::sleep(DNS_RETRY_DELAY).await;
+                }
+            }
+        }
+    }
+
+    Err(SshError::DnsResolutionFailed(format!(
+        "{}: {}",
+        host, last_error
+    )))
+}
+
+/// Filter by family and, for `Auto`, interleave families starting with
+/// whichever the resolver listed first
+fn order_addresses(addrs: Vec<SocketAddr>, family: AddressFamily) -> Vec<SocketAddr> {
+    match family {
+        AddressFamily::V4 => addrs.into_iter().filter(|a| a.is_ipv4()).collect(),
+        AddressFamily::V6 => addrs.into_iter().filter(|a| a.is_ipv6()).collect(),
+        AddressFamily::Auto => {
+            let prefer_v6 = addrs.first().map(|a| a.is_ipv6()).unwrap_or(false);
+            let (mut first, mut second): (Vec<_>, Vec<_>) =
+                addrs.into_iter().partition(|a| a.is_ipv6() == prefer_v6);
+
+            let mut ordered = Vec::with_capacity(first.len() + second.len());
+            first.reverse();
+            second.reverse();
+            while let Some(addr) = first.pop() {
+                ordered.push(addr);
+                if let Some(addr) = second.pop() {
+                    ordered.push(addr);
+                }
+            }
+            ordered.extend(second.into_iter().rev());
+            ordered
+        }
+    }
+}
+
+/// Dial the candidates, returning the first stream that connects
+///
+/// Attempts overlap: the next address is dialed as soon as the previous one
+/// fails or after `ATTEMPT_DELAY`, and the first to connect wins. `timeout`
+/// bounds the whole attempt; addresses not reached before it runs out are
+/// skipped.
+pub async fn connect_tcp(
+    addrs: &[SocketAddr],
+    timeout: Duration,
+) -> Result<(TcpStream, SocketAddr), SshError> {
+    let deadline = Instant::now() + timeout;
+    let mut all_refused = true;
+    let mut last_error = String::new();
+
+    let dial = |addr: SocketAddr| async move {
+        let per_address = deadline
+            .saturating_duration_since(Instant::now())
+            .min(PER_ADDRESS_TIMEOUT);
+        (
+            addr,
+            tokio::time::timeout(per_address, TcpStream::connect(addr)).await,
+        )
+    };
+    let mut remaining = addrs.iter().copied().peekable();
+    let mut attempts = FuturesUnordered::new();
+    let mut next_attempt = Instant::now();
+
+    loop {
+        let now = Instant::now();
+        let due = attempts.is_empty() || now >= next_attempt;
+        if let Some(addr) = remaining.next_if(|_| due) {
+            if now >= deadline {
+                // Out of time; nothing further is dialed
+                all_refused = false;
+                last_error = format!("{}: timed out", addr);
+                remaining.by_ref().for_each(drop);
+            } else {
+                attempts.push(dial(addr));
+                next_attempt = now + ATTEMPT_DELAY;
+            }
+            continue;
+        }
+        if attempts.is_empty() {
+            break;
+        }
+
+        tokio::select! {
+            Some((addr, result)) = attempts.next() => match result {
+                Ok(Ok(stream)) => {
+                    tracing::debug!("Connected to {}", addr);
+                    return Ok((stream, addr));
+                }
+                Ok(Err(e)) => {
+                    tracing::debug!("Connecting to {} failed: {}", addr, e);
+                    all_refused &= e.kind() == std::io::ErrorKind::ConnectionRefused;
+                    last_error = format!("{}: {}", addr, e);
+                    // A failed attempt hands over to the next address right away
+                    next_attempt = Instant::now();
+                }
+                Err(_) => {
+                    tracing::debug!("Connecting to {} timed out", addr);
+                    all_refused = false;
+                    last_error = format!("{}: timed out", addr);
+                    next_attempt = Instant::now();
+                }
+            },
+            _ = tokio::time::sleep_until(next_attempt), if remaining.peek().is_some() => {}
+        }
+    }
+
+    if all_refused {
+        Err(SshError::ConnectionRefused(last_error))
+    } else {
+        Err(SshError::ConnectionFailed(last_error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    /// A local port nothing listens on
+    async fn closed_port() -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        listener.local_addr().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_connect_tries_addresses_in_order() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let open = listener.local_addr().unwrap();
+        let closed = closed_port().await;
+
+        let (_, addr) = connect_tcp(&[closed, open], Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert_eq!(addr, open);
+
+        assert!(matches!(
+            connect_tcp(&[closed], Duration::from_secs(5)).await,
+            Err(SshError::ConnectionRefused(_))
+        ));
+    }
+
+    #[test]
+    fn test_order_addresses() {
+        let v4 = |last: u8| SocketAddr::from(([192, 0, 2, last], 22));
+        let v6 = |last: u16| SocketAddr::from(([0x2001, 0xdb8, 0, 0, 0, 0, 0, last], 22));
+        let addrs = vec![v6(1), v6(2), v6(3), v4(1), v4(2)];
+
+        // Families alternate, starting with the one listed first
+        assert_eq!(
+            order_addresses(addrs.clone(), AddressFamily::Auto),
+            [v6(1), v4(1), v6(2), v4(2), v6(3)]
+        );
+        assert_eq!(
+            order_addresses(vec![v4(1), v6(1), v6(2)], AddressFamily::Auto),
+            [v4(1), v6(1), v6(2)]
+        );
+        assert_eq!(
+            order_addresses(addrs.clone(), AddressFamily::V4),
+            [v4(1), v4(2)]
+        );
+        assert_eq!(
+            order_addresses(addrs, AddressFamily::V6),
+            [v6(1), v6(2), v6(3)]
+        );
+        assert!(order_addresses(vec![v6(1)], AddressFamily::V4).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_connect_does_not_wait_for_a_stalled_address() {
+        // A listener whose backlog is full leaves further connects hanging
+        let socket = tokio::net::TcpSocket::new_v4().unwrap();
+        socket.bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let stalled = socket.listen(0).unwrap();
+        let stalled_addr = stalled.local_addr().unwrap();
+        let mut queued = Vec::new();
+        while let Ok(Ok(stream)) =
+            tokio::time::timeout(Duration::from_millis(200), TcpStream::connect(stalled_addr)).await
+        {
+            queued.push(stream);
+        }
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let open = listener.local_addr().unwrap();
+        let started = Instant::now();
+        let (_, addr) = connect_tcp(&[stalled_addr, open], Duration::from_secs(10))
+            .await
+            .unwrap();
+        assert_eq!(addr, open);
+        assert!(started.elapsed() < PER_ADDRESS_TIMEOUT);
+    }
+
+    #[tokio::test]
+    async fn test_connect_stops_at_deadline() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let open = listener.local_addr().unwrap();
+
+        // Once the time is used up no further address is dialed
+        assert!(matches!(
+            connect_tcp(&[open], Duration::ZERO).await,
+            Err(SshError::ConnectionFailed(e)) if e.ends_with("timed out")
+        ));
+    }
+}
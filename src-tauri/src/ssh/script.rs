@@ -0,0 +1,237 @@
+//! Running local script files on a remote host
+//!
+//! Small scripts are piped to the interpreter's stdin. Larger ones are
+//! copied to a remote temp file first, which also leaves stdin free for
+//! commands inside the script.
+
+use super::client::{CommandOutput, OutputChunk, OutputStream, SshClient, SshError};
+use crate::sftp::helper::{helper_script, missing_tool};
+use crate::utils::shell_quote;
+use std::path::Path;
+
+/// Scripts up to this size are sent over stdin
+const INLINE_LIMIT: usize = 64 * 1024;
+
+/// Interpreters that take `-s` to read the script from stdin
+const SHELLS: &[&str] = &["sh", "bash", "zsh", "dash", "ksh", "mksh", "ash"];
+
+/// Run the local file at `local_path` on the server with `interpreter`
+///
+/// Output is passed to `on_output` as it arrives, split at UTF-8
+/// character boundaries.
+pub async fn run_local_script<F>(
+    client: &SshClient,
+    local_path: &Path,
+    interpreter: &str,
+    args: &[String],
+    on_output: F,
+) -> Result<CommandOutput, SshError>
+where
+    F: FnMut(OutputChunk) + Send,
+{
+    validate_interpreter(interpreter)?;
+    let script = tokio::fs::read(local_path).await?;
+    let args: Vec<String> = args.iter().map(|a| shell_quote(a)).collect();
+    let args = args.join(" ");
+    let profile = client.config.exec_profile.as_deref();
+
+    let mut decoder = ChunkDecoder::new(on_output);
+    let mut temp = None;
+    let output = if script.len() <= INLINE_LIMIT {
+        let stdin_flag = if is_shell(interpreter) { "-s --" } else { "-" };
+        let command = format!("{} {} {}", interpreter, stdin_flag, args);
+        let command = client
+            .posix_command(&helper_script(profile, &[interpreter], &command))
+            .await?;
+        client
+            .execute_with_callback(&command, &script, |stream, data| decoder.push(stream, data))
+            .await?
+    } else {
+        let path = upload_temp(client, &script).await?;
+        let quoted = shell_quote(&path);
+        let command = format!(
+            "trap 'rm -f {q}' EXIT; chmod +x {q} && {} {q} {}",
+            interpreter,
+            args,
+            q = quoted
+        );
+        temp = Some(quoted);
+        let command = client
+            .posix_command(&helper_script(profile, &[interpreter], &command))
+            .await?;
+        client
+            .execute_with_callback(&command, &[], |stream, data| decoder.push(stream, data))
+            .await?
+    };
+    decoder.finish();
+
+    if let Some(tool) = missing_tool(&output) {
+        // The trap is only set once the interpreter check has passed
+        if let Some(quoted) = temp {
+            if let Ok(command) = client.posix_command(&format!("rm -f {}", quoted)).await {
+                let _ = client.execute(&command).await;
+            }
+        }
+        return Err(SshError::InterpreterNotFound(tool));
+    }
+
+    tracing::info!(
+        "Ran {} on {} with {} (exit {})",
+        local_path.display(),
+        client.config.host,
+        interpreter,
+        output.exit_code
+    );
+    Ok(output)
+}
+
+/// Copy `script` into a fresh remote temp file, returning its path
+async fn upload_temp(client: &SshClient, script: &[u8]) -> Result<String, SshError> {
+    let output = client
+        .execute(&client.posix_command("mktemp").await?)
+        .await?;
+    let path = output.stdout.trim().to_string();
+    if output.exit_code != 0 || path.is_empty() {
+        return Err(SshError::CommandFailed(format!(
+            "Could not create temp file: {}",
+            output.stderr.trim()
+        )));
+    }
+
+    let upload = client
+        .execute_with_input(
+            &client
+                .posix_command(&format!("cat > {}", shell_quote(&path)))
+                .await?,
+            script,
+        )
+        .await?;
+    if upload.exit_code != 0 {
+        let remove = client
+            .posix_command(&format!("rm -f {}", shell_quote(&path)))
+            .await?;
+        let _ = client.execute(&remove).await;
+        return Err(SshError::CommandFailed(format!(
+            "Could not upload script: {}",
+            upload.stderr.trim()
+        )));
+    }
+    Ok(path)
+}
+
+/// Allow bare names like `python3` or absolute paths, nothing with shell syntax
+fn validate_interpreter(interpreter: &str) -> Result<(), SshError> {
+    let valid = !interpreter.is_empty()
+        && !interpreter.starts_with('-')
+        && interpreter
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '/' | '.' | '_' | '-' | '+'));
+    if valid {
+        Ok(())
+    } else {
+        Err(SshError::CommandFailed(format!(
+            "Invalid interpreter: {}",
+            interpreter
+        )))
+    }
+}
+
+fn is_shell(interpreter: &str) -> bool {
+    let name = interpreter.rsplit('/').next().unwrap_or(interpreter);
+    SHELLS.contains(&name)
+}
+
+/// Take the decodable part of `pending`, holding back an incomplete trailing character
+pub(crate) fn take_complete(pending: &mut Vec<u8>) -> Option<String> {
+    let complete = match std::str::from_utf8(pending) {
+        Ok(_) => pending.len(),
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        Err(_) => pending.len(),
+    };
+    if complete == 0 {
+        return None;
+    }
+    let rest = pending.split_off(complete);
+    let text = String::from_utf8_lossy(pending).to_string();
+    *pending = rest;
+    Some(text)
+}
+
+/// Turns raw stream data into `OutputChunk`s without splitting characters
+pub(crate) struct ChunkDecoder<F> {
+    on_output: F,
+    seq: u64,
+    stdout: Vec<u8>,
+    stderr: Vec<u8>,
+}
+
+impl<F: FnMut(OutputChunk)> ChunkDecoder<F> {
+    pub(crate) fn new(on_output: F) -> Self {
+        Self {
+            on_output,
+            seq: 0,
+            stdout: Vec::new(),
+            stderr: Vec::new(),
+        }
+    }
+
+    pub(crate) fn push(&mut self, stream: OutputStream, data: &[u8]) {
+        let pending = match stream {
+            OutputStream::Stdout => &mut self.stdout,
+            OutputStream::Stderr => &mut self.stderr,
+        };
+        pending.extend_from_slice(data);
+        if let Some(text) = take_complete(pending) {
+            self.emit(stream, text);
+        }
+    }
+
+    pub(crate) fn finish(&mut self) {
+        for stream in [OutputStream::Stdout, OutputStream::Stderr] {
+            let pending = match stream {
+                OutputStream::Stdout => std::mem::take(&mut self.stdout),
+                OutputStream::Stderr => std::mem::take(&mut self.stderr),
+            };
+            if !pending.is_empty() {
+                self.emit(stream, String::from_utf8_lossy(&pending).to_string());
+            }
+        }
+    }
+
+    fn emit(&mut self, stream: OutputStream, data: String) {
+        (self.on_output)(OutputChunk {
+            seq: self.seq,
+            stream,
+            data,
+        });
+        self.seq += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_script_helpers() {
+        assert!(validate_interpreter("python3").is_ok());
+        assert!(validate_interpreter("/usr/bin/bash").is_ok());
+        assert!(validate_interpreter("bash; rm -rf /").is_err());
+        assert!(validate_interpreter("-c").is_err());
+        assert!(is_shell("/bin/bash"));
+        assert!(!is_shell("python3"));
+
+        let mut chunks = Vec::new();
+        let mut decoder = ChunkDecoder::new(|chunk: OutputChunk| chunks.push(chunk));
+        let bytes = "ü!".as_bytes();
+        decoder.push(OutputStream::Stdout, &bytes[..1]);
+        decoder.push(OutputStream::Stderr, b"err");
+        decoder.push(OutputStream::Stdout, &bytes[1..]);
+        decoder.finish();
+        drop(decoder);
+
+        let data: Vec<&str> = chunks.iter().map(|c| c.data.as_str()).collect();
+        assert_eq!(data, ["err", "ü!"]);
+        assert_eq!(chunks[1].seq, 1);
+    }
+}
@@ -0,0 +1,209 @@
+//! Detecting the login shell behind exec channels
+//!
+//! Exec helpers are written for POSIX `sh`, but the server runs them with
+//! the user's login shell. fish and csh get the script wrapped in
+//! `sh -c`; PowerShell, cmd.exe, restricted and unrecognised shells are
+//! refused rather than sent commands they would misparse.
+
+use super::SshError;
+use crate::utils::shell_quote;
+use serde::{Deserialize, Serialize};
+
+/// Probe that prints something distinctive in every shell we know
+///
+/// POSIX shells expand `$0` to their name, PowerShell expands
+/// `$PSVersionTable`, cmd.exe echoes the line unchanged. fish and csh fail
+/// on the unknown variables and say so on stderr.
+pub const PROBE_COMMAND: &str = "echo \"nexus|$0|$SHELL|$PSVersionTable\"";
+
+/// Kind of shell that runs exec commands
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ShellKind {
+    Posix,
+    Fish,
+    Csh,
+    PowerShell,
+    Cmd,
+    /// rbash and friends; helpers can't rely on paths or redirection
+    Restricted,
+    Unknown,
+}
+
+impl ShellKind {
+    /// Parse the output of `PROBE_COMMAND`
+    pub fn from_probe(stdout: &str, stderr: &str) -> Self {
+        let line = stdout
+            .lines()
+            .map(|l| l.trim().trim_start_matches('"'))
+            .find(|l| l.starts_with("nexus|"));
+        let Some(line) = line else {
+            let stderr = stderr.to_lowercase();
+            return if stderr.contains("fish") {
+                Self::Fish
+            } else if stderr.contains("undefined variable") {
+                Self::Csh
+            } else if stderr.contains("restricted") {
+                Self::Restricted
+            } else {
+                Self::Unknown
+            };
+        };
+
+        let fields: Vec<&str> = line.trim_end_matches('"').split('|').collect();
+        let zero = fields.get(1).copied().unwrap_or_default();
+        let shell = fields.get(2).copied().unwrap_or_default();
+        let ps = fields.get(3).copied().unwrap_or_default();
+
+        // cmd.exe echoes the variables literally
+        if zero == "$0" {
+            return Self::Cmd;
+        }
+        if ps.contains("PSVersion") || ps.contains("Hashtable") {
+            return Self::PowerShell;
+        }
+        let name = if zero.is_empty() { shell } else { zero };
+        let name = name.rsplit(['/', '\\']).next().unwrap_or(name);
+        match name.trim_start_matches('-') {
+            "sh" | "bash" | "dash" | "zsh" | "ksh" | "mksh" | "ash" | "yash" | "posh"
+            | "busybox" => Self::Posix,
+            "rbash" | "rksh" | "rzsh" | "lshell" => Self::Restricted,
+            "fish" => Self::Fish,
+            "csh" | "tcsh" => Self::Csh,
+            _ => Self::Unknown,
+        }
+    }
+
+    /// Turn a POSIX `sh` script into a command this shell runs correctly
+    pub fn adapt(&self, script: &str) -> Result<String, SshError> {
+        match self {
+            Self::Posix => Ok(script.to_string()),
+            Self::Fish => Ok(format!("sh -c {}", fish_quote(script))),
+            // csh keeps backslash-newline inside single quotes as a newline
+            Self::Csh => Ok(format!(
+                "sh -c {}",
+                shell_quote(script).replace('\n', "\\\n")
+            )),
+            other => Err(SshError::UnsupportedShell(other.name().to_string())),
+        }
+    }
+
+    /// Variable holding the exit status of the last command
+    pub fn status_var(&self) -> &'static str {
+        match self {
+            Self::Fish | Self::Csh => "$status",
+            _ => "$?",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Posix => "posix",
+            Self::Fish => "fish",
+            Self::Csh => "csh",
+            Self::PowerShell => "powershell",
+            Self::Cmd => "cmd",
+            Self::Restricted => "restricted",
+            Self::Unknown => "unknown",
+        }
+    }
+}
+
+/// Whether `name` can be exported from a POSIX shell
+pub fn is_env_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Input for a fresh interactive shell: export `env`, then run `commands`
+///
+/// Empty when there is nothing to do. Each line ends in a newline as if
+/// typed; blank commands are skipped.
+pub fn init_script(env: &[(String, String)], commands: &[String]) -> String {
+    let mut script = String::new();
+    if !env.is_empty() {
+        let assignments: Vec<String> = env
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, shell_quote(value)))
+            .collect();
+        script.push_str(&format!("export {}\n", assignments.join(" ")));
+    }
+    for command in commands.iter().map(|c| c.trim()).filter(|c| !c.is_empty()) {
+        script.push_str(command);
+        script.push('\n');
+    }
+    script
+}
+
+/// Single-quote for fish, where `\` and `'` are escapes inside quotes
+fn fish_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_probe() {
+        assert_eq!(
+            ShellKind::from_probe("nexus|bash|/bin/bash|\n", ""),
+            ShellKind::Posix
+        );
+        assert_eq!(
+            ShellKind::from_probe("nexus|-zsh||\n", ""),
+            ShellKind::Posix
+        );
+        assert_eq!(
+            ShellKind::from_probe("nexus|rbash|/bin/rbash|\n", ""),
+            ShellKind::Restricted
+        );
+        assert_eq!(
+            ShellKind::from_probe(
+                "nexus|||System.Management.Automation.PSVersionHashTable\r\n",
+                ""
+            ),
+            ShellKind::PowerShell
+        );
+        assert_eq!(
+            ShellKind::from_probe("\"nexus|$0|$SHELL|$PSVersionTable\"\r\n", ""),
+            ShellKind::Cmd
+        );
+        assert_eq!(
+            ShellKind::from_probe("", "fish: $0 is not supported"),
+            ShellKind::Fish
+        );
+        assert_eq!(
+            ShellKind::from_probe("", "PSVersionTable: Undefined variable."),
+            ShellKind::Csh
+        );
+        assert_eq!(ShellKind::from_probe("", ""), ShellKind::Unknown);
+
+        assert_eq!(
+            ShellKind::Fish.adapt("echo 'a\\b'").unwrap(),
+            "sh -c 'echo \\'a\\\\b\\''"
+        );
+        assert!(ShellKind::PowerShell.adapt("ls").is_err());
+    }
+
+    #[test]
+    fn test_init_script() {
+        assert_eq!(init_script(&[], &[" ".into()]), "");
+        let env = [
+            ("APP_ENV".to_string(), "prod".to_string()),
+            ("GREETING".to_string(), "it's me".to_string()),
+        ];
+        assert_eq!(
+            init_script(&env, &["cd /app".into(), "source env".into()]),
+            "export APP_ENV='prod' GREETING='it'\\''s me'\ncd /app\nsource env\n"
+        );
+
+        assert!(is_env_name("_PATH2"));
+        assert!(!is_env_name("2PATH"));
+        assert!(!is_env_name("A-B"));
+        assert!(!is_env_name(""));
+    }
+}
@@ -0,0 +1,64 @@
+//! Connection lifecycle states and reason codes
+//!
+//! Reported to the frontend through `AppEvent::ConnectionStateChanged` so
+//! status indicators can follow a connection from dialing to teardown.
+
+use super::SshError;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Stage of a connection's lifecycle
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionState {
+    Resolving,
+    Connecting,
+    Authenticating,
+    Connected,
+    Disconnected,
+    Failed,
+}
+
+/// Why a connection changed state
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionReason {
+    UserRequested,
+    RemoteClosed,
+    NetworkLost,
+    DnsFailed,
+    ConnectionRefused,
+    Timeout,
+    AuthFailed,
+    HostKeyChanged,
+    KeyError,
+    /// Agent auth was requested but no agent is running
+    AgentUnavailable,
+    /// The server stopped answering keepalives
+    KeepaliveTimeout,
+    Error,
+}
+
+impl ConnectionReason {
+    /// Reason code for a failed connect
+    pub fn from_error(err: &SshError) -> Self {
+        match err {
+            SshError::DnsResolutionFailed(_) => Self::DnsFailed,
+            SshError::ConnectionRefused(_) => Self::ConnectionRefused,
+            SshError::Timeout => Self::Timeout,
+            SshError::AuthenticationFailed => Self::AuthFailed,
+            SshError::HostKeyChanged(_) => Self::HostKeyChanged,
+            SshError::KeyError(_) => Self::KeyError,
+            SshError::AgentUnavailable(_) => Self::AgentUnavailable,
+            SshError::KeepaliveTimeout => Self::KeepaliveTimeout,
+            SshError::JumpHostFailed { source, .. } => Self::from_error(source),
+            _ => Self::Error,
+        }
+    }
+}
+
+/// Callback invoked as a client moves through its stages
+///
+/// `SshClient::connect` reports each stage without a reason; a connection
+/// dropped for missed keepalives is reported as `Disconnected` with one.
+pub type StateListener = Arc<dyn Fn(ConnectionState, Option<ConnectionReason>) + Send + Sync>;
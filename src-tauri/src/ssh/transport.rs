@@ -0,0 +1,82 @@
+//! Local transports for SSH connections
+//!
+//! Besides TCP, an SSH server can be reached through a Unix domain socket
+//! (local VMs and containers, e.g. lima/colima) or, on Windows, a named
+//! pipe. The stream is handed to russh exactly like a TCP stream.
+
+use super::SshError;
+use serde::{Deserialize, Serialize};
+use std::io;
+
+/// How to reach the SSH server
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Transport {
+    /// `host`/`port` over TCP
+    #[default]
+    Tcp,
+    /// A Unix domain socket; `host` is then only used for display
+    UnixSocket { path: String },
+    /// A Windows named pipe such as `\\.\pipe\ssh-vm`
+    NamedPipe { path: String },
+}
+
+impl Transport {
+    /// Human readable target, for logs
+    pub fn describe(&self) -> Option<String> {
+        match self {
+            Self::Tcp => None,
+            Self::UnixSocket { path } => Some(format!("unix:{}", path)),
+            Self::NamedPipe { path } => Some(format!("pipe:{}", path)),
+        }
+    }
+}
+
+/// Connect to a Unix domain socket
+#[cfg(unix)]
+pub async fn connect_unix(path: &str) -> Result<tokio::net::UnixStream, SshError> {
+    tokio::net::UnixStream::connect(path)
+        .await
+        .map_err(|e| local_error("Socket", path, e))
+}
+
+/// Unix sockets are unavailable here; always fails
+#[cfg(not(unix))]
+pub async fn connect_unix(_path: &str) -> Result<tokio::net::TcpStream, SshError> {
+    Err(SshError::ConnectionFailed(
+        "Unix domain sockets are not supported on this platform".to_string(),
+    ))
+}
+
+/// Open a Windows named pipe
+#[cfg(windows)]
+pub async fn connect_pipe(
+    path: &str,
+) -> Result<tokio::net::windows::named_pipe::NamedPipeClient, SshError> {
+    tokio::net::windows::named_pipe::ClientOptions::new()
+        .open(path)
+        .map_err(|e| local_error("Named pipe", path, e))
+}
+
+/// Named pipes are unavailable here; always fails
+#[cfg(not(windows))]
+pub async fn connect_pipe(_path: &str) -> Result<tokio::net::TcpStream, SshError> {
+    Err(SshError::ConnectionFailed(
+        "Named pipes are only supported on Windows".to_string(),
+    ))
+}
+
+fn local_error(kind: &str, path: &str, err: io::Error) -> SshError {
+    match err.kind() {
+        io::ErrorKind::NotFound => {
+            SshError::ConnectionFailed(format!("{} {} does not exist", kind, path))
+        }
+        io::ErrorKind::ConnectionRefused => {
+            SshError::ConnectionRefused(format!("{} {}: nothing is listening", kind, path))
+        }
+        io::ErrorKind::PermissionDenied => {
+            SshError::ConnectionFailed(format!("{} {}: permission denied", kind, path))
+        }
+        _ => SshError::ConnectionFailed(format!("{} {}: {}", kind, path, err)),
+    }
+}
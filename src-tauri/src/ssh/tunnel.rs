@@ -0,0 +1,253 @@
+//! Port forwarding over an SSH connection
+//!
+//! Local forwards (`ssh -L`) listen on this machine and open a direct-tcpip
+//! channel per accepted connection. Remote forwards (`ssh -R`) ask the
+//! server to listen; it opens a forwarded-tcpip channel per connection,
+//! which `ClientHandler` hands to `serve_forwarded`. Tunnels belong to the
+//! client that opened them and are closed with it.
+
+use super::client::ClientHandler;
+use super::SshError;
+use parking_lot::Mutex;
+use russh::client::{Handle, Msg};
+use russh::Channel;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Weak};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::RwLock;
+use tokio::task::{JoinHandle, JoinSet};
+use uuid::Uuid;
+
+/// Direction of a tunnel
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TunnelKind {
+    /// Listen locally, connect from the server
+    Local,
+    /// Listen on the server, connect from here
+    Remote,
+}
+
+/// An open tunnel
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tunnel {
+    pub id: Uuid,
+    #[serde(rename = "type")]
+    pub kind: TunnelKind,
+    /// Address being listened on, `host:port`
+    pub bind_addr: String,
+    /// Where connections are forwarded to, `host:port`
+    pub target: String,
+}
+
+/// Local targets of remote forwards, by the address and port the server listens on
+pub(crate) type RemoteTargets = Arc<Mutex<HashMap<(String, u32), (String, u16)>>>;
+
+enum Forward {
+    /// Accept loop; aborting it closes the listener and its connections
+    Local(JoinHandle<()>),
+    Remote {
+        address: String,
+        port: u32,
+    },
+}
+
+/// Tunnels of one client
+#[derive(Default)]
+pub(crate) struct Tunnels {
+    active: Mutex<HashMap<Uuid, (Tunnel, Forward)>>,
+    remote_targets: RemoteTargets,
+}
+
+impl Tunnels {
+    pub fn remote_targets(&self) -> RemoteTargets {
+        self.remote_targets.clone()
+    }
+
+    pub fn list(&self) -> Vec<Tunnel> {
+        let mut list: Vec<_> = self
+            .active
+            .lock()
+            .values()
+            .map(|(t, _)| t.clone())
+            .collect();
+        list.sort_by(|a, b| a.bind_addr.cmp(&b.bind_addr));
+        list
+    }
+
+    /// Start forwarding connections to `listener` through `session`
+    pub fn add_local(
+        &self,
+        listener: TcpListener,
+        session: Weak<RwLock<Handle<ClientHandler>>>,
+        target: (String, u16),
+    ) -> Result<Tunnel, SshError> {
+        let tunnel = Tunnel {
+            id: Uuid::new_v4(),
+            kind: TunnelKind::Local,
+            bind_addr: listener.local_addr()?.to_string(),
+            target: join_host_port(&target.0, target.1),
+        };
+        let task = tokio::spawn(serve_local(listener, session, target));
+        self.active
+            .lock()
+            .insert(tunnel.id, (tunnel.clone(), Forward::Local(task)));
+        Ok(tunnel)
+    }
+
+    /// Record a forward the server has agreed to
+    pub fn add_remote(&self, address: String, port: u32, target: (String, u16)) -> Tunnel {
+        let tunnel = Tunnel {
+            id: Uuid::new_v4(),
+            kind: TunnelKind::Remote,
+            bind_addr: join_host_port(&address, port),
+            target: join_host_port(&target.0, target.1),
+        };
+        self.remote_targets
+            .lock()
+            .insert((address.clone(), port), target);
+        self.active.lock().insert(
+            tunnel.id,
+            (tunnel.clone(), Forward::Remote { address, port }),
+        );
+        tunnel
+    }
+
+    /// Stop a tunnel; returns the server-side forward to cancel, if any
+    pub fn remove(&self, id: Uuid) -> Option<Option<(String, u32)>> {
+        let (_, forward) = self.active.lock().remove(&id)?;
+        Some(match forward {
+            Forward::Local(task) => {
+                task.abort();
+                None
+            }
+            Forward::Remote { address, port } => {
+                self.remote_targets.lock().remove(&(address.clone(), port));
+                Some((address, port))
+            }
+        })
+    }
+
+    pub fn ids(&self) -> Vec<Uuid> {
+        self.active.lock().keys().copied().collect()
+    }
+}
+
+impl Drop for Tunnels {
+    fn drop(&mut self) {
+        for (_, forward) in self.active.get_mut().values() {
+            if let Forward::Local(task) = forward {
+                task.abort();
+            }
+        }
+    }
+}
+
+/// Accept local connections and forward each over its own channel
+async fn serve_local(
+    listener: TcpListener,
+    session: Weak<RwLock<Handle<ClientHandler>>>,
+    (host, port): (String, u16),
+) {
+    let mut connections = JoinSet::new();
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                tracing::warn!("Tunnel listener failed: {}", e);
+                break;
+            }
+        };
+        // The client is gone; nothing can be forwarded any more
+        let Some(session) = session.upgrade() else {
+            break;
+        };
+        let (host, port) = (host.clone(), port);
+        connections.spawn(async move {
+            let opened = session
+                .read()
+                .await
+                .channel_open_direct_tcpip(
+                    host.as_str(),
+                    port as u32,
+                    peer.ip().to_string(),
+                    peer.port() as u32,
+                )
+                .await;
+            drop(session);
+            match opened {
+                Ok(channel) => pipe(stream, channel).await,
+                Err(e) => tracing::warn!("Could not open tunnel to {}:{}: {}", host, port, e),
+            }
+        });
+        // Reap finished connections so the set doesn't grow without bound
+        while connections.try_join_next().is_some() {}
+    }
+}
+
+/// Connect a channel the server opened for a remote forward to its local target
+pub(crate) fn serve_forwarded(channel: Channel<Msg>, (host, port): (String, u16)) {
+    tokio::spawn(async move {
+        match TcpStream::connect((host.as_str(), port)).await {
+            Ok(stream) => pipe(stream, channel).await,
+            Err(e) => {
+                tracing::warn!("Could not reach tunnel target {}:{}: {}", host, port, e);
+                let _ = channel.close().await;
+            }
+        }
+    });
+}
+
+async fn pipe(mut stream: TcpStream, channel: Channel<Msg>) {
+    let mut channel = channel.into_stream();
+    if let Err(e) = tokio::io::copy_bidirectional(&mut stream, &mut channel).await {
+        tracing::debug!("Tunnel connection ended: {}", e);
+    }
+}
+
+/// Split `host:port`, with IPv6 hosts in brackets
+pub fn split_host_port(addr: &str) -> Result<(String, u16), SshError> {
+    let invalid = || SshError::InvalidAddress(addr.to_string());
+    let (host, port) = addr.rsplit_once(':').ok_or_else(invalid)?;
+    let host = match host.strip_prefix('[') {
+        Some(inner) => inner.strip_suffix(']').ok_or_else(invalid)?,
+        None if host.contains(':') => return Err(invalid()),
+        None => host,
+    };
+    if host.is_empty() {
+        return Err(invalid());
+    }
+    let port = port.parse().map_err(|_| invalid())?;
+    Ok((host.to_string(), port))
+}
+
+fn join_host_port(host: &str, port: impl std::fmt::Display) -> String {
+    if host.contains(':') {
+        format!("[{}]:{}", host, port)
+    } else {
+        format!("{}:{}", host, port)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_host_port() {
+        assert_eq!(
+            split_host_port("localhost:8080").unwrap(),
+            ("localhost".to_string(), 8080)
+        );
+        assert_eq!(
+            split_host_port("[::1]:22").unwrap(),
+            ("::1".to_string(), 22)
+        );
+        assert!(split_host_port("::1:22").is_err());
+        assert!(split_host_port("host").is_err());
+        assert!(split_host_port(":80").is_err());
+        assert!(split_host_port("host:99999").is_err());
+        assert_eq!(join_host_port("::1", 22), "[::1]:22");
+    }
+}
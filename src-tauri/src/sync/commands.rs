@@ -0,0 +1,23 @@
+//! Sync Tauri Commands
+
+use super::{SyncError, SyncOutcome, SyncResolution, SyncStatusReport};
+use std::collections::HashMap;
+
+/// Get the sync status, failure counts and next retry time
+#[tauri::command]
+pub fn get_sync_status() -> SyncStatusReport {
+    super::status()
+}
+
+/// Sync DataSphere with the configured provider now
+///
+/// Changes from both sides are merged. Items changed on both sides fail
+/// with a conflict; call again with `choices` (item id to side), or with
+/// `resolution` to keep one side's whole vault.
+#[tauri::command]
+pub async fn sync_now(
+    resolution: Option<SyncResolution>,
+    choices: Option<HashMap<String, SyncResolution>>,
+) -> Result<SyncOutcome, SyncError> {
+    super::sync_now(resolution, &choices.unwrap_or_default()).await
+}
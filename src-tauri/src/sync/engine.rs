@@ -0,0 +1,464 @@
+//! Sync engine
+//!
+//! The remote file holds every encrypted data file, so nothing leaves the
+//! device unencrypted. `sync_now` compares the remote version and the local
+//! files with what they were after the last sync. If only one side changed,
+//! it is copied to the other. If both did, the two are merged against the
+//! data of the last sync (see `merge`); items changed on both sides stop the
+//! sync with a `SyncConflict` until the user picks a side.
+//!
+//! That bookkeeping is kept per device next to the data files:
+//! `sync_state.json` holds ETags and timestamps only, `sync_base.json` the
+//! data of the last sync, encrypted like the data files.
+
+use super::merge;
+use super::s3::S3Backend;
+use super::webdav::WebDavBackend;
+use super::{PushResult, RemoteVersion, SyncConflict, SyncError};
+use crate::datasphere::{self, DataSet, DataSphereError, SyncProvider, VaultFile};
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+use zeroize::Zeroizing;
+
+const STATE_FILE: &str = "sync_state.json";
+const BASE_FILE: &str = "sync_base.json";
+
+/// Format version of the remote file
+const BUNDLE_VERSION: u8 = 1;
+
+/// Held for the whole of a sync, so two syncs can't interleave
+static SYNC_LOCK: Lazy<tokio::sync::Mutex<()>> = Lazy::new(|| tokio::sync::Mutex::new(()));
+
+/// Where both sides were after the last successful sync
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SyncState {
+    pub etag: Option<String>,
+    pub remote_modified: Option<DateTime<Utc>>,
+    /// Newest local data file
+    pub local_modified: Option<DateTime<Utc>>,
+    pub synced_at: DateTime<Utc>,
+}
+
+/// The remote file
+#[derive(Serialize, Deserialize)]
+struct SyncBundle {
+    version: u8,
+    /// Encrypted data files by name
+    files: BTreeMap<String, VaultFile>,
+}
+
+/// Side kept when resolving a conflict
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncResolution {
+    /// Overwrite the remote vault with this device's
+    KeepLocal,
+    /// Replace this device's vault with the remote one
+    KeepRemote,
+}
+
+/// What a sync did
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum SyncOutcome {
+    UpToDate,
+    Pushed,
+    /// Changes from both sides were merged and uploaded
+    Merged,
+    /// With `unlock_required` the remote vault uses another master password
+    /// and the storage was locked; unlock it with that password
+    Pulled {
+        unlock_required: bool,
+    },
+}
+
+/// The configured provider
+enum Backend {
+    WebDav(WebDavBackend),
+    S3(S3Backend),
+}
+
+impl Backend {
+    async fn head(&self) -> Result<Option<RemoteVersion>, SyncError> {
+        match self {
+            Backend::WebDav(backend) => backend.head().await,
+            Backend::S3(backend) => backend.head().await,
+        }
+    }
+
+    async fn pull(&self) -> Result<Option<(Vec<u8>, RemoteVersion)>, SyncError> {
+        match self {
+            Backend::WebDav(backend) => backend.pull().await,
+            Backend::S3(backend) => backend.pull().await,
+        }
+    }
+
+    async fn push(&self, body: Vec<u8>, expected: Option<&str>) -> Result<PushResult, SyncError> {
+        match self {
+            Backend::WebDav(backend) => backend.push(body, expected).await,
+            Backend::S3(backend) => backend.push(body, expected).await,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum SyncAction {
+    UpToDate,
+    Push,
+    Pull,
+    Conflict,
+}
+
+/// Decide what to do from the last sync's state and both sides' versions
+fn plan(
+    state: Option<&SyncState>,
+    local_modified: Option<DateTime<Utc>>,
+    remote: Option<&RemoteVersion>,
+) -> SyncAction {
+    let Some(remote) = remote else {
+        return match local_modified {
+            Some(_) => SyncAction::Push,
+            None => SyncAction::UpToDate,
+        };
+    };
+    // Never synced: only a device without data may take the remote vault unasked
+    let Some(state) = state else {
+        return match local_modified {
+            Some(_) => SyncAction::Conflict,
+            None => SyncAction::Pull,
+        };
+    };
+
+    let remote_changed = match (&remote.etag, &state.etag) {
+        (Some(etag), Some(synced)) => etag != synced,
+        _ => remote.modified != state.remote_modified,
+    };
+    let local_changed = local_modified != state.local_modified;
+    match (local_changed, remote_changed) {
+        (false, false) => SyncAction::UpToDate,
+        (true, false) => SyncAction::Push,
+        (false, true) => SyncAction::Pull,
+        (true, true) => SyncAction::Conflict,
+    }
+}
+
+/// Sync with the configured provider
+///
+/// `resolution` settles a conflict reported by an earlier sync for the whole
+/// vault, `choices` for single items by id.
+pub async fn sync_now(
+    resolution: Option<SyncResolution>,
+    choices: &HashMap<String, SyncResolution>,
+) -> Result<SyncOutcome, SyncError> {
+    let _sync = SYNC_LOCK.lock().await;
+    let (backend, data_dir) = configured_backend()?;
+
+    let remote = super::run(|| backend.head()).await?;
+    let local_modified = local_modified()?;
+    let action = match resolution {
+        Some(SyncResolution::KeepLocal) => SyncAction::Push,
+        Some(SyncResolution::KeepRemote) => SyncAction::Pull,
+        None => plan(
+            load_state(&data_dir).as_ref(),
+            local_modified,
+            remote.as_ref(),
+        ),
+    };
+
+    let result = match action {
+        SyncAction::UpToDate => Ok(SyncOutcome::UpToDate),
+        SyncAction::Push => {
+            let expected = remote.as_ref().and_then(|r| r.etag.as_deref());
+            push(&backend, &data_dir, expected).await
+        }
+        SyncAction::Pull => pull(&backend, &data_dir).await,
+        SyncAction::Conflict => merge_remote(&backend, &data_dir, local_modified, choices).await,
+    };
+    match &result {
+        Ok(_) => super::set_conflict(None),
+        Err(SyncError::Conflict(found)) => super::set_conflict(Some(found.clone())),
+        Err(_) => {}
+    }
+    result
+}
+
+/// When the last sync finished, if this device has synced
+pub fn last_synced_at(data_dir: &Path) -> Option<DateTime<Utc>> {
+    load_state(data_dir).map(|state| state.synced_at)
+}
+
+async fn push(
+    backend: &Backend,
+    data_dir: &Path,
+    expected: Option<&str>,
+) -> Result<SyncOutcome, SyncError> {
+    let (body, local_modified, base) = {
+        let storage = datasphere::storage().read();
+        let storage = storage.as_ref().ok_or(DataSphereError::NotInitialized)?;
+        let bundle = SyncBundle {
+            version: BUNDLE_VERSION,
+            files: storage.encrypted_files()?,
+        };
+        let body = serde_json::to_vec(&bundle).map_err(|e| SyncError::General(e.to_string()))?;
+        (body, storage.modified_at(), storage.data_set())
+    };
+
+    // Re-read after the upload; not every server returns the ETag on PUT
+    let remote = match super::run(|| backend.push(body.clone(), expected)).await? {
+        PushResult::Stored(_) => super::run(|| backend.head()).await?,
+        PushResult::Changed => {
+            let remote = super::run(|| backend.head()).await?;
+            return Err(conflict(local_modified, remote.as_ref()));
+        }
+    };
+    save_state(data_dir, remote.as_ref(), local_modified)?;
+    save_base(&base);
+    tracing::info!("Pushed DataSphere to the sync provider");
+    Ok(SyncOutcome::Pushed)
+}
+
+async fn pull(backend: &Backend, data_dir: &Path) -> Result<SyncOutcome, SyncError> {
+    let (bundle, remote) = download(backend).await?;
+    let (unlocked, local_modified) = {
+        let mut storage = datasphere::storage().write();
+        let storage = storage.as_mut().ok_or(DataSphereError::NotInitialized)?;
+        let unlocked = storage.replace_files(&bundle.files)?;
+        (unlocked, storage.modified_at())
+    };
+    save_state(data_dir, Some(&remote), local_modified)?;
+    if unlocked {
+        if let Some(storage) = datasphere::storage().read().as_ref() {
+            save_base(&storage.data_set());
+        }
+    }
+    if !unlocked {
+        datasphere::lock();
+    }
+    tracing::info!("Pulled DataSphere from the sync provider");
+    Ok(SyncOutcome::Pulled {
+        unlock_required: !unlocked,
+    })
+}
+
+/// Merge the remote vault into the local one and upload the result
+async fn merge_remote(
+    backend: &Backend,
+    data_dir: &Path,
+    local_modified: Option<DateTime<Utc>>,
+    choices: &HashMap<String, SyncResolution>,
+) -> Result<SyncOutcome, SyncError> {
+    let (bundle, remote) = download(backend).await?;
+    {
+        let mut storage = datasphere::storage().write();
+        let storage = storage.as_mut().ok_or(DataSphereError::NotInitialized)?;
+        // Data under another master password can't be read to merge it
+        if !storage.same_key(&bundle.files) {
+            return Err(conflict(local_modified, Some(&remote)));
+        }
+        let remote_data = storage.decrypt_files(&bundle.files)?;
+        let base = storage
+            .read_private_file::<DataSet>(BASE_FILE)
+            .unwrap_or_else(|e| {
+                tracing::warn!("Merging without the last sync's data: {}", e);
+                None
+            });
+        let result = merge::merge(base.as_ref(), &storage.data_set(), &remote_data, choices);
+        if !result.conflicts.is_empty() {
+            return Err(SyncError::Conflict(SyncConflict {
+                local_modified,
+                remote_modified: remote.modified,
+                items: result.conflicts,
+            }));
+        }
+        storage.replace_data(result.merged)?;
+    }
+    push(backend, data_dir, remote.etag.as_deref()).await?;
+    Ok(SyncOutcome::Merged)
+}
+
+/// Download and parse the remote vault
+async fn download(backend: &Backend) -> Result<(SyncBundle, RemoteVersion), SyncError> {
+    let (body, remote) = super::run(|| backend.pull())
+        .await?
+        .ok_or_else(|| SyncError::General("The remote vault no longer exists".to_string()))?;
+    let bundle: SyncBundle = serde_json::from_slice(&body)
+        .map_err(|e| SyncError::General(format!("Unreadable remote vault: {}", e)))?;
+    if bundle.version != BUNDLE_VERSION {
+        return Err(SyncError::General(format!(
+            "Unsupported remote vault version {}",
+            bundle.version
+        )));
+    }
+    Ok((bundle, remote))
+}
+
+/// Backend for the provider in settings, and the data directory
+fn configured_backend() -> Result<(Backend, PathBuf), SyncError> {
+    let storage = datasphere::storage().read();
+    let storage = storage.as_ref().ok_or(DataSphereError::NotInitialized)?;
+    let settings = storage.get_settings();
+    if !settings.sync_enabled {
+        return Err(SyncError::NotConfigured);
+    }
+
+    let credentials = |entry: Option<Uuid>| {
+        entry
+            .and_then(|id| storage.get_vault_entry(id))
+            .ok_or_else(|| {
+                SyncError::General("The sync credentials are missing from the vault".to_string())
+            })
+    };
+    let password = |entry| credentials(entry).map(|entry| Zeroizing::new(entry.secret));
+    let backend = match settings.sync_provider.ok_or(SyncError::NotConfigured)? {
+        SyncProvider::WebDAV {
+            url,
+            username,
+            password_entry,
+        } => Backend::WebDav(WebDavBackend::new(
+            &url,
+            &username,
+            password(password_entry)?,
+        )?),
+        SyncProvider::Nextcloud {
+            url,
+            username,
+            password_entry,
+        } => Backend::WebDav(WebDavBackend::nextcloud(
+            &url,
+            &username,
+            password(password_entry)?,
+        )?),
+        SyncProvider::S3 {
+            bucket,
+            region,
+            prefix,
+            endpoint,
+            credentials_entry,
+        } => {
+            let entry = credentials(credentials_entry)?;
+            let access_key = entry.username.clone().ok_or_else(|| {
+                SyncError::General(
+                    "The S3 credentials need the access key id as username".to_string(),
+                )
+            })?;
+            Backend::S3(S3Backend::new(
+                &bucket,
+                &region,
+                &prefix,
+                endpoint.as_deref(),
+                &access_key,
+                Zeroizing::new(entry.secret),
+            )?)
+        }
+    };
+    Ok((backend, storage.data_dir().to_path_buf()))
+}
+
+fn local_modified() -> Result<Option<DateTime<Utc>>, SyncError> {
+    let storage = datasphere::storage().read();
+    let storage = storage.as_ref().ok_or(DataSphereError::NotInitialized)?;
+    Ok(storage.modified_at())
+}
+
+fn conflict(local_modified: Option<DateTime<Utc>>, remote: Option<&RemoteVersion>) -> SyncError {
+    SyncError::Conflict(SyncConflict {
+        local_modified,
+        remote_modified: remote.and_then(|r| r.modified),
+        items: Vec::new(),
+    })
+}
+
+/// Keep the synced data as the base of the next merge
+///
+/// Without it the next merge falls back to `updated_at`, so failing to
+/// write it doesn't fail the sync.
+fn save_base(data: &DataSet) {
+    let storage = datasphere::storage().read();
+    if let Some(Err(e)) = storage
+        .as_ref()
+        .map(|s| s.write_private_file(BASE_FILE, data))
+    {
+        tracing::warn!("Could not save the sync base: {}", e);
+    }
+}
+
+fn load_state(data_dir: &Path) -> Option<SyncState> {
+    let data = fs::read_to_string(data_dir.join(STATE_FILE)).ok()?;
+    serde_json::from_str(&data).ok()
+}
+
+fn save_state(
+    data_dir: &Path,
+    remote: Option<&RemoteVersion>,
+    local_modified: Option<DateTime<Utc>>,
+) -> Result<(), SyncError> {
+    let state = SyncState {
+        etag: remote.and_then(|r| r.etag.clone()),
+        remote_modified: remote.and_then(|r| r.modified),
+        local_modified,
+        synced_at: Utc::now(),
+    };
+    let json = serde_json::to_vec_pretty(&state).map_err(|e| SyncError::General(e.to_string()))?;
+    let tmp = data_dir.join(format!("{}.tmp", STATE_FILE));
+    fs::write(&tmp, json)
+        .and_then(|_| fs::rename(&tmp, data_dir.join(STATE_FILE)))
+        .map_err(|e| SyncError::General(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_plan() {
+        let at = |hour| Some(Utc.with_ymd_and_hms(2024, 5, 1, hour, 0, 0).unwrap());
+        let remote = |etag: &str| RemoteVersion {
+            etag: Some(etag.to_string()),
+            modified: at(9),
+        };
+        let state = SyncState {
+            etag: Some("\"a\"".to_string()),
+            remote_modified: at(9),
+            local_modified: at(8),
+            synced_at: Utc::now(),
+        };
+
+        assert_eq!(
+            plan(Some(&state), at(8), Some(&remote("\"a\""))),
+            SyncAction::UpToDate
+        );
+        assert_eq!(
+            plan(Some(&state), at(10), Some(&remote("\"a\""))),
+            SyncAction::Push
+        );
+        assert_eq!(
+            plan(Some(&state), at(8), Some(&remote("\"b\""))),
+            SyncAction::Pull
+        );
+        assert_eq!(
+            plan(Some(&state), at(10), Some(&remote("\"b\""))),
+            SyncAction::Conflict
+        );
+
+        // First sync
+        assert_eq!(plan(None, at(8), None), SyncAction::Push);
+        assert_eq!(plan(None, None, Some(&remote("\"a\""))), SyncAction::Pull);
+        assert_eq!(
+            plan(None, at(8), Some(&remote("\"a\""))),
+            SyncAction::Conflict
+        );
+
+        // Without ETags the modification time decides
+        let no_etag = RemoteVersion {
+            etag: None,
+            modified: at(11),
+        };
+        assert_eq!(plan(Some(&state), at(8), Some(&no_etag)), SyncAction::Pull);
+    }
+}
@@ -0,0 +1,322 @@
+//! Three-way merge of synced data
+//!
+//! Local and remote data are compared item by item with the base, the data
+//! both sides had after the last sync. An item changed on one side takes
+//! that side's version, deletions included. An item changed differently on
+//! both sides is a conflict unless the user picked a side for it.
+//!
+//! Items the base doesn't have, as on the first sync, go to the newer
+//! `updated_at`. An item the other side deleted is dropped unless it was
+//! edited after the deletion, which is a conflict too. Settings are taken
+//! from the side that changed them, this device's if both did.
+
+use super::SyncResolution;
+use crate::datasphere::DataSet;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use uuid::Uuid;
+
+/// Kind of a merged item
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemKind {
+    Host,
+    Group,
+    Snippet,
+    VaultEntry,
+    Profile,
+    KnownHost,
+}
+
+/// An item changed differently on both devices
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MergeConflict {
+    pub kind: ItemKind,
+    /// Id to pick a side for; the name for profiles, `host:port:key_type`
+    /// for known hosts
+    pub id: String,
+    pub name: String,
+    pub local_updated_at: Option<DateTime<Utc>>,
+    pub remote_updated_at: Option<DateTime<Utc>>,
+    pub local_deleted: bool,
+    pub remote_deleted: bool,
+}
+
+/// Merged data, usable only when there are no conflicts
+#[derive(Debug)]
+pub struct MergeResult {
+    pub merged: DataSet,
+    pub conflicts: Vec<MergeConflict>,
+}
+
+/// Merge `local` and `remote` against their common `base`
+///
+/// `choices` settles conflicts from an earlier merge by item id.
+pub fn merge(
+    base: Option<&DataSet>,
+    local: &DataSet,
+    remote: &DataSet,
+    choices: &HashMap<String, SyncResolution>,
+) -> MergeResult {
+    let mut merge = Merge {
+        choices,
+        conflicts: Vec::new(),
+    };
+    let no_tombstones = HashMap::new();
+    let tombstones = (&local.tombstones, &remote.tombstones);
+
+    let hosts = merge.items(
+        ItemKind::Host,
+        base.map(|b| &b.hosts),
+        &local.hosts,
+        &remote.hosts,
+        tombstones,
+        |h| (h.name.clone(), Some(h.updated_at)),
+    );
+    let groups = merge.items(
+        ItemKind::Group,
+        base.map(|b| &b.groups),
+        &local.groups,
+        &remote.groups,
+        tombstones,
+        |g| (g.name.clone(), None),
+    );
+    let snippets = merge.items(
+        ItemKind::Snippet,
+        base.map(|b| &b.snippets),
+        &local.snippets,
+        &remote.snippets,
+        tombstones,
+        |s| (s.name.clone(), Some(s.updated_at)),
+    );
+    let vault = merge.items(
+        ItemKind::VaultEntry,
+        base.map(|b| &b.vault),
+        &local.vault,
+        &remote.vault,
+        tombstones,
+        |e| (e.name.clone(), Some(e.updated_at)),
+    );
+    let profiles = merge.items(
+        ItemKind::Profile,
+        base.map(|b| &b.profiles),
+        &local.profiles,
+        &remote.profiles,
+        (&no_tombstones, &no_tombstones),
+        |p| (p.name.clone(), None),
+    );
+    let known_hosts = merge.items(
+        ItemKind::KnownHost,
+        base.map(known_hosts_by_key).as_ref(),
+        &known_hosts_by_key(local),
+        &known_hosts_by_key(remote),
+        (&no_tombstones, &no_tombstones),
+        |k| (format!("{}:{}", k.host, k.port), Some(k.added_at)),
+    );
+
+    let settings = match base {
+        Some(base) if same(Some(&local.settings), Some(&base.settings)) => remote.settings.clone(),
+        _ => local.settings.clone(),
+    };
+
+    // Keep the newer deletion time; items that are present again lose theirs
+    let mut merged_tombstones = local.tombstones.clone();
+    for (id, deleted_at) in &remote.tombstones {
+        let entry = merged_tombstones.entry(*id).or_insert(*deleted_at);
+        *entry = (*entry).max(*deleted_at);
+    }
+    merged_tombstones.retain(|id: &Uuid, _| {
+        !hosts.contains_key(id)
+            && !groups.contains_key(id)
+            && !snippets.contains_key(id)
+            && !vault.contains_key(id)
+    });
+
+    let mut known_hosts: Vec<_> = known_hosts.into_values().collect();
+    known_hosts.sort_by(|a, b| (&a.host, a.port, &a.key_type).cmp(&(&b.host, b.port, &b.key_type)));
+
+    MergeResult {
+        merged: DataSet {
+            hosts,
+            groups,
+            snippets,
+            vault,
+            settings,
+            profiles,
+            known_hosts,
+            tombstones: merged_tombstones,
+        },
+        conflicts: merge.conflicts,
+    }
+}
+
+/// Deletion times by item key
+type Tombstones<K> = HashMap<K, DateTime<Utc>>;
+
+struct Merge<'a> {
+    choices: &'a HashMap<String, SyncResolution>,
+    conflicts: Vec<MergeConflict>,
+}
+
+impl Merge<'_> {
+    /// Merge one collection; `describe` gives an item's name and `updated_at`
+    fn items<K, T>(
+        &mut self,
+        kind: ItemKind,
+        base: Option<&HashMap<K, T>>,
+        local: &HashMap<K, T>,
+        remote: &HashMap<K, T>,
+        tombstones: (&Tombstones<K>, &Tombstones<K>),
+        describe: impl Fn(&T) -> (String, Option<DateTime<Utc>>),
+    ) -> HashMap<K, T>
+    where
+        K: Eq + Hash + Clone + ToString,
+        T: Clone + Serialize,
+    {
+        let (local_tombstones, remote_tombstones) = tombstones;
+        let keys: HashSet<&K> = local
+            .keys()
+            .chain(remote.keys())
+            .chain(base.into_iter().flat_map(|b| b.keys()))
+            .collect();
+
+        let mut merged = HashMap::new();
+        for key in keys {
+            let (l, r) = (local.get(key), remote.get(key));
+            let updated = |item: Option<&T>| item.and_then(|item| describe(item).1);
+            // Whether an item is gone because the other side deleted it
+            let deleted = |item: &T, tombstones: &Tombstones<K>| {
+                tombstones
+                    .get(key)
+                    .map(|deleted_at| describe(item).1.is_none_or(|u| u <= *deleted_at))
+            };
+
+            let pick = match base.and_then(|b| b.get(key)) {
+                Some(b) => match (!same(l, Some(b)), !same(r, Some(b))) {
+                    (false, _) => Some(r),
+                    (true, false) => Some(l),
+                    (true, true) if same(l, r) => Some(l),
+                    (true, true) => None,
+                },
+                None => match (l, r) {
+                    (Some(_), Some(_)) if same(l, r) => Some(l),
+                    (Some(_), Some(_)) => match (updated(l), updated(r)) {
+                        (Some(lu), Some(ru)) if ru > lu => Some(r),
+                        (Some(_), Some(_)) => Some(l),
+                        _ => None,
+                    },
+                    (Some(item), None) => match deleted(item, remote_tombstones) {
+                        Some(true) => Some(None),
+                        Some(false) => None,
+                        None => Some(l),
+                    },
+                    (None, Some(item)) => match deleted(item, local_tombstones) {
+                        Some(true) => Some(None),
+                        Some(false) => None,
+                        None => Some(r),
+                    },
+                    (None, None) => Some(None),
+                },
+            };
+
+            let pick = pick.or_else(|| match self.choices.get(&key.to_string()) {
+                Some(SyncResolution::KeepLocal) => Some(l),
+                Some(SyncResolution::KeepRemote) => Some(r),
+                None => {
+                    let name = l.or(r).map(|item| describe(item).0).unwrap_or_default();
+                    self.conflicts.push(MergeConflict {
+                        kind,
+                        id: key.to_string(),
+                        name,
+                        local_updated_at: updated(l),
+                        remote_updated_at: updated(r),
+                        local_deleted: l.is_none(),
+                        remote_deleted: r.is_none(),
+                    });
+                    Some(l)
+                }
+            });
+            if let Some(Some(item)) = pick {
+                merged.insert(key.clone(), item.clone());
+            }
+        }
+        merged
+    }
+}
+
+fn known_hosts_by_key(data: &DataSet) -> HashMap<String, crate::ssh::KnownHost> {
+    data.known_hosts
+        .iter()
+        .map(|k| (format!("{}:{}:{}", k.host, k.port, k.key_type), k.clone()))
+        .collect()
+}
+
+/// Compare items by their serialized form, so types need no `PartialEq`
+fn same<T: Serialize>(a: Option<&T>, b: Option<&T>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => serde_json::to_value(a).ok() == serde_json::to_value(b).ok(),
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datasphere::Host;
+    use chrono::Duration;
+
+    fn host(name: &str) -> Host {
+        Host::new(name.into(), format!("{}.local", name), "root".into())
+    }
+
+    #[test]
+    fn test_three_way_merge() {
+        let (kept, edited, deleted, both) =
+            (host("kept"), host("edited"), host("deleted"), host("both"));
+        let mut base = DataSet::default();
+        for h in [&kept, &edited, &deleted, &both] {
+            base.hosts.insert(h.id, h.clone());
+        }
+        let (mut local, mut remote) = (base.clone(), base.clone());
+
+        // Remote edits one host, local deletes another and adds one
+        let later = Utc::now() + Duration::minutes(5);
+        let edit = |h: &Host, name: &str| Host {
+            name: name.into(),
+            updated_at: later,
+            ..h.clone()
+        };
+        remote
+            .hosts
+            .insert(edited.id, edit(&edited, "edited remotely"));
+        local.hosts.remove(&deleted.id);
+        local.tombstones.insert(deleted.id, Utc::now());
+        let added = host("added");
+        local.hosts.insert(added.id, added.clone());
+        // Both edit the same host
+        local.hosts.insert(both.id, edit(&both, "local name"));
+        remote.hosts.insert(both.id, edit(&both, "remote name"));
+
+        let result = merge(Some(&base), &local, &remote, &HashMap::new());
+        assert_eq!(result.conflicts.len(), 1);
+        assert_eq!(result.conflicts[0].id, both.id.to_string());
+        let merged = &result.merged.hosts;
+        assert_eq!(merged[&edited.id].name, "edited remotely");
+        assert!(merged.contains_key(&kept.id) && merged.contains_key(&added.id));
+        assert!(!merged.contains_key(&deleted.id));
+
+        let choices = HashMap::from([(both.id.to_string(), SyncResolution::KeepRemote)]);
+        let result = merge(Some(&base), &local, &remote, &choices);
+        assert!(result.conflicts.is_empty());
+        assert_eq!(result.merged.hosts[&both.id].name, "remote name");
+        assert!(result.merged.tombstones.contains_key(&deleted.id));
+
+        // Without a base, a stale copy of a deleted host doesn't come back
+        let result = merge(None, &local, &base, &choices);
+        assert!(!result.merged.hosts.contains_key(&deleted.id));
+        assert_eq!(result.merged.hosts[&both.id].name, "local name");
+    }
+}
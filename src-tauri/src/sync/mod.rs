@@ -0,0 +1,207 @@
+//! Sync Module
+//!
+//! Shared plumbing for syncing DataSphere to a remote provider (WebDAV,
+//! S3, Nextcloud). Every provider request goes through `run`, which retries
+//! transient failures with backoff and stops calling a remote that keeps
+//! failing until its cooldown has passed.
+
+pub mod commands;
+mod engine;
+pub mod merge;
+mod resilience;
+pub mod s3;
+pub mod webdav;
+
+pub use engine::{sync_now, SyncOutcome, SyncResolution, SyncState};
+pub use resilience::{CircuitBreaker, RetryPolicy};
+
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+
+/// Name of the vault file at the provider
+pub const REMOTE_FILE: &str = "nexus-datasphere.json";
+
+/// Health of the sync provider, shared by all sync operations
+static BREAKER: Lazy<RwLock<CircuitBreaker>> = Lazy::new(|| RwLock::new(CircuitBreaker::new()));
+
+/// Conflict found by the last sync, until a sync succeeds
+static CONFLICT: Lazy<RwLock<Option<SyncConflict>>> = Lazy::new(|| RwLock::new(None));
+
+/// Get the sync circuit breaker
+pub fn breaker() -> &'static RwLock<CircuitBreaker> {
+    &BREAKER
+}
+
+fn set_conflict(conflict: Option<SyncConflict>) {
+    *CONFLICT.write() = conflict;
+}
+
+/// Overall sync state
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum SyncStatus {
+    /// Sync is turned off in settings
+    Disabled,
+    Healthy,
+    /// Recent attempts failed; attempts are paused until `next_retry_at`
+    Degraded {
+        last_error: String,
+    },
+}
+
+/// Sync status with failure bookkeeping, for the UI
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncStatusReport {
+    pub status: SyncStatus,
+    pub consecutive_failures: u32,
+    pub total_failures: u64,
+    pub last_error: Option<String>,
+    pub last_success_at: Option<DateTime<Utc>>,
+    /// When the next attempt is allowed; `None` if attempts are not paused
+    pub next_retry_at: Option<DateTime<Utc>>,
+    /// When this device last synced successfully
+    pub last_synced_at: Option<DateTime<Utc>>,
+    /// Unresolved conflict from the last sync
+    pub conflict: Option<SyncConflict>,
+}
+
+/// Version of the remote vault file
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemoteVersion {
+    pub etag: Option<String>,
+    pub modified: Option<DateTime<Utc>>,
+}
+
+/// Outcome of an upload
+#[derive(Debug)]
+pub enum PushResult {
+    /// Stored; the new ETag if the server sent one
+    Stored(Option<String>),
+    /// The remote file isn't the version the upload was based on
+    Changed,
+}
+
+/// Local and remote vault both changed since the last sync
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SyncConflict {
+    /// When the local data last changed
+    pub local_modified: Option<DateTime<Utc>>,
+    /// When the remote vault last changed, as reported by the server
+    pub remote_modified: Option<DateTime<Utc>>,
+    /// Items changed on both sides; empty when the vaults couldn't be merged
+    pub items: Vec<merge::MergeConflict>,
+}
+
+/// Sync Error types
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum SyncError {
+    #[error("Sync is not configured")]
+    NotConfigured,
+    #[error("HTTP {status}: {message}")]
+    Http { status: u16, message: String },
+    #[error("Request timed out")]
+    Timeout,
+    #[error("Network error: {0}")]
+    Network(String),
+    #[error("Sync paused after repeated failures until {0}")]
+    CircuitOpen(DateTime<Utc>),
+    /// Serialized with both timestamps
+    #[error("Local and remote vault both changed since the last sync")]
+    Conflict(SyncConflict),
+    #[error("Sync error: {0}")]
+    General(String),
+}
+
+impl SyncError {
+    /// Whether retrying the same request may succeed
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::Http { status, .. } => *status >= 500 || *status == 408 || *status == 429,
+            Self::Timeout | Self::Network(_) => true,
+            _ => false,
+        }
+    }
+}
+
+impl From<reqwest::Error> for SyncError {
+    fn from(err: reqwest::Error) -> Self {
+        if err.is_timeout() {
+            SyncError::Timeout
+        } else if let Some(status) = err.status() {
+            SyncError::Http {
+                status: status.as_u16(),
+                message: err.to_string(),
+            }
+        } else if err.is_connect() || err.is_request() {
+            SyncError::Network(err.to_string())
+        } else {
+            SyncError::General(err.to_string())
+        }
+    }
+}
+
+impl From<crate::datasphere::DataSphereError> for SyncError {
+    fn from(err: crate::datasphere::DataSphereError) -> Self {
+        SyncError::General(err.to_string())
+    }
+}
+
+/// Conflicts go to the frontend as `{ code, message, conflict }`
+/// (`SyncConflictError` in src/types); everything else is its message string
+impl Serialize for SyncError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::SerializeStruct;
+
+        match self {
+            SyncError::Conflict(conflict) => {
+                let mut error = serializer.serialize_struct("SyncError", 3)?;
+                error.serialize_field("code", "conflict")?;
+                error.serialize_field("message", &self.to_string())?;
+                error.serialize_field("conflict", conflict)?;
+                error.end()
+            }
+            _ => serializer.serialize_str(&self.to_string()),
+        }
+    }
+}
+
+/// Run a provider request with retries, honouring the circuit breaker
+///
+/// While the breaker is open the request is not attempted at all. Once the
+/// cooldown has passed the next call acts as the probe that closes it again.
+pub async fn run<T, F, Fut>(op: F) -> Result<T, SyncError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, SyncError>>,
+{
+    breaker().read().check(Utc::now())?;
+
+    let result = RetryPolicy::default().retry(op).await;
+
+    let mut breaker = breaker().write();
+    match &result {
+        Ok(_) => breaker.record_success(Utc::now()),
+        Err(e) => breaker.record_failure(e, Utc::now()),
+    }
+    result
+}
+
+/// Current sync status
+pub fn status() -> SyncStatusReport {
+    let storage = crate::datasphere::storage().read();
+    let enabled = storage
+        .as_ref()
+        .is_some_and(|s| s.get_settings().sync_enabled);
+    let mut report = breaker().read().report(enabled, Utc::now());
+    report.last_synced_at = storage
+        .as_ref()
+        .and_then(|s| engine::last_synced_at(s.data_dir()));
+    report.conflict = CONFLICT.read().clone();
+    report
+}
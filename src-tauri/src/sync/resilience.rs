@@ -0,0 +1,186 @@
+//! Retry with backoff and a circuit breaker for sync requests
+
+use super::{SyncError, SyncStatus, SyncStatusReport};
+use chrono::{DateTime, Utc};
+use std::future::Future;
+use std::time::Duration;
+
+/// Consecutive failed operations before attempts are paused
+const FAILURE_THRESHOLD: u32 = 5;
+
+/// First pause once the breaker opens; doubles with every further failure
+const BASE_COOLDOWN: Duration = Duration::from_secs(60);
+
+/// Longest pause between attempts
+const MAX_COOLDOWN: Duration = Duration::from_secs(60 * 60);
+
+/// Retries for transient failures within one operation
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before retry number `retry` (starting at 1)
+    pub fn delay(&self, retry: u32) -> Duration {
+        backoff(self.base_delay, self.max_delay, retry.saturating_sub(1))
+    }
+
+    /// Run `op`, retrying transient errors until attempts run out
+    pub async fn retry<T, F, Fut>(&self, mut op: F) -> Result<T, SyncError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, SyncError>>,
+    {
+        let mut attempt = 1;
+        loop {
+            match op().await {
+                Err(e) if e.is_transient() && attempt < self.max_attempts => {
+                    let delay = self.delay(attempt);
+                    tracing::debug!(
+                        "Sync attempt {} failed ({}), retrying in {:?}",
+                        attempt,
+                        e,
+                        delay
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Tracks failures of the sync provider and pauses attempts while it is down
+#[derive(Debug, Clone, Default)]
+pub struct CircuitBreaker {
+    consecutive_failures: u32,
+    total_failures: u64,
+    last_error: Option<String>,
+    last_success_at: Option<DateTime<Utc>>,
+    open_until: Option<DateTime<Utc>>,
+}
+
+impl CircuitBreaker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Refuse attempts while the breaker is open
+    pub fn check(&self, now: DateTime<Utc>) -> Result<(), SyncError> {
+        match self.open_until {
+            Some(until) if until > now => Err(SyncError::CircuitOpen(until)),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn record_success(&mut self, now: DateTime<Utc>) {
+        if self.open_until.is_some() {
+            tracing::info!("Sync provider recovered");
+        }
+        self.consecutive_failures = 0;
+        self.open_until = None;
+        self.last_error = None;
+        self.last_success_at = Some(now);
+    }
+
+    pub fn record_failure(&mut self, error: &SyncError, now: DateTime<Utc>) {
+        self.consecutive_failures += 1;
+        self.total_failures += 1;
+        self.last_error = Some(error.to_string());
+
+        if self.consecutive_failures >= FAILURE_THRESHOLD {
+            let cooldown = backoff(
+                BASE_COOLDOWN,
+                MAX_COOLDOWN,
+                self.consecutive_failures - FAILURE_THRESHOLD,
+            );
+            let until = now + chrono::Duration::from_std(cooldown).unwrap_or_default();
+            // Log only when the breaker opens, not on every failed probe
+            if self.open_until.is_none() {
+                tracing::warn!(
+                    "Sync paused after {} consecutive failures: {}",
+                    self.consecutive_failures,
+                    error
+                );
+            }
+            self.open_until = Some(until);
+        }
+    }
+
+    pub fn report(&self, enabled: bool, now: DateTime<Utc>) -> SyncStatusReport {
+        let status = match (&self.open_until, &self.last_error) {
+            _ if !enabled => SyncStatus::Disabled,
+            (Some(_), Some(error)) => SyncStatus::Degraded {
+                last_error: error.clone(),
+            },
+            _ => SyncStatus::Healthy,
+        };
+        SyncStatusReport {
+            status,
+            consecutive_failures: self.consecutive_failures,
+            total_failures: self.total_failures,
+            last_error: self.last_error.clone(),
+            last_success_at: self.last_success_at,
+            next_retry_at: self.open_until.filter(|until| *until > now),
+            last_synced_at: None,
+            conflict: None,
+        }
+    }
+}
+
+/// `base * 2^exponent`, capped at `max`
+fn backoff(base: Duration, max: Duration, exponent: u32) -> Duration {
+    base.saturating_mul(2u32.saturating_pow(exponent)).min(max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_breaker_opens_and_recovers() {
+        let mut breaker = CircuitBreaker::new();
+        let now = Utc::now();
+        let error = SyncError::Http {
+            status: 503,
+            message: "unavailable".to_string(),
+        };
+
+        for _ in 0..FAILURE_THRESHOLD - 1 {
+            breaker.record_failure(&error, now);
+        }
+        assert!(breaker.check(now).is_ok());
+
+        breaker.record_failure(&error, now);
+        assert!(matches!(breaker.check(now), Err(SyncError::CircuitOpen(_))));
+        assert!(matches!(
+            breaker.report(true, now).status,
+            SyncStatus::Degraded { .. }
+        ));
+
+        // The cooldown has passed, so the next attempt is the probe
+        let later = now + chrono::Duration::from_std(BASE_COOLDOWN).unwrap();
+        assert!(breaker.check(later).is_ok());
+
+        breaker.record_success(later);
+        let report = breaker.report(true, later);
+        assert_eq!(report.status, SyncStatus::Healthy);
+        assert_eq!(report.consecutive_failures, 0);
+        assert_eq!(report.total_failures, FAILURE_THRESHOLD as u64);
+        assert!(report.next_retry_at.is_none());
+    }
+}
@@ -0,0 +1,146 @@
+//! WebDAV backend
+//!
+//! The vault is one file in a WebDAV collection. Its ETag is the version:
+//! uploads send `If-Match` with the ETag last seen (or `If-None-Match: *`
+//! for the first upload), so a file changed by another device is never
+//! overwritten.
+
+use super::{PushResult, RemoteVersion, SyncError, REMOTE_FILE};
+use chrono::{DateTime, Utc};
+use reqwest::header::{ETAG, IF_MATCH, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::{Response, StatusCode};
+use std::time::Duration;
+use zeroize::Zeroizing;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Client for the vault file on a WebDAV server
+pub struct WebDavBackend {
+    http: reqwest::Client,
+    file_url: String,
+    username: String,
+    password: Zeroizing<String>,
+}
+
+impl WebDavBackend {
+    /// Backend for the vault file in the collection at `url`
+    pub fn new(url: &str, username: &str, password: Zeroizing<String>) -> Result<Self, SyncError> {
+        let http = reqwest::Client::builder()
+            .connect_timeout(CONNECT_TIMEOUT)
+            .timeout(REQUEST_TIMEOUT)
+            .build()?;
+        Ok(Self {
+            http,
+            file_url: format!("{}/{}", url.trim_end_matches('/'), REMOTE_FILE),
+            username: username.to_string(),
+            password,
+        })
+    }
+
+    /// Backend for a Nextcloud user's files, which are served under
+    /// `remote.php/dav/files/<user>`
+    pub fn nextcloud(
+        url: &str,
+        username: &str,
+        password: Zeroizing<String>,
+    ) -> Result<Self, SyncError> {
+        let collection = format!(
+            "{}/remote.php/dav/files/{}",
+            url.trim_end_matches('/'),
+            username
+        );
+        Self::new(&collection, username, password)
+    }
+
+    /// Version of the remote file, `None` if there is none yet
+    pub async fn head(&self) -> Result<Option<RemoteVersion>, SyncError> {
+        let response = self
+            .http
+            .head(&self.file_url)
+            .basic_auth(&self.username, Some(self.password.as_str()))
+            .send()
+            .await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let response = check(response).await?;
+        Ok(Some(version(&response)))
+    }
+
+    /// Download the remote file, `None` if there is none yet
+    pub async fn pull(&self) -> Result<Option<(Vec<u8>, RemoteVersion)>, SyncError> {
+        let response = self
+            .http
+            .get(&self.file_url)
+            .basic_auth(&self.username, Some(self.password.as_str()))
+            .send()
+            .await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let response = check(response).await?;
+        let version = version(&response);
+        Ok(Some((response.bytes().await?.to_vec(), version)))
+    }
+
+    /// Upload `body` if the remote file still has ETag `expected`, or
+    /// doesn't exist yet when `expected` is `None`
+    pub async fn push(
+        &self,
+        body: Vec<u8>,
+        expected: Option<&str>,
+    ) -> Result<PushResult, SyncError> {
+        let request = self
+            .http
+            .put(&self.file_url)
+            .basic_auth(&self.username, Some(self.password.as_str()))
+            .header(reqwest::header::CONTENT_TYPE, "application/json");
+        let request = match expected {
+            Some(etag) => request.header(IF_MATCH, etag),
+            None => request.header(IF_NONE_MATCH, "*"),
+        };
+        let response = request.body(body).send().await?;
+        if response.status() == StatusCode::PRECONDITION_FAILED {
+            return Ok(PushResult::Changed);
+        }
+        let response = check(response).await?;
+        Ok(PushResult::Stored(version(&response).etag))
+    }
+}
+
+/// Turn an error status into `SyncError::Http`
+async fn check(response: Response) -> Result<Response, SyncError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let message = match status {
+        StatusCode::UNAUTHORIZED => "Wrong WebDAV username or password".to_string(),
+        _ => response
+            .text()
+            .await
+            .ok()
+            .filter(|body| !body.trim().is_empty())
+            .unwrap_or_else(|| status.to_string()),
+    };
+    Err(SyncError::Http {
+        status: status.as_u16(),
+        message,
+    })
+}
+
+fn version(response: &Response) -> RemoteVersion {
+    let header = |name| response.headers().get(name).and_then(|v| v.to_str().ok());
+    RemoteVersion {
+        etag: header(ETAG).map(str::to_string),
+        modified: header(LAST_MODIFIED).and_then(parse_http_date),
+    }
+}
+
+/// Parse an HTTP date such as `Wed, 21 Oct 2015 07:28:00 GMT`
+fn parse_http_date(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc2822(value)
+        .ok()
+        .map(|date| date.with_timezone(&Utc))
+}
@@ -1,14 +1,48 @@
 //! Terminal Tauri Commands
 
-use super::{manager::manager, TerminalError, TerminalInfo};
-use crate::ssh::{AuthMethod, SshConfig};
+use super::export::{self, ExportFormat};
+use super::group::TerminalGroup;
+use super::input_log::{self, InputLogReport};
+use super::{
+    manager::manager, LoginBanner, TerminalError, TerminalEvent, TerminalInfo, TerminalSession,
+};
+use crate::audit;
+use crate::plugins::{self, HookEvent, SessionContext};
+use crate::retention::{self, StorageCategory};
+use crate::ssh::{AuthMethod, ConnectionReason, ConnectionState};
+use crate::utils::{emit_app_event, AppEvent, AuditAction, BulkResult};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use std::path::PathBuf;
 use uuid::Uuid;
-use tauri::AppHandle;
+use tauri::{AppHandle, Emitter};
 
 /// Create a new terminal session
+///
+/// If another terminal is already connected to `host_id`, the new one opens
+/// an extra shell on that connection instead of waiting for `connect_terminal`.
 #[tauri::command]
-pub fn create_terminal(host_id: Uuid, host_name: String) -> TerminalInfo {
-    manager().write().create_session(host_id, host_name)
+pub async fn create_terminal(
+    app: AppHandle,
+    host_id: Uuid,
+    host_name: String,
+) -> Result<TerminalInfo, TerminalError> {
+    let shared = manager().read().find_host_client(host_id);
+
+    let Some(client) = shared else {
+        return Ok(manager().write().create_session(host_id, host_name));
+    };
+
+    let mut session = TerminalSession::new(host_id, host_name);
+    session.attach(client, app).await?;
+
+    let info = session.info();
+    manager().write().insert_session(session.id, session);
+    tracing::info!(
+        "Terminal {} shares an existing connection to host {}",
+        info.id,
+        host_id
+    );
+    Ok(info)
 }
 
 /// Get terminal session info
@@ -27,42 +61,52 @@ pub fn get_terminals() -> Vec<TerminalInfo> {
 }
 
 /// Connect terminal to SSH
+///
+/// `host_id` is the stored host the tab was opened for. Everything but the
+/// credentials, which the user may just have typed in, comes from it. If
+/// another terminal is already connected to the host, this one opens an
+/// extra shell on that connection and the credentials aren't used.
 #[tauri::command]
 pub async fn connect_terminal(
     app: AppHandle,
     session_id: Uuid,
-    host: String,
-    port: u16,
-    username: String,
-    auth_type: String,
+    host_id: Uuid,
     password: Option<String>,
     key_path: Option<String>,
     passphrase: Option<String>,
 ) -> Result<(), TerminalError> {
-    let auth_method = match auth_type.as_str() {
-        "password" => AuthMethod::Password(password.unwrap_or_default()),
-        "private_key" => AuthMethod::PrivateKey {
-            key_path: key_path.unwrap_or_default(),
-            passphrase,
-        },
-        "agent" => AuthMethod::Agent,
-        _ => return Err(TerminalError::ConnectionFailed("Invalid auth type".to_string())),
+    let (host, mut config) = {
+        let storage = crate::datasphere::storage().read();
+        let storage = storage
+            .as_ref()
+            .ok_or_else(|| TerminalError::ConnectionFailed("Storage is locked".to_string()))?;
+        let host = storage
+            .get_host(host_id)
+            .ok_or_else(|| TerminalError::ConnectionFailed(format!("Unknown host {}", host_id)))?;
+        // Includes the profile's keepalive settings and the jump hosts
+        let config = storage
+            .host_ssh_config(&host)
+            .map_err(|e| TerminalError::ConnectionFailed(e.to_string()))?;
+        (host, config)
     };
 
-    let config = SshConfig {
-        host: host.clone(),
-        port,
-        username,
-        auth_method,
-        timeout_seconds: 30,
+    config.auth_method = match config.auth_method {
+        AuthMethod::Password(saved) => AuthMethod::Password(password.unwrap_or(saved)),
+        AuthMethod::PrivateKey {
+            key_path: saved_key,
+            passphrase: saved_passphrase,
+        } => AuthMethod::PrivateKey {
+            key_path: key_path.unwrap_or(saved_key),
+            passphrase: passphrase.or(saved_passphrase),
+        },
+        AuthMethod::Agent => AuthMethod::Agent,
     };
 
     // Check if terminal session exists, create if not
-    let session_exists = manager().read().get_session(session_id).is_some();
-    if !session_exists {
-        // Create a new terminal session with the same ID
-        let mut mgr = manager().write();
-        mgr.create_session_with_id(session_id, session_id, host);
+    if manager().read().get_session(session_id).is_none() {
+        manager()
+            .write()
+            .create_session_with_id(session_id, host_id, host.name.clone());
     }
 
     // Take session out to avoid holding lock across await
@@ -70,12 +114,40 @@ pub async fn connect_terminal(
         .write()
         .close_session(session_id)
         .ok_or_else(|| TerminalError::SessionNotFound(session_id.to_string()))?;
+    // Connection events name the terminal's host, which is now this one
+    session.host_id = host_id;
+    session.host_name = host.name;
 
-    let result = session.connect(config, app).await;
+    let context = SessionContext {
+        session_id,
+        host: config.host.clone(),
+        port: config.port,
+        username: config.username.clone(),
+    };
+    let shared = manager().read().find_host_client(host_id);
+    let result = match shared {
+        Some(client) => {
+            tracing::info!(
+                "Terminal {} shares an existing connection to host {}",
+                session_id,
+                host_id
+            );
+            session.attach(client, app).await
+        }
+        None => session.connect(config, app).await,
+    };
 
     // Put session back
     manager().write().insert_session(session_id, session);
 
+    if result.is_ok() {
+        audit::log_action(
+            AuditAction::Connect,
+            format!("{}@{}:{}", context.username, context.host, context.port),
+            Some(session_id),
+        );
+        plugins::dispatch(HookEvent::SessionConnect(context));
+    }
     result
 }
 
@@ -89,6 +161,11 @@ pub async fn write_terminal(session_id: Uuid, data: String) -> Result<(), Termin
         .ok_or_else(|| TerminalError::SessionNotFound(session_id.to_string()))?;
 
     let result = session.write(data.as_bytes()).await;
+    if result.is_ok() {
+        for line in session.submitted_lines(&data) {
+            audit::log_action(AuditAction::CommandExecuted, line, Some(session_id));
+        }
+    }
 
     // Put session back
     manager().write().insert_session(session_id, session);
@@ -96,6 +173,66 @@ pub async fn write_terminal(session_id: Uuid, data: String) -> Result<(), Termin
     result
 }
 
+/// Group terminals for broadcast input
+#[tauri::command]
+pub fn create_terminal_group(session_ids: Vec<Uuid>) -> Result<TerminalGroup, TerminalError> {
+    let mut mgr = manager().write();
+    mgr.check_sessions(&session_ids)?;
+    Ok(mgr.groups_mut().create(&session_ids))
+}
+
+/// Add terminals to a broadcast group
+#[tauri::command]
+pub fn add_to_group(
+    group_id: Uuid,
+    session_ids: Vec<Uuid>,
+) -> Result<TerminalGroup, TerminalError> {
+    let mut mgr = manager().write();
+    mgr.check_sessions(&session_ids)?;
+    mgr.groups_mut().add(group_id, &session_ids)
+}
+
+/// Remove terminals from a broadcast group
+#[tauri::command]
+pub fn remove_from_group(
+    group_id: Uuid,
+    session_ids: Vec<Uuid>,
+) -> Result<TerminalGroup, TerminalError> {
+    manager()
+        .write()
+        .groups_mut()
+        .remove(group_id, &session_ids)
+}
+
+/// Delete a broadcast group; its terminals stay open
+#[tauri::command]
+pub fn delete_terminal_group(group_id: Uuid) -> Result<(), TerminalError> {
+    manager()
+        .write()
+        .groups_mut()
+        .delete(group_id)
+        .map(|_| ())
+        .ok_or_else(|| TerminalError::GroupNotFound(group_id.to_string()))
+}
+
+/// Write the same input to every terminal in a group
+///
+/// Each terminal is reported on its own; one that is disconnected or
+/// fails doesn't stop the input reaching the rest.
+#[tauri::command]
+pub async fn write_terminal_broadcast(
+    group_id: Uuid,
+    data: String,
+) -> Result<BulkResult<()>, TerminalError> {
+    let session_ids = manager().read().groups().get(group_id)?.session_ids.clone();
+
+    let mut result = BulkResult::new();
+    for session_id in session_ids {
+        result.push(session_id, write_terminal(session_id, data.clone()).await);
+    }
+    Ok(result)
+}
+
 /// Resize terminal
 #[tauri::command]
 pub async fn resize_terminal(session_id: Uuid, cols: u32, rows: u32) -> Result<(), TerminalError> {
@@ -115,11 +252,160 @@ pub async fn resize_terminal(session_id: Uuid, cols: u32, rows: u32) -> Result<(
 
 /// Close terminal session
 #[tauri::command]
-pub async fn close_terminal(session_id: Uuid) -> Result<(), TerminalError> {
+pub async fn close_terminal(app: AppHandle, session_id: Uuid) -> Result<(), TerminalError> {
     let mut session = manager()
         .write()
         .close_session(session_id)
         .ok_or_else(|| TerminalError::SessionNotFound(session_id.to_string()))?;
 
-    session.disconnect().await
+    session.disconnect().await?;
+    manager().write().groups_mut().forget_session(session_id);
+    crate::sftp::manager().write().remove_session(&session_id);
+    // Closed on purpose, so reconnect_all must not bring it back
+    crate::session::manager().write().close_session(session_id);
+
+    emit_app_event(
+        &app,
+        AppEvent::connection_state(
+            Some(session.host_id),
+            session_id,
+            ConnectionState::Disconnected,
+            Some(ConnectionReason::UserRequested),
+        ),
+    );
+    Ok(())
+}
+
+/// Get the login banner and MOTD captured for a terminal
+#[tauri::command]
+pub fn get_login_banner(session_id: Uuid) -> Result<LoginBanner, TerminalError> {
+    let mgr = manager().read();
+    let session = mgr
+        .sessions()
+        .find(|s| s.id == session_id)
+        .ok_or_else(|| TerminalError::SessionNotFound(session_id.to_string()))?;
+    Ok(session.login_banner())
+}
+
+/// Get a terminal's buffered output so the frontend can repaint it
+///
+/// The bytes are returned base64-encoded, exactly as they were emitted.
+#[tauri::command]
+pub fn get_terminal_scrollback(session_id: Uuid) -> Result<String, TerminalError> {
+    let mgr = manager().read();
+    let session = mgr
+        .sessions()
+        .find(|s| s.id == session_id)
+        .ok_or_else(|| TerminalError::SessionNotFound(session_id.to_string()))?;
+    Ok(BASE64.encode(session.scrollback_bytes()))
+}
+
+/// Render a terminal's scrollback as plain text, stripped text or HTML
+#[tauri::command]
+pub fn export_terminal_output(
+    session_id: Uuid,
+    format: ExportFormat,
+) -> Result<String, TerminalError> {
+    let mgr = manager().read();
+    let session = mgr
+        .sessions()
+        .find(|s| s.id == session_id)
+        .ok_or_else(|| TerminalError::SessionNotFound(session_id.to_string()))?;
+    Ok(export::render(&session.scrollback(), format))
+}
+
+/// Render an asciinema `.cast` recording like `export_terminal_output`
+#[tauri::command]
+pub fn export_recording(path: String, format: ExportFormat) -> Result<String, TerminalError> {
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| TerminalError::Export(format!("{}: {}", path, e)))?;
+    Ok(export::render(&export::cast_output(&content)?, format))
+}
+
+/// Enable or disable compliance input logging for a terminal
+#[tauri::command]
+pub fn set_input_logging(
+    app: AppHandle,
+    session_id: Uuid,
+    enabled: bool,
+) -> Result<TerminalInfo, TerminalError> {
+    let log_dir = input_log_dir(&app)?;
+    let key = if enabled {
+        Some(input_log::load_key()?)
+    } else {
+        None
+    };
+
+    let info = {
+        let mut mgr = manager().write();
+        let session = mgr
+            .get_session_mut(session_id)
+            .ok_or_else(|| TerminalError::SessionNotFound(session_id.to_string()))?;
+
+        if let Some(key) = &key {
+            session.enable_input_logging(&log_dir, key)?;
+        } else {
+            session.disable_input_logging();
+        }
+        session.info()
+    };
+
+    // Let the UI show a prominent indicator while logging is active
+    let _ = app.emit(
+        &format!("terminal-data-{}", session_id),
+        TerminalEvent::InputLogging(enabled),
+    );
+
+    Ok(info)
+}
+
+/// Start recording a terminal's output as an asciicast v2 file
+///
+/// Returns the path of the `.cast` file, below the recordings directory.
+#[tauri::command]
+pub fn start_recording(app: AppHandle, session_id: Uuid) -> Result<PathBuf, TerminalError> {
+    let dir = retention::category_dir(&app, StorageCategory::Recordings)
+        .map_err(|e| TerminalError::Recording(e.to_string()))?;
+
+    let path = manager()
+        .write()
+        .get_session_mut(session_id)
+        .ok_or_else(|| TerminalError::SessionNotFound(session_id.to_string()))?
+        .start_recording(&dir)?;
+
+    let _ = app.emit(
+        &format!("terminal-data-{}", session_id),
+        TerminalEvent::Recording(true),
+    );
+    Ok(path)
+}
+
+/// Stop recording a terminal
+///
+/// Returns the path of the finished recording, or `None` if none was running.
+#[tauri::command]
+pub fn stop_recording(app: AppHandle, session_id: Uuid) -> Result<Option<PathBuf>, TerminalError> {
+    let path = manager()
+        .write()
+        .get_session_mut(session_id)
+        .ok_or_else(|| TerminalError::SessionNotFound(session_id.to_string()))?
+        .stop_recording();
+
+    let _ = app.emit(
+        &format!("terminal-data-{}", session_id),
+        TerminalEvent::Recording(false),
+    );
+    Ok(path)
+}
+
+/// Read and verify the input log of a terminal session
+#[tauri::command]
+pub fn get_input_log(app: AppHandle, session_id: Uuid) -> Result<InputLogReport, TerminalError> {
+    let key = input_log::load_key()?;
+    input_log::read_log(&input_log_dir(&app)?, session_id, &key)
+}
+
+fn input_log_dir(app: &AppHandle) -> Result<PathBuf, TerminalError> {
+    retention::category_dir(app, StorageCategory::InputLogs)
+        .map_err(|e| TerminalError::InputLog(e.to_string()))
 }
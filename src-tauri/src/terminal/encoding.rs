@@ -0,0 +1,182 @@
+//! Character encoding of terminal I/O
+//!
+//! Output from the server is decoded incrementally, so a multi-byte
+//! character split across two reads is never turned into replacement
+//! characters. Legacy hosts can use any encoding known to `encoding_rs`
+//! (e.g. `latin1`, `windows-1252`, `shift_jis`); input typed in the
+//! frontend is transcoded from UTF-8 before it is sent.
+
+use super::TerminalError;
+use crate::ssh::SshConfig;
+use encoding_rs::{CoderResult, Decoder, EncoderResult, Encoding, UTF_8};
+use std::borrow::Cow;
+
+/// Encodings used for one terminal session
+#[derive(Debug, Clone, Copy)]
+pub struct TerminalEncoding {
+    /// Encoding of what the server sends
+    output: &'static Encoding,
+    /// Encoding of what we send to the server
+    input: &'static Encoding,
+    normalize_newlines: bool,
+}
+
+impl Default for TerminalEncoding {
+    fn default() -> Self {
+        Self {
+            output: UTF_8,
+            input: UTF_8,
+            normalize_newlines: false,
+        }
+    }
+}
+
+impl TerminalEncoding {
+    /// Look up the encodings configured for a connection
+    ///
+    /// The send encoding defaults to the output encoding.
+    pub fn from_config(config: &SshConfig) -> Result<Self, TerminalError> {
+        let output = lookup(config.encoding.as_deref())?.unwrap_or(UTF_8);
+        let input = lookup(config.send_encoding.as_deref())?.unwrap_or(output);
+        Ok(Self {
+            output,
+            input,
+            normalize_newlines: config.normalize_newlines,
+        })
+    }
+
+    /// A fresh decoder for the server's output
+    pub fn decoder(&self) -> OutputDecoder {
+        OutputDecoder {
+            decoder: self.output.new_decoder_without_bom_handling(),
+            normalize_newlines: self.normalize_newlines,
+            after_cr: false,
+        }
+    }
+
+    /// Transcode UTF-8 input for the server
+    ///
+    /// Characters the target encoding can't represent become `?`.
+    pub fn encode_input<'a>(&self, data: &'a [u8]) -> Cow<'a, [u8]> {
+        if self.input == UTF_8 {
+            return Cow::Borrowed(data);
+        }
+
+        let text = String::from_utf8_lossy(data);
+        let mut encoder = self.input.new_encoder();
+        let mut out = Vec::with_capacity(text.len());
+        let mut rest: &str = &text;
+        loop {
+            let (result, read) =
+                encoder.encode_from_utf8_to_vec_without_replacement(rest, &mut out, false);
+            rest = &rest[read..];
+            match result {
+                EncoderResult::InputEmpty => break,
+                EncoderResult::OutputFull => out.reserve(rest.len() + 16),
+                EncoderResult::Unmappable(_) => out.push(b'?'),
+            }
+        }
+        Cow::Owned(out)
+    }
+}
+
+/// Stateful decoder for one PTY output stream
+pub struct OutputDecoder {
+    decoder: Decoder,
+    normalize_newlines: bool,
+    /// The previous chunk ended in `\r`
+    after_cr: bool,
+}
+
+impl OutputDecoder {
+    /// Decode a chunk, keeping incomplete trailing bytes for the next call
+    pub fn decode(&mut self, data: &[u8]) -> String {
+        let mut text = String::with_capacity(data.len());
+        let mut rest = data;
+        loop {
+            if let Some(needed) = self.decoder.max_utf8_buffer_length(rest.len()) {
+                text.reserve(needed);
+            }
+            let (result, read, _) = self.decoder.decode_to_string(rest, &mut text, false);
+            rest = &rest[read..];
+            if result == CoderResult::InputEmpty {
+                break;
+            }
+        }
+
+        if self.normalize_newlines {
+            text = self.normalize(&text);
+        }
+        text
+    }
+
+    /// Turn bare CR and bare LF line endings into CRLF
+    fn normalize(&mut self, text: &str) -> String {
+        let mut out = String::with_capacity(text.len() + text.len() / 8);
+        for c in text.chars() {
+            match c {
+                '\n' if !self.after_cr => out.push_str("\r\n"),
+                '\n' => out.push('\n'),
+                _ => {
+                    if self.after_cr {
+                        out.push('\n');
+                    }
+                    out.push(c);
+                }
+            }
+            self.after_cr = c == '\r';
+        }
+        out
+    }
+}
+
+/// Resolve an encoding label; `None` when no label is set
+fn lookup(label: Option<&str>) -> Result<Option<&'static Encoding>, TerminalError> {
+    match label.map(str::trim).filter(|l| !l.is_empty()) {
+        None => Ok(None),
+        Some(label) => Encoding::for_label(label.as_bytes())
+            .map(Some)
+            .ok_or_else(|| TerminalError::InvalidEncoding(label.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encoding(output: &str, normalize_newlines: bool) -> TerminalEncoding {
+        let output = lookup(Some(output)).unwrap().unwrap();
+        TerminalEncoding {
+            output,
+            input: output,
+            normalize_newlines,
+        }
+    }
+
+    #[test]
+    fn test_terminal_encoding() {
+        // A UTF-8 character split across reads is not mangled
+        let mut decoder = TerminalEncoding::default().decoder();
+        let bytes = "grüße".as_bytes();
+        let first = decoder.decode(&bytes[..3]);
+        let second = decoder.decode(&bytes[3..]);
+        assert_eq!(format!("{}{}", first, second), "grüße");
+        assert_eq!(first, "gr");
+
+        // Latin-1 both ways
+        let latin1 = encoding("latin1", false);
+        assert_eq!(latin1.decoder().decode(b"gr\xfc\xdfe"), "grüße");
+        assert_eq!(
+            &*latin1.encode_input("grüße €".as_bytes()),
+            b"gr\xfc\xdfe \x80"
+        );
+        assert_eq!(&*latin1.encode_input("日".as_bytes()), b"?");
+
+        // Bare CR and LF become CRLF, even across chunks
+        let mut decoder = encoding("utf-8", true).decoder();
+        assert_eq!(decoder.decode(b"a\rb\nc\r"), "a\r\nb\r\nc\r");
+        assert_eq!(decoder.decode(b"\nd"), "\nd");
+
+        assert!(lookup(Some("klingon")).is_err());
+    }
+}
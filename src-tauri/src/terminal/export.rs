@@ -0,0 +1,419 @@
+//! Rendering terminal output for use outside the app
+//!
+//! Output is replayed onto simple lines: `\r`, backspace and erase-line
+//! overwrite text the way a terminal would, so progress bars and prompt
+//! redraws don't end up duplicated. Cursor movement beyond that is ignored.
+//! An escape sequence cut off at the end of the buffer is dropped.
+
+use super::TerminalError;
+use serde::{Deserialize, Serialize};
+
+/// Format of exported terminal output
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    /// The output as received, ANSI sequences included
+    PlainText,
+    /// Text only, for pasting into tickets
+    AnsiStripped,
+    /// A `<pre>` block with colors and text attributes kept
+    Html,
+}
+
+/// Render terminal output in the given format
+pub fn render(output: &str, format: ExportFormat) -> String {
+    match format {
+        ExportFormat::PlainText => tokenize(output)
+            .into_iter()
+            .map(|token| match token {
+                Token::Text(s) | Token::Sgr(s) | Token::Csi(s, _) | Token::Escape(s) => s,
+            })
+            .collect(),
+        ExportFormat::AnsiStripped => {
+            let lines = replay(output);
+            let mut text = lines
+                .iter()
+                .map(|line| {
+                    let text: String = line.iter().map(|cell| cell.ch).collect();
+                    text.trim_end().to_string()
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            text.truncate(text.trim_end().len());
+            text.push('\n');
+            text
+        }
+        ExportFormat::Html => to_html(&replay(output)),
+    }
+}
+
+/// Output recorded in an asciinema `.cast` file
+///
+/// Supports the v1 JSON format and the line-based v2/v3 formats.
+pub fn cast_output(content: &str) -> Result<String, TerminalError> {
+    let invalid = |msg: &str| TerminalError::Export(format!("Invalid recording: {}", msg));
+
+    let mut lines = content.lines().filter(|l| !l.trim().is_empty());
+    let header = lines.next().ok_or_else(|| invalid("empty file"))?;
+    let header: serde_json::Value = serde_json::from_str(header)
+        .or_else(|_| serde_json::from_str(content))
+        .map_err(|e| invalid(&e.to_string()))?;
+
+    match header.get("version").and_then(|v| v.as_u64()) {
+        Some(1) => {
+            let frames = header
+                .get("stdout")
+                .and_then(|s| s.as_array())
+                .ok_or_else(|| invalid("missing stdout"))?;
+            Ok(frames
+                .iter()
+                .filter_map(|frame| frame.get(1)?.as_str())
+                .collect())
+        }
+        Some(2) | Some(3) => {
+            let mut output = String::new();
+            for line in lines {
+                let event: serde_json::Value =
+                    serde_json::from_str(line).map_err(|e| invalid(&e.to_string()))?;
+                if event.get(1).and_then(|c| c.as_str()) == Some("o") {
+                    output.push_str(event.get(2).and_then(|d| d.as_str()).unwrap_or_default());
+                }
+            }
+            Ok(output)
+        }
+        _ => Err(invalid("unknown version")),
+    }
+}
+
+enum Token<'a> {
+    Text(&'a str),
+    /// Full sequence and its parameters
+    Sgr(&'a str),
+    /// Full sequence, final byte
+    Csi(&'a str, u8),
+    Escape(&'a str),
+}
+
+/// Split output into text and escape sequences, dropping a cut-off one at the end
+fn tokenize(input: &str) -> Vec<Token<'_>> {
+    const ESC: u8 = 0x1b;
+    let bytes = input.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] != ESC {
+            let end = bytes[i..]
+                .iter()
+                .position(|&b| b == ESC)
+                .map_or(bytes.len(), |p| i + p);
+            tokens.push(Token::Text(&input[i..end]));
+            i = end;
+            continue;
+        }
+
+        let end = match bytes.get(i + 1) {
+            None => None,
+            Some(b'[') => bytes[i + 2..]
+                .iter()
+                .position(|b| (0x40..=0x7e).contains(b))
+                .map(|p| i + 2 + p + 1),
+            // OSC, DCS and friends end with BEL or ST
+            Some(b']' | b'P' | b'_' | b'^' | b'X') => {
+                let body = &bytes[i + 2..];
+                body.iter().enumerate().find_map(|(p, &b)| match b {
+                    0x07 => Some(i + 2 + p + 1),
+                    ESC if body.get(p + 1) == Some(&b'\\') => Some(i + 2 + p + 2),
+                    _ => None,
+                })
+            }
+            // Charset selection and the like take one more byte
+            Some(0x20..=0x2f) => (i + 2 < bytes.len()).then_some(i + 3),
+            Some(_) => Some(i + 2),
+        };
+        let Some(end) = end else {
+            break;
+        };
+        // Multi-byte characters never contain ASCII, but keep slicing safe
+        let end = (end..=bytes.len())
+            .find(|&e| input.is_char_boundary(e))
+            .unwrap_or(bytes.len());
+        let sequence = &input[i..end];
+        tokens.push(if bytes.get(i + 1) == Some(&b'[') {
+            match bytes[end - 1] {
+                b'm' => Token::Sgr(sequence),
+                final_byte => Token::Csi(sequence, final_byte),
+            }
+        } else {
+            Token::Escape(sequence)
+        });
+        i = end;
+    }
+    tokens
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Color {
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Style {
+    fg: Option<Color>,
+    bg: Option<Color>,
+    bold: bool,
+    dim: bool,
+    italic: bool,
+    underline: bool,
+    inverse: bool,
+}
+
+impl Style {
+    fn apply_sgr(&mut self, params: &str) {
+        let params: Vec<u16> = params
+            .split([';', ':'])
+            .map(|p| p.parse().unwrap_or(0))
+            .collect();
+        let mut iter = params.into_iter();
+        while let Some(param) = iter.next() {
+            match param {
+                0 => *self = Style::default(),
+                1 => self.bold = true,
+                2 => self.dim = true,
+                3 => self.italic = true,
+                4 => self.underline = true,
+                7 => self.inverse = true,
+                22 => (self.bold, self.dim) = (false, false),
+                23 => self.italic = false,
+                24 => self.underline = false,
+                27 => self.inverse = false,
+                30..=37 => self.fg = Some(Color::Indexed((param - 30) as u8)),
+                90..=97 => self.fg = Some(Color::Indexed((param - 90 + 8) as u8)),
+                40..=47 => self.bg = Some(Color::Indexed((param - 40) as u8)),
+                100..=107 => self.bg = Some(Color::Indexed((param - 100 + 8) as u8)),
+                39 => self.fg = None,
+                49 => self.bg = None,
+                38 | 48 => {
+                    let color = match iter.next() {
+                        Some(5) => iter.next().map(|n| Color::Indexed(n as u8)),
+                        Some(2) => match (iter.next(), iter.next(), iter.next()) {
+                            (Some(r), Some(g), Some(b)) => {
+                                Some(Color::Rgb(r as u8, g as u8, b as u8))
+                            }
+                            _ => None,
+                        },
+                        _ => None,
+                    };
+                    if param == 38 {
+                        self.fg = color;
+                    } else {
+                        self.bg = color;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn css(&self) -> String {
+        let (mut fg, mut bg) = (self.fg, self.bg);
+        if self.inverse {
+            fg = Some(self.bg.unwrap_or(Color::Rgb(0x1e, 0x1e, 0x1e)));
+            bg = Some(self.fg.unwrap_or(Color::Rgb(0xd4, 0xd4, 0xd4)));
+        }
+        let mut css = Vec::new();
+        if let Some(fg) = fg {
+            css.push(format!("color:{}", hex_color(fg)));
+        }
+        if let Some(bg) = bg {
+            css.push(format!("background-color:{}", hex_color(bg)));
+        }
+        if self.bold {
+            css.push("font-weight:bold".to_string());
+        }
+        if self.dim {
+            css.push("opacity:0.7".to_string());
+        }
+        if self.italic {
+            css.push("font-style:italic".to_string());
+        }
+        if self.underline {
+            css.push("text-decoration:underline".to_string());
+        }
+        css.join(";")
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Cell {
+    ch: char,
+    style: Style,
+}
+
+/// Replay output onto lines of styled characters
+fn replay(output: &str) -> Vec<Vec<Cell>> {
+    let mut lines = Vec::new();
+    let mut line: Vec<Cell> = Vec::new();
+    let mut col: usize = 0;
+    let mut style = Style::default();
+
+    for token in tokenize(output) {
+        match token {
+            Token::Text(text) => {
+                for ch in text.chars() {
+                    match ch {
+                        '\n' => {
+                            lines.push(std::mem::take(&mut line));
+                            col = 0;
+                        }
+                        '\r' => col = 0,
+                        '\x08' => col = col.saturating_sub(1),
+                        '\t' => {
+                            let next = (col / 8 + 1) * 8;
+                            while col < next {
+                                put(&mut line, col, Cell { ch: ' ', style });
+                                col += 1;
+                            }
+                        }
+                        c if c.is_control() => {}
+                        ch => {
+                            put(&mut line, col, Cell { ch, style });
+                            col += 1;
+                        }
+                    }
+                }
+            }
+            Token::Sgr(sequence) => style.apply_sgr(&sequence[2..sequence.len() - 1]),
+            // Erase in line
+            Token::Csi(sequence, b'K') => match &sequence[2..sequence.len() - 1] {
+                "" | "0" => line.truncate(col),
+                "2" => line.clear(),
+                _ => {}
+            },
+            Token::Csi(..) | Token::Escape(_) => {}
+        }
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+fn put(line: &mut Vec<Cell>, col: usize, cell: Cell) {
+    if col < line.len() {
+        line[col] = cell;
+    } else {
+        let blank = Cell {
+            ch: ' ',
+            style: Style::default(),
+        };
+        line.resize(col, blank);
+        line.push(cell);
+    }
+}
+
+fn to_html(lines: &[Vec<Cell>]) -> String {
+    let mut html = String::from(
+        "<pre style=\"background-color:#1e1e1e;color:#d4d4d4;font-family:monospace;padding:8px\">",
+    );
+    for (i, line) in lines.iter().enumerate() {
+        if i > 0 {
+            html.push('\n');
+        }
+        let mut start = 0;
+        while start < line.len() {
+            let style = line[start].style;
+            let end = line[start..]
+                .iter()
+                .position(|cell| cell.style != style)
+                .map_or(line.len(), |p| start + p);
+            let text: String = line[start..end].iter().map(|cell| cell.ch).collect();
+            let css = style.css();
+            if css.is_empty() {
+                html.push_str(&escape_html(&text));
+            } else {
+                html.push_str(&format!(
+                    "<span style=\"{}\">{}</span>",
+                    css,
+                    escape_html(&text)
+                ));
+            }
+            start = end;
+        }
+    }
+    html.push_str("</pre>\n");
+    html
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// xterm's default palette
+const BASE_COLORS: [(u8, u8, u8); 16] = [
+    (0x00, 0x00, 0x00),
+    (0xcd, 0x00, 0x00),
+    (0x00, 0xcd, 0x00),
+    (0xcd, 0xcd, 0x00),
+    (0x00, 0x00, 0xee),
+    (0xcd, 0x00, 0xcd),
+    (0x00, 0xcd, 0xcd),
+    (0xe5, 0xe5, 0xe5),
+    (0x7f, 0x7f, 0x7f),
+    (0xff, 0x00, 0x00),
+    (0x00, 0xff, 0x00),
+    (0xff, 0xff, 0x00),
+    (0x5c, 0x5c, 0xff),
+    (0xff, 0x00, 0xff),
+    (0x00, 0xff, 0xff),
+    (0xff, 0xff, 0xff),
+];
+
+fn hex_color(color: Color) -> String {
+    let (r, g, b) = match color {
+        Color::Rgb(r, g, b) => (r, g, b),
+        Color::Indexed(n @ 0..=15) => BASE_COLORS[n as usize],
+        // 6x6x6 color cube
+        Color::Indexed(n @ 16..=231) => {
+            let level = |v: u8| if v == 0 { 0 } else { 55 + v * 40 };
+            let n = n - 16;
+            (level(n / 36), level(n / 6 % 6), level(n % 6))
+        }
+        Color::Indexed(n) => {
+            let gray = 8 + (n - 232) * 10;
+            (gray, gray, gray)
+        }
+    };
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_formats() {
+        let output = "$ ls\r\n\x1b[1;34mdir\x1b[0m a<b\r\n50%\r100%\r\n\x1b[3";
+
+        assert_eq!(
+            render(output, ExportFormat::PlainText),
+            "$ ls\r\n\x1b[1;34mdir\x1b[0m a<b\r\n50%\r100%\r\n"
+        );
+        assert_eq!(
+            render(output, ExportFormat::AnsiStripped),
+            "$ ls\ndir a<b\n100%\n"
+        );
+
+        let html = render(output, ExportFormat::Html);
+        assert!(html.contains("<span style=\"color:#0000ee;font-weight:bold\">dir</span> a&lt;b"));
+        assert!(!html.contains("50%"));
+
+        let cast = "{\"version\": 2, \"width\": 80, \"height\": 24}\n[0.1, \"o\", \"hi \"]\n[0.2, \"i\", \"x\"]\n[0.3, \"o\", \"there\"]\n";
+        assert_eq!(cast_output(cast).unwrap(), "hi there");
+        assert!(cast_output("{\"version\": 9}").is_err());
+    }
+}
@@ -0,0 +1,116 @@
+//! Terminal Groups
+//!
+//! A group names a set of terminal sessions that broadcast input goes to.
+//! Output is untouched and stays per session. A session may be in several
+//! groups; closing it removes it from all of them.
+
+use super::TerminalError;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Sessions receiving the same input
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TerminalGroup {
+    pub id: Uuid,
+    /// Members in the order they were added
+    pub session_ids: Vec<Uuid>,
+}
+
+/// All terminal groups
+#[derive(Debug, Default)]
+pub struct TerminalGroups {
+    groups: HashMap<Uuid, TerminalGroup>,
+}
+
+impl TerminalGroups {
+    pub fn create(&mut self, session_ids: &[Uuid]) -> TerminalGroup {
+        let mut group = TerminalGroup {
+            id: Uuid::new_v4(),
+            session_ids: Vec::new(),
+        };
+        add_members(&mut group, session_ids);
+        self.groups.insert(group.id, group.clone());
+        group
+    }
+
+    pub fn get(&self, id: Uuid) -> Result<&TerminalGroup, TerminalError> {
+        self.groups
+            .get(&id)
+            .ok_or_else(|| TerminalError::GroupNotFound(id.to_string()))
+    }
+
+    /// Add sessions; ones already in the group are skipped
+    pub fn add(&mut self, id: Uuid, session_ids: &[Uuid]) -> Result<TerminalGroup, TerminalError> {
+        let group = self.get_mut(id)?;
+        add_members(group, session_ids);
+        Ok(group.clone())
+    }
+
+    /// Remove sessions; the group stays even when it ends up empty
+    pub fn remove(
+        &mut self,
+        id: Uuid,
+        session_ids: &[Uuid],
+    ) -> Result<TerminalGroup, TerminalError> {
+        let group = self.get_mut(id)?;
+        group.session_ids.retain(|s| !session_ids.contains(s));
+        Ok(group.clone())
+    }
+
+    pub fn delete(&mut self, id: Uuid) -> Option<TerminalGroup> {
+        self.groups.remove(&id)
+    }
+
+    /// Take a closed session out of every group
+    pub fn forget_session(&mut self, session_id: Uuid) {
+        for group in self.groups.values_mut() {
+            group.session_ids.retain(|s| *s != session_id);
+        }
+    }
+
+    fn get_mut(&mut self, id: Uuid) -> Result<&mut TerminalGroup, TerminalError> {
+        self.groups
+            .get_mut(&id)
+            .ok_or_else(|| TerminalError::GroupNotFound(id.to_string()))
+    }
+}
+
+fn add_members(group: &mut TerminalGroup, session_ids: &[Uuid]) {
+    for id in session_ids {
+        if !group.session_ids.contains(id) {
+            group.session_ids.push(*id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_group_membership() {
+        let (a, b, c) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let mut groups = TerminalGroups::default();
+
+        let group = groups.create(&[a, b, a]);
+        assert_eq!(group.session_ids, vec![a, b]);
+        assert_eq!(
+            groups.add(group.id, &[b, c]).unwrap().session_ids,
+            vec![a, b, c]
+        );
+        assert_eq!(
+            groups.remove(group.id, &[a]).unwrap().session_ids,
+            vec![b, c]
+        );
+
+        groups.forget_session(c);
+        assert_eq!(groups.get(group.id).unwrap().session_ids, vec![b]);
+
+        assert!(groups.delete(group.id).is_some());
+        assert!(matches!(
+            groups.add(group.id, &[a]),
+            Err(TerminalError::GroupNotFound(_))
+        ));
+    }
+}
@@ -0,0 +1,388 @@
+//! Compliance Input Logging
+//!
+//! Opt-in, per-session record of every byte an operator types into a terminal.
+//! Entries are encrypted individually and chained with an HMAC keyed by the
+//! input log key, so removing or editing a line breaks verification of
+//! everything after it. The latest entry is also recorded in an encrypted
+//! `.head` file, which exposes lines cut off the end of the log. Input is
+//! logged as typed, before conversion to the server's encoding, once the SSH
+//! channel has accepted it; if it can't be logged the terminal is closed.
+
+use super::TerminalError;
+use crate::datasphere::{self, DataSphereCrypto, EncryptedData};
+use crate::retention::{self, ActiveFileGuard};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+use zeroize::Zeroizing;
+
+/// Hash used as the predecessor of the first entry in a chain
+const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// DataSphere private file holding the input log key
+const KEY_FILE: &str = "input_log_key.json";
+
+/// A single logged input chunk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InputLogEntry {
+    pub seq: u64,
+    pub timestamp: DateTime<Utc>,
+    /// Base64-encoded raw input bytes
+    pub data: String,
+    pub prev_hash: String,
+    pub hash: String,
+}
+
+impl InputLogEntry {
+    fn compute_hash(
+        key: &[u8],
+        seq: u64,
+        timestamp: &DateTime<Utc>,
+        data: &str,
+        prev_hash: &str,
+    ) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes keys of any length");
+        mac.update(prev_hash.as_bytes());
+        mac.update(&seq.to_be_bytes());
+        mac.update(timestamp.to_rfc3339().as_bytes());
+        mac.update(data.as_bytes());
+        hex::encode(mac.finalize().into_bytes())
+    }
+}
+
+/// Last entry written to a log, kept beside it to detect truncation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct LogHead {
+    seq: u64,
+    hash: String,
+}
+
+/// Decrypted input log with chain verification result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InputLogReport {
+    pub session_id: Uuid,
+    pub entries: Vec<InputLogEntry>,
+    pub chain_valid: bool,
+    /// Sequence number of the first entry that failed verification
+    pub broken_at: Option<u64>,
+}
+
+/// Append-only encrypted input logger for one terminal session
+pub struct InputLogger {
+    path: PathBuf,
+    head_path: PathBuf,
+    key: Zeroizing<[u8; 32]>,
+    crypto: DataSphereCrypto,
+    seq: u64,
+    last_hash: String,
+    _active: [ActiveFileGuard; 2],
+}
+
+impl InputLogger {
+    /// Open (or continue) the input log for a session
+    pub fn open(log_dir: &Path, session_id: Uuid, key: &[u8; 32]) -> Result<Self, TerminalError> {
+        fs::create_dir_all(log_dir).map_err(|e| TerminalError::InputLog(e.to_string()))?;
+        let crypto = DataSphereCrypto::from_key(key);
+        let path = log_path(log_dir, session_id);
+        let head_path = head_path(log_dir, session_id);
+
+        // Resume the chain if the session already has a log
+        let entries = read_entries(&path, &crypto)?;
+        if truncated_at(&entries, read_head(&head_path, &crypto)?.as_ref()).is_some() {
+            // Appending would overwrite the head and hide the missing lines
+            return Err(TerminalError::InputLog(format!(
+                "Input log of session {} is missing entries",
+                session_id
+            )));
+        }
+        let (seq, last_hash) = match entries.last() {
+            Some(entry) => (entry.seq + 1, entry.hash.clone()),
+            None => (0, GENESIS_HASH.to_string()),
+        };
+
+        Ok(Self {
+            _active: [
+                retention::mark_active(&path),
+                retention::mark_active(&head_path),
+            ],
+            path,
+            head_path,
+            key: Zeroizing::new(*key),
+            crypto,
+            seq,
+            last_hash,
+        })
+    }
+
+    /// Append an input chunk to the log
+    pub fn append(&mut self, data: &[u8]) -> Result<(), TerminalError> {
+        let timestamp = Utc::now();
+        let data = BASE64.encode(data);
+        let hash =
+            InputLogEntry::compute_hash(&*self.key, self.seq, &timestamp, &data, &self.last_hash);
+
+        let entry = InputLogEntry {
+            seq: self.seq,
+            timestamp,
+            data,
+            prev_hash: self.last_hash.clone(),
+            hash: hash.clone(),
+        };
+
+        let encrypted = self
+            .crypto
+            .encrypt_json(&entry)
+            .map_err(|e| TerminalError::InputLog(e.to_string()))?;
+        let line = serde_json::to_string(&encrypted)
+            .map_err(|e| TerminalError::InputLog(e.to_string()))?;
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|e| TerminalError::InputLog(e.to_string()))?;
+        writeln!(file, "{}", line).map_err(|e| TerminalError::InputLog(e.to_string()))?;
+        self.write_head(&LogHead {
+            seq: self.seq,
+            hash: hash.clone(),
+        })?;
+
+        self.seq += 1;
+        self.last_hash = hash;
+        Ok(())
+    }
+
+    fn write_head(&self, head: &LogHead) -> Result<(), TerminalError> {
+        let encrypted = self
+            .crypto
+            .encrypt_json(head)
+            .map_err(|e| TerminalError::InputLog(e.to_string()))?;
+        let json = serde_json::to_string(&encrypted)
+            .map_err(|e| TerminalError::InputLog(e.to_string()))?;
+        // Replace atomically so a crash can't leave a head that fails to decrypt
+        let tmp = self.head_path.with_extension("head.tmp");
+        fs::write(&tmp, json).map_err(|e| TerminalError::InputLog(e.to_string()))?;
+        fs::rename(&tmp, &self.head_path).map_err(|e| TerminalError::InputLog(e.to_string()))
+    }
+}
+
+/// Read and verify the input log of a session
+pub fn read_log(
+    log_dir: &Path,
+    session_id: Uuid,
+    key: &[u8; 32],
+) -> Result<InputLogReport, TerminalError> {
+    let path = log_path(log_dir, session_id);
+    if !path.exists() {
+        return Err(TerminalError::InputLog(format!(
+            "No input log for session {}",
+            session_id
+        )));
+    }
+
+    let crypto = DataSphereCrypto::from_key(key);
+    let entries = read_entries(&path, &crypto)?;
+
+    let mut broken_at = None;
+    let mut prev_hash = GENESIS_HASH.to_string();
+    for (index, entry) in entries.iter().enumerate() {
+        let expected = InputLogEntry::compute_hash(
+            key,
+            entry.seq,
+            &entry.timestamp,
+            &entry.data,
+            &entry.prev_hash,
+        );
+        if entry.seq != index as u64 || entry.prev_hash != prev_hash || entry.hash != expected {
+            broken_at = Some(index as u64);
+            break;
+        }
+        prev_hash = entry.hash.clone();
+    }
+    if broken_at.is_none() {
+        let head = read_head(&head_path(log_dir, session_id), &crypto)?;
+        broken_at = truncated_at(&entries, head.as_ref());
+    }
+
+    Ok(InputLogReport {
+        session_id,
+        entries,
+        chain_valid: broken_at.is_none(),
+        broken_at,
+    })
+}
+
+fn log_path(log_dir: &Path, session_id: Uuid) -> PathBuf {
+    log_dir.join(format!("{}.log", session_id))
+}
+
+fn head_path(log_dir: &Path, session_id: Uuid) -> PathBuf {
+    log_dir.join(format!("{}.head", session_id))
+}
+
+fn read_head(path: &Path, crypto: &DataSphereCrypto) -> Result<Option<LogHead>, TerminalError> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(path).map_err(|e| TerminalError::InputLog(e.to_string()))?;
+    let encrypted: EncryptedData =
+        serde_json::from_str(&content).map_err(|e| TerminalError::InputLog(e.to_string()))?;
+    crypto
+        .decrypt_json(&encrypted)
+        .map(Some)
+        .map_err(|e| TerminalError::InputLog(e.to_string()))
+}
+
+/// Sequence number of the first entry missing from the end of the log
+///
+/// A log with entries but no head counts as cut off after its last entry.
+/// An entry past the head is one whose head update failed, which also
+/// closed the terminal.
+fn truncated_at(entries: &[InputLogEntry], head: Option<&LogHead>) -> Option<u64> {
+    let last = entries.last();
+    let intact = match head {
+        Some(head) => last.is_some_and(|entry| {
+            entry.seq > head.seq || (entry.seq == head.seq && entry.hash == head.hash)
+        }),
+        None => last.is_none(),
+    };
+    (!intact).then(|| last.map_or(0, |entry| entry.seq + 1))
+}
+
+fn read_entries(
+    path: &Path,
+    crypto: &DataSphereCrypto,
+) -> Result<Vec<InputLogEntry>, TerminalError> {
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+
+    let content = fs::read_to_string(path).map_err(|e| TerminalError::InputLog(e.to_string()))?;
+
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let encrypted: EncryptedData =
+                serde_json::from_str(line).map_err(|e| TerminalError::InputLog(e.to_string()))?;
+            crypto
+                .decrypt_json(&encrypted)
+                .map_err(|e| TerminalError::InputLog(e.to_string()))
+        })
+        .collect()
+}
+
+/// Load the input log key, generating one on first use
+///
+/// The key is kept encrypted in the DataSphere rather than next to the logs,
+/// so reading them takes the master password, and a password change
+/// re-encrypts it along with the other private files.
+pub fn load_key() -> Result<Zeroizing<[u8; 32]>, TerminalError> {
+    let storage = datasphere::storage().write();
+    let storage = storage
+        .as_ref()
+        .ok_or_else(|| TerminalError::InputLog("DataSphere is locked".to_string()))?;
+
+    let stored: Option<String> = storage
+        .read_private_file(KEY_FILE)
+        .map_err(|e| TerminalError::InputLog(e.to_string()))?;
+    if let Some(encoded) = stored.map(Zeroizing::new) {
+        let bytes = Zeroizing::new(
+            BASE64
+                .decode(encoded.as_bytes())
+                .map_err(|e| TerminalError::InputLog(e.to_string()))?,
+        );
+        let key: [u8; 32] = bytes
+            .as_slice()
+            .try_into()
+            .map_err(|_| TerminalError::InputLog("Invalid input log key".to_string()))?;
+        return Ok(Zeroizing::new(key));
+    }
+
+    let key = Zeroizing::new(DataSphereCrypto::generate_key());
+    storage
+        .write_private_file(KEY_FILE, &*Zeroizing::new(BASE64.encode(key.as_slice())))
+        .map_err(|e| TerminalError::InputLog(e.to_string()))?;
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chain_detects_tampering() {
+        let dir = std::env::temp_dir().join(format!("nexus-input-log-{}", Uuid::new_v4()));
+        let session_id = Uuid::new_v4();
+        let key = DataSphereCrypto::generate_key();
+
+        let mut logger = InputLogger::open(&dir, session_id, &key).unwrap();
+        logger.append(b"ls -la\r").unwrap();
+        logger.append(b"whoami\r").unwrap();
+        logger.append(b"exit\r").unwrap();
+
+        let report = read_log(&dir, session_id, &key).unwrap();
+        assert!(report.chain_valid);
+        assert_eq!(report.entries.len(), 3);
+
+        // Drop the middle line and the chain must break there
+        let path = log_path(&dir, session_id);
+        let lines: Vec<String> = fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(String::from)
+            .collect();
+        fs::write(&path, format!("{}\n{}\n", lines[0], lines[2])).unwrap();
+
+        let report = read_log(&dir, session_id, &key).unwrap();
+        assert!(!report.chain_valid);
+        assert_eq!(report.broken_at, Some(1));
+
+        // Cutting off the tail leaves a valid chain, but not a valid head
+        fs::write(&path, format!("{}\n{}\n", lines[0], lines[1])).unwrap();
+        let report = read_log(&dir, session_id, &key).unwrap();
+        assert!(!report.chain_valid);
+        assert_eq!(report.broken_at, Some(2));
+        assert!(InputLogger::open(&dir, session_id, &key).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_chain_needs_the_key() {
+        let dir = std::env::temp_dir().join(format!("nexus-input-log-{}", Uuid::new_v4()));
+        let session_id = Uuid::new_v4();
+        let key = DataSphereCrypto::generate_key();
+
+        let mut logger = InputLogger::open(&dir, session_id, &key).unwrap();
+        logger.append(b"ls -la\r").unwrap();
+
+        // An entry chained with another key doesn't verify
+        let crypto = DataSphereCrypto::from_key(&key);
+        let timestamp = Utc::now();
+        let data = BASE64.encode(b"rm -rf /\r");
+        let prev_hash = logger.last_hash.clone();
+        let forged = InputLogEntry {
+            seq: 1,
+            hash: InputLogEntry::compute_hash(&[0; 32], 1, &timestamp, &data, &prev_hash),
+            timestamp,
+            data,
+            prev_hash,
+        };
+        let line = serde_json::to_string(&crypto.encrypt_json(&forged).unwrap()).unwrap();
+        let mut file = OpenOptions::new().append(true).open(&logger.path).unwrap();
+        writeln!(file, "{}", line).unwrap();
+
+        let report = read_log(&dir, session_id, &key).unwrap();
+        assert!(!report.chain_valid);
+        assert_eq!(report.broken_at, Some(1));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
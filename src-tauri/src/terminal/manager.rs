@@ -1,12 +1,25 @@
 //! Terminal Session Manager
 
-use super::{TerminalError, TerminalEvent, TerminalInfo};
-use crate::ssh::{SshConfig, SshClient};
+use super::encoding::TerminalEncoding;
+use super::group::TerminalGroups;
+use super::input_log::InputLogger;
+use super::recording::{self, CastRecorder};
+use super::scrollback::{self, Scrollback};
+use super::{LoginBanner, TerminalError, TerminalEvent, TerminalInfo};
+use crate::mcp;
+use crate::session::{self, SessionStatus};
+use crate::ssh::{shell, ConnectionReason, ConnectionState, PromptDetector, SshClient, SshConfig};
+use crate::utils::{emit_app_event, AppEvent};
 use once_cell::sync::Lazy;
 use parking_lot::RwLock;
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
 use tauri::{AppHandle, Emitter};
 use tokio::sync::mpsc;
+use tokio::time::Instant;
 use uuid::Uuid;
 
 /// Global terminal manager
@@ -18,6 +31,19 @@ pub fn manager() -> &'static RwLock<TerminalManager> {
     &TERMINAL_MANAGER
 }
 
+/// How long to hold back PTY output while waiting for the first prompt
+const MOTD_CAPTURE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Give up on finding a prompt once this much output is buffered
+const MOTD_CAPTURE_LIMIT: usize = 64 * 1024;
+
+/// Host init commands waiting for the shell's first prompt
+struct PendingInit {
+    detector: PromptDetector,
+    script: String,
+    deadline: Instant,
+}
+
 /// Terminal session
 pub struct TerminalSession {
     pub id: Uuid,
@@ -25,9 +51,19 @@ pub struct TerminalSession {
     pub host_name: String,
     pub cols: u32,
     pub rows: u32,
-    ssh_client: Option<SshClient>,
+    ssh_client: Option<Arc<SshClient>>,
     input_tx: Option<mpsc::Sender<Vec<u8>>>,
     resize_tx: Option<mpsc::Sender<(u32, u32)>>,
+    /// Compliance input log, written once input has reached the channel
+    input_logger: Arc<parking_lot::Mutex<Option<InputLogger>>>,
+    motd: Arc<parking_lot::Mutex<Option<String>>>,
+    /// Output seen so far; kept across reconnects
+    scrollback: Arc<parking_lot::Mutex<Scrollback>>,
+    /// asciicast recording of the output, if one is running
+    recording: Arc<parking_lot::Mutex<Option<CastRecorder>>>,
+    encoding: TerminalEncoding,
+    /// Input typed since the last Enter, for the audit log
+    typed: String,
 }
 
 impl TerminalSession {
@@ -41,6 +77,14 @@ impl TerminalSession {
             ssh_client: None,
             input_tx: None,
             resize_tx: None,
+            input_logger: Arc::default(),
+            motd: Arc::default(),
+            scrollback: Arc::new(parking_lot::Mutex::new(Scrollback::with_limit(
+                scrollback::configured_limit(),
+            ))),
+            recording: Arc::default(),
+            encoding: TerminalEncoding::default(),
+            typed: String::new(),
         }
     }
 
@@ -54,6 +98,14 @@ impl TerminalSession {
             ssh_client: None,
             input_tx: None,
             resize_tx: None,
+            input_logger: Arc::default(),
+            motd: Arc::default(),
+            scrollback: Arc::new(parking_lot::Mutex::new(Scrollback::with_limit(
+                scrollback::configured_limit(),
+            ))),
+            recording: Arc::default(),
+            encoding: TerminalEncoding::default(),
+            typed: String::new(),
         }
     }
 
@@ -63,27 +115,82 @@ impl TerminalSession {
             host_id: self.host_id,
             host_name: self.host_name.clone(),
             connected: self.ssh_client.as_ref().map(|c| c.is_connected()).unwrap_or(false),
+            remote_addr: self
+                .ssh_client
+                .as_ref()
+                .and_then(|c| c.connected_addr())
+                .map(|addr| addr.to_string()),
             cols: self.cols,
             rows: self.rows,
+            input_logging: self.input_logger.lock().is_some(),
+            recording: self.recording.lock().is_some(),
         }
     }
 
     /// Connect to SSH and start shell
-    pub async fn connect(&mut self, config: SshConfig, app: AppHandle) -> Result<(), TerminalError> {
+    pub async fn connect(
+        &mut self,
+        config: SshConfig,
+        app: AppHandle,
+    ) -> Result<(), TerminalError> {
         let mut client = SshClient::new(config);
 
-        client
-            .connect()
-            .await
-            .map_err(|e| TerminalError::ConnectionFailed(e.to_string()))?;
+        let (host_id, session_id) = (self.host_id, self.id);
+        let listener_app = app.clone();
+        client.set_state_listener(Arc::new(move |state, reason| {
+            // The I/O task reports disconnects, for every terminal sharing the client
+            if state == ConnectionState::Disconnected {
+                return;
+            }
+            emit_app_event(
+                &listener_app,
+                AppEvent::connection_state(Some(host_id), session_id, state, reason),
+            );
+        }));
+
+        if let Err(e) = client.connect().await {
+            emit_app_event(
+                &app,
+                AppEvent::connection_state(
+                    Some(host_id),
+                    session_id,
+                    ConnectionState::Failed,
+                    Some(ConnectionReason::from_error(&e)),
+                ),
+            );
+            return Err(TerminalError::ConnectionFailed(e.to_string()));
+        }
 
+        self.attach(Arc::new(client), app).await
+    }
+
+    /// Start a shell on an already connected client
+    ///
+    /// The client may be shared with other sessions; each gets its own PTY
+    /// channel and I/O task.
+    pub async fn attach(
+        &mut self,
+        client: Arc<SshClient>,
+        app: AppHandle,
+    ) -> Result<(), TerminalError> {
         // Open shell with PTY
-        let mut channel = client
+        let (mut channel, refused_env) = client
             .open_shell(self.cols, self.rows)
             .await
             .map_err(|e| TerminalError::Ssh(e.to_string()))?;
 
-        let session_id = self.id;
+        // Variables the server refused are exported along with the init commands
+        let script = shell::init_script(&refused_env, &client.config.init_commands);
+        let mut pending_init = (!script.is_empty()).then(|| PendingInit {
+            detector: PromptDetector::login(),
+            script,
+            deadline: Instant::now() + MOTD_CAPTURE_TIMEOUT,
+        });
+
+        let (host_id, session_id) = (self.host_id, self.id);
+
+        let encoding = TerminalEncoding::from_config(&client.config)?;
+        let mut decoder = encoding.decoder();
 
         // Create channels for input and resize
         let (input_tx, mut input_rx) = mpsc::channel::<Vec<u8>>(100);
@@ -92,35 +199,136 @@ impl TerminalSession {
         // Clone app handle for the task
         let app_clone = app.clone();
 
+        // Hold output back until the first prompt so the banner can be stripped
+        let mut motd_capture = client.config.suppress_motd.then(|| {
+            (
+                PromptDetector::login(),
+                Instant::now() + MOTD_CAPTURE_TIMEOUT,
+            )
+        });
+        let motd_store = self.motd.clone();
+        *motd_store.lock() = None;
+        // What the server sent while output is held back
+        let mut held_raw = Vec::new();
+        let scrollback = self.scrollback.clone();
+        let recording = self.recording.clone();
+        let input_logger = self.input_logger.clone();
+        let keepalive_lost = client.keepalive_flag();
+        let counters = session::reset_counters(session_id);
+
         // Spawn task to handle input and resize
         tokio::spawn(async move {
             loop {
                 tokio::select! {
                     // Handle input data
-                    Some(data) = input_rx.recv() => {
-                        if let Err(e) = channel.data(&data[..]).await {
+                    data = input_rx.recv() => {
+                        let Some(data) = data else {
+                            // Session closed; the connection may be shared, so close only this channel
+                            let _ = channel.close().await;
+                            break;
+                        };
+                        let encoded = encoding.encode_input(&data);
+                        if let Err(e) = channel.data(&encoded[..]).await {
                             tracing::error!("Failed to send data to channel: {}", e);
                             break;
                         }
+                        counters.add_sent(encoded.len());
+                        // Stop the terminal once input can't be logged, so nothing more goes unlogged
+                        let logged = input_logger
+                            .lock()
+                            .as_mut()
+                            .map_or(Ok(()), |logger| logger.append(&data));
+                        if let Err(e) = logged {
+                            tracing::error!("Failed to log input of terminal {}: {}", session_id, e);
+                            let _ = app_clone.emit(
+                                &format!("terminal-data-{}", session_id),
+                                TerminalEvent::Error(format!("Input logging failed: {}", e)),
+                            );
+                            let _ = channel.close().await;
+                            let _ = app_clone.emit(
+                                &format!("terminal-data-{}", session_id),
+                                TerminalEvent::Disconnected,
+                            );
+                            session::manager().write().set_status(session_id, SessionStatus::Error);
+                            break;
+                        }
                     }
                     // Handle resize
                     Some((cols, rows)) = resize_rx.recv() => {
                         if let Err(e) = channel.window_change(cols, rows, 0, 0).await {
                             tracing::error!("Failed to resize channel: {}", e);
                         }
+                        recording::record(&recording, |r| r.resize(cols, rows));
+                    }
+                    // No prompt showed up; release whatever was held back
+                    _ = tokio::time::sleep_until(
+                        motd_capture.as_ref().map_or_else(Instant::now, |(_, deadline)| *deadline)
+                    ), if motd_capture.is_some() => {
+                        if let Some((mut detector, _)) = motd_capture.take() {
+                            let text = detector.take_buffer();
+                            scrollback.lock().push(&std::mem::take(&mut held_raw));
+                            recording::record(&recording, |r| r.output(&text));
+                            mcp::logs::publish(session_id, &text);
+                            let _ = app_clone.emit(
+                                &format!("terminal-data-{}", session_id),
+                                TerminalEvent::Data(text),
+                            );
+                        }
+                    }
+                    // No prompt showed up; send the init commands anyway
+                    _ = tokio::time::sleep_until(
+                        pending_init.as_ref().map_or_else(Instant::now, |init| init.deadline)
+                    ), if pending_init.is_some() => {
+                        if let Some(init) = pending_init.take() {
+                            if let Err(e) = channel.data(init.script.as_bytes()).await {
+                                tracing::warn!("Failed to send init commands: {}", e);
+                            }
+                        }
                     }
                     // Read from SSH
                     msg = channel.wait() => {
                         match msg {
-                            Some(russh::ChannelMsg::Data { data }) => {
-                                let text = String::from_utf8_lossy(&data).to_string();
-                                let _ = app_clone.emit(
-                                    &format!("terminal-data-{}", session_id),
-                                    TerminalEvent::Data(text),
-                                );
-                            }
-                            Some(russh::ChannelMsg::ExtendedData { data, ext: 1 }) => {
-                                let text = String::from_utf8_lossy(&data).to_string();
+                            Some(russh::ChannelMsg::Data { data })
+                            | Some(russh::ChannelMsg::ExtendedData { data, ext: 1 }) => {
+                                counters.add_received(data.len());
+                                // Scrollback keeps the bytes as sent, in the server's encoding
+                                if motd_capture.is_some() {
+                                    held_raw.extend_from_slice(&data);
+                                } else {
+                                    scrollback.lock().push(&data);
+                                }
+                                let mut text = decoder.decode(&data);
+                                if text.is_empty() {
+                                    continue;
+                                }
+                                if let Some(init) = pending_init.as_mut() {
+                                    if init.detector.feed_login(&text).is_some()
+                                        || init.detector.buffered_len() > MOTD_CAPTURE_LIMIT
+                                    {
+                                        if let Err(e) = channel.data(init.script.as_bytes()).await {
+                                            tracing::warn!("Failed to send init commands: {}", e);
+                                        }
+                                        pending_init = None;
+                                    }
+                                }
+                                if let Some((detector, _)) = motd_capture.as_mut() {
+                                    if let Some((banner, prompt)) = detector.feed_login(&text) {
+                                        *motd_store.lock() = Some(banner.replace("\r\n", "\n"));
+                                        motd_capture = None;
+                                        // Only the prompt is shown, so only it is kept
+                                        held_raw.clear();
+                                        scrollback.lock().push(prompt.as_bytes());
+                                        text = prompt;
+                                    } else if detector.buffered_len() > MOTD_CAPTURE_LIMIT {
+                                        text = detector.take_buffer();
+                                        motd_capture = None;
+                                        scrollback.lock().push(&std::mem::take(&mut held_raw));
+                                    } else {
+                                        continue;
+                                    }
+                                }
+                                recording::record(&recording, |r| r.output(&text));
+                                mcp::logs::publish(session_id, &text);
                                 let _ = app_clone.emit(
                                     &format!("terminal-data-{}", session_id),
                                     TerminalEvent::Data(text),
@@ -131,6 +339,23 @@ impl TerminalSession {
                                     &format!("terminal-data-{}", session_id),
                                     TerminalEvent::Disconnected,
                                 );
+                                let (reason, status) = if msg.is_some() {
+                                    (ConnectionReason::RemoteClosed, SessionStatus::Disconnected)
+                                } else if keepalive_lost.load(Ordering::Relaxed) {
+                                    (ConnectionReason::KeepaliveTimeout, SessionStatus::Error)
+                                } else {
+                                    (ConnectionReason::NetworkLost, SessionStatus::Error)
+                                };
+                                session::manager().write().set_status(session_id, status);
+                                emit_app_event(
+                                    &app_clone,
+                                    AppEvent::connection_state(
+                                        Some(host_id),
+                                        session_id,
+                                        ConnectionState::Disconnected,
+                                        Some(reason),
+                                    ),
+                                );
                                 break;
                             }
                             _ => {}
@@ -145,10 +370,23 @@ impl TerminalSession {
             &format!("terminal-data-{}", self.id),
             TerminalEvent::Connected,
         );
+        emit_app_event(
+            &app,
+            AppEvent::connection_state(
+                Some(self.host_id),
+                self.id,
+                ConnectionState::Connected,
+                None,
+            ),
+        );
+        session::manager()
+            .write()
+            .mark_connected(self.id, self.host_id, &self.host_name);
 
         self.ssh_client = Some(client);
         self.input_tx = Some(input_tx);
         self.resize_tx = Some(resize_tx);
+        self.encoding = encoding;
         Ok(())
     }
 
@@ -159,11 +397,41 @@ impl TerminalSession {
                 .await
                 .map_err(|e| TerminalError::Ssh(format!("Failed to send input: {}", e)))?;
         } else {
-            return Err(TerminalError::Ssh("Not connected".to_string()));
+            return Err(TerminalError::NotConnected);
         }
         Ok(())
     }
 
+    /// Lines submitted by `data`, put together from the input typed so far
+    ///
+    /// Only typed input is seen; lines the shell completes or recalls from
+    /// history are logged as typed.
+    pub fn submitted_lines(&mut self, data: &str) -> Vec<String> {
+        // Arrow and function keys arrive as escape sequences of their own
+        if data.starts_with('\u{1b}') {
+            return Vec::new();
+        }
+        let mut lines = Vec::new();
+        for c in data.chars() {
+            match c {
+                '\r' | '\n' => {
+                    let line = std::mem::take(&mut self.typed);
+                    if !line.trim().is_empty() {
+                        lines.push(line);
+                    }
+                }
+                '\u{7f}' | '\u{8}' => {
+                    self.typed.pop();
+                }
+                // Ctrl-C drops the line
+                '\u{3}' => self.typed.clear(),
+                c if !c.is_control() => self.typed.push(c),
+                _ => {}
+            }
+        }
+        lines
+    }
+
     /// Resize terminal
     pub async fn resize(&mut self, cols: u32, rows: u32) -> Result<(), TerminalError> {
         self.cols = cols;
@@ -177,9 +445,78 @@ impl TerminalSession {
         Ok(())
     }
 
-    /// Get mutable SSH client
-    pub fn get_ssh_client_mut(&mut self) -> Option<&mut SshClient> {
-        self.ssh_client.as_mut()
+    /// Enable compliance input logging into the given directory
+    pub fn enable_input_logging(
+        &mut self,
+        log_dir: &Path,
+        key: &[u8; 32],
+    ) -> Result<(), TerminalError> {
+        let mut input_logger = self.input_logger.lock();
+        if input_logger.is_none() {
+            *input_logger = Some(InputLogger::open(log_dir, self.id, key)?);
+            tracing::warn!("Input logging enabled for terminal {}", self.id);
+        }
+        Ok(())
+    }
+
+    /// Disable compliance input logging
+    pub fn disable_input_logging(&mut self) {
+        if self.input_logger.lock().take().is_some() {
+            tracing::info!("Input logging disabled for terminal {}", self.id);
+        }
+    }
+
+    /// Start recording output into a new `.cast` file in `dir`
+    ///
+    /// Returns the file's path; if a recording is already running, its path.
+    pub fn start_recording(&mut self, dir: &Path) -> Result<PathBuf, TerminalError> {
+        let mut recording = self.recording.lock();
+        if let Some(recorder) = recording.as_ref() {
+            return Ok(recorder.path().to_path_buf());
+        }
+        let recorder = CastRecorder::create(dir, self.id, self.cols, self.rows, &self.host_name)?;
+        let path = recorder.path().to_path_buf();
+        *recording = Some(recorder);
+        tracing::info!("Recording terminal {} to {}", self.id, path.display());
+        Ok(path)
+    }
+
+    /// Stop recording; returns the finished file, if one was being written
+    pub fn stop_recording(&mut self) -> Option<PathBuf> {
+        let recorder = self.recording.lock().take()?;
+        tracing::info!("Stopped recording terminal {}", self.id);
+        Some(recorder.path().to_path_buf())
+    }
+
+    /// Login messages captured for this session
+    ///
+    /// `motd` is only captured when the host suppresses it.
+    pub fn login_banner(&self) -> LoginBanner {
+        LoginBanner {
+            auth_banner: self.ssh_client.as_ref().and_then(|c| c.auth_banner()),
+            motd: self.motd.lock().clone(),
+        }
+    }
+
+    /// Output received so far, ANSI sequences included
+    pub fn scrollback(&self) -> String {
+        let bytes = self.scrollback.lock().bytes();
+        self.encoding.decoder().decode(&bytes)
+    }
+
+    /// Output received so far as UTF-8, the way it was sent to the frontend
+    pub fn scrollback_bytes(&self) -> Vec<u8> {
+        self.scrollback().into_bytes()
+    }
+
+    /// Get a shared handle to the SSH client
+    pub fn ssh_client(&self) -> Option<Arc<SshClient>> {
+        self.ssh_client.clone()
+    }
+
+    /// Whether the I/O task of a connected session has exited
+    pub fn io_task_finished(&self) -> bool {
+        self.input_tx.as_ref().is_some_and(|tx| tx.is_closed())
     }
 
     /// Disconnect
@@ -188,11 +525,19 @@ impl TerminalSession {
         self.input_tx = None;
         self.resize_tx = None;
 
-        if let Some(mut client) = self.ssh_client.take() {
-            client
-                .disconnect()
-                .await
-                .map_err(|e| TerminalError::Ssh(e.to_string()))?;
+        if let Some(client) = self.ssh_client.take() {
+            // Other tabs or helpers may share the connection; only the last one closes it
+            match Arc::try_unwrap(client) {
+                Ok(mut client) => client
+                    .disconnect()
+                    .await
+                    .map_err(|e| TerminalError::Ssh(e.to_string()))?,
+                Err(client) => tracing::info!(
+                    "Connection of terminal {} is still shared by {} users; leaving it open",
+                    self.id,
+                    Arc::strong_count(&client) - 1
+                ),
+            }
         }
         Ok(())
     }
@@ -201,12 +546,14 @@ impl TerminalSession {
 /// Terminal manager
 pub struct TerminalManager {
     sessions: HashMap<Uuid, TerminalSession>,
+    groups: TerminalGroups,
 }
 
 impl TerminalManager {
     pub fn new() -> Self {
         Self {
             sessions: HashMap::new(),
+            groups: TerminalGroups::default(),
         }
     }
 
@@ -219,7 +566,12 @@ impl TerminalManager {
     }
 
     /// Create a new terminal session with a specific ID
-    pub fn create_session_with_id(&mut self, id: Uuid, host_id: Uuid, host_name: String) -> TerminalInfo {
+    pub fn create_session_with_id(
+        &mut self,
+        id: Uuid,
+        host_id: Uuid,
+        host_name: String,
+    ) -> TerminalInfo {
         let session = TerminalSession::new_with_id(id, host_id, host_name);
         let info = session.info();
         self.sessions.insert(session.id, session);
@@ -236,6 +588,25 @@ impl TerminalManager {
         self.sessions.values().map(|s| s.info()).collect()
     }
 
+    /// Iterate over all sessions
+    pub fn sessions(&self) -> impl Iterator<Item = &TerminalSession> {
+        self.sessions.values()
+    }
+
+    /// Get the SSH client of a connected session
+    pub fn get_ssh_client(&self, id: Uuid) -> Option<Arc<SshClient>> {
+        self.sessions.get(&id).and_then(|s| s.ssh_client())
+    }
+
+    /// Find a live connection already open to a host, for sharing between tabs
+    pub fn find_host_client(&self, host_id: Uuid) -> Option<Arc<SshClient>> {
+        self.sessions
+            .values()
+            .filter(|s| s.host_id == host_id)
+            .filter_map(|s| s.ssh_client())
+            .find(|c| c.is_connected())
+    }
+
     /// Get mutable session
     pub fn get_session_mut(&mut self, id: Uuid) -> Option<&mut TerminalSession> {
         self.sessions.get_mut(&id)
@@ -250,6 +621,23 @@ impl TerminalManager {
     pub fn insert_session(&mut self, id: Uuid, session: TerminalSession) {
         self.sessions.insert(id, session);
     }
+
+    /// Check that every id belongs to an open session
+    pub fn check_sessions(&self, ids: &[Uuid]) -> Result<(), TerminalError> {
+        match ids.iter().find(|id| !self.sessions.contains_key(id)) {
+            Some(id) => Err(TerminalError::SessionNotFound(id.to_string())),
+            None => Ok(()),
+        }
+    }
+
+    /// Broadcast groups
+    pub fn groups(&self) -> &TerminalGroups {
+        &self.groups
+    }
+
+    pub fn groups_mut(&mut self) -> &mut TerminalGroups {
+        &mut self.groups
+    }
 }
 
 impl Default for TerminalManager {
@@ -3,7 +3,13 @@
 //! Manages terminal sessions and bridges SSH I/O with the frontend
 
 pub mod commands;
+pub mod encoding;
+pub mod export;
+pub mod group;
+pub mod input_log;
 pub mod manager;
+pub mod recording;
+pub mod scrollback;
 
 pub use manager::{manager, TerminalManager, TerminalSession};
 
@@ -17,8 +23,21 @@ pub struct TerminalInfo {
     pub host_id: Uuid,
     pub host_name: String,
     pub connected: bool,
+    /// Address the connection was established to, for direct TCP connections
+    pub remote_addr: Option<String>,
     pub cols: u32,
     pub rows: u32,
+    pub input_logging: bool,
+    pub recording: bool,
+}
+
+/// Login messages of a terminal session, kept apart from its output
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoginBanner {
+    /// Pre-authentication banner sent by the SSH server
+    pub auth_banner: Option<String>,
+    /// MOTD and other login output stripped before the first prompt
+    pub motd: Option<String>,
 }
 
 /// Terminal data event (sent to frontend)
@@ -30,6 +49,8 @@ pub enum TerminalEvent {
     Disconnected,
     Error(String),
     Latency(u32),
+    InputLogging(bool),
+    Recording(bool),
 }
 
 /// Terminal Error
@@ -37,12 +58,32 @@ pub enum TerminalEvent {
 pub enum TerminalError {
     #[error("Session not found: {0}")]
     SessionNotFound(String),
+    #[error("Terminal group not found: {0}")]
+    GroupNotFound(String),
     #[error("Not connected")]
     NotConnected,
     #[error("Connection failed: {0}")]
     ConnectionFailed(String),
     #[error("SSH error: {0}")]
     Ssh(String),
+    #[error("Input log error: {0}")]
+    InputLog(String),
+    #[error("Unknown encoding: {0}")]
+    InvalidEncoding(String),
+    #[error("Export failed: {0}")]
+    Export(String),
+    #[error("Recording failed: {0}")]
+    Recording(String),
+}
+
+impl crate::utils::ErrorCode for TerminalError {
+    fn code(&self) -> &'static str {
+        match self {
+            TerminalError::SessionNotFound(_) | TerminalError::GroupNotFound(_) => "not_found",
+            TerminalError::NotConnected => "not_connected",
+            _ => "error",
+        }
+    }
 }
 
 impl Serialize for TerminalError {
@@ -0,0 +1,132 @@
+//! Session Recording
+//!
+//! Writes terminal output to an asciicast v2 file: a JSON header with the
+//! terminal size, then one `[time, "o", data]` line per output chunk and
+//! `[time, "r", "COLSxROWS"]` when the terminal is resized. Lines are
+//! written as they happen, so a recording cut short by a crash still plays.
+
+use super::TerminalError;
+use crate::retention::{self, ActiveFileGuard};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use uuid::Uuid;
+
+/// An asciicast recording in progress
+pub struct CastRecorder {
+    path: PathBuf,
+    file: File,
+    started: Instant,
+    _active: ActiveFileGuard,
+}
+
+impl CastRecorder {
+    /// Create `<session>-<time>.cast` in `dir` and write its header
+    pub fn create(
+        dir: &Path,
+        session_id: Uuid,
+        cols: u32,
+        rows: u32,
+        title: &str,
+    ) -> Result<Self, TerminalError> {
+        fs::create_dir_all(dir).map_err(|e| TerminalError::Recording(e.to_string()))?;
+        let now = chrono::Utc::now();
+        let path = dir.join(format!(
+            "{}-{}.cast",
+            session_id,
+            now.format("%Y%m%d-%H%M%S")
+        ));
+        let file = File::create(&path).map_err(|e| TerminalError::Recording(e.to_string()))?;
+
+        let mut recorder = Self {
+            _active: retention::mark_active(&path),
+            path,
+            file,
+            started: Instant::now(),
+        };
+        let header = serde_json::json!({
+            "version": 2,
+            "width": cols,
+            "height": rows,
+            "timestamp": now.timestamp(),
+            "title": title,
+            "env": { "TERM": "xterm-256color" },
+        });
+        recorder.write_line(&header.to_string())?;
+        Ok(recorder)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Record output as the frontend received it
+    pub fn output(&mut self, data: &str) -> Result<(), TerminalError> {
+        self.event("o", data)
+    }
+
+    /// Record a terminal resize
+    pub fn resize(&mut self, cols: u32, rows: u32) -> Result<(), TerminalError> {
+        self.event("r", &format!("{}x{}", cols, rows))
+    }
+
+    fn event(&mut self, code: &str, data: &str) -> Result<(), TerminalError> {
+        // Microsecond precision keeps the lines short
+        let time = self.started.elapsed().as_micros() as f64 / 1_000_000.0;
+        let line = serde_json::to_string(&(time, code, data))
+            .map_err(|e| TerminalError::Recording(e.to_string()))?;
+        self.write_line(&line)
+    }
+
+    fn write_line(&mut self, line: &str) -> Result<(), TerminalError> {
+        writeln!(self.file, "{}", line).map_err(|e| TerminalError::Recording(e.to_string()))
+    }
+}
+
+/// Pass an event to the recorder in `slot`, stopping the recording if writing fails
+pub fn record<F>(slot: &parking_lot::Mutex<Option<CastRecorder>>, write: F)
+where
+    F: FnOnce(&mut CastRecorder) -> Result<(), TerminalError>,
+{
+    let mut slot = slot.lock();
+    let Some(recorder) = slot.as_mut() else {
+        return;
+    };
+    if let Err(e) = write(recorder) {
+        tracing::warn!(
+            "Stopped recording {} after an error: {}",
+            recorder.path.display(),
+            e
+        );
+        *slot = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terminal::export::cast_output;
+
+    #[test]
+    fn test_recording_round_trip() {
+        let dir = std::env::temp_dir().join(format!("nexus-recording-{}", Uuid::new_v4()));
+        let mut recorder = CastRecorder::create(&dir, Uuid::new_v4(), 120, 40, "web-1").unwrap();
+        recorder.output("$ ls\r\n").unwrap();
+        recorder.resize(100, 30).unwrap();
+        recorder.output("a \"b\"\r\n").unwrap();
+        let path = recorder.path().to_path_buf();
+        drop(recorder);
+
+        let content = fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines.len(), 4);
+        let header: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(header["width"], 120);
+        assert_eq!(header["height"], 40);
+        assert!(lines[2].ends_with(",\"r\",\"100x30\"]"));
+        assert_eq!(cast_output(&content).unwrap(), "$ ls\r\na \"b\"\r\n");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
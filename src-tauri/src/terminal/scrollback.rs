@@ -0,0 +1,93 @@
+//! Server-side scrollback of terminal output
+//!
+//! Keeps the most recent output of a session, byte for byte as the server
+//! sent it, so a remounted terminal can be repainted and the output
+//! exported after the frontend has dropped it. Readers decode it with the
+//! session's encoding. When the buffer is full,
+//! whole lines are dropped from the front so the oldest kept line starts
+//! cleanly, without half an escape sequence or character.
+
+use crate::datasphere;
+use std::collections::VecDeque;
+
+/// Most output kept per session unless configured otherwise
+pub const SCROLLBACK_LIMIT: usize = 1024 * 1024;
+
+/// Configured scrollback size in bytes
+pub fn configured_limit() -> usize {
+    datasphere::storage()
+        .read()
+        .as_ref()
+        .map_or(SCROLLBACK_LIMIT, |s| s.get_settings().scrollback_bytes)
+}
+
+/// Output of one terminal session, ANSI sequences included
+#[derive(Debug)]
+pub struct Scrollback {
+    bytes: VecDeque<u8>,
+    limit: usize,
+}
+
+impl Default for Scrollback {
+    fn default() -> Self {
+        Self::with_limit(SCROLLBACK_LIMIT)
+    }
+}
+
+impl Scrollback {
+    pub fn with_limit(limit: usize) -> Self {
+        Self {
+            bytes: VecDeque::new(),
+            limit,
+        }
+    }
+
+    pub fn push(&mut self, data: &[u8]) {
+        self.bytes.extend(data);
+        if self.bytes.len() <= self.limit {
+            return;
+        }
+
+        let cut = self.bytes.len() - self.limit;
+        // Drop the rest of the cut line too, including any escape sequence in it
+        let cut = match self.bytes.range(cut..).position(|&b| b == b'\n') {
+            Some(newline) => cut + newline + 1,
+            // No line break left; at least start on a character boundary
+            None => {
+                cut + self
+                    .bytes
+                    .range(cut..)
+                    .position(|&b| b & 0xC0 != 0x80)
+                    .unwrap_or(self.bytes.len() - cut)
+            }
+        };
+        self.bytes.drain(..cut);
+    }
+
+    /// The buffered output, exactly as received
+    pub fn bytes(&self) -> Vec<u8> {
+        let (front, back) = self.bytes.as_slices();
+        [front, back].concat()
+    }
+
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.bytes()).into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scrollback_trims_whole_lines() {
+        let mut scrollback = Scrollback::with_limit(16);
+        scrollback.push(b"first \x1b[31mline\n");
+        scrollback.push(b"second\nthird");
+        assert_eq!(scrollback.bytes(), b"second\nthird");
+
+        scrollback.push("ü".repeat(10).as_bytes());
+        assert!(scrollback.bytes().len() <= 16);
+        assert_eq!(scrollback.text(), "ü".repeat(8));
+    }
+}
@@ -0,0 +1,119 @@
+//! Per-host results for operations on many hosts
+//!
+//! A fleet operation reports each host on its own, so one unreachable or
+//! misconfigured host never fails the rest. Commands return
+//! `Ok(BulkResult)` even when every host failed; only problems that affect
+//! the whole request (bad arguments, storage not ready) are errors.
+
+use serde::Serialize;
+use std::fmt::Display;
+
+/// Short machine-readable code for an error, e.g. `auth_failed`
+pub trait ErrorCode: Display {
+    fn code(&self) -> &'static str;
+}
+
+/// Outcome for a single host
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum BulkOutcome<T> {
+    Ok { value: T },
+    Err { code: String, message: String },
+}
+
+/// One host's entry in a `BulkResult`
+#[derive(Debug, Clone, Serialize)]
+pub struct BulkItem<T> {
+    /// Host or session id the outcome belongs to
+    pub id: String,
+    #[serde(flatten)]
+    pub outcome: BulkOutcome<T>,
+}
+
+/// Results of a multi-host operation, in request order
+#[derive(Debug, Clone, Serialize)]
+pub struct BulkResult<T> {
+    pub items: Vec<BulkItem<T>>,
+    pub succeeded: usize,
+    pub failed: usize,
+}
+
+impl<T> Default for BulkResult<T> {
+    fn default() -> Self {
+        Self {
+            items: Vec::new(),
+            succeeded: 0,
+            failed: 0,
+        }
+    }
+}
+
+impl<T> BulkResult<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push_ok(&mut self, id: impl ToString, value: T) {
+        self.succeeded += 1;
+        self.items.push(BulkItem {
+            id: id.to_string(),
+            outcome: BulkOutcome::Ok { value },
+        });
+    }
+
+    pub fn push_err(&mut self, id: impl ToString, code: &str, message: impl ToString) {
+        self.failed += 1;
+        self.items.push(BulkItem {
+            id: id.to_string(),
+            outcome: BulkOutcome::Err {
+                code: code.to_string(),
+                message: message.to_string(),
+            },
+        });
+    }
+
+    /// Record a host's result, keeping the error's code and message
+    pub fn push<E: ErrorCode>(&mut self, id: impl ToString, result: Result<T, E>) {
+        match result {
+            Ok(value) => self.push_ok(id, value),
+            Err(e) => self.push_err(id, e.code(), e),
+        }
+    }
+
+    pub fn all_ok(&self) -> bool {
+        self.failed == 0
+    }
+
+    /// Values of the hosts that succeeded
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.items.iter().filter_map(|item| match &item.outcome {
+            BulkOutcome::Ok { value } => Some(value),
+            BulkOutcome::Err { .. } => None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ssh::SshError;
+
+    #[test]
+    fn test_bulk_result() {
+        let mut result = BulkResult::new();
+        result.push("a", Ok::<u32, SshError>(1));
+        result.push("b", Err::<u32, _>(SshError::AuthenticationFailed));
+        result.push_ok("c", 3);
+
+        assert_eq!((result.succeeded, result.failed), (2, 1));
+        assert!(!result.all_ok());
+        assert_eq!(result.values().copied().collect::<Vec<_>>(), vec![1, 3]);
+
+        let json = serde_json::to_value(&result).unwrap();
+        assert_eq!(json["items"][0]["status"], "ok");
+        assert_eq!(json["items"][0]["value"], 1);
+        assert_eq!(json["items"][1]["status"], "err");
+        assert_eq!(json["items"][1]["code"], "auth_failed");
+        assert_eq!(json["items"][1]["message"], "Authentication failed");
+    }
+}
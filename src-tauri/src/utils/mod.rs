@@ -1,6 +1,16 @@
 //! Utility functions and helpers
 
+pub mod bulk;
+
+pub use bulk::{BulkItem, BulkOutcome, BulkResult, ErrorCode};
+
+use crate::ssh::{ConnectionReason, ConnectionState};
 use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+use uuid::Uuid;
+
+/// Event name all `AppEvent`s are emitted on
+pub const APP_EVENT: &str = "app-event";
 
 /// Application event for frontend notifications
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -9,22 +19,60 @@ pub enum AppEvent {
     HostConnected { host_id: String, session_id: String },
     HostDisconnected { host_id: String, session_id: String },
     LatencyUpdate { session_id: String, latency_ms: u32 },
-    FileTransferProgress { session_id: String, path: String, progress: f32 },
+    FileTransferProgress {
+        session_id: String,
+        path: String,
+        progress: f32,
+        bytes_transferred: u64,
+        total_bytes: u64,
+    },
     FileTransferComplete { session_id: String, path: String },
+    ConnectionStateChanged {
+        host_id: Option<String>,
+        session_id: String,
+        state: ConnectionState,
+        reason: Option<ConnectionReason>,
+    },
     Error { message: String },
 }
 
-/// Audit log entry
+impl AppEvent {
+    pub fn connection_state(
+        host_id: Option<Uuid>,
+        session_id: Uuid,
+        state: ConnectionState,
+        reason: Option<ConnectionReason>,
+    ) -> Self {
+        Self::ConnectionStateChanged {
+            host_id: host_id.map(|id| id.to_string()),
+            session_id: session_id.to_string(),
+            state,
+            reason,
+        }
+    }
+}
+
+/// Emit an application event to the frontend
+pub fn emit_app_event(app: &AppHandle, event: AppEvent) {
+    if let Err(e) = app.emit(APP_EVENT, event) {
+        tracing::warn!("Failed to emit app event: {}", e);
+    }
+}
+
+/// Audit log entry, hash-chained to the one before it
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AuditLogEntry {
+    pub seq: u64,
     pub timestamp: chrono::DateTime<chrono::Utc>,
     pub action: AuditAction,
     pub details: String,
     pub session_id: Option<String>,
+    pub prev_hash: String,
+    pub hash: String,
 }
 
 /// Audit actions
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum AuditAction {
     Connect,
@@ -36,6 +84,7 @@ pub enum AuditAction {
     SettingsChanged,
     HostAdded,
     HostRemoved,
+    AuditLogCleared,
 }
 
 /// Format bytes to human readable string
@@ -55,3 +104,37 @@ pub fn format_bytes(bytes: u64) -> String {
         format!("{:.2} {}", size, UNITS[unit_index])
     }
 }
+
+/// Quote a string for safe use as a single POSIX shell word
+pub fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// Match `*` (any run) and `?` (any one character)
+pub fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, from)) => {
+                    p = star + 1;
+                    t = from + 1;
+                    backtrack = Some((star, from + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
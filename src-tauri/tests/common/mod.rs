@@ -0,0 +1,31 @@
+//! Shared setup for the tests against a real sshd
+//!
+//! They expect an sshd on this machine, configured through the environment:
+//!
+//! - `NEXUS_TEST_SSH_HOST` / `NEXUS_TEST_SSH_PORT`, default `127.0.0.1:22`
+//! - `NEXUS_TEST_SSH_USER`, default `$USER`
+//! - `NEXUS_TEST_SSH_PASSWORD` or `NEXUS_TEST_SSH_KEY`; the SSH agent otherwise
+
+use std::env;
+use stumpfworks_nexus_lib::ssh::{AuthMethod, SshConfig};
+
+pub fn test_config() -> SshConfig {
+    let auth = match (
+        env::var("NEXUS_TEST_SSH_PASSWORD"),
+        env::var("NEXUS_TEST_SSH_KEY"),
+    ) {
+        (Ok(password), _) => AuthMethod::Password(password),
+        (_, Ok(key_path)) => AuthMethod::PrivateKey {
+            key_path,
+            passphrase: None,
+        },
+        _ => AuthMethod::Agent,
+    };
+    serde_json::from_value(serde_json::json!({
+        "host": env::var("NEXUS_TEST_SSH_HOST").unwrap_or_else(|_| "127.0.0.1".to_string()),
+        "port": env::var("NEXUS_TEST_SSH_PORT").ok().and_then(|p| p.parse::<u16>().ok()).unwrap_or(22),
+        "username": env::var("NEXUS_TEST_SSH_USER").or_else(|_| env::var("USER")).expect("NEXUS_TEST_SSH_USER or USER"),
+        "auth_method": auth,
+    }))
+    .expect("valid SSH config")
+}
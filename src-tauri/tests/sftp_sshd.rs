@@ -0,0 +1,46 @@
+//! SFTP against a real sshd
+//!
+//! Only built with `--features sshd-tests`; see `common` for the sshd it
+//! expects.
+//!
+//! ```sh
+//! cargo test --features sshd-tests --test sftp_sshd
+//! ```
+
+#![cfg(feature = "sshd-tests")]
+
+mod common;
+
+use common::test_config;
+use std::env;
+use stumpfworks_nexus_lib::sftp;
+use stumpfworks_nexus_lib::ssh::SshClient;
+use uuid::Uuid;
+
+#[tokio::test]
+async fn test_list_tmp() {
+    let marker = format!("nexus-sftp-test-{}", Uuid::new_v4());
+    let marker_path = env::temp_dir().join(&marker);
+    std::fs::write(&marker_path, b"nexus").unwrap();
+
+    let mut ssh = SshClient::new(test_config());
+    ssh.connect().await.expect("connect to sshd");
+
+    let session_id = Uuid::new_v4();
+    let info = sftp::manager::connect(session_id, &ssh, Some("/tmp"))
+        .await
+        .expect("open SFTP session");
+    assert_eq!(info.start_path, "/tmp");
+
+    let client = sftp::manager().read().get_client(&session_id).unwrap();
+    let entries = client.list_dir("/tmp").await.expect("list /tmp");
+    let entry = entries
+        .iter()
+        .find(|e| e.name == marker)
+        .expect("marker file listed");
+    assert!(!entry.is_dir);
+    assert_eq!(entry.size, 5);
+
+    sftp::manager().write().remove_session(&session_id);
+    std::fs::remove_file(&marker_path).unwrap();
+}
@@ -0,0 +1,41 @@
+//! SSH commands against a real sshd
+//!
+//! Only built with `--features sshd-tests`; see `common` for the sshd it
+//! expects.
+//!
+//! ```sh
+//! cargo test --features sshd-tests --test ssh_sshd
+//! ```
+
+#![cfg(feature = "sshd-tests")]
+
+mod common;
+
+use common::test_config;
+use std::time::{Duration, Instant};
+use stumpfworks_nexus_lib::ssh::{SshClient, SshError};
+
+#[tokio::test]
+async fn test_command_timeout() {
+    let mut ssh = SshClient::new(test_config());
+    ssh.connect().await.expect("connect to sshd");
+
+    let started = Instant::now();
+    let result = ssh
+        .execute_with_timeout("echo started; sleep 10", Duration::from_secs(1))
+        .await;
+    assert!(started.elapsed() < Duration::from_secs(5));
+    match result {
+        Err(SshError::CommandTimeout { seconds, output }) => {
+            assert_eq!(seconds, 1);
+            assert_eq!(output.stdout, "started\n");
+        }
+        other => panic!("expected a timeout, got {:?}", other.map(|o| o.exit_code)),
+    }
+
+    // Without a deadline nothing changes
+    let output = ssh.execute("echo done").await.expect("run echo");
+    assert_eq!(output.stdout, "done\n");
+
+    ssh.disconnect().await.expect("disconnect");
+}
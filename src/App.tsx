@@ -1,4 +1,5 @@
-import { useEffect } from "react";
+import { useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
 import { BrowserRouter, Routes, Route } from "react-router-dom";
 import { useThemeStore } from "./store/themeStore";
 import MainLayout from "./components/layout/MainLayout";
@@ -10,9 +11,12 @@ import VaultView from "./components/vault/VaultView";
 import Settings from "./components/settings/Settings";
 import CommandPalette from "./components/command-palette/CommandPalette";
 import { ToastContainer } from "./components/ui/Toast";
+import UnlockScreen from "./components/ui/UnlockScreen";
+import type { AppStatus } from "./types";
 
 function App() {
   const { theme, initTheme } = useThemeStore();
+  const [locked, setLocked] = useState<boolean | null>(null);
 
   useEffect(() => {
     initTheme();
@@ -29,6 +33,22 @@ function App() {
     }
   }, [theme]);
 
+  useEffect(() => {
+    invoke<AppStatus>("get_app_status")
+      .then((status) => setLocked(status.locked))
+      .catch(() => setLocked(true));
+  }, []);
+
+  // Everything below reads the DataSphere, which stays locked until unlocked
+  if (locked !== false) {
+    return (
+      <div className="h-screen w-screen overflow-hidden bg-[#0A0A0F]">
+        {locked && <UnlockScreen onUnlocked={() => setLocked(false)} />}
+        <ToastContainer />
+      </div>
+    );
+  }
+
   return (
     <BrowserRouter>
       <div className="h-screen w-screen overflow-hidden bg-[#0A0A0F]">
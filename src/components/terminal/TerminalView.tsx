@@ -67,10 +67,7 @@ export default function TerminalView() {
     try {
       await invoke("connect_terminal", {
         sessionId: currentSessionId,
-        host: currentHost.hostname,
-        port: currentHost.port,
-        username: currentHost.username,
-        authType: currentHost.auth_type,
+        hostId: currentHost.id,
         password: password || null,
         keyPath: keyPath || null,
         passphrase: passphrase || null,
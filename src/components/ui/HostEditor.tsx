@@ -3,7 +3,7 @@ import * as Dialog from "@radix-ui/react-dialog";
 import * as Select from "@radix-ui/react-select";
 import { X, ChevronDown, Check, Eye, EyeOff, Key, Lock, Server } from "lucide-react";
 import { Host, useHostStore } from "../../store/hostStore";
-import { cn } from "../../lib/utils";
+import { cn, errorMessage } from "../../lib/utils";
 
 interface HostEditorProps {
   host?: Host | null;
@@ -140,7 +140,7 @@ export default function HostEditor({ host, isOpen, onClose }: HostEditorProps) {
 
       onClose();
     } catch (err) {
-      setError(String(err));
+      setError(errorMessage(err));
     } finally {
       setIsSubmitting(false);
     }
@@ -0,0 +1,85 @@
+import { useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { Eye, EyeOff, Lock } from "lucide-react";
+import { toast } from "./Toast";
+import type { AppStatus } from "../../types";
+import { errorMessage } from "../../lib/utils";
+
+interface UnlockScreenProps {
+  onUnlocked: () => void;
+}
+
+export default function UnlockScreen({ onUnlocked }: UnlockScreenProps) {
+  const [password, setPassword] = useState("");
+  const [showPassword, setShowPassword] = useState(false);
+  const [isUnlocking, setIsUnlocking] = useState(false);
+  const [error, setError] = useState<string | null>(null);
+
+  const handleSubmit = async (e: React.FormEvent) => {
+    e.preventDefault();
+    if (!password) return;
+
+    setIsUnlocking(true);
+    setError(null);
+    try {
+      const status = await invoke<AppStatus>("unlock_datasphere", { password });
+      setPassword("");
+      for (const failure of status.load_failures) {
+        toast.warning(
+          `Could not read ${failure.file}`,
+          `It was moved to ${failure.preserved_as}: ${failure.error}`
+        );
+      }
+      onUnlocked();
+    } catch (err) {
+      setError(errorMessage(err));
+    } finally {
+      setIsUnlocking(false);
+    }
+  };
+
+  return (
+    <div className="h-full w-full flex items-center justify-center">
+      <form
+        onSubmit={handleSubmit}
+        className="w-full max-w-sm p-6 space-y-4 bg-white dark:bg-gray-800 rounded-macos-lg shadow-macos"
+      >
+        <div className="flex items-center gap-2 text-lg font-semibold text-text-primary dark:text-text-primary-dark">
+          <Lock className="w-5 h-5" />
+          Unlock DataSphere
+        </div>
+        <p className="text-sm text-text-secondary">
+          Enter your master password. On first start, the password you enter becomes the master
+          password.
+        </p>
+
+        <div className="relative">
+          <input
+            type={showPassword ? "text" : "password"}
+            value={password}
+            onChange={(e) => setPassword(e.target.value)}
+            className="input pr-10"
+            placeholder="Master password"
+            autoFocus
+            required
+          />
+          <button
+            type="button"
+            onClick={() => setShowPassword(!showPassword)}
+            className="absolute right-2 top-1/2 -translate-y-1/2 p-1 text-text-secondary hover:text-text-primary"
+          >
+            {showPassword ? <EyeOff className="w-4 h-4" /> : <Eye className="w-4 h-4" />}
+          </button>
+        </div>
+
+        {error && <div className="text-sm text-red-500">{error}</div>}
+
+        <div className="flex justify-end">
+          <button type="submit" className="btn-primary" disabled={isUnlocking}>
+            {isUnlocking ? "Unlocking…" : "Unlock"}
+          </button>
+        </div>
+      </form>
+    </div>
+  );
+}
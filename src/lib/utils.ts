@@ -33,6 +33,17 @@ export function formatRelativeTime(date: Date | string): string {
   return then.toLocaleDateString();
 }
 
+/**
+ * Message of a rejected command, whether it threw a string or a structured
+ * error (see `CommandError`)
+ */
+export function errorMessage(err: unknown): string {
+  if (typeof err === "object" && err !== null && "message" in err) {
+    return String((err as { message: unknown }).message);
+  }
+  return String(err);
+}
+
 /**
  * Debounce function
  */
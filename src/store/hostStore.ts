@@ -1,5 +1,6 @@
 import { create } from "zustand";
 import { invoke } from "@tauri-apps/api/core";
+import { errorMessage } from "../lib/utils";
 
 export interface Host {
   id: string;
@@ -19,6 +20,20 @@ export interface Host {
   created_at: string;
   updated_at: string;
   last_connected: string | null;
+  initial_path?: string | null;
+  suppress_motd?: boolean;
+  exec_profile?: string | null;
+  transport?:
+    | { type: "tcp" }
+    | { type: "unix_socket"; path: string }
+    | { type: "named_pipe"; path: string };
+  encoding?: string | null;
+  send_encoding?: string | null;
+  normalize_newlines?: boolean;
+  security_profile?: string | null;
+  proxy_jump?: string | null;
+  env?: Record<string, string>;
+  init_commands?: string[];
 }
 
 export interface HostGroup {
@@ -59,7 +74,7 @@ export const useHostStore = create<HostState>((set, get) => ({
       const hosts = await invoke<Host[]>("get_hosts");
       set({ hosts, isLoading: false });
     } catch (error) {
-      set({ error: String(error), isLoading: false });
+      set({ error: errorMessage(error), isLoading: false });
     }
   },
 
@@ -68,7 +83,7 @@ export const useHostStore = create<HostState>((set, get) => ({
       const groups = await invoke<HostGroup[]>("get_host_groups");
       set({ groups });
     } catch (error) {
-      set({ error: String(error) });
+      set({ error: errorMessage(error) });
     }
   },
 
@@ -82,7 +97,7 @@ export const useHostStore = create<HostState>((set, get) => ({
       }));
       return host;
     } catch (error) {
-      set({ error: String(error), isLoading: false });
+      set({ error: errorMessage(error), isLoading: false });
       throw error;
     }
   },
@@ -96,7 +111,7 @@ export const useHostStore = create<HostState>((set, get) => ({
         isLoading: false,
       }));
     } catch (error) {
-      set({ error: String(error), isLoading: false });
+      set({ error: errorMessage(error), isLoading: false });
       throw error;
     }
   },
@@ -111,7 +126,7 @@ export const useHostStore = create<HostState>((set, get) => ({
         isLoading: false,
       }));
     } catch (error) {
-      set({ error: String(error), isLoading: false });
+      set({ error: errorMessage(error), isLoading: false });
       throw error;
     }
   },
@@ -1,5 +1,6 @@
 import { create } from "zustand";
 import { invoke } from "@tauri-apps/api/core";
+import { errorMessage } from "../lib/utils";
 
 export type SessionStatus =
   | "disconnected"
@@ -45,7 +46,7 @@ export const useSessionStore = create<SessionState>((set) => ({
       const sessions = await invoke<Session[]>("get_sessions");
       set({ sessions, isLoading: false });
     } catch (error) {
-      set({ error: String(error), isLoading: false });
+      set({ error: errorMessage(error), isLoading: false });
     }
   },
 
@@ -63,7 +64,7 @@ export const useSessionStore = create<SessionState>((set) => ({
       }));
       return session;
     } catch (error) {
-      set({ error: String(error), isLoading: false });
+      set({ error: errorMessage(error), isLoading: false });
       throw error;
     }
   },
@@ -79,7 +80,7 @@ export const useSessionStore = create<SessionState>((set) => ({
         isLoading: false,
       }));
     } catch (error) {
-      set({ error: String(error), isLoading: false });
+      set({ error: errorMessage(error), isLoading: false });
       throw error;
     }
   },
@@ -2,12 +2,38 @@
 export type { Host, HostGroup } from "../store/hostStore";
 export type { Session, SessionStatus } from "../store/sessionStore";
 
+import type { Host } from "../store/hostStore";
+
+export interface LoadFailure {
+  file: string;
+  error: string;
+  preserved_as: string;
+}
+
+export interface AppStatus {
+  version: string;
+  datasphere_initialized: boolean;
+  locked: boolean;
+  load_failures: LoadFailure[];
+}
+
+export interface SessionStats {
+  session_id: string;
+  bytes_sent: number;
+  bytes_received: number;
+  connected_at: string | null;
+  uptime_seconds: number | null;
+}
+
 // SSH Types
 export interface SshConfig {
   host: string;
   port: number;
   username: string;
   auth_method: AuthMethod;
+  keepalive_seconds?: number | null;
+  keepalive_max?: number;
+  jump_hosts?: SshConfig[];
 }
 
 export type AuthMethod =
@@ -15,10 +41,54 @@ export type AuthMethod =
   | { type: "private_key"; data: { key_path: string; passphrase?: string } }
   | { type: "agent" };
 
+export type TunnelKind = "local" | "remote";
+
+export interface Tunnel {
+  id: string;
+  type: TunnelKind;
+  bind_addr: string;
+  target: string;
+}
+
+export interface HostProbe {
+  host_id: string;
+  reachable: boolean;
+  banner: string | null;
+  rtt_ms: number | null;
+  error: string | null;
+}
+
+export interface HostFingerprint {
+  host: string;
+  port: number;
+  key_type: string;
+  sha256: string;
+  md5: string;
+}
+
 export interface CommandOutput {
   stdout: string;
   stderr: string;
   exit_code: number;
+  chunks?: OutputChunk[];
+  cancelled: boolean;
+}
+
+export interface OutputChunk {
+  seq: number;
+  stream: 'stdout' | 'stderr';
+  data: string;
+}
+
+export type TerminalOutput =
+  | { type: "Stdout"; data: string }
+  | { type: "Stderr"; data: string }
+  | { type: "Exit"; data: number }
+  | { type: "Error"; data: string };
+
+export interface TerminalGroup {
+  id: string;
+  session_ids: string[];
 }
 
 // SFTP Types
@@ -29,6 +99,26 @@ export interface FileEntry {
   size: number;
   modified: string | null;
   permissions: string | null;
+  symlink_target?: string | null;
+}
+
+export interface FindResult {
+  matches: FileEntry[];
+  truncated: boolean;
+  skipped: string[];
+}
+
+export interface RemoteFileContent {
+  content_base64: string;
+  mtime: number | null;
+  size: number;
+  sha256: string;
+}
+
+export interface RemoteFileStat {
+  mtime: number | null;
+  size: number;
+  sha256: string;
 }
 
 export interface TransferProgress {
@@ -37,11 +127,26 @@ export interface TransferProgress {
   progress: number;
   bytes_transferred: number;
   total_bytes: number;
+  files_completed: number;
+  files_total: number;
+}
+
+export interface TransferFileError {
+  path: string;
+  error: string;
+}
+
+export interface DirTransferResult {
+  files_transferred: number;
+  bytes_transferred: number;
+  symlinks: string[];
+  errors: TransferFileError[];
 }
 
 // Settings Types
 export type Theme = "light" | "dark" | "system";
 export type CursorStyle = "block" | "underline" | "bar";
+export type TrustPolicy = "strict" | "tofu_logged" | "accept_all";
 
 export interface Settings {
   theme: Theme;
@@ -53,12 +158,106 @@ export interface Settings {
   show_latency: boolean;
   sync_enabled: boolean;
   sync_provider: SyncProvider | null;
+  host_key_policy?: TrustPolicy;
+  remove_partial_transfers?: boolean;
+  scrollback_bytes?: number;
+  disabled_plugins?: string[];
+  editor_max_file_bytes?: number;
+  audit_log_encrypted?: boolean;
+  trash_retention_days?: number | null;
+}
+
+// Audit Log Types
+export type AuditAction =
+  | "connect"
+  | "disconnect"
+  | "command_executed"
+  | "file_uploaded"
+  | "file_downloaded"
+  | "file_deleted"
+  | "settings_changed"
+  | "host_added"
+  | "host_removed"
+  | "audit_log_cleared";
+
+export interface AuditLogEntry {
+  seq: number;
+  timestamp: string;
+  action: AuditAction;
+  details: string;
+  session_id: string | null;
+  prev_hash: string;
+  hash: string;
+}
+
+export interface AuditFilter {
+  actions?: AuditAction[];
+  session_id?: string | null;
+  since?: string | null;
+  until?: string | null;
+  text?: string | null;
+  limit?: number | null;
+}
+
+export interface AuditLogReport {
+  entries: AuditLogEntry[];
+  chain_valid: boolean;
+  broken_at: number | null;
 }
 
 export type SyncProvider =
-  | { type: "WebDAV"; url: string; username: string }
-  | { type: "S3"; bucket: string; region: string }
-  | { type: "Nextcloud"; url: string; username: string };
+  | { type: "WebDAV"; url: string; username: string; password_entry: string | null }
+  | {
+      type: "S3";
+      bucket: string;
+      region: string;
+      prefix: string;
+      endpoint: string | null;
+      credentials_entry: string | null;
+    }
+  | { type: "Nextcloud"; url: string; username: string; password_entry: string | null };
+
+export type SyncStatus =
+  | { state: "disabled" }
+  | { state: "healthy" }
+  | { state: "degraded"; last_error: string };
+
+export type MergeItemKind = "host" | "group" | "snippet" | "vault_entry" | "profile" | "known_host";
+
+export interface MergeConflict {
+  kind: MergeItemKind;
+  id: string;
+  name: string;
+  local_updated_at: string | null;
+  remote_updated_at: string | null;
+  local_deleted: boolean;
+  remote_deleted: boolean;
+}
+
+export interface SyncConflict {
+  local_modified: string | null;
+  remote_modified: string | null;
+  items: MergeConflict[];
+}
+
+export interface SyncStatusReport {
+  status: SyncStatus;
+  consecutive_failures: number;
+  total_failures: number;
+  last_error: string | null;
+  last_success_at: string | null;
+  next_retry_at: string | null;
+  last_synced_at: string | null;
+  conflict: SyncConflict | null;
+}
+
+export type SyncResolution = "keep_local" | "keep_remote";
+
+export type SyncOutcome =
+  | { action: "up_to_date" }
+  | { action: "pushed" }
+  | { action: "merged" }
+  | { action: "pulled"; unlock_required: boolean };
 
 // Snippet Types
 export interface Snippet {
@@ -72,6 +271,115 @@ export interface Snippet {
   updated_at: string;
 }
 
+export interface RenderedSnippet {
+  content: string;
+  unfilled: string[];
+}
+
+export type VaultEntryType = "password" | "ssh_key" | "api_key" | "note";
+
+export interface VaultEntry {
+  id: string;
+  name: string;
+  entry_type: VaultEntryType;
+  username: string | null;
+  /** Empty for SSH keys; `get_vault_secret` returns the private key */
+  secret: string;
+  public_key: string | null;
+  url: string | null;
+  notes: string | null;
+  folder: string | null;
+  tags: string[];
+  created_at: string;
+  updated_at: string;
+}
+
+export type TrashItem =
+  | { kind: "host"; item: Host }
+  | { kind: "vault_entry"; item: VaultEntry };
+
+export type TrashedItem = TrashItem & { deleted_at: string };
+
+export interface PasswordOptions {
+  lowercase: boolean;
+  uppercase: boolean;
+  digits: boolean;
+  symbols: boolean;
+  exclude_ambiguous: boolean;
+  pronounceable: boolean;
+}
+
+export interface StrengthEstimate {
+  score: 0 | 1 | 2 | 3 | 4;
+  crack_time_estimate: string;
+  warnings: string[];
+}
+
+export interface RemoteInfo {
+  os: string;
+  distro: string | null;
+  kernel: string | null;
+  arch: string | null;
+  shell: string | null;
+  hostname: string | null;
+}
+
+export interface ConnectionTestResult {
+  success: boolean;
+  auth_ok: boolean;
+  latency_ms: number | null;
+  error: string | null;
+}
+
+export interface FieldError {
+  field: string;
+  message: string;
+}
+
+/** Rejection from `add_host`/`update_host`; other errors are plain strings */
+export interface ValidationError {
+  code: "validation";
+  message: string;
+  fields: FieldError[];
+}
+
+/** SSH command stopped at its deadline; other SSH errors are plain strings */
+export interface CommandTimeoutError {
+  code: "timeout";
+  message: string;
+  /** What the command printed before it was stopped */
+  output: CommandOutput;
+}
+
+/** Sync rejected because both sides changed; other sync errors are plain strings */
+export interface SyncConflictError {
+  code: "conflict";
+  message: string;
+  conflict: SyncConflict;
+}
+
+/**
+ * What a rejected command throws: a message, or one of the errors above,
+ * which all carry a `code` and a `message`. Use `errorMessage` to show it.
+ */
+export type CommandError = string | ValidationError | CommandTimeoutError | SyncConflictError;
+
+export type KeyAlgorithm = { type: "ed25519" } | { type: "rsa"; bits: number };
+
+export interface GeneratedSshKey {
+  entry_id: string;
+  public_key: string;
+  fingerprint: string;
+}
+
+export type KeyInstallOutcome = "added" | "already_present";
+
+export interface GlobalSearchResults {
+  hosts: Host[];
+  snippets: Snippet[];
+  vault: VaultEntry[];
+}
+
 // MCP Types
 export type AiProvider = "claude" | "chatgpt" | "ollama" | { custom: string };
 
@@ -92,10 +400,54 @@ export interface McpApprovalRequest {
 }
 
 // Event Types
+export type ConnectionState =
+  | "resolving"
+  | "connecting"
+  | "authenticating"
+  | "connected"
+  | "disconnected"
+  | "failed";
+
+export type ConnectionReason =
+  | "user_requested"
+  | "remote_closed"
+  | "network_lost"
+  | "dns_failed"
+  | "connection_refused"
+  | "timeout"
+  | "auth_failed"
+  | "host_key_changed"
+  | "key_error"
+  | "agent_unavailable"
+  | "keepalive_timeout"
+  | "error";
+
 export type AppEvent =
   | { type: "host_connected"; data: { host_id: string; session_id: string } }
   | { type: "host_disconnected"; data: { host_id: string; session_id: string } }
   | { type: "latency_update"; data: { session_id: string; latency_ms: number } }
   | { type: "file_transfer_progress"; data: TransferProgress }
   | { type: "file_transfer_complete"; data: { session_id: string; path: string } }
+  | {
+      type: "connection_state_changed";
+      data: {
+        host_id: string | null;
+        session_id: string;
+        state: ConnectionState;
+        reason: ConnectionReason | null;
+      };
+    }
   | { type: "error"; data: { message: string } };
+
+export type BulkItem<T> = { id: string } & (
+  | { status: "ok"; value: T }
+  | { status: "err"; code: string; message: string }
+);
+
+export interface BulkResult<T> {
+  items: BulkItem<T>[];
+  succeeded: number;
+  failed: number;
+}
+
+export type ExportFormat = "plain_text" | "ansi_stripped" | "html";